@@ -0,0 +1,534 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/cli/main.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! The `worldgen` command.
+//!
+//! Reads a world definition from a RON or TOML file, as described by
+//! `WorldDef`, and writes a region of the world as a PNG image, a CSV file
+//! or a map for the Tiled editor, chosen by the extension of the output:
+//!
+//! ```text
+//! worldgen island.toml --output island.png --region=-2,-2,1,1 --seed 42
+//! ```
+//!
+//! The definition's tiles are given as text, such as `"~"` or `"forest"`.
+//! Each tile is coloured by its text, so the same tile has the same colour
+//! in every image. A TMX map is written along with a TSX tileset and its
+//! PNG image, holding a square of colour for each tile. With `--legend`,
+//! an image listing each tile's colour is written too, along with a JSON
+//! manifest of the same.
+//!
+//! With `--stage`, the definition is read as a pipeline, as described by
+//! `PipelineDef`, and the values of the named stage are written instead,
+//! as a greyscale PNG image from the lowest value to the highest, or as a
+//! CSV file:
+//!
+//! ```text
+//! worldgen rivers.ron --stage flow --output flow.png --region=0,0,3,3
+//! ```
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+use image::{Rgba, RgbaImage};
+
+use worldgen::definition::{LoadError, NoiseMapDef, PipelineDef, StageDef, WorldDef};
+use worldgen::export::chunk_to_csv;
+use worldgen::export::legend::Legend;
+use worldgen::export::png::{Colour, Gradient};
+use worldgen::export::tmx::{Tileset, TmxMap};
+use worldgen::noisemap::Seed;
+use worldgen::pipeline::Grid;
+use worldgen::world::{Size, World};
+
+/// The size in pixels of each tile in a TMX map's tileset.
+const TILE_SIZE: u32 = 16;
+
+/// Generates worlds from definition files.
+#[derive(Debug, Parser)]
+#[command(name = "worldgen", version)]
+struct Args {
+    /// The world definition, or the pipeline definition with `--stage`, as
+    /// a RON or TOML file
+    definition: PathBuf,
+
+    /// The file to write, as PNG, CSV or TMX by its extension
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// The chunks to generate, as `x0,y0,x1,y1` (inclusive)
+    #[arg(short, long, default_value = "0,0,0,0", value_parser = parse_region)]
+    region: RangeInclusive<(i64, i64)>,
+
+    /// Reseed the world and each of its noise maps. Digits are used as the
+    /// seed's value, and any other text is hashed
+    #[arg(short, long)]
+    seed: Option<Seed>,
+
+    /// The size of each chunk, as `WxH`, instead of the definition's
+    #[arg(long, value_parser = parse_size)]
+    size: Option<Size>,
+
+    /// Also write a legend of the tiles' colours, as `<output>-legend.png`
+    /// with a JSON manifest beside it
+    #[arg(long, conflicts_with = "stage")]
+    legend: bool,
+
+    /// Read the definition as a pipeline, and write the values of the named
+    /// stage
+    #[arg(long)]
+    stage: Option<String>,
+}
+
+/// The formats that can be written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Format {
+    Png,
+    Csv,
+    Tmx,
+}
+
+impl Format {
+    /// Returns the format of a file, by its extension.
+    fn of(path: &Path) -> Result<Format, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("png") => Ok(Format::Png),
+            Some("csv") => Ok(Format::Csv),
+            Some("tmx") => Ok(Format::Tmx),
+            _ => Err(format!(
+                "can't tell the format of {} (expected .png, .csv or .tmx)",
+                path.display()
+            )),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("worldgen: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let format = Format::of(&args.output)?;
+
+    if let Some(ref stage) = args.stage {
+        return run_pipeline(&args, format, stage);
+    }
+
+    let mut definition = load(&args.definition, WorldDef::from_ron, WorldDef::from_toml)?;
+
+    if let Some(seed) = args.seed {
+        definition.seed = seed;
+
+        for map in definition.maps.values_mut() {
+            reseed(map, seed);
+        }
+    }
+
+    if let Some(size) = args.size {
+        definition.size = size;
+    }
+
+    // Every distinct tile, in the order they are defined, for the tileset.
+    let mut tiles: Vec<String> = Vec::new();
+    for tile in definition.tiles.iter() {
+        if !tiles.contains(&tile.value) {
+            tiles.push(tile.value.clone());
+        }
+    }
+
+    let world = definition.build()?;
+
+    match format {
+        Format::Png => world.render_png(&args.output, args.region, |tile| colour(tile))?,
+        Format::Csv => fs::write(
+            &args.output,
+            chunk_to_csv(&world.generate_area(args.region)?),
+        )?,
+        Format::Tmx => write_tmx(&world, args.region, &tiles, &args.output)?,
+    }
+
+    if args.legend {
+        write_legend(&tiles, &args.output)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the values of a stage of a pipeline.
+fn run_pipeline(args: &Args, format: Format, stage: &str) -> Result<(), Box<dyn Error>> {
+    if format == Format::Tmx {
+        return Err("a stage can only be written as PNG or CSV".into());
+    }
+
+    let mut definition = load(
+        &args.definition,
+        PipelineDef::from_ron,
+        PipelineDef::from_toml,
+    )?;
+
+    if let Some(seed) = args.seed {
+        for stage in definition.stages.values_mut() {
+            if let StageDef::Noise(ref mut map) = *stage {
+                reseed(map, seed);
+            }
+        }
+    }
+
+    if let Some(size) = args.size {
+        definition.size = size;
+    }
+
+    let layers = definition
+        .build()?
+        .generate_stages(args.region.clone(), &[stage])?;
+    let values = layers.get(stage)?;
+
+    match format {
+        Format::Csv => fs::write(&args.output, chunk_to_csv(values))?,
+        _ => write_greyscale(values, &args.output)?,
+    }
+
+    Ok(())
+}
+
+/// Reads a definition, as RON or TOML by its extension.
+fn load<D>(
+    path: &Path,
+    from_ron: fn(&str) -> Result<D, LoadError>,
+    from_toml: fn(&str) -> Result<D, LoadError>,
+) -> Result<D, Box<dyn Error>> {
+    let document = fs::read_to_string(path)
+        .map_err(|error| format!("can't read {}: {}", path.display(), error))?;
+
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("ron") => Ok(from_ron(&document)?),
+        Some("toml") => Ok(from_toml(&document)?),
+        _ => Err(format!(
+            "can't tell the format of {} (expected .ron or .toml)",
+            path.display()
+        )
+        .into()),
+    }
+}
+
+/// Replaces the seed of a noise map, and of any maps inside it, with one
+/// made from the given seed and its own, so that maps which shared a seed
+/// still do.
+fn reseed(map: &mut NoiseMapDef, seed: Seed) {
+    match *map {
+        NoiseMapDef::Noise {
+            seed: ref mut own, ..
+        } => *own = Seed::of((seed.value, own.value)),
+        NoiseMapDef::Sum(ref mut maps) => {
+            for (_, map) in maps.iter_mut() {
+                reseed(map, seed);
+            }
+        }
+        NoiseMapDef::Modified { ref mut map, .. } => reseed(map, seed),
+    }
+}
+
+/// Writes a region as a TMX map, along with its tileset.
+fn write_tmx(
+    world: &World<String>,
+    region: RangeInclusive<(i64, i64)>,
+    tiles: &[String],
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("world");
+
+    let (tsx, png) = (format!("{}.tsx", stem), format!("{}-tiles.png", stem));
+    let width = TILE_SIZE * tiles.len().max(1) as u32;
+
+    let mut image = RgbaImage::new(width, TILE_SIZE);
+    for (x, _, pixel) in image.enumerate_pixels_mut() {
+        if let Some(tile) = tiles.get((x / TILE_SIZE) as usize) {
+            *pixel = Rgba(colour(tile));
+        }
+    }
+    image.save(path.with_file_name(&png))?;
+
+    let tileset = Tileset::new(stem, &png, width, TILE_SIZE).tile_size(TILE_SIZE, TILE_SIZE);
+    tileset.write(BufWriter::new(File::create(path.with_file_name(&tsx))?))?;
+
+    let area = world.generate_area(region)?;
+    let map = TmxMap::new(&tsx, &tileset).layer("tiles", &area, |tile| {
+        tiles
+            .iter()
+            .position(|other| other == tile)
+            .map(|id| id as u32)
+    });
+    map.write(BufWriter::new(File::create(path)?))?;
+
+    Ok(())
+}
+
+/// Writes a legend of the tiles' colours beside the map at the given path.
+fn write_legend(tiles: &[String], path: &Path) -> Result<(), Box<dyn Error>> {
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("world");
+
+    let mut legend = Legend::from_tiles(tiles, |tile| (tile.clone(), colour(tile)));
+    if let Some(map) = path.file_name().and_then(|name| name.to_str()) {
+        legend = legend.map(map);
+    }
+
+    legend.save(path.with_file_name(format!("{}-legend.png", stem)))?;
+    Ok(())
+}
+
+/// Writes values as a PNG image, from black at the lowest value to white at
+/// the highest.
+fn write_greyscale(values: &Grid, path: &Path) -> Result<(), Box<dyn Error>> {
+    let (low, high) = values
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &value| {
+            (low.min(value), high.max(value))
+        });
+
+    let gradient = Gradient::new()
+        .stop(low, [0, 0, 0, 255])
+        .stop(high, [255, 255, 255, 255]);
+
+    let width = values.first().map_or(0, Vec::len);
+    let image = RgbaImage::from_fn(width as u32, values.len() as u32, |x, y| {
+        Rgba(gradient.colour_at(values[y as usize][x as usize]))
+    });
+
+    image.save(path)?;
+    Ok(())
+}
+
+/// Returns the colour a tile is drawn with, picking a hue from its text.
+fn colour(tile: &str) -> Colour {
+    // FNV-1a, which stays the same between Rust versions
+    let hash = tile.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+
+    let hue = (hash % 360) as f64 / 60.0;
+    let (saturation, value) = (0.6, 0.85);
+
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    let m = value - chroma;
+    let channel = |c: f64| ((c + m) * 255.0).round() as u8;
+    [channel(r), channel(g), channel(b), 255]
+}
+
+/// Reads a region of chunks, as `x0,y0,x1,y1`.
+fn parse_region(text: &str) -> Result<RangeInclusive<(i64, i64)>, String> {
+    let values: Vec<i64> = text
+        .split(',')
+        .map(|value| value.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid region `{}`", text))?;
+
+    match values[..] {
+        [x0, y0, x1, y1] => Ok((x0, y0)..=(x1, y1)),
+        _ => Err(format!("invalid region `{}` (expected x0,y0,x1,y1)", text)),
+    }
+}
+
+/// Reads a chunk size, as `WxH`.
+fn parse_size(text: &str) -> Result<Size, String> {
+    let (w, h) = text
+        .split_once('x')
+        .ok_or_else(|| format!("invalid size `{}` (expected WxH)", text))?;
+
+    match (w.trim().parse(), h.trim().parse()) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok(Size::of(w, h)),
+        _ => Err(format!("invalid size `{}`", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::process;
+
+    use super::*;
+
+    const WORLD: &str = r#"(
+        size: (w: 4, h: 3),
+        seed: "island",
+        maps: {
+            "elevation": Noise(
+                noise: Perlin((octaves: 4)),
+                seed: "elevation",
+                step: (x: 0.05, y: 0.05),
+            ),
+        },
+        tiles: [
+            (value: "~", constraints: [Threshold(map: "elevation", constraint: LT(0.0))]),
+            (value: "grass"),
+        ],
+    )"#;
+
+    const PIPELINE: &str = r#"(
+        size: (w: 8, h: 8),
+        stages: {
+            "height": Noise(Noise(
+                noise: Perlin((octaves: 4)),
+                seed: "height",
+                step: (x: 0.1, y: 0.1),
+            )),
+            "flow": Flow("height"),
+        },
+    )"#;
+
+    /// Returns an empty directory for a test to write its files in.
+    fn scratch(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("worldgen-cli-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn args(arguments: &[&str]) -> Args {
+        Args::try_parse_from([&"worldgen"].into_iter().chain(arguments)).unwrap()
+    }
+
+    #[test]
+    fn parses_regions() {
+        assert_eq!(parse_region("-2,-2,1,1"), Ok((-2, -2)..=(1, 1)));
+        assert_eq!(parse_region("0, 1, 2, 3"), Ok((0, 1)..=(2, 3)));
+        assert!(parse_region("0,0,1").is_err());
+        assert!(parse_region("0,0,1,1,2").is_err());
+        assert!(parse_region("a,b,c,d").is_err());
+        assert!(parse_region("").is_err());
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("16x8"), Ok(Size::of(16, 8)));
+        assert_eq!(parse_size("16 x 8"), Ok(Size::of(16, 8)));
+        assert!(parse_size("16").is_err());
+        assert!(parse_size("0x8").is_err());
+        assert!(parse_size("16x-8").is_err());
+        assert!(parse_size("axb").is_err());
+    }
+
+    #[test]
+    fn tells_formats_by_extension() {
+        assert_eq!(Format::of(Path::new("world.png")), Ok(Format::Png));
+        assert_eq!(Format::of(Path::new("world.PNG")), Ok(Format::Png));
+        assert_eq!(Format::of(Path::new("out/world.csv")), Ok(Format::Csv));
+        assert_eq!(Format::of(Path::new("world.tmx")), Ok(Format::Tmx));
+        assert!(Format::of(Path::new("world.txt")).is_err());
+        assert!(Format::of(Path::new("world")).is_err());
+    }
+
+    #[test]
+    fn writes_a_world() {
+        let dir = scratch("world");
+        let (definition, output) = (dir.join("island.ron"), dir.join("island.csv"));
+        fs::write(&definition, WORLD).unwrap();
+
+        run(args(&[
+            definition.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            "--region=0,0,1,0",
+            "--legend",
+        ]))
+        .unwrap();
+
+        let csv = fs::read_to_string(&output).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        for line in csv.lines() {
+            assert_eq!(line.split(',').count(), 8);
+            assert!(line.split(',').all(|tile| tile == "~" || tile == "grass"));
+        }
+
+        assert!(dir.join("island-legend.png").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn writes_a_pipeline_stage() {
+        let dir = scratch("pipeline");
+        let definition = dir.join("rivers.ron");
+        fs::write(&definition, PIPELINE).unwrap();
+
+        for output in ["flow.csv", "flow.png"] {
+            run(args(&[
+                definition.to_str().unwrap(),
+                "--stage",
+                "flow",
+                "--output",
+                dir.join(output).to_str().unwrap(),
+                "--region=0,0,1,0",
+            ]))
+            .unwrap();
+        }
+
+        let csv = fs::read_to_string(dir.join("flow.csv")).unwrap();
+        assert_eq!(csv.lines().count(), 8);
+        for line in csv.lines() {
+            let flows: Vec<f64> = line.split(',').map(|flow| flow.parse().unwrap()).collect();
+            assert_eq!(flows.len(), 16);
+            assert!(flows.iter().all(|&flow| flow >= 1.0));
+        }
+
+        let image = image::open(dir.join("flow.png")).unwrap();
+        assert_eq!((image.width(), image.height()), (16, 8));
+
+        let tmx = args(&[
+            definition.to_str().unwrap(),
+            "--stage",
+            "flow",
+            "--output",
+            dir.join("flow.tmx").to_str().unwrap(),
+        ]);
+        assert!(run(tmx).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
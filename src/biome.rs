@@ -0,0 +1,184 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/biome.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Biome assignment from elevation, moisture and temperature.
+//!
+//! A `BiomeTable` holds three noise maps, and a list of biomes that each
+//! cover a range of elevation, moisture and temperature, in the style of a
+//! Whittaker diagram. Rather than writing a tile with three constraints for
+//! every biome, the table can be added to a world in one go:
+//!
+//! ```
+//! # use worldgen::biome::{Biome, BiomeTable};
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed};
+//! # use worldgen::world::{Size, Tile, World};
+//! # let noise = PerlinNoise::new();
+//! # let elevation = Box::new(NoiseMap::new(noise).set(Seed::of("elevation")));
+//! # let moisture = Box::new(NoiseMap::new(noise).set(Seed::of("moisture")));
+//! # let temperature = Box::new(NoiseMap::new(noise).set(Seed::of("temperature")));
+//! let biomes = BiomeTable::new(elevation, moisture, temperature)
+//!     .add(Biome::new('~').elevation(f64::NEG_INFINITY..0.0))
+//!     .add(Biome::new('*').temperature(f64::NEG_INFINITY..-0.5))
+//!     .add(Biome::new('T').moisture(0.3..f64::INFINITY))
+//!     .add(Biome::new('.').moisture(f64::NEG_INFINITY..-0.3))
+//!     .add(Biome::new(','));
+//!
+//! let world = World::new()
+//!     .set(Size::of(20, 20))
+//!     .add_biomes(&biomes);
+//!
+//! let tiles = world.generate(0, 0).unwrap();
+//! ```
+//!
+//! Like tiles, the first biome whose ranges contain the values is chosen.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::noisemap::NoiseMapGeneratorBase;
+use crate::world::tile::{Constraint, ConstraintType};
+use crate::world::Tile;
+
+/// A biome, covering ranges of elevation, moisture and temperature.
+///
+/// Ranges include their lower bound but not their upper bound, and are
+/// unbounded unless they are set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Biome<T> {
+    value: T,
+
+    elevation: Range<f64>,
+    moisture: Range<f64>,
+    temperature: Range<f64>,
+}
+
+impl<T> Biome<T> {
+    /// Construct a biome represented by the given value, covering every
+    /// combination of values.
+    pub fn new(value: T) -> Biome<T> {
+        Biome {
+            value,
+
+            elevation: f64::NEG_INFINITY..f64::INFINITY,
+            moisture: f64::NEG_INFINITY..f64::INFINITY,
+            temperature: f64::NEG_INFINITY..f64::INFINITY,
+        }
+    }
+
+    /// Set the range of elevation the biome covers.
+    pub fn elevation(self, elevation: Range<f64>) -> Biome<T> {
+        Biome { elevation, ..self }
+    }
+
+    /// Set the range of moisture the biome covers.
+    pub fn moisture(self, moisture: Range<f64>) -> Biome<T> {
+        Biome { moisture, ..self }
+    }
+
+    /// Set the range of temperature the biome covers.
+    pub fn temperature(self, temperature: Range<f64>) -> Biome<T> {
+        Biome {
+            temperature,
+            ..self
+        }
+    }
+
+    /// Returns true if the biome covers the given values.
+    pub fn contains(&self, elevation: f64, moisture: f64, temperature: f64) -> bool {
+        self.elevation.contains(&elevation)
+            && self.moisture.contains(&moisture)
+            && self.temperature.contains(&temperature)
+    }
+}
+
+/// A table of biomes, chosen between by the values of an elevation, a
+/// moisture, and a temperature noise map.
+pub struct BiomeTable<T> {
+    elevation: Arc<dyn NoiseMapGeneratorBase>,
+    moisture: Arc<dyn NoiseMapGeneratorBase>,
+    temperature: Arc<dyn NoiseMapGeneratorBase>,
+
+    biomes: Vec<Biome<T>>,
+}
+
+impl<T: Clone> BiomeTable<T> {
+    /// Construct an empty biome table using the given noise maps.
+    pub fn new(
+        elevation: Box<dyn NoiseMapGeneratorBase>,
+        moisture: Box<dyn NoiseMapGeneratorBase>,
+        temperature: Box<dyn NoiseMapGeneratorBase>,
+    ) -> BiomeTable<T> {
+        BiomeTable {
+            elevation: Arc::from(elevation),
+            moisture: Arc::from(moisture),
+            temperature: Arc::from(temperature),
+
+            biomes: Vec::new(),
+        }
+    }
+
+    /// Add a biome to the table.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, biome: Biome<T>) -> BiomeTable<T> {
+        let mut new = self;
+        new.biomes.push(biome);
+        new
+    }
+
+    /// Returns the first biome covering the given values.
+    pub fn lookup(&self, elevation: f64, moisture: f64, temperature: f64) -> Option<&T> {
+        self.biomes
+            .iter()
+            .find(|biome| biome.contains(elevation, moisture, temperature))
+            .map(|biome| &biome.value)
+    }
+
+    /// Returns the biome at the given world position.
+    pub fn biome_at(&self, x: i64, y: i64) -> Option<&T> {
+        self.lookup(
+            self.elevation.get_value(x, y),
+            self.moisture.get_value(x, y),
+            self.temperature.get_value(x, y),
+        )
+    }
+
+    /// Returns a tile for each biome in the table, constrained to the
+    /// biome's ranges. All of the tiles share the table's noise maps.
+    pub fn tiles(&self) -> Vec<Tile<T>> {
+        self.biomes
+            .iter()
+            .map(|biome| {
+                let axes = [
+                    (&self.elevation, &biome.elevation),
+                    (&self.moisture, &biome.moisture),
+                    (&self.temperature, &biome.temperature),
+                ];
+
+                axes.iter()
+                    .filter(|(_, range)| range.start.is_finite() || range.end.is_finite())
+                    .fold(Tile::new(biome.value.clone()), |tile, (nm, range)| {
+                        tile.when(Constraint::new(
+                            Box::new((*nm).clone()),
+                            ConstraintType::Between(range.start, range.end),
+                        ))
+                    })
+            })
+            .collect()
+    }
+}
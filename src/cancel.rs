@@ -0,0 +1,94 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/cancel.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Cancellation of long generations.
+//!
+//! A `CancelToken` is given to a generator with its `cancel_on` method, and
+//! kept by the application. Cancelling the token (from any thread) makes
+//! the generator give up at the next point it checks, between rows of
+//! chunks or between stages, rather than finishing work whose result is no
+//! longer wanted.
+//!
+//! ```
+//! # use worldgen::cancel::CancelToken;
+//! # use worldgen::world::{AreaError, Size, Tile, World};
+//! let token = CancelToken::new();
+//!
+//! let world = World::new()
+//!     .set(Size::of(16, 16))
+//!     .add(Tile::new(','))
+//!     .cancel_on(token.clone());
+//!
+//! // For example, when the user changes the world's parameters
+//! token.cancel();
+//!
+//! assert_eq!(world.generate_area((0, 0)..=(7, 7)), Err(AreaError::Cancelled));
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag shared between an application and a generator, which the
+/// application sets to stop generation.
+///
+/// Clones of a token share the same flag. A token can't be reset, so a new
+/// one should be made for each generation.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Construct a token that hasn't been cancelled.
+    pub fn new() -> CancelToken {
+        Default::default()
+    }
+
+    /// Cancels generation using this token.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Returns an error if the token has been cancelled.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The error returned when generation was stopped by its `CancelToken`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "generation was cancelled")
+    }
+}
+
+impl Error for Cancelled {}
@@ -0,0 +1,389 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/climate.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Climate layers derived from elevation.
+//!
+//! These noise maps model the climate over a heightmap, for use alongside
+//! it in tile constraints or a `BiomeTable`.
+
+use crate::noisemap::{next_id, NoiseMapGenerator, NoiseMapGeneratorBase, Size};
+use crate::terrain::sample_with_margin;
+
+/// A map of moisture carried over a heightmap by a prevailing wind, with
+/// values between 0 (arid) and 1 (wet).
+///
+/// The moisture at each position is found by following the wind across the
+/// terrain upwind of it. Air picks up moisture over the sea, and dries out
+/// slowly over land. Where the wind is forced up a slope, it cools and
+/// drops its moisture as rain, so the far sides of mountain ranges are left
+/// in a dry rain shadow.
+///
+/// The wind is given by the compass direction it blows from, in degrees
+/// clockwise from north, where north is the top of the map.
+///
+/// The defaults are:
+///
+/// ```text
+/// wind = 270.0 (from the west)
+/// reach = 64
+/// sea level = 0.0
+/// rainfall = 2.0
+/// evaporation = 0.1
+/// drying = 0.01
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate worldgen;
+/// # use worldgen::climate::MoistureMap;
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+/// # use worldgen::world::{World, Tile};
+/// # use worldgen::world::tile::{Constraint, ConstraintType};
+/// # fn main() {
+/// # let noise = PerlinNoise::new();
+/// let heights = NoiseMap::new(noise)
+///     .set(Size::of(32, 32))
+///     .set(Step::of(0.03, 0.03));
+///
+/// let moisture = MoistureMap::new(heights).wind(225.0).reach(32);
+///
+/// let chunk = moisture.generate_chunk(0, 0);
+/// assert!(chunk.iter().flatten().all(|&v| (0.0..=1.0).contains(&v)));
+///
+/// // The same values whatever the size of the chunk
+/// let whole = moisture.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = moisture.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+///
+/// let world = World::new()
+///     .set(Size::of(32, 32))
+///     .add(Tile::new('~').when(constraint!(Box::new(heights), < 0.0)))
+///     .add(Tile::new('T').when(constraint!(Box::new(moisture), > 0.5)))
+///     .add(Tile::new('.'));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct MoistureMap<NM> {
+    heights: NM,
+
+    wind: f64,
+    reach: u32,
+    sea_level: f64,
+    rainfall: f64,
+    evaporation: f64,
+    drying: f64,
+
+    id: u64,
+}
+
+impl<NM: NoiseMapGenerator> MoistureMap<NM> {
+    /// Construct a moisture map over the given heightmap.
+    pub fn new(heights: NM) -> MoistureMap<NM> {
+        MoistureMap {
+            heights,
+
+            wind: 270.0,
+            reach: 64,
+            sea_level: 0.0,
+            rainfall: 2.0,
+            evaporation: 0.1,
+            drying: 0.01,
+
+            id: next_id(),
+        }
+    }
+
+    /// Set the direction the prevailing wind blows from.
+    pub fn wind(self, wind: f64) -> MoistureMap<NM> {
+        MoistureMap { wind, ..self }
+    }
+
+    /// Set how many cells upwind of each position are followed.
+    pub fn reach(self, reach: u32) -> MoistureMap<NM> {
+        MoistureMap { reach, ..self }
+    }
+
+    /// Set the height below which the terrain is sea.
+    pub fn sea_level(self, sea_level: f64) -> MoistureMap<NM> {
+        MoistureMap { sea_level, ..self }
+    }
+
+    /// Set how much of its moisture the air drops for each unit of height
+    /// it rises.
+    pub fn rainfall(self, rainfall: f64) -> MoistureMap<NM> {
+        MoistureMap { rainfall, ..self }
+    }
+
+    /// Set the fraction of the remaining capacity the air fills with
+    /// moisture over each cell of sea.
+    pub fn evaporation(self, evaporation: f64) -> MoistureMap<NM> {
+        MoistureMap {
+            evaporation,
+            ..self
+        }
+    }
+
+    /// Set the fraction of its moisture the air loses over each cell of
+    /// land.
+    pub fn drying(self, drying: f64) -> MoistureMap<NM> {
+        MoistureMap { drying, ..self }
+    }
+}
+
+impl<NM: NoiseMapGenerator> NoiseMapGeneratorBase for MoistureMap<NM> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.heights.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let margin = self.reach as i64 + 1;
+        let heights = sample_with_margin(&self.heights, size, x, y, margin);
+
+        // The direction pointing upwind.
+        let angle = self.wind.to_radians();
+        let (ux, uy) = (angle.sin(), -angle.cos());
+
+        (0..size.h)
+            .map(|cy| {
+                (0..size.w)
+                    .map(|cx| {
+                        let height = |step: u32| {
+                            let hx = (cx as f64 + ux * step as f64).round() as i64 + margin;
+                            let hy = (cy as f64 + uy * step as f64).round() as i64 + margin;
+                            heights[hy as usize][hx as usize]
+                        };
+
+                        let mut moisture: f64 = 0.5;
+                        let mut previous = height(self.reach);
+
+                        for step in (0..self.reach).rev() {
+                            let current = height(step);
+
+                            if current < self.sea_level {
+                                moisture += (1.0 - moisture) * self.evaporation;
+                            } else {
+                                let rise = (current - previous).max(0.0);
+                                moisture *= 1.0 - self.drying;
+                                moisture *= 1.0 - (rise * self.rainfall).min(1.0);
+                            }
+
+                            previous = current;
+                        }
+
+                        moisture.clamp(0.0, 1.0)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (0.0, 1.0)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+/// A map of temperature over a heightmap, with values between -1 (polar)
+/// and 1 (tropical).
+///
+/// Temperature falls with latitude, from 1 along the equator (a row of the
+/// world) to -1 at the poles, a given distance north and south of it. The
+/// falloff towards the poles can be shaped with an exponent: above 1 keeps
+/// more of the world warm, and below 1 keeps more of it cold. Temperature
+/// also falls with height above sea level, by the lapse rate for each unit
+/// of height.
+///
+/// The temperature can also change with the seasons. The season is a time
+/// of year between 0 and 1, with 0 the middle of winter north of the
+/// equator (and of summer south of it) and 0.5 the middle of summer. Each
+/// position is warmed or cooled by the seasonal swing, scaled by its
+/// latitude, so the equator stays the same all year while the snow line
+/// moves back and forth towards the poles.
+///
+/// The defaults are:
+///
+/// ```text
+/// equator = 0
+/// pole distance = 512.0
+/// falloff = 1.0
+/// lapse rate = 1.0
+/// sea level = 0.0
+/// season = 0.0
+/// seasonal swing = 0.0
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::climate::TemperatureMap;
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+/// # let noise = PerlinNoise::new();
+/// let heights = NoiseMap::new(noise)
+///     .set(Size::of(32, 32))
+///     .set(Step::of(0.03, 0.03));
+///
+/// let temperature = TemperatureMap::new(heights)
+///     .equator(64)
+///     .pole_distance(256.0)
+///     .lapse_rate(0.5);
+///
+/// // Freezing at the poles
+/// assert_eq!(temperature.get_value(10, 64 + 256), -1.0);
+/// assert_eq!(temperature.get_value(10, 64 - 300), -1.0);
+///
+/// // Warmer in the north in summer, and colder in the south
+/// let winter = temperature.seasonal_swing(0.5);
+/// let summer = winter.season(0.5);
+/// assert!(summer.get_value(10, 0) > winter.get_value(10, 0));
+/// assert!(summer.get_value(10, 128) < winter.get_value(10, 128));
+///
+/// // The same values whatever the size of the chunk
+/// let whole = summer.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = summer.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TemperatureMap<NM> {
+    heights: NM,
+
+    equator: i64,
+    pole_distance: f64,
+    falloff: f64,
+    lapse_rate: f64,
+    sea_level: f64,
+    season: f64,
+    seasonal_swing: f64,
+
+    id: u64,
+}
+
+impl<NM: NoiseMapGenerator> TemperatureMap<NM> {
+    /// Construct a temperature map over the given heightmap.
+    pub fn new(heights: NM) -> TemperatureMap<NM> {
+        TemperatureMap {
+            heights,
+
+            equator: 0,
+            pole_distance: 512.0,
+            falloff: 1.0,
+            lapse_rate: 1.0,
+            sea_level: 0.0,
+            season: 0.0,
+            seasonal_swing: 0.0,
+
+            id: next_id(),
+        }
+    }
+
+    /// Set the world row of the equator.
+    pub fn equator(self, equator: i64) -> TemperatureMap<NM> {
+        TemperatureMap { equator, ..self }
+    }
+
+    /// Set the distance from the equator to the poles.
+    pub fn pole_distance(self, pole_distance: f64) -> TemperatureMap<NM> {
+        TemperatureMap {
+            pole_distance: pole_distance.max(f64::EPSILON),
+            ..self
+        }
+    }
+
+    /// Set the exponent shaping the falloff towards the poles.
+    pub fn falloff(self, falloff: f64) -> TemperatureMap<NM> {
+        TemperatureMap { falloff, ..self }
+    }
+
+    /// Set how much the temperature falls for each unit of height above
+    /// sea level.
+    pub fn lapse_rate(self, lapse_rate: f64) -> TemperatureMap<NM> {
+        TemperatureMap { lapse_rate, ..self }
+    }
+
+    /// Set the height above which temperature starts to fall.
+    pub fn sea_level(self, sea_level: f64) -> TemperatureMap<NM> {
+        TemperatureMap { sea_level, ..self }
+    }
+
+    /// Set the time of year, as a fraction of the year between 0 and 1.
+    pub fn season(self, season: f64) -> TemperatureMap<NM> {
+        TemperatureMap { season, ..self }
+    }
+
+    /// Set how much warmer the poles are in the middle of summer than on
+    /// average, and so how much colder they are in the middle of winter.
+    pub fn seasonal_swing(self, seasonal_swing: f64) -> TemperatureMap<NM> {
+        TemperatureMap {
+            seasonal_swing,
+            ..self
+        }
+    }
+
+    fn temperature(&self, y: i64, height: f64) -> f64 {
+        let latitude = ((y - self.equator).abs() as f64 / self.pole_distance).min(1.0);
+
+        // The hemispheres have opposite seasons, with winter in the north
+        // at the start of the year.
+        let hemisphere = if y < self.equator { 1.0 } else { -1.0 };
+        let season = -(self.season * 2.0 * std::f64::consts::PI).cos() * hemisphere;
+
+        let temperature =
+            1.0 - 2.0 * latitude.powf(self.falloff) + season * latitude * self.seasonal_swing;
+        let altitude = (height - self.sea_level).max(0.0);
+
+        (temperature - altitude * self.lapse_rate).clamp(-1.0, 1.0)
+    }
+}
+
+impl<NM: NoiseMapGenerator> NoiseMapGeneratorBase for TemperatureMap<NM> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.heights.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        self.heights
+            .generate_sized_chunk(size, x, y)
+            .into_iter()
+            .zip(y * size.h..)
+            .map(|(row, world_y)| {
+                row.into_iter()
+                    .map(|height| self.temperature(world_y, height))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        self.temperature(world_y, self.heights.get_value(world_x, world_y))
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (-1.0, 1.0)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
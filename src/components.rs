@@ -0,0 +1,407 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/components.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Labelling of connected areas of generated tiles.
+//!
+//! `Components` finds the connected areas of a region of tiles, such as
+//! the individual islands, lakes and forests of a map, and gives each one
+//! an id. Cells are connected to the four cells beside them (not the
+//! diagonals), so areas that only touch at a corner stay apart.
+//!
+//! ```
+//! # #[macro_use] extern crate worldgen;
+//! # use worldgen::components::Components;
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+//! # use worldgen::world::{Size, Tile, World};
+//! # use worldgen::world::tile::{Constraint, ConstraintType};
+//! # fn main() {
+//! # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05)));
+//! let world = World::new()
+//!     .set(Size::of(16, 16))
+//!     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+//!     .add(Tile::new(','));
+//!
+//! let tiles = world.generate_area((0, 0)..=(3, 3)).unwrap();
+//! let islands = Components::matching(&tiles, |&tile| tile == ',');
+//!
+//! if let Some(largest) = islands.largest() {
+//!     let (x, y) = islands.start(largest);
+//!     assert_eq!(tiles[y][x], ',');
+//! }
+//! # }
+//! ```
+//!
+//! The borders of each area can be traced as lines, for drawing coasts and
+//! borders as vectors, or for following a coast:
+//!
+//! ```
+//! # use worldgen::components::Components;
+//! let tiles = vec![
+//!     vec!['~', '~', '~', '~'],
+//!     vec!['~', '.', '.', '~'],
+//!     vec!['~', '~', '~', '~'],
+//! ];
+//!
+//! let islands = Components::matching(&tiles, |&tile| tile == '.');
+//! let coasts = islands.borders();
+//!
+//! assert_eq!(coasts.len(), 1);
+//! assert!(coasts[0].closed);
+//! assert_eq!(
+//!     coasts[0].points,
+//!     vec![(0.5, 0.5), (2.5, 0.5), (2.5, 1.5), (0.5, 1.5), (0.5, 0.5)]
+//! );
+//! ```
+
+use std::collections::HashMap;
+
+/// A corner between cells, where `(x, y)` is the top left corner of the
+/// cell at `(x, y)`.
+type Corner = (i64, i64);
+
+/// A line along the border of an area.
+///
+/// The points are in the coordinates of the region, on the corners between
+/// cells, so `(0.5, 1.5)` lies between the cells at `(0, 1)`, `(1, 1)`,
+/// `(0, 2)` and `(1, 2)`. Only the points where the line turns are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Border {
+    /// The id of the area the border surrounds.
+    pub area: usize,
+
+    /// The points of the line, in order, with the area on the right (with
+    /// `y` pointing down, as in the region). The outside of an area runs
+    /// clockwise, and the edges of holes in it anticlockwise.
+    pub points: Vec<(f64, f64)>,
+
+    /// Whether the line forms a loop, in which case the first and last
+    /// points are the same.
+    pub closed: bool,
+}
+
+/// The connected areas of a region of tiles.
+///
+/// Area ids count up from 0, in the order the areas are first reached when
+/// reading the region row by row.
+#[derive(Debug, Clone)]
+pub struct Components {
+    labels: Vec<Vec<Option<usize>>>,
+
+    starts: Vec<(usize, usize)>,
+    areas: Vec<usize>,
+}
+
+impl Components {
+    /// Label the connected areas of equal tiles. Every cell belongs to an
+    /// area.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::components::Components;
+    /// let tiles = vec![
+    ///     vec!['~', '~', '.', '~'],
+    ///     vec!['.', '.', '.', '~'],
+    /// ];
+    ///
+    /// let areas = Components::of(&tiles);
+    ///
+    /// assert_eq!(areas.len(), 3);
+    /// assert_eq!(areas.label(0, 0), Some(0));
+    /// assert_eq!(areas.area(areas.label(2, 0).unwrap()), 4);
+    /// assert_ne!(areas.label(0, 0), areas.label(3, 0));
+    /// ```
+    pub fn of<T: PartialEq>(grid: &[Vec<T>]) -> Components {
+        Components::label_with(grid, |_| true, |a, b| a == b)
+    }
+
+    /// Label the connected areas of tiles satisfying the predicate. Cells
+    /// that don't satisfy it belong to no area.
+    pub fn matching<T, F: Fn(&T) -> bool>(grid: &[Vec<T>], predicate: F) -> Components {
+        Components::label_with(grid, predicate, |_, _| true)
+    }
+
+    /// Labels the connected areas of the included cells, where neighbouring
+    /// cells are connected if both are included and `connected` holds.
+    fn label_with<T, I, C>(grid: &[Vec<T>], included: I, connected: C) -> Components
+    where
+        I: Fn(&T) -> bool,
+        C: Fn(&T, &T) -> bool,
+    {
+        let mut labels: Vec<Vec<Option<usize>>> =
+            grid.iter().map(|row| vec![None; row.len()]).collect();
+
+        let mut starts = Vec::new();
+        let mut areas = Vec::new();
+
+        for y in 0..grid.len() {
+            for x in 0..grid[y].len() {
+                if labels[y][x].is_some() || !included(&grid[y][x]) {
+                    continue;
+                }
+
+                let label = starts.len();
+                let mut area = 0;
+                let mut stack = vec![(x, y)];
+                labels[y][x] = Some(label);
+
+                while let Some((cx, cy)) = stack.pop() {
+                    area += 1;
+
+                    let neighbours = [
+                        (cx.wrapping_sub(1), cy),
+                        (cx + 1, cy),
+                        (cx, cy.wrapping_sub(1)),
+                        (cx, cy + 1),
+                    ];
+
+                    for (nx, ny) in neighbours {
+                        let neighbour = match grid.get(ny).and_then(|row| row.get(nx)) {
+                            Some(neighbour) => neighbour,
+                            None => continue,
+                        };
+
+                        if labels[ny][nx].is_none()
+                            && included(neighbour)
+                            && connected(&grid[cy][cx], neighbour)
+                        {
+                            labels[ny][nx] = Some(label);
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+
+                starts.push((x, y));
+                areas.push(area);
+            }
+        }
+
+        Components {
+            labels,
+
+            starts,
+            areas,
+        }
+    }
+
+    /// Returns the id of the area the given cell belongs to, if any.
+    pub fn label(&self, x: usize, y: usize) -> Option<usize> {
+        self.labels[y][x]
+    }
+
+    /// Returns the area id of every cell.
+    pub fn labels(&self) -> &Vec<Vec<Option<usize>>> {
+        &self.labels
+    }
+
+    /// Returns the number of areas.
+    pub fn len(&self) -> usize {
+        self.areas.len()
+    }
+
+    /// Returns true if there are no areas.
+    pub fn is_empty(&self) -> bool {
+        self.areas.is_empty()
+    }
+
+    /// Returns the number of cells in an area.
+    pub fn area(&self, label: usize) -> usize {
+        self.areas[label]
+    }
+
+    /// Returns the first cell of an area, reading the region row by row.
+    pub fn start(&self, label: usize) -> (usize, usize) {
+        self.starts[label]
+    }
+
+    /// Returns the cells of an area, in row order.
+    pub fn cells(&self, label: usize) -> Vec<(usize, usize)> {
+        self.labels
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(move |&(_, &other)| other == Some(label))
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect()
+    }
+
+    /// Traces the borders of every area, against cells of other areas (or
+    /// of no area), as lines.
+    ///
+    /// The edge of the region isn't a border, so an area reaching it has
+    /// open lines that end there. Borders are returned in order of the
+    /// cells they start from, reading the region row by row.
+    pub fn borders(&self) -> Vec<Border> {
+        let mut edges: Vec<(usize, Corner, Corner)> = Vec::new();
+
+        for (y, row) in self.labels.iter().enumerate() {
+            for (x, &label) in row.iter().enumerate() {
+                let label = match label {
+                    Some(label) => label,
+                    None => continue,
+                };
+
+                let (x, y) = (x as i64, y as i64);
+                let sides = [
+                    ((0, -1), (x, y), (x + 1, y)),
+                    ((1, 0), (x + 1, y), (x + 1, y + 1)),
+                    ((0, 1), (x + 1, y + 1), (x, y + 1)),
+                    ((-1, 0), (x, y + 1), (x, y)),
+                ];
+
+                for ((dx, dy), from, to) in sides {
+                    match self.label_at(x + dx, y + dy) {
+                        Some(other) if other != Some(label) => edges.push((label, from, to)),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let mut outgoing: HashMap<(usize, Corner), Vec<usize>> = HashMap::new();
+        let mut incoming: HashMap<(usize, Corner), usize> = HashMap::new();
+
+        for (index, &(label, from, to)) in edges.iter().enumerate() {
+            outgoing.entry((label, from)).or_default().push(index);
+            *incoming.entry((label, to)).or_default() += 1;
+        }
+
+        let mut used = vec![false; edges.len()];
+        let mut borders = Vec::new();
+
+        // Open lines start where nothing leads in, so trace those first,
+        // then the loops left over.
+        let open = (0..edges.len())
+            .filter(|&index| !incoming.contains_key(&(edges[index].0, edges[index].1)));
+        let starts: Vec<_> = open.chain(0..edges.len()).collect();
+
+        for start in starts {
+            if used[start] {
+                continue;
+            }
+
+            let label = edges[start].0;
+            let mut corners = vec![edges[start].1];
+            let mut edge = start;
+
+            loop {
+                used[edge] = true;
+
+                let (_, from, to) = edges[edge];
+                corners.push(to);
+
+                let direction = (to.0 - from.0, to.1 - from.1);
+                let next = outgoing
+                    .get(&(label, to))
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .filter(|&next| !used[next])
+                    .min_by_key(|&next| turn(direction, edges[next].1, edges[next].2));
+
+                match next {
+                    Some(next) => edge = next,
+                    None => break,
+                }
+            }
+
+            borders.push(Border {
+                area: label,
+                closed: corners.first() == corners.last(),
+                points: simplify(&corners)
+                    .into_iter()
+                    .map(|(x, y)| (x as f64 - 0.5, y as f64 - 0.5))
+                    .collect(),
+            });
+        }
+
+        borders
+    }
+
+    /// Returns the label of a cell, or `None` if it is outside the region.
+    fn label_at(&self, x: i64, y: i64) -> Option<Option<usize>> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+
+        self.labels
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+    }
+
+    /// Returns the id of the area with the most cells, if there are any
+    /// areas. Ties go to the lowest id.
+    pub fn largest(&self) -> Option<usize> {
+        (0..self.areas.len()).max_by_key(|&label| (self.areas[label], usize::MAX - label))
+    }
+}
+
+/// Ranks the edge leading on from a corner: right turns first, then
+/// straight on, then left turns. Where an area touches itself at a corner,
+/// turning right keeps to the cells on one side of it.
+fn turn((dx, dy): (i64, i64), from: Corner, to: Corner) -> u8 {
+    let next = (to.0 - from.0, to.1 - from.1);
+
+    if next == (-dy, dx) {
+        0
+    } else if next == (dx, dy) {
+        1
+    } else {
+        2
+    }
+}
+
+/// Removes the corners a line passes straight through.
+fn simplify(corners: &[Corner]) -> Vec<Corner> {
+    let mut points = Vec::with_capacity(corners.len());
+
+    for (index, &corner) in corners.iter().enumerate() {
+        if index > 0 && index + 1 < corners.len() {
+            let (previous, next) = (corners[index - 1], corners[index + 1]);
+            let before = (corner.0 - previous.0, corner.1 - previous.1);
+            let after = (next.0 - corner.0, next.1 - corner.1);
+
+            if before == after {
+                continue;
+            }
+        }
+
+        points.push(corner);
+    }
+
+    // A loop may start partway along a straight line.
+    let len = points.len();
+    if len > 3 && points[0] == points[len - 1] {
+        let (start, second, last) = (points[0], points[1], points[len - 2]);
+
+        let after = ((second.0 - start.0).signum(), (second.1 - start.1).signum());
+        let before = ((start.0 - last.0).signum(), (start.1 - last.1).signum());
+
+        if before == after {
+            points.remove(0);
+            points[len - 2] = points[0];
+        }
+    }
+
+    points
+}
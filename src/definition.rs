@@ -0,0 +1,828 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/definition.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! World definitions as plain data.
+//!
+//! A `World` holds boxed noise maps, so it can't be saved or sent anywhere
+//! itself. A `WorldDef` describes the same world as data: named noise maps
+//! built from noise sources, and tiles whose constraints refer to the maps
+//! by name. With the `serde` feature enabled every type here can be
+//! serialized, along with generated chunks and the world's properties.
+//!
+//! ```
+//! # use std::collections::BTreeMap;
+//! # use worldgen::definition::{ConstraintDef, NoiseDef, NoiseMapDef, TileDef, WorldDef};
+//! # use worldgen::noisemap::{Amplitude, Seed, Step};
+//! # use worldgen::version::GENERATION_VERSION;
+//! # use worldgen::world::Size;
+//! # use worldgen::world::tile::ConstraintType;
+//! let mut maps = BTreeMap::new();
+//! maps.insert(
+//!     "elevation".to_string(),
+//!     NoiseMapDef::Noise {
+//!         noise: NoiseDef::Perlin(Default::default()),
+//!         seed: Seed::of("elevation"),
+//!         step: Step::of(0.05, 0.05),
+//!         amplitude: Amplitude::of(1.0),
+//!     },
+//! );
+//!
+//! let definition = WorldDef {
+//!     size: Size::of(20, 10),
+//!     seed: Seed::of("island"),
+//!     maps,
+//!     tiles: vec![
+//!         TileDef::new('~').when(ConstraintDef::Threshold {
+//!             map: "elevation".to_string(),
+//!             constraint: ConstraintType::LT(0.0),
+//!         }),
+//!         TileDef::new(','),
+//!     ],
+//!     smoothing: None,
+//!     version: GENERATION_VERSION,
+//! };
+//!
+//! let world = definition.build().unwrap();
+//! let tiles = world.generate(0, 0).unwrap();
+//! ```
+//!
+//! Each named map is built once and shared by every constraint using it,
+//! so it is only generated once per chunk.
+//!
+//! With the `ron` or `toml` features enabled, `load_ron` and `load_toml`
+//! build a world straight from a document, so a world can be tweaked
+//! without recompiling. Seeds in a document can be written either as
+//! integers or as text, which is hashed as by `Seed::of`.
+//!
+//! A `PipelineDef` describes a `WorldPipeline` the same way, with named
+//! stages that are either noise maps or terrain passes over other stages.
+//!
+//! A definition records the `GENERATION_VERSION` it was written for, and
+//! won't build with a different version of this crate, as the world would
+//! no longer generate the same tiles. Documents that leave the version out
+//! are taken to be written for the current version.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::noise::coherent::CoherentNoise;
+use crate::noise::fault::FaultNoise;
+use crate::noise::octaved::OctavedNoise;
+use crate::noise::NoiseProvider;
+use crate::noisemap::{
+    next_id, Amplitude, Modifier, NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size,
+    Step,
+};
+use crate::pipeline::{PipelineError, WorldPipeline};
+use crate::terrain::FlowMap;
+use crate::version::{self, VersionMismatch};
+#[cfg(feature = "rhai")]
+use crate::world::tile::ScriptError;
+use crate::world::tile::{Constraint, ConstraintType};
+use crate::world::{SmoothingRule, Tile, World};
+
+/// A noise source.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NoiseDef {
+    /// A single octave of coherent noise.
+    Coherent,
+
+    /// Perlin noise, with its octave properties.
+    Perlin(OctavedNoise<CoherentNoise>),
+
+    /// Fault formation noise.
+    Fault(FaultNoise),
+}
+
+/// A noise map.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NoiseMapDef {
+    /// A `NoiseMap` of a noise source.
+    Noise {
+        noise: NoiseDef,
+        #[cfg_attr(feature = "serde", serde(default))]
+        seed: Seed,
+        step: Step,
+        #[cfg_attr(feature = "serde", serde(default))]
+        amplitude: Amplitude,
+    },
+
+    /// A combination of noise maps, each with an integer weight, like
+    /// `nm1 + nm2 * 5`. The values are normalised by the total weight.
+    Sum(Vec<(i64, NoiseMapDef)>),
+
+    /// A noise map with a modifier applied to its values, like `nm / 2.0`.
+    Modified {
+        map: Box<NoiseMapDef>,
+        modifier: Modifier,
+    },
+}
+
+/// A constraint, referring to noise maps by name.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConstraintDef {
+    /// See `Constraint::new`.
+    Threshold {
+        map: String,
+        constraint: ConstraintType,
+    },
+
+    /// See `Constraint::difference`.
+    Difference {
+        a: String,
+        b: String,
+        constraint: ConstraintType,
+    },
+
+    /// See `Constraint::chance_with_seed`. Without a seed, positions are
+    /// chosen as by `Constraint::chance`.
+    Chance {
+        probability: f64,
+        #[cfg_attr(feature = "serde", serde(default))]
+        seed: Option<Seed>,
+    },
+
+    /// See `Constraint::script`, with the variables' noise maps given by
+    /// name. Requires the `rhai` feature.
+    #[cfg(feature = "rhai")]
+    Script { source: String, maps: Vec<String> },
+
+    /// See `Constraint::any`.
+    Any(Vec<ConstraintDef>),
+
+    /// See `Constraint::all`.
+    All(Vec<ConstraintDef>),
+
+    /// See `Constraint::not`.
+    Not(Box<ConstraintDef>),
+}
+
+/// A tile.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileDef<T> {
+    pub value: T,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub constraints: Vec<ConstraintDef>,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub weight: Option<f64>,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tags: Vec<String>,
+}
+
+impl<T> TileDef<T> {
+    /// Construct a tile represented by the given value, without any
+    /// constraints.
+    pub fn new(value: T) -> TileDef<T> {
+        TileDef {
+            value,
+            constraints: Vec::new(),
+            weight: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Adds a constraint to the tile.
+    pub fn when(self, constraint: ConstraintDef) -> TileDef<T> {
+        let mut new = self;
+        new.constraints.push(constraint);
+        new
+    }
+
+    /// Tags the tile. See `Tile::tag`.
+    pub fn tag(self, tag: &str) -> TileDef<T> {
+        let mut new = self;
+        new.tags.push(tag.to_string());
+        new
+    }
+
+    /// Sets the weight of the tile. See `Tile::weight`.
+    pub fn weight(self, weight: f64) -> TileDef<T> {
+        TileDef {
+            weight: Some(weight),
+            ..self
+        }
+    }
+}
+
+/// A world.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorldDef<T> {
+    pub size: Size,
+
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub seed: Seed,
+
+    /// The noise maps used by the tiles' constraints, by name.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub maps: BTreeMap<String, NoiseMapDef>,
+
+    pub tiles: Vec<TileDef<T>>,
+
+    /// See `World::smooth`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub smoothing: Option<(usize, SmoothingRule)>,
+
+    /// The version of the generation algorithms the world was defined
+    /// for. See the `version` module.
+    #[cfg_attr(feature = "serde", serde(default = "current_version"))]
+    pub version: u32,
+}
+
+/// A stage of a pipeline, referring to other stages by name.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StageDef {
+    /// The values of a noise map.
+    Noise(NoiseMapDef),
+
+    /// The accumulated flow of water over the named stage, read as heights.
+    /// See `FlowMap`.
+    Flow(String),
+}
+
+/// A pipeline.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PipelineDef {
+    pub size: Size,
+
+    /// The time of year, between 0 and 1. See `WorldPipeline::time`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub time: f64,
+
+    pub stages: BTreeMap<String, StageDef>,
+
+    /// The version of the generation algorithms the pipeline was defined
+    /// for. See the `version` module.
+    #[cfg_attr(feature = "serde", serde(default = "current_version"))]
+    pub version: u32,
+}
+
+#[cfg(feature = "serde")]
+fn current_version() -> u32 {
+    version::GENERATION_VERSION
+}
+
+/// The error returned when a definition can't be built.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DefinitionError {
+    /// A constraint refers to a noise map that isn't defined.
+    UnknownMap(String),
+
+    /// A constraint script doesn't compile.
+    #[cfg(feature = "rhai")]
+    Script(ScriptError),
+
+    /// The definition was written for another version of the generation
+    /// algorithms.
+    Version(VersionMismatch),
+
+    /// The stages of a pipeline don't fit together.
+    Pipeline(PipelineError),
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DefinitionError::UnknownMap(ref name) => write!(f, "no noise map named {:?}", name),
+
+            #[cfg(feature = "rhai")]
+            DefinitionError::Script(ref err) => write!(f, "{}", err),
+
+            DefinitionError::Version(ref err) => write!(f, "{}", err),
+
+            DefinitionError::Pipeline(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for DefinitionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            #[cfg(feature = "rhai")]
+            DefinitionError::Script(ref err) => Some(err),
+
+            DefinitionError::Version(ref err) => Some(err),
+
+            DefinitionError::Pipeline(ref err) => Some(err),
+
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "rhai")]
+impl From<ScriptError> for DefinitionError {
+    fn from(err: ScriptError) -> DefinitionError {
+        DefinitionError::Script(err)
+    }
+}
+
+impl From<VersionMismatch> for DefinitionError {
+    fn from(err: VersionMismatch) -> DefinitionError {
+        DefinitionError::Version(err)
+    }
+}
+
+impl From<PipelineError> for DefinitionError {
+    fn from(err: PipelineError) -> DefinitionError {
+        DefinitionError::Pipeline(err)
+    }
+}
+
+/// The error returned when a definition can't be loaded from a document.
+#[cfg(any(feature = "ron", feature = "toml"))]
+#[derive(Debug)]
+pub enum LoadError {
+    /// The RON document isn't a valid definition.
+    #[cfg(feature = "ron")]
+    Ron(ron::error::SpannedError),
+
+    /// The TOML document isn't a valid definition.
+    #[cfg(feature = "toml")]
+    Toml(toml::de::Error),
+
+    /// The definition was read, but the world couldn't be built from it.
+    Definition(DefinitionError),
+}
+
+#[cfg(any(feature = "ron", feature = "toml"))]
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "ron")]
+            LoadError::Ron(ref err) => write!(f, "invalid RON definition: {}", err),
+            #[cfg(feature = "toml")]
+            LoadError::Toml(ref err) => write!(f, "invalid TOML definition: {}", err),
+            LoadError::Definition(ref err) => err.fmt(f),
+        }
+    }
+}
+
+#[cfg(any(feature = "ron", feature = "toml"))]
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            #[cfg(feature = "ron")]
+            LoadError::Ron(ref err) => Some(err),
+            #[cfg(feature = "toml")]
+            LoadError::Toml(ref err) => Some(err),
+            LoadError::Definition(ref err) => Some(err),
+        }
+    }
+}
+
+#[cfg(feature = "ron")]
+impl From<ron::error::SpannedError> for LoadError {
+    fn from(err: ron::error::SpannedError) -> LoadError {
+        LoadError::Ron(err)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl From<toml::de::Error> for LoadError {
+    fn from(err: toml::de::Error) -> LoadError {
+        LoadError::Toml(err)
+    }
+}
+
+#[cfg(any(feature = "ron", feature = "toml"))]
+impl From<DefinitionError> for LoadError {
+    fn from(err: DefinitionError) -> LoadError {
+        LoadError::Definition(err)
+    }
+}
+
+/// The noise maps of a definition, once built.
+type Maps = BTreeMap<String, Arc<dyn NoiseMapGeneratorBase>>;
+
+impl NoiseDef {
+    fn build(self, seed: Seed, step: Step, amplitude: Amplitude) -> Box<dyn NoiseMapGeneratorBase> {
+        match self {
+            NoiseDef::Coherent => noise_map(CoherentNoise, seed, step, amplitude),
+            NoiseDef::Perlin(noise) => noise_map(noise, seed, step, amplitude),
+            NoiseDef::Fault(noise) => noise_map(noise, seed, step, amplitude),
+        }
+    }
+}
+
+fn noise_map<N: NoiseProvider + 'static>(
+    noise: N,
+    seed: Seed,
+    step: Step,
+    amplitude: Amplitude,
+) -> Box<dyn NoiseMapGeneratorBase> {
+    Box::new(NoiseMap::new(noise).set(seed).set(step).set(amplitude))
+}
+
+impl NoiseMapDef {
+    /// Builds the noise map.
+    pub fn build(&self) -> Box<dyn NoiseMapGeneratorBase> {
+        match *self {
+            NoiseMapDef::Noise {
+                noise,
+                seed,
+                step,
+                amplitude,
+            } => noise.build(seed, step, amplitude),
+
+            NoiseMapDef::Sum(ref maps) => Box::new(DefinedMap {
+                kind: DefinedKind::Sum(
+                    maps.iter()
+                        .map(|&(weight, ref map)| (weight, map.build()))
+                        .collect(),
+                ),
+                id: next_id(),
+            }),
+
+            NoiseMapDef::Modified { ref map, modifier } => Box::new(DefinedMap {
+                kind: DefinedKind::Modified(map.build(), modifier),
+                id: next_id(),
+            }),
+        }
+    }
+}
+
+impl ConstraintDef {
+    fn build(&self, maps: &Maps) -> Result<Constraint, DefinitionError> {
+        let map = |name: &String| -> Result<Box<dyn NoiseMapGeneratorBase>, DefinitionError> {
+            maps.get(name)
+                .map(|map| Box::new(map.clone()) as Box<dyn NoiseMapGeneratorBase>)
+                .ok_or_else(|| DefinitionError::UnknownMap(name.clone()))
+        };
+
+        let all = |constraints: &[ConstraintDef]| {
+            constraints
+                .iter()
+                .map(|constraint| constraint.build(maps))
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        Ok(match *self {
+            ConstraintDef::Threshold {
+                map: ref name,
+                constraint,
+            } => Constraint::new(map(name)?, constraint),
+
+            ConstraintDef::Difference {
+                ref a,
+                ref b,
+                constraint,
+            } => Constraint::difference(map(a)?, map(b)?, constraint),
+
+            ConstraintDef::Chance {
+                probability,
+                seed: Some(seed),
+            } => Constraint::chance_with_seed(probability, seed),
+
+            ConstraintDef::Chance {
+                probability,
+                seed: None,
+            } => Constraint::chance(probability),
+
+            #[cfg(feature = "rhai")]
+            ConstraintDef::Script {
+                ref source,
+                ref maps,
+            } => {
+                let maps = maps
+                    .iter()
+                    .map(|name| Ok((name.as_str(), map(name)?.into())))
+                    .collect::<Result<Vec<_>, DefinitionError>>()?;
+
+                Constraint::script_with_maps(source, maps)?
+            }
+
+            ConstraintDef::Any(ref constraints) => Constraint::any(all(constraints)?),
+            ConstraintDef::All(ref constraints) => Constraint::all(all(constraints)?),
+            ConstraintDef::Not(ref constraint) => constraint.build(maps)?.not(),
+        })
+    }
+}
+
+impl<T: Clone> TileDef<T> {
+    fn build(&self, maps: &Maps) -> Result<Tile<T>, DefinitionError> {
+        let mut tile = Tile::new(self.value.clone());
+
+        for constraint in self.constraints.iter() {
+            tile = tile.when(constraint.build(maps)?);
+        }
+
+        for tag in self.tags.iter() {
+            tile = tile.tag(tag);
+        }
+
+        Ok(match self.weight {
+            Some(weight) => tile.weight(weight),
+            None => tile,
+        })
+    }
+}
+
+impl<T: Clone> WorldDef<T> {
+    /// Builds the world.
+    ///
+    /// Fails if the definition was written for another version of the
+    /// generation algorithms, or a constraint refers to a noise map that
+    /// isn't defined.
+    pub fn build(&self) -> Result<World<T>, DefinitionError> {
+        version::check(self.version)?;
+
+        let maps: Maps = self
+            .maps
+            .iter()
+            .map(|(name, map)| (name.clone(), Arc::from(map.build())))
+            .collect();
+
+        let mut world = World::new().set(self.size).set(self.seed);
+
+        for tile in self.tiles.iter() {
+            world = world.add(tile.build(&maps)?);
+        }
+
+        Ok(match self.smoothing {
+            Some((iterations, rule)) => world.smooth(iterations, rule),
+            None => world,
+        })
+    }
+}
+
+#[cfg(feature = "ron")]
+impl<T: serde::de::DeserializeOwned> WorldDef<T> {
+    /// Reads a definition from a RON document.
+    pub fn from_ron(document: &str) -> Result<WorldDef<T>, LoadError> {
+        Ok(ron::from_str(document)?)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl<T: serde::de::DeserializeOwned> WorldDef<T> {
+    /// Reads a definition from a TOML document.
+    pub fn from_toml(document: &str) -> Result<WorldDef<T>, LoadError> {
+        Ok(toml::from_str(document)?)
+    }
+}
+
+impl PipelineDef {
+    /// Builds the pipeline.
+    ///
+    /// Fails if the definition was written for another version of the
+    /// generation algorithms, or a stage reads a stage that isn't defined.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::collections::BTreeMap;
+    /// # use worldgen::definition::{NoiseDef, NoiseMapDef, PipelineDef, StageDef};
+    /// # use worldgen::noisemap::{Amplitude, Seed, Step};
+    /// # use worldgen::version::GENERATION_VERSION;
+    /// # use worldgen::world::Size;
+    /// let mut stages = BTreeMap::new();
+    /// stages.insert(
+    ///     "height".to_string(),
+    ///     StageDef::Noise(NoiseMapDef::Noise {
+    ///         noise: NoiseDef::Perlin(Default::default()),
+    ///         seed: Seed::of("height"),
+    ///         step: Step::of(0.05, 0.05),
+    ///         amplitude: Amplitude::of(1.0),
+    ///     }),
+    /// );
+    /// stages.insert("flow".to_string(), StageDef::Flow("height".to_string()));
+    ///
+    /// let definition = PipelineDef {
+    ///     size: Size::of(16, 16),
+    ///     time: 0.0,
+    ///     stages,
+    ///     version: GENERATION_VERSION,
+    /// };
+    ///
+    /// let layers = definition.build().unwrap().generate((0, 0)..=(1, 1)).unwrap();
+    /// assert_eq!(layers.get("flow").unwrap().len(), 32);
+    /// ```
+    pub fn build(&self) -> Result<WorldPipeline, DefinitionError> {
+        version::check(self.version)?;
+
+        let mut pipeline = WorldPipeline::new(self.size).time(self.time);
+
+        for (name, stage) in self.stages.iter() {
+            pipeline = match *stage {
+                StageDef::Noise(ref map) => pipeline.noise(name, map.build()),
+
+                StageDef::Flow(ref heights) => {
+                    let input = heights.clone();
+                    pipeline.stage(name, &[heights], move |inputs| {
+                        Ok(FlowMap::new(inputs.get(&input)?).accumulation().clone())
+                    })
+                }
+            };
+        }
+
+        pipeline.validate()?;
+        Ok(pipeline)
+    }
+}
+
+#[cfg(feature = "ron")]
+impl PipelineDef {
+    /// Reads a definition from a RON document.
+    pub fn from_ron(document: &str) -> Result<PipelineDef, LoadError> {
+        Ok(ron::from_str(document)?)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl PipelineDef {
+    /// Reads a definition from a TOML document.
+    pub fn from_toml(document: &str) -> Result<PipelineDef, LoadError> {
+        Ok(toml::from_str(document)?)
+    }
+}
+
+/// Builds a world from a RON document describing a `WorldDef`.
+///
+/// Requires the `ron` feature.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::definition::load_ron;
+/// # use worldgen::world::World;
+/// let world: World<char> = load_ron(r#"(
+///     size: (w: 20, h: 10),
+///     seed: "island",
+///     maps: {
+///         "elevation": Noise(
+///             noise: Perlin((octaves: 4)),
+///             seed: "elevation",
+///             step: (x: 0.05, y: 0.05),
+///         ),
+///     },
+///     tiles: [
+///         (value: '~', constraints: [Threshold(map: "elevation", constraint: LT(0.0))]),
+///         (value: ','),
+///     ],
+/// )"#).unwrap();
+///
+/// let tiles = world.generate(0, 0).unwrap();
+/// ```
+#[cfg(feature = "ron")]
+pub fn load_ron<T>(document: &str) -> Result<World<T>, LoadError>
+where
+    T: Clone + serde::de::DeserializeOwned,
+{
+    Ok(WorldDef::from_ron(document)?.build()?)
+}
+
+/// Builds a world from a TOML document describing a `WorldDef`.
+///
+/// Requires the `toml` feature.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::definition::load_toml;
+/// # use worldgen::world::World;
+/// let world: World<char> = load_toml(r#"
+///     seed = "island"
+///     size = { w = 20, h = 10 }
+///
+///     [maps.elevation.Noise]
+///     noise = { Perlin = { octaves = 4 } }
+///     seed = "elevation"
+///     step = { x = 0.05, y = 0.05 }
+///
+///     [[tiles]]
+///     value = "~"
+///     constraints = [{ Threshold = { map = "elevation", constraint = { LT = 0.0 } } }]
+///
+///     [[tiles]]
+///     value = ","
+/// "#).unwrap();
+///
+/// let tiles = world.generate(0, 0).unwrap();
+/// ```
+#[cfg(feature = "toml")]
+pub fn load_toml<T>(document: &str) -> Result<World<T>, LoadError>
+where
+    T: Clone + serde::de::DeserializeOwned,
+{
+    Ok(WorldDef::from_toml(document)?.build()?)
+}
+
+/// A noise map combining or modifying other noise maps, whose types are
+/// only known once the definition is built.
+struct DefinedMap {
+    kind: DefinedKind,
+    id: u64,
+}
+
+enum DefinedKind {
+    Sum(Vec<(i64, Box<dyn NoiseMapGeneratorBase>)>),
+    Modified(Box<dyn NoiseMapGeneratorBase>, Modifier),
+}
+
+impl DefinedMap {
+    fn total_weight(maps: &[(i64, Box<dyn NoiseMapGeneratorBase>)]) -> f64 {
+        maps.iter().map(|&(weight, _)| weight).sum::<i64>() as f64
+    }
+}
+
+impl NoiseMapGeneratorBase for DefinedMap {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        self.generate_sized_chunk(Size::default(), x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        match self.kind {
+            DefinedKind::Sum(ref maps) => {
+                let total = DefinedMap::total_weight(maps);
+                let mut chunk = vec![vec![0.0; size.w as usize]; size.h as usize];
+
+                for &(weight, ref map) in maps.iter() {
+                    let values = map.generate_sized_chunk(size, x, y);
+
+                    for (row, values) in chunk.iter_mut().zip(values.iter()) {
+                        for (value, other) in row.iter_mut().zip(values.iter()) {
+                            *value += other * weight as f64 / total;
+                        }
+                    }
+                }
+
+                chunk
+            }
+
+            DefinedKind::Modified(ref map, modifier) => map
+                .generate_sized_chunk(size, x, y)
+                .into_iter()
+                .map(|row| row.into_iter().map(|value| modifier.apply(value)).collect())
+                .collect(),
+        }
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        match self.kind {
+            DefinedKind::Sum(ref maps) => {
+                let total = DefinedMap::total_weight(maps);
+
+                maps.iter()
+                    .map(|&(weight, ref map)| map.get_value(world_x, world_y) * weight as f64)
+                    .sum::<f64>()
+                    / total
+            }
+
+            DefinedKind::Modified(ref map, modifier) => {
+                modifier.apply(map.get_value(world_x, world_y))
+            }
+        }
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        match self.kind {
+            DefinedKind::Sum(ref maps) => {
+                let total = DefinedMap::total_weight(maps);
+
+                maps.iter()
+                    .map(|&(weight, ref map)| {
+                        let (low, high) = map.bounds();
+                        let (low, high) = (low * weight as f64, high * weight as f64);
+                        (low.min(high) / total, low.max(high) / total)
+                    })
+                    .fold((0.0, 0.0), |(low, high), (l, h)| (low + l, high + h))
+            }
+
+            DefinedKind::Modified(ref map, modifier) => {
+                let (low, high) = map.bounds();
+                modifier.bounds(low, high)
+            }
+        }
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
@@ -0,0 +1,294 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/dungeon.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Dungeon interiors built by binary space partitioning.
+//!
+//! The area of the dungeon is split in two, again and again, into a tree of
+//! smaller areas. Each area at the bottom of the tree gets a room, and the
+//! two halves of every split are joined by a corridor, so every room can be
+//! reached from every other.
+//!
+//! Dungeons are generated as chunks of tiles, like the output of a `World`:
+//!
+//! ```
+//! # use worldgen::dungeon::Dungeon;
+//! # use worldgen::world::{Seed, Size};
+//! let dungeon = Dungeon::new(Size::of(60, 30)).seed(Seed::of("crypt"));
+//!
+//! let tiles = dungeon.generate('#', '.');
+//! assert_eq!(tiles.len(), 30);
+//!
+//! let layout = dungeon.layout();
+//! let room = layout.rooms[0];
+//! assert_eq!(tiles[room.y as usize][room.x as usize], '.');
+//! ```
+
+use crate::noisemap::{Seed, Size};
+use crate::rng::{hash, hash_all};
+use crate::world::Chunk;
+
+/// A rectangular room within a dungeon.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Room {
+    pub x: i64,
+    pub y: i64,
+    pub w: i64,
+    pub h: i64,
+}
+
+impl Room {
+    /// Returns the position of the centre of the room.
+    pub fn centre(&self) -> (i64, i64) {
+        (self.x + self.w / 2, self.y + self.h / 2)
+    }
+}
+
+/// A corridor within a dungeon, as a polyline of the positions where it
+/// starts, turns, and ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Corridor {
+    pub points: Vec<(i64, i64)>,
+}
+
+/// The rooms and corridors making up a dungeon.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub rooms: Vec<Room>,
+    pub corridors: Vec<Corridor>,
+}
+
+/// A dungeon generator.
+///
+/// The defaults are:
+///
+/// ```text
+/// min leaf = 8
+/// min room = 3
+/// max depth = 6
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Dungeon {
+    size: Size,
+    seed: Seed,
+
+    min_leaf: i64,
+    min_room: i64,
+    max_depth: u32,
+}
+
+impl Dungeon {
+    /// Construct a dungeon generator for the given area.
+    pub fn new(size: Size) -> Dungeon {
+        Dungeon {
+            size,
+            seed: Default::default(),
+
+            min_leaf: 8,
+            min_room: 3,
+            max_depth: 6,
+        }
+    }
+
+    /// Set the seed of the dungeon.
+    pub fn seed(self, seed: Seed) -> Dungeon {
+        Dungeon { seed, ..self }
+    }
+
+    /// Set the smallest width or height an area can be split into.
+    pub fn min_leaf(self, min_leaf: i64) -> Dungeon {
+        Dungeon {
+            min_leaf: min_leaf.max(3),
+            ..self
+        }
+    }
+
+    /// Set the smallest width or height of a room, space permitting.
+    pub fn min_room(self, min_room: i64) -> Dungeon {
+        Dungeon {
+            min_room: min_room.max(1),
+            ..self
+        }
+    }
+
+    /// Set the number of times the area can be split in a row.
+    pub fn max_depth(self, max_depth: u32) -> Dungeon {
+        Dungeon { max_depth, ..self }
+    }
+
+    /// Returns the rooms and corridors of the dungeon.
+    pub fn layout(&self) -> Layout {
+        let mut layout = Layout {
+            rooms: Vec::new(),
+            corridors: Vec::new(),
+        };
+
+        let area = Room {
+            x: 0,
+            y: 0,
+            w: self.size.w,
+            h: self.size.h,
+        };
+
+        self.split(area, 1, 0, &mut layout);
+        layout
+    }
+
+    /// Generates the dungeon as a chunk of tiles, using the given tiles for
+    /// walls and for the floors of rooms and corridors.
+    ///
+    /// A dungeon needs at least 3×3 cells to fit a room inside its walls,
+    /// so a smaller dungeon is all walls.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::dungeon::Dungeon;
+    /// # use worldgen::world::Size;
+    /// let tiny = Dungeon::new(Size::of(2, 1));
+    /// assert!(tiny.layout().rooms.is_empty());
+    /// assert_eq!(tiny.generate('#', '.'), vec![vec!['#', '#']]);
+    ///
+    /// assert!(Dungeon::new(Size::of(0, 0)).generate('#', '.').is_empty());
+    /// assert_eq!(Dungeon::new(Size::of(3, 3)).generate('#', '.')[1][1], '.');
+    /// ```
+    pub fn generate<T: Clone>(&self, wall: T, floor: T) -> Chunk<T> {
+        let (w, h) = (self.size.w.max(0) as usize, self.size.h.max(0) as usize);
+        let mut tiles = vec![vec![wall; w]; h];
+        let layout = self.layout();
+
+        for room in layout.rooms.iter() {
+            for y in room.y..room.y + room.h {
+                for x in room.x..room.x + room.w {
+                    tiles[y as usize][x as usize] = floor.clone();
+                }
+            }
+        }
+
+        for corridor in layout.corridors.iter() {
+            for pair in corridor.points.windows(2) {
+                let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+
+                for y in y0.min(y1)..=y0.max(y1) {
+                    for x in x0.min(x1)..=x0.max(x1) {
+                        tiles[y as usize][x as usize] = floor.clone();
+                    }
+                }
+            }
+        }
+
+        tiles
+    }
+
+    /// Returns a random value for the given node of the tree.
+    fn random(&self, node: u64, value: u64) -> u64 {
+        hash(hash_all(self.seed.value, &[node]), value)
+    }
+
+    /// Returns a random value in the given inclusive range.
+    fn between(&self, node: u64, value: u64, low: i64, high: i64) -> i64 {
+        if high <= low {
+            low
+        } else {
+            low + (self.random(node, value) % (high - low + 1) as u64) as i64
+        }
+    }
+
+    /// Splits an area, adding its rooms and corridors to the layout, and
+    /// returns the index of the first of its rooms (the number of rooms
+    /// before it, if it has none).
+    fn split(&self, area: Room, node: u64, depth: u32, layout: &mut Layout) -> usize {
+        let can_split_x = area.w >= 2 * self.min_leaf;
+        let can_split_y = area.h >= 2 * self.min_leaf;
+
+        if depth >= self.max_depth || !(can_split_x || can_split_y) {
+            return self.room(area, node, layout);
+        }
+
+        let vertical = match (can_split_x, can_split_y) {
+            (true, false) => true,
+            (false, true) => false,
+            _ if area.w != area.h => area.w > area.h,
+            _ => self.random(node, 0) & 1 == 0,
+        };
+
+        let (first, second) = if vertical {
+            let at = self.between(node, 1, self.min_leaf, area.w - self.min_leaf);
+            (
+                Room { w: at, ..area },
+                Room {
+                    x: area.x + at,
+                    w: area.w - at,
+                    ..area
+                },
+            )
+        } else {
+            let at = self.between(node, 1, self.min_leaf, area.h - self.min_leaf);
+            (
+                Room { h: at, ..area },
+                Room {
+                    y: area.y + at,
+                    h: area.h - at,
+                    ..area
+                },
+            )
+        };
+
+        let first_rooms = self.split(first, node * 2, depth + 1, layout);
+        let second_rooms = self.split(second, node * 2 + 1, depth + 1, layout);
+
+        // Join a room from each half, if both have one
+        let (end, rooms) = (second_rooms as i64, layout.rooms.len() as i64);
+        if first_rooms as i64 == end || end == rooms {
+            return first_rooms;
+        }
+
+        let a = layout.rooms[self.between(node, 2, first_rooms as i64, end - 1) as usize].centre();
+        let b = layout.rooms[self.between(node, 3, end, rooms - 1) as usize].centre();
+
+        let corner = if self.random(node, 4) & 1 == 0 {
+            (b.0, a.1)
+        } else {
+            (a.0, b.1)
+        };
+
+        layout.corridors.push(Corridor {
+            points: vec![a, corner, b],
+        });
+
+        first_rooms
+    }
+
+    /// Places a room within an area, leaving a wall around it, and returns
+    /// its index. An area without space inside its walls is left without a
+    /// room, and the index the room would have had is returned.
+    fn room(&self, area: Room, node: u64, layout: &mut Layout) -> usize {
+        if area.w < 3 || area.h < 3 {
+            return layout.rooms.len();
+        }
+
+        let (max_w, max_h) = (area.w - 2, area.h - 2);
+
+        let w = self.between(node, 5, self.min_room.min(max_w), max_w);
+        let h = self.between(node, 6, self.min_room.min(max_h), max_h);
+        let x = self.between(node, 7, area.x + 1, area.x + area.w - 1 - w);
+        let y = self.between(node, 8, area.y + 1, area.y + area.h - 1 - h);
+
+        layout.rooms.push(Room { x, y, w, h });
+        layout.rooms.len() - 1
+    }
+}
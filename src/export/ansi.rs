@@ -0,0 +1,205 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/ansi.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Rendering of chunks in the terminal, with ANSI colours.
+//!
+//! Each tile is drawn as a `Style`: a glyph, with optional foreground and
+//! background colours, chosen by a given function. This is enough to
+//! prototype a roguelike on top of a world.
+//!
+//! ```
+//! # use worldgen::export::ansi::{render_ansi, Colour, Style};
+//! # use worldgen::world::{Size, Tile, World};
+//! let world = World::new()
+//!     .set(Size::of(8, 4))
+//!     .add(Tile::new('~'));
+//!
+//! let text = render_ansi(&world.generate(0, 0).unwrap(), |&tile| match tile {
+//!     '~' => Style::new('~').fg(Colour::Cyan).bg(Colour::Blue),
+//!     tile => Style::new(tile),
+//! });
+//!
+//! print!("{}", text);
+//! assert!(text.starts_with("\x1b[0;36;44m~~~~~~~~\x1b[0m\n"));
+//! ```
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::world::Chunk;
+
+/// A terminal colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Colour {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+
+    /// A colour from the 256 colour palette, where the first 16 are the
+    /// normal and bright versions of the basic colours.
+    Fixed(u8),
+
+    /// A true colour, for terminals that support them.
+    Rgb(u8, u8, u8),
+}
+
+impl Colour {
+    /// Appends the SGR parameters for the colour, as a foreground colour
+    /// or a background colour.
+    fn write_codes(self, codes: &mut String, background: bool) {
+        let offset = if background { 10 } else { 0 };
+
+        let basic = match self {
+            Colour::Black => 0,
+            Colour::Red => 1,
+            Colour::Green => 2,
+            Colour::Yellow => 3,
+            Colour::Blue => 4,
+            Colour::Magenta => 5,
+            Colour::Cyan => 6,
+            Colour::White => 7,
+
+            Colour::Fixed(index) => {
+                write!(codes, ";{};5;{}", 38 + offset, index).unwrap();
+                return;
+            }
+
+            Colour::Rgb(r, g, b) => {
+                write!(codes, ";{};2;{};{};{}", 38 + offset, r, g, b).unwrap();
+                return;
+            }
+        };
+
+        write!(codes, ";{}", 30 + offset + basic).unwrap();
+    }
+}
+
+/// How a tile is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style {
+    glyph: char,
+
+    foreground: Option<Colour>,
+    background: Option<Colour>,
+    bold: bool,
+}
+
+impl Style {
+    /// Construct a style drawing the given glyph, in the terminal's
+    /// default colours.
+    pub fn new(glyph: char) -> Style {
+        Style {
+            glyph,
+
+            foreground: None,
+            background: None,
+            bold: false,
+        }
+    }
+
+    /// Set the colour of the glyph.
+    pub fn fg(self, colour: Colour) -> Style {
+        Style {
+            foreground: Some(colour),
+            ..self
+        }
+    }
+
+    /// Set the colour behind the glyph.
+    pub fn bg(self, colour: Colour) -> Style {
+        Style {
+            background: Some(colour),
+            ..self
+        }
+    }
+
+    /// Draw the glyph in bold.
+    pub fn bold(self) -> Style {
+        Style { bold: true, ..self }
+    }
+
+    /// Returns the escape sequence switching to the style, resetting any
+    /// earlier style first.
+    fn escape(&self) -> String {
+        let mut codes = String::from("\x1b[0");
+
+        if self.bold {
+            codes.push_str(";1");
+        }
+
+        if let Some(colour) = self.foreground {
+            colour.write_codes(&mut codes, false);
+        }
+
+        if let Some(colour) = self.background {
+            colour.write_codes(&mut codes, true);
+        }
+
+        codes.push('m');
+        codes
+    }
+}
+
+/// Renders a chunk as text for the terminal, with a line for each row,
+/// each tile drawn in the style given by the function.
+///
+/// Escape sequences are only written where the style changes, and the
+/// style is reset at the end of each line.
+pub fn render_ansi<T, F>(chunk: &Chunk<T>, style: F) -> String
+where
+    F: Fn(&T) -> Style,
+{
+    let mut text = String::new();
+
+    for row in chunk.iter() {
+        let mut current: Option<Style> = None;
+
+        for tile in row.iter() {
+            let style = style(tile);
+            let changed = current.is_none_or(|current| {
+                (current.foreground, current.background, current.bold)
+                    != (style.foreground, style.background, style.bold)
+            });
+
+            if changed {
+                text.push_str(&style.escape());
+                current = Some(style);
+            }
+
+            text.push(style.glyph);
+        }
+
+        text.push_str("\x1b[0m\n");
+    }
+
+    text
+}
+
+/// Renders a chunk with `render_ansi`, writing it to the given writer.
+pub fn write_ansi<T, F, W>(chunk: &Chunk<T>, style: F, mut writer: W) -> io::Result<()>
+where
+    F: Fn(&T) -> Style,
+    W: Write,
+{
+    writer.write_all(render_ansi(chunk, style).as_bytes())
+}
@@ -0,0 +1,414 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/anvil.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Export of voxel columns as Minecraft region files, with the `anvil`
+//! feature enabled.
+//!
+//! Minecraft stores its worlds in the Anvil format: region files of 32 by
+//! 32 chunks, each chunk 16 by 16 blocks across. An `AnvilRegion` writes the
+//! columns of blocks built by the `voxel` module, along with their biomes,
+//! as one of these files, so a generated world can be opened in the game or
+//! in any of the viewers that read its saves:
+//!
+//! ```
+//! # use worldgen::export::anvil::AnvilRegion;
+//! # use worldgen::voxel::{biome_columns, Columns};
+//! let columns = Columns::new("minecraft:stone")
+//!     .layer("minecraft:grass_block", 1)
+//!     .layer("minecraft:dirt", 3)
+//!     .water("minecraft:water", 64)
+//!     .scale(16.0)
+//!     .base(64.0);
+//!
+//! // One chunk of gentle slopes, all plains
+//! let heights: Vec<Vec<f64>> = (0..16)
+//!     .map(|z| (0..16).map(|x| (x + z) as f64 / 16.0 - 1.0).collect())
+//!     .collect();
+//! let biomes = vec![vec!["minecraft:plains"; 16]; 16];
+//!
+//! let chunk = biome_columns(&heights, &biomes, |_| &columns);
+//!
+//! let region = AnvilRegion::new(0, 0);
+//! assert_eq!(region.file_name(), "r.0.0.mca");
+//!
+//! let mut file = Vec::new();
+//! region.write(&chunk, |&block| block, |&biome| biome, &mut file).unwrap();
+//! assert_eq!(file.len() % 4096, 0);
+//! ```
+//!
+//! Blocks and biomes are given by their namespaced ids, such as
+//! `minecraft:stone`, and the bottom of each column is the bottom of the
+//! world. Chunks are written in the format used since Minecraft 1.18, and
+//! only for the parts of the region the columns cover completely. They are
+//! marked as unlit, so the game lights them when they are loaded.
+
+use std::io::{self, Write};
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::voxel::Column;
+use crate::world::Chunk;
+
+/// The number of chunks along each side of a region.
+const REGION: usize = 32;
+
+/// The number of blocks along each side of a chunk, and the height of each
+/// of its sections.
+const CHUNK: usize = 16;
+
+/// The size in bytes of the sectors a region file is divided into.
+const SECTOR: usize = 4096;
+
+/// The id of zlib compression for the chunks of a region file.
+const ZLIB: u8 = 2;
+
+/// The settings for writing a Minecraft region file.
+///
+/// The defaults are:
+///
+/// ```text
+/// min_y = -64
+/// height = 384
+/// data_version = 3953 (Minecraft 1.21)
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AnvilRegion {
+    x: i32,
+    z: i32,
+
+    min_y: i32,
+    height: u32,
+    data_version: i32,
+}
+
+/// A value in Minecraft's NBT format.
+enum Tag {
+    Byte(i8),
+    Int(i32),
+    Long(i64),
+    String(String),
+    List(Vec<Tag>),
+    Compound(Vec<(&'static str, Tag)>),
+    LongArray(Vec<i64>),
+}
+
+impl AnvilRegion {
+    /// Construct the settings for the region at the given region
+    /// coordinates, which holds the blocks from `x * 512` and `z * 512`.
+    pub fn new(x: i32, z: i32) -> AnvilRegion {
+        AnvilRegion {
+            x,
+            z,
+
+            min_y: -64,
+            height: 384,
+            data_version: 3953,
+        }
+    }
+
+    /// Set the height of the bottom of the world, where columns start.
+    /// This is rounded down to a multiple of 16.
+    pub fn min_y(self, min_y: i32) -> AnvilRegion {
+        AnvilRegion {
+            min_y: min_y.div_euclid(CHUNK as i32) * CHUNK as i32,
+            ..self
+        }
+    }
+
+    /// Set the height of the world in blocks, above which columns are cut
+    /// off. This is rounded up to a multiple of 16.
+    pub fn height(self, height: u32) -> AnvilRegion {
+        AnvilRegion {
+            height: height.div_ceil(CHUNK as u32) * CHUNK as u32,
+            ..self
+        }
+    }
+
+    /// Set the data version written in each chunk, which tells the game
+    /// which version of Minecraft the chunk is for.
+    pub fn data_version(self, data_version: i32) -> AnvilRegion {
+        AnvilRegion {
+            data_version,
+            ..self
+        }
+    }
+
+    /// Returns the name of the region's file, as the game expects to find
+    /// it in a world's `region` directory.
+    pub fn file_name(&self) -> String {
+        format!("r.{}.{}.mca", self.x, self.z)
+    }
+
+    /// Writes the region file for a grid of columns and their biomes, such
+    /// as from `biome_columns`, starting at the region's north west corner
+    /// with rows running southwards.
+    ///
+    /// The given functions return the id of each block and of each biome.
+    /// Biomes are sampled every four blocks, as the game stores them.
+    pub fn write<M, B, F, G, W>(
+        &self,
+        columns: &Chunk<(B, Column<M>)>,
+        block: F,
+        biome: G,
+        mut writer: W,
+    ) -> io::Result<()>
+    where
+        F: Fn(&M) -> &str,
+        G: Fn(&B) -> &str,
+        W: Write,
+    {
+        let mut header = vec![0; SECTOR * 2];
+        let mut sectors = Vec::new();
+
+        for chunk_z in 0..REGION {
+            for chunk_x in 0..REGION {
+                let covered = (0..CHUNK).all(|z| {
+                    columns
+                        .get(chunk_z * CHUNK + z)
+                        .is_some_and(|row| row.len() >= (chunk_x + 1) * CHUNK)
+                });
+
+                if !covered {
+                    continue;
+                }
+
+                let tag = self.chunk(columns, chunk_x, chunk_z, &block, &biome);
+
+                let mut nbt = Vec::new();
+                write_root(&tag, &mut nbt);
+
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&nbt)?;
+                let compressed = encoder.finish()?;
+
+                let mut payload = Vec::with_capacity(compressed.len() + 5);
+                payload.extend_from_slice(&(compressed.len() as u32 + 1).to_be_bytes());
+                payload.push(ZLIB);
+                payload.extend_from_slice(&compressed);
+                payload.resize(payload.len().div_ceil(SECTOR) * SECTOR, 0);
+
+                let (offset, count) = (2 + sectors.len() / SECTOR, payload.len() / SECTOR);
+                if count > u8::MAX as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "chunk is too large for a region file",
+                    ));
+                }
+
+                let location = ((offset as u32) << 8 | count as u32).to_be_bytes();
+                let index = (chunk_z * REGION + chunk_x) * 4;
+                header[index..index + 4].copy_from_slice(&location);
+
+                sectors.extend_from_slice(&payload);
+            }
+        }
+
+        writer.write_all(&header)?;
+        writer.write_all(&sectors)
+    }
+
+    /// Returns the NBT of a single chunk of the region.
+    fn chunk<M, B, F, G>(
+        &self,
+        columns: &Chunk<(B, Column<M>)>,
+        chunk_x: usize,
+        chunk_z: usize,
+        block: &F,
+        biome: &G,
+    ) -> Tag
+    where
+        F: Fn(&M) -> &str,
+        G: Fn(&B) -> &str,
+    {
+        let column = |x: usize, z: usize| &columns[chunk_z * CHUNK + z][chunk_x * CHUNK + x];
+
+        // The block at each level of each column, up to the top of the
+        // world, with air above the ground.
+        let levels: Vec<Vec<&str>> = (0..CHUNK * CHUNK)
+            .map(|index| {
+                let (_, runs) = column(index % CHUNK, index / CHUNK);
+
+                runs.iter()
+                    .flat_map(|(material, count)| {
+                        std::iter::repeat_n(block(material), *count as usize)
+                    })
+                    .take(self.height as usize)
+                    .collect()
+            })
+            .collect();
+
+        let sections = (0..self.height as usize / CHUNK)
+            .map(|section| {
+                let mut blocks = Palette::default();
+                let mut indices = Vec::with_capacity(CHUNK * CHUNK * CHUNK);
+
+                // Blocks are ordered by height, then z, then x.
+                for y in 0..CHUNK {
+                    for column in levels.iter() {
+                        let name = column.get(section * CHUNK + y).copied();
+                        indices.push(blocks.index(name.unwrap_or("minecraft:air")));
+                    }
+                }
+
+                // Biomes are stored for cells of 4 by 4 by 4 blocks, and
+                // are the same all the way up each column.
+                let mut biomes = Palette::default();
+                let cells: Vec<u64> = (0..4 * 4 * 4)
+                    .map(|cell| {
+                        let (x, z) = (cell % 4 * 4, cell / 4 % 4 * 4);
+                        biomes.index(biome(&column(x, z).0))
+                    })
+                    .collect();
+
+                let block_states = blocks.tag(&indices, 4, |name| {
+                    Tag::Compound(vec![("Name", Tag::String(name))])
+                });
+                let biomes = biomes.tag(&cells, 1, Tag::String);
+
+                Tag::Compound(vec![
+                    (
+                        "Y",
+                        Tag::Byte((self.min_y / CHUNK as i32 + section as i32) as i8),
+                    ),
+                    ("block_states", block_states),
+                    ("biomes", biomes),
+                ])
+            })
+            .collect();
+
+        Tag::Compound(vec![
+            ("DataVersion", Tag::Int(self.data_version)),
+            ("xPos", Tag::Int(self.x * REGION as i32 + chunk_x as i32)),
+            ("zPos", Tag::Int(self.z * REGION as i32 + chunk_z as i32)),
+            ("yPos", Tag::Int(self.min_y / CHUNK as i32)),
+            ("Status", Tag::String("minecraft:full".to_string())),
+            ("LastUpdate", Tag::Long(0)),
+            ("isLightOn", Tag::Byte(0)),
+            ("sections", Tag::List(sections)),
+        ])
+    }
+}
+
+/// The distinct names used in a section, in the order they first appear.
+#[derive(Default)]
+struct Palette {
+    names: Vec<String>,
+}
+
+impl Palette {
+    /// Returns the index of a name, adding it if it is new.
+    fn index(&mut self, name: &str) -> u64 {
+        match self.names.iter().position(|other| other == name) {
+            Some(index) => index as u64,
+            None => {
+                self.names.push(name.to_string());
+                self.names.len() as u64 - 1
+            }
+        }
+    }
+
+    /// Returns the palette and packed indices as a tag, with each index
+    /// taking at least the given number of bits.
+    fn tag<F>(self, indices: &[u64], min_bits: u32, entry: F) -> Tag
+    where
+        F: Fn(String) -> Tag,
+    {
+        let single = self.names.len() == 1;
+        let bits = (u64::BITS - (self.names.len() as u64 - 1).leading_zeros()).max(min_bits);
+        let palette = Tag::List(self.names.into_iter().map(entry).collect());
+
+        if single {
+            return Tag::Compound(vec![("palette", palette)]);
+        }
+
+        // Indices don't span two longs, so any bits left over are unused.
+        let data = indices
+            .chunks((u64::BITS / bits) as usize)
+            .map(|indices| {
+                indices.iter().enumerate().fold(0, |long, (index, &value)| {
+                    long | value << (index as u32 * bits)
+                }) as i64
+            })
+            .collect();
+
+        Tag::Compound(vec![("palette", palette), ("data", Tag::LongArray(data))])
+    }
+}
+
+impl Tag {
+    fn id(&self) -> u8 {
+        match *self {
+            Tag::Byte(_) => 1,
+            Tag::Int(_) => 3,
+            Tag::Long(_) => 4,
+            Tag::String(_) => 8,
+            Tag::List(_) => 9,
+            Tag::Compound(_) => 10,
+            Tag::LongArray(_) => 12,
+        }
+    }
+
+    /// Appends the tag's value, without its id or name.
+    fn write(&self, out: &mut Vec<u8>) {
+        match *self {
+            Tag::Byte(value) => out.push(value as u8),
+            Tag::Int(value) => out.extend_from_slice(&value.to_be_bytes()),
+            Tag::Long(value) => out.extend_from_slice(&value.to_be_bytes()),
+            Tag::String(ref value) => write_string(value, out),
+            Tag::List(ref tags) => {
+                out.push(tags.first().map_or(0, Tag::id));
+                out.extend_from_slice(&(tags.len() as i32).to_be_bytes());
+
+                for tag in tags.iter() {
+                    tag.write(out);
+                }
+            }
+            Tag::Compound(ref tags) => {
+                for (name, tag) in tags.iter() {
+                    out.push(tag.id());
+                    write_string(name, out);
+                    tag.write(out);
+                }
+
+                out.push(0);
+            }
+            Tag::LongArray(ref values) => {
+                out.extend_from_slice(&(values.len() as i32).to_be_bytes());
+
+                for value in values.iter() {
+                    out.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+        }
+    }
+}
+
+/// Appends the unnamed root tag of an NBT document.
+fn write_root(tag: &Tag, out: &mut Vec<u8>) {
+    out.push(tag.id());
+    write_string("", out);
+    tag.write(out);
+}
+
+/// Appends a string, prefixed by its length. Ids are ASCII, so this is the
+/// same as the modified UTF-8 NBT uses.
+fn write_string(value: &str, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    out.extend_from_slice(value.as_bytes());
+}
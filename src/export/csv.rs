@@ -0,0 +1,69 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/csv.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Export of chunks as comma-separated values.
+
+use std::fmt::Display;
+
+use crate::world::Chunk;
+
+/// Writes a chunk as CSV, with a line for each row.
+///
+/// This works for both noise map chunks and world chunks, with each value
+/// written as it is displayed. Values containing commas, quotes or line
+/// breaks are quoted.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::export::chunk_to_csv;
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size};
+/// # use worldgen::world::{Size as WorldSize, Tile, World};
+/// let nm = NoiseMap::new(PerlinNoise::new()).set(Size::of(4, 4));
+/// let values = chunk_to_csv(&nm.generate_chunk(0, 0));
+/// assert_eq!(values.lines().count(), 4);
+///
+/// let world = World::new()
+///     .set(WorldSize::of(3, 2))
+///     .add(Tile::new(','));
+///
+/// let tiles = chunk_to_csv(&world.generate(0, 0).unwrap());
+/// assert_eq!(tiles, "\",\",\",\",\",\"\n\",\",\",\",\",\"\n");
+/// ```
+pub fn chunk_to_csv<T: Display>(chunk: &Chunk<T>) -> String {
+    let mut csv = String::new();
+
+    for row in chunk.iter() {
+        let fields: Vec<_> = row.iter().map(|value| field(&value.to_string())).collect();
+
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// Quotes a field if it needs to be.
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
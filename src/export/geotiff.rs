@@ -0,0 +1,198 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/geotiff.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Export of noise as GeoTIFF rasters, with the `geotiff` feature enabled.
+//!
+//! A GeoTIFF is a TIFF image with tags placing it on the Earth, which GIS
+//! tools such as QGIS read as a raster layer. The values are written as a
+//! single band of 32 bit floats, so they keep their meaning (as elevations,
+//! for example) rather than being turned into colours. A `GeoReference`
+//! gives the coordinate reference system, by its EPSG code, along with the
+//! position of the top left corner of the raster and the size of each cell
+//! in the units of that system:
+//!
+//! ```
+//! # use std::io::Cursor;
+//! # use worldgen::export::geotiff::{write_noise_geotiff, GeoReference};
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Size, Step};
+//! let nm = NoiseMap::new(PerlinNoise::new())
+//!     .set(Size::of(32, 32))
+//!     .set(Step::of(0.02, 0.02));
+//!
+//! // Cells of 30 metres, in the UTM zone covering the Alps
+//! let reference = GeoReference::projected(32632)
+//!     .origin(300000.0, 5200000.0)
+//!     .cell_size(30.0, 30.0);
+//!
+//! let mut tiff = Cursor::new(Vec::new());
+//! write_noise_geotiff(&nm, (0, 0)..=(1, 1), &reference, &mut tiff).unwrap();
+//! ```
+//!
+//! The first row of values is the northern edge of the raster, as in any
+//! image, so the origin is its north west corner.
+
+use std::io::{Seek, Write};
+use std::ops::RangeInclusive;
+
+use tiff::encoder::{colortype, TiffEncoder};
+use tiff::tags::Tag;
+use tiff::TiffError;
+
+use crate::noisemap::NoiseMapGeneratorBase;
+use crate::world::Chunk;
+
+/// The GeoKey giving the kind of coordinate reference system.
+const MODEL_TYPE: u16 = 1024;
+
+/// The GeoKey giving whether values cover their cells or sit on points.
+const RASTER_TYPE: u16 = 1025;
+
+/// The GeoKey giving the EPSG code of a geographic system.
+const GEOGRAPHIC_TYPE: u16 = 2048;
+
+/// The GeoKey giving the EPSG code of a projected system.
+const PROJECTED_TYPE: u16 = 3072;
+
+/// A coordinate reference system, by its EPSG code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Crs {
+    /// A system of latitude and longitude, such as 4326 for WGS 84.
+    Geographic(u16),
+
+    /// A projected system, such as 3857 for Web Mercator or one of the UTM
+    /// zones.
+    Projected(u16),
+}
+
+/// Where a raster sits on the Earth.
+///
+/// The defaults are:
+///
+/// ```text
+/// origin = (0.0, 0.0)
+/// cell_size = (1.0, 1.0)
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GeoReference {
+    crs: Crs,
+
+    origin: (f64, f64),
+    cell_size: (f64, f64),
+}
+
+impl GeoReference {
+    /// Construct a reference in the given coordinate reference system.
+    pub fn new(crs: Crs) -> GeoReference {
+        GeoReference {
+            crs,
+
+            origin: (0.0, 0.0),
+            cell_size: (1.0, 1.0),
+        }
+    }
+
+    /// Construct a reference in the geographic system with the given EPSG
+    /// code, in degrees.
+    pub fn geographic(epsg: u16) -> GeoReference {
+        GeoReference::new(Crs::Geographic(epsg))
+    }
+
+    /// Construct a reference in the projected system with the given EPSG
+    /// code, usually in metres.
+    pub fn projected(epsg: u16) -> GeoReference {
+        GeoReference::new(Crs::Projected(epsg))
+    }
+
+    /// Set the coordinates of the top left corner of the raster, as the
+    /// easting (or longitude) and northing (or latitude).
+    pub fn origin(self, x: f64, y: f64) -> GeoReference {
+        GeoReference {
+            origin: (x, y),
+            ..self
+        }
+    }
+
+    /// Set the width and height of each cell. Rows run southwards from the
+    /// origin, so the height is given as a positive distance.
+    pub fn cell_size(self, width: f64, height: f64) -> GeoReference {
+        GeoReference {
+            cell_size: (width, height),
+            ..self
+        }
+    }
+
+    /// Returns the GeoKey directory describing the system.
+    fn keys(&self) -> Vec<u16> {
+        let (model, (key, epsg)) = match self.crs {
+            Crs::Geographic(epsg) => (2, (GEOGRAPHIC_TYPE, epsg)),
+            Crs::Projected(epsg) => (1, (PROJECTED_TYPE, epsg)),
+        };
+
+        // The version of the directory and the number of keys, then each
+        // key with its value held in place, in order of the keys
+        let mut directory = vec![1, 1, 0, 3];
+        for (key, value) in [(MODEL_TYPE, model), (RASTER_TYPE, 1), (key, epsg)] {
+            directory.extend_from_slice(&[key, 0, 1, value]);
+        }
+
+        directory
+    }
+}
+
+/// Writes a chunk of values as a GeoTIFF.
+///
+/// Rows longer than the shortest row are cut down to its length.
+pub fn write_geotiff<W: Write + Seek>(
+    values: &Chunk<f64>,
+    reference: &GeoReference,
+    writer: W,
+) -> Result<(), TiffError> {
+    let width = values.iter().map(Vec::len).min().unwrap_or(0);
+    let data: Vec<f32> = values
+        .iter()
+        .flat_map(|row| row[..width].iter().map(|&value| value as f32))
+        .collect();
+
+    let mut tiff = TiffEncoder::new(writer)?;
+    let mut image = tiff.new_image::<colortype::Gray32Float>(width as u32, values.len() as u32)?;
+
+    let (x, y) = reference.origin;
+    let (cell_width, cell_height) = reference.cell_size;
+
+    let directory = image.encoder();
+    directory.write_tag(Tag::ModelPixelScaleTag, &[cell_width, cell_height, 0.0][..])?;
+    directory.write_tag(Tag::ModelTiepointTag, &[0.0, 0.0, 0.0, x, y, 0.0][..])?;
+    directory.write_tag(Tag::GeoKeyDirectoryTag, &reference.keys()[..])?;
+
+    image.write_data(&data)
+}
+
+/// Writes the chunks of a noise map between two chunk coordinates
+/// (inclusive) as a GeoTIFF.
+///
+/// See the module documentation for an example.
+pub fn write_noise_geotiff<W: Write + Seek>(
+    nm: &dyn NoiseMapGeneratorBase,
+    region: RangeInclusive<(i64, i64)>,
+    reference: &GeoReference,
+    writer: W,
+) -> Result<(), TiffError> {
+    let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+    write_geotiff(&nm.generate_area(x0, y0, x1, y1), reference, writer)
+}
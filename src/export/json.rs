@@ -0,0 +1,163 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/json.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Export of chunks as JSON.
+
+use std::fmt::Write;
+
+use crate::world::Chunk;
+
+/// A value that can be written as JSON.
+///
+/// This is implemented for numbers, booleans, characters and strings, and
+/// for options of them, which are written as `null` when empty. Non-finite
+/// numbers are also written as `null`, as JSON can't represent them.
+pub trait JsonValue {
+    /// Appends the value to a JSON document.
+    fn write_json(&self, json: &mut String);
+}
+
+/// Writes a chunk as a JSON array of rows, each an array of values.
+///
+/// This works for both noise map chunks and world chunks, as long as the
+/// tiles are values JSON can represent. Tiles of other types can be mapped
+/// to one first.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::export::chunk_to_json;
+/// # use worldgen::world::{Size, Tile, World};
+/// let world = World::new()
+///     .set(Size::of(3, 2))
+///     .add(Tile::new('~'));
+///
+/// let json = chunk_to_json(&world.generate(0, 0).unwrap());
+/// assert_eq!(json, r#"[["~","~","~"],["~","~","~"]]"#);
+///
+/// assert_eq!(chunk_to_json(&vec![vec![0.5, -1.0]]), "[[0.5,-1]]");
+/// ```
+pub fn chunk_to_json<T: JsonValue>(chunk: &Chunk<T>) -> String {
+    let mut json = String::from("[");
+
+    for (y, row) in chunk.iter().enumerate() {
+        if y > 0 {
+            json.push(',');
+        }
+
+        json.push('[');
+
+        for (x, value) in row.iter().enumerate() {
+            if x > 0 {
+                json.push(',');
+            }
+
+            value.write_json(&mut json);
+        }
+
+        json.push(']');
+    }
+
+    json.push(']');
+    json
+}
+
+macro_rules! json_integer {
+    ($($t:ty),*) => {
+        $(
+            impl JsonValue for $t {
+                fn write_json(&self, json: &mut String) {
+                    write!(json, "{}", self).unwrap();
+                }
+            }
+        )*
+    };
+}
+
+json_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! json_float {
+    ($($t:ty),*) => {
+        $(
+            impl JsonValue for $t {
+                fn write_json(&self, json: &mut String) {
+                    if self.is_finite() {
+                        write!(json, "{}", self).unwrap();
+                    } else {
+                        json.push_str("null");
+                    }
+                }
+            }
+        )*
+    };
+}
+
+json_float!(f32, f64);
+
+impl JsonValue for bool {
+    fn write_json(&self, json: &mut String) {
+        json.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+impl JsonValue for str {
+    fn write_json(&self, json: &mut String) {
+        json.push('"');
+
+        for c in self.chars() {
+            match c {
+                '"' => json.push_str("\\\""),
+                '\\' => json.push_str("\\\\"),
+                '\n' => json.push_str("\\n"),
+                '\r' => json.push_str("\\r"),
+                '\t' => json.push_str("\\t"),
+                c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+                c => json.push(c),
+            }
+        }
+
+        json.push('"');
+    }
+}
+
+impl JsonValue for String {
+    fn write_json(&self, json: &mut String) {
+        self.as_str().write_json(json)
+    }
+}
+
+impl JsonValue for char {
+    fn write_json(&self, json: &mut String) {
+        self.encode_utf8(&mut [0; 4]).write_json(json)
+    }
+}
+
+impl<T: JsonValue + ?Sized> JsonValue for &T {
+    fn write_json(&self, json: &mut String) {
+        (**self).write_json(json)
+    }
+}
+
+impl<T: JsonValue> JsonValue for Option<T> {
+    fn write_json(&self, json: &mut String) {
+        match *self {
+            Some(ref value) => value.write_json(json),
+            None => json.push_str("null"),
+        }
+    }
+}
@@ -0,0 +1,354 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/legend.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Legends for rendered maps, as an image and a JSON manifest.
+//!
+//! A map rendered with a palette means little to someone who doesn't have
+//! the palette. A `Legend` lists each tile's name and colour, and can be
+//! written as JSON for tools, or, with the `image` feature enabled, drawn
+//! as an image of swatches and names to share beside the map:
+//!
+//! ```
+//! # use worldgen::export::legend::Legend;
+//! let legend = Legend::new()
+//!     .entry("Water", [30, 80, 200, 255])
+//!     .entry("Grass", [60, 170, 60, 255])
+//!     .map("world.png");
+//!
+//! assert_eq!(
+//!     legend.to_json(),
+//!     r##"{"map":"world.png","tiles":[{"name":"Water","colour":"#1e50c8"},{"name":"Grass","colour":"#3caa3c"}]}"##
+//! );
+//! ```
+//!
+//! Colours are written in the manifest as hex codes, with the alpha only
+//! when the colour isn't opaque. Names are drawn in a small built-in font
+//! of capital letters, digits and common punctuation, so lower case
+//! letters are drawn as capitals and other characters as question marks.
+
+use std::fmt::Write;
+
+#[cfg(feature = "image")]
+use std::fs;
+#[cfg(feature = "image")]
+use std::path::Path;
+
+#[cfg(feature = "image")]
+use image::{ImageError, Rgba, RgbaImage};
+
+use super::JsonValue;
+
+/// The width of each character of the built-in font, in pixels before
+/// scaling, including the space after it.
+#[cfg(feature = "image")]
+const ADVANCE: u32 = 6;
+
+/// The height of each character of the built-in font, in pixels before
+/// scaling.
+#[cfg(feature = "image")]
+const GLYPH_HEIGHT: u32 = 7;
+
+/// A list of tiles' names and colours, describing a rendered map.
+///
+/// The defaults are:
+///
+/// ```text
+/// map = none
+/// swatch_size = 16
+/// background = [255, 255, 255, 255]
+/// text_colour = [0, 0, 0, 255]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Legend {
+    entries: Vec<(String, [u8; 4])>,
+    map: Option<String>,
+
+    swatch_size: u32,
+    background: [u8; 4],
+    text_colour: [u8; 4],
+}
+
+impl Legend {
+    /// Construct a legend without any entries.
+    pub fn new() -> Legend {
+        Legend {
+            entries: Vec::new(),
+            map: None,
+
+            swatch_size: 16,
+            background: [255, 255, 255, 255],
+            text_colour: [0, 0, 0, 255],
+        }
+    }
+
+    /// Construct a legend from a list of tiles and a palette giving the
+    /// name and colour of each. Tiles with the same name as an earlier
+    /// tile are left out.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::export::legend::Legend;
+    /// # use worldgen::presets::Terrain;
+    /// let tiles = [Terrain::Water, Terrain::Beach, Terrain::Water];
+    ///
+    /// let legend = Legend::from_tiles(&tiles, |tile| {
+    ///     let colour = match *tile {
+    ///         Terrain::Water => [30, 80, 200, 255],
+    ///         _ => [230, 210, 150, 255],
+    ///     };
+    ///
+    ///     (format!("{:?}", tile), colour)
+    /// });
+    ///
+    /// assert_eq!(legend.len(), 2);
+    /// ```
+    pub fn from_tiles<'a, T, I, F>(tiles: I, palette: F) -> Legend
+    where
+        T: 'a,
+        I: IntoIterator<Item = &'a T>,
+        F: Fn(&T) -> (String, [u8; 4]),
+    {
+        tiles.into_iter().fold(Legend::new(), |legend, tile| {
+            let (name, colour) = palette(tile);
+            legend.entry(&name, colour)
+        })
+    }
+
+    /// Adds a tile to the legend, unless it already has one with the same
+    /// name.
+    pub fn entry(self, name: &str, colour: [u8; 4]) -> Legend {
+        let mut new = self;
+
+        if !new.entries.iter().any(|(other, _)| other == name) {
+            new.entries.push((name.to_string(), colour));
+        }
+
+        new
+    }
+
+    /// Set the file name of the map the legend describes, which is
+    /// recorded in the manifest.
+    pub fn map(self, map: &str) -> Legend {
+        Legend {
+            map: Some(map.to_string()),
+            ..self
+        }
+    }
+
+    /// Set the size in pixels of the square of colour drawn for each tile.
+    /// Names are drawn at a scale to match.
+    pub fn swatch_size(self, swatch_size: u32) -> Legend {
+        Legend {
+            swatch_size: swatch_size.max(1),
+            ..self
+        }
+    }
+
+    /// Set the colour behind the swatches and names.
+    pub fn background(self, background: [u8; 4]) -> Legend {
+        Legend { background, ..self }
+    }
+
+    /// Set the colour the names are drawn in.
+    pub fn text_colour(self, text_colour: [u8; 4]) -> Legend {
+        Legend {
+            text_colour,
+            ..self
+        }
+    }
+
+    /// Returns the number of tiles in the legend.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the legend has no tiles.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the legend as a JSON manifest.
+    pub fn to_json(&self) -> String {
+        self.manifest(None)
+    }
+
+    /// Draws the legend as an image, with a row for each tile holding its
+    /// swatch and name.
+    #[cfg(feature = "image")]
+    pub fn render(&self) -> RgbaImage {
+        let size = self.swatch_size;
+        let scale = (size / 8).max(1);
+        let padding = (size / 4).max(2);
+
+        let widest = self
+            .entries
+            .iter()
+            .map(|(name, _)| name.chars().count() as u32 * ADVANCE * scale)
+            .max()
+            .unwrap_or(0);
+
+        let mut image = RgbaImage::from_pixel(
+            padding * 3 + size + widest,
+            padding + self.entries.len() as u32 * (size + padding),
+            Rgba(self.background),
+        );
+
+        let mut fill = |left: u32, top: u32, width: u32, height: u32, colour: [u8; 4]| {
+            for y in top..top + height {
+                for x in left..left + width {
+                    image.put_pixel(x, y, Rgba(colour));
+                }
+            }
+        };
+
+        for (index, (name, colour)) in self.entries.iter().enumerate() {
+            let top = padding + index as u32 * (size + padding);
+            fill(padding, top, size, size, *colour);
+
+            let left = padding * 2 + size;
+            let text_top = top + size.saturating_sub(GLYPH_HEIGHT * scale) / 2;
+
+            for (column, c) in name.chars().enumerate() {
+                let glyph_left = left + column as u32 * ADVANCE * scale;
+
+                for (row, bits) in glyph(c).iter().enumerate() {
+                    for bit in 0..5 {
+                        if bits & (0x10 >> bit) != 0 {
+                            let (x, y) = (glyph_left + bit * scale, text_top + row as u32 * scale);
+                            fill(x, y, scale, scale, self.text_colour);
+                        }
+                    }
+                }
+            }
+        }
+
+        image
+    }
+
+    /// Saves the legend as a PNG image, with its manifest beside it in a
+    /// JSON file of the same name. The manifest records the image's file
+    /// name as well as the map's.
+    #[cfg(feature = "image")]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        let path = path.as_ref();
+        self.render().save(path)?;
+
+        let image = path.file_name().and_then(|name| name.to_str());
+        fs::write(path.with_extension("json"), self.manifest(image)).map_err(ImageError::IoError)
+    }
+
+    fn manifest(&self, image: Option<&str>) -> String {
+        let mut json = String::from("{");
+
+        if let Some(ref map) = self.map {
+            json.push_str("\"map\":");
+            map.write_json(&mut json);
+            json.push(',');
+        }
+
+        if let Some(image) = image {
+            json.push_str("\"image\":");
+            image.write_json(&mut json);
+            json.push(',');
+        }
+
+        json.push_str("\"tiles\":[");
+
+        for (index, (name, colour)) in self.entries.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+
+            json.push_str("{\"name\":");
+            name.write_json(&mut json);
+
+            let [r, g, b, a] = *colour;
+            write!(json, ",\"colour\":\"#{:02x}{:02x}{:02x}", r, g, b).unwrap();
+            if a != 255 {
+                write!(json, "{:02x}", a).unwrap();
+            }
+
+            json.push_str("\"}");
+        }
+
+        json.push_str("]}");
+        json
+    }
+}
+
+impl Default for Legend {
+    fn default() -> Legend {
+        Legend::new()
+    }
+}
+
+/// Returns the rows of a character of the built-in font, with the leftmost
+/// pixel of each row in the fifth bit.
+#[cfg(feature = "image")]
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x11, 0x1f, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '\'' => [0x0c, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        _ => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
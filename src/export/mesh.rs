@@ -0,0 +1,442 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/mesh.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Export of heightmaps as triangle meshes.
+//!
+//! A `TerrainMesh` turns a grid of heights into a mesh, with a vertex for
+//! each value and two triangles between each four, and writes it as a
+//! Wavefront OBJ file or a glTF file, for previewing terrain in a model
+//! viewer and for engines that take meshes rather than heightmaps:
+//!
+//! ```
+//! # use worldgen::export::mesh::TerrainMesh;
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Size, Step};
+//! let nm = NoiseMap::new(PerlinNoise::new())
+//!     .set(Size::of(16, 16))
+//!     .set(Step::of(0.05, 0.05));
+//!
+//! let mesh = TerrainMesh::from_noise(&nm, (0, 0)..=(1, 1))
+//!     .scale(8.0)
+//!     .palette(|height| if height < 0.0 { [40, 80, 200, 255] } else { [60, 160, 60, 255] });
+//!
+//! assert_eq!(mesh.vertex_count(), 32 * 32);
+//! assert_eq!(mesh.triangle_count(), 31 * 31 * 2);
+//!
+//! let mut obj = Vec::new();
+//! mesh.write_obj(&mut obj).unwrap();
+//!
+//! let mut gltf = Vec::new();
+//! mesh.write_gltf(&mut gltf).unwrap();
+//! ```
+//!
+//! The mesh is laid out with the y axis up, as both formats expect: the
+//! columns of the heights run along the x axis and the rows along the z
+//! axis. Smooth normals are written along with the positions, and colours
+//! for each vertex when a palette is given. OBJ files hold colours after the
+//! positions of their vertices, which most tools read, while glTF files
+//! embed all of their data, so the mesh is a single file.
+
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use crate::noisemap::NoiseMapGeneratorBase;
+use crate::world::Chunk;
+
+/// The glTF component type of 32 bit floats.
+const FLOAT: u32 = 5126;
+
+/// The glTF component type of unsigned bytes.
+const UNSIGNED_BYTE: u32 = 5121;
+
+/// The glTF component type of unsigned 32 bit integers.
+const UNSIGNED_INT: u32 = 5125;
+
+/// A mesh of terrain, built from a grid of heights.
+///
+/// The defaults are:
+///
+/// ```text
+/// scale = 1.0
+/// spacing = 1.0
+/// ```
+///
+/// and no colours.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainMesh {
+    heights: Chunk<f64>,
+
+    scale: f64,
+    spacing: f64,
+    colours: Option<Chunk<[u8; 4]>>,
+}
+
+impl TerrainMesh {
+    /// Construct a mesh of the given heights.
+    ///
+    /// Rows longer than the shortest row are cut down to its length.
+    pub fn new(heights: &Chunk<f64>) -> TerrainMesh {
+        let width = heights.iter().map(Vec::len).min().unwrap_or(0);
+
+        TerrainMesh {
+            heights: heights.iter().map(|row| row[..width].to_vec()).collect(),
+
+            scale: 1.0,
+            spacing: 1.0,
+            colours: None,
+        }
+    }
+
+    /// Construct a mesh of the chunks of a noise map between two chunk
+    /// coordinates (inclusive).
+    pub fn from_noise(
+        nm: &dyn NoiseMapGeneratorBase,
+        region: RangeInclusive<(i64, i64)>,
+    ) -> TerrainMesh {
+        let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+        TerrainMesh::new(&nm.generate_area(x0, y0, x1, y1))
+    }
+
+    /// Set the factor heights are multiplied by.
+    pub fn scale(self, scale: f64) -> TerrainMesh {
+        TerrainMesh { scale, ..self }
+    }
+
+    /// Set the distance between neighbouring vertices.
+    pub fn spacing(self, spacing: f64) -> TerrainMesh {
+        TerrainMesh { spacing, ..self }
+    }
+
+    /// Colour each vertex by its height (before it is scaled), with the
+    /// given palette of RGBA colours, such as `Gradient::colour_at`.
+    pub fn palette<F>(self, palette: F) -> TerrainMesh
+    where
+        F: Fn(f64) -> [u8; 4],
+    {
+        let colours = self
+            .heights
+            .iter()
+            .map(|row| row.iter().map(|&height| palette(height)).collect())
+            .collect();
+
+        TerrainMesh {
+            colours: Some(colours),
+            ..self
+        }
+    }
+
+    /// Colour each vertex by the tile at the same position of a chunk of
+    /// tiles, with the given palette of RGBA colours. Vertices without a
+    /// tile are white.
+    pub fn tile_palette<T, F>(self, tiles: &Chunk<T>, palette: F) -> TerrainMesh
+    where
+        F: Fn(&T) -> [u8; 4],
+    {
+        let colours = self
+            .heights
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                (0..row.len())
+                    .map(|x| {
+                        tiles
+                            .get(y)
+                            .and_then(|row| row.get(x))
+                            .map_or([255; 4], &palette)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        TerrainMesh {
+            colours: Some(colours),
+            ..self
+        }
+    }
+
+    /// Returns the number of vertices in the mesh.
+    pub fn vertex_count(&self) -> usize {
+        let (width, depth) = self.size();
+        width * depth
+    }
+
+    /// Returns the number of triangles in the mesh.
+    pub fn triangle_count(&self) -> usize {
+        let (width, depth) = self.size();
+        width.saturating_sub(1) * depth.saturating_sub(1) * 2
+    }
+
+    /// Writes the mesh as a Wavefront OBJ file.
+    pub fn write_obj<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let positions = self.positions();
+        let normals = self.normals();
+
+        writeln!(writer, "# worldgen terrain mesh")?;
+
+        for (index, [x, y, z]) in positions.iter().enumerate() {
+            write!(writer, "v {} {} {}", x, y, z)?;
+
+            if let Some(colour) = self.colour(index) {
+                let [r, g, b, _] = colour.map(|channel| channel as f32 / 255.0);
+                write!(writer, " {} {} {}", r, g, b)?;
+            }
+
+            writeln!(writer)?;
+        }
+
+        for [x, y, z] in normals.iter() {
+            writeln!(writer, "vn {} {} {}", x, y, z)?;
+        }
+
+        for triangle in self.indices().chunks(3) {
+            let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+            writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the mesh as a glTF file, with its data embedded.
+    pub fn write_gltf<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let positions = self.positions();
+        let normals = self.normals();
+        let indices = self.indices();
+
+        let mut buffer = Vec::new();
+        let mut views = Vec::new();
+        let mut accessors = Vec::new();
+
+        // The lowest and highest position, which glTF requires.
+        let (min, max) = positions.iter().fold(
+            ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]),
+            |(min, max), position| {
+                (
+                    [0, 1, 2].map(|axis| min[axis].min(position[axis])),
+                    [0, 1, 2].map(|axis| max[axis].max(position[axis])),
+                )
+            },
+        );
+
+        let bounds = if positions.is_empty() {
+            String::new()
+        } else {
+            format!(
+                r#","min":[{},{},{}],"max":[{},{},{}]"#,
+                min[0], min[1], min[2], max[0], max[1], max[2]
+            )
+        };
+
+        let start = buffer.len();
+        positions
+            .iter()
+            .flatten()
+            .for_each(|value| buffer.extend_from_slice(&value.to_le_bytes()));
+        views.push(view(start, buffer.len(), 34962));
+        accessors.push(accessor(0, FLOAT, positions.len(), "VEC3", &bounds));
+
+        let start = buffer.len();
+        normals
+            .iter()
+            .flatten()
+            .for_each(|value| buffer.extend_from_slice(&value.to_le_bytes()));
+        views.push(view(start, buffer.len(), 34962));
+        accessors.push(accessor(1, FLOAT, normals.len(), "VEC3", ""));
+
+        let mut attributes = String::from(r#""POSITION":0,"NORMAL":1"#);
+
+        if let Some(colours) = &self.colours {
+            let start = buffer.len();
+            colours
+                .iter()
+                .flatten()
+                .for_each(|colour| buffer.extend_from_slice(colour));
+            views.push(view(start, buffer.len(), 34962));
+            accessors.push(accessor(
+                views.len() - 1,
+                UNSIGNED_BYTE,
+                positions.len(),
+                "VEC4",
+                r#","normalized":true"#,
+            ));
+
+            attributes.push_str(&format!(r#","COLOR_0":{}"#, accessors.len() - 1));
+        }
+
+        let start = buffer.len();
+        indices
+            .iter()
+            .for_each(|index| buffer.extend_from_slice(&index.to_le_bytes()));
+        views.push(view(start, buffer.len(), 34963));
+        accessors.push(accessor(
+            views.len() - 1,
+            UNSIGNED_INT,
+            indices.len(),
+            "SCALAR",
+            "",
+        ));
+
+        write!(
+            writer,
+            concat!(
+                r#"{{"asset":{{"version":"2.0","generator":"worldgen"}},"#,
+                r#""scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0,"name":"terrain"}}],"#,
+                r#""meshes":[{{"primitives":[{{"attributes":{{{}}},"indices":{},"mode":4}}]}}],"#,
+                r#""buffers":[{{"byteLength":{},"uri":"data:application/octet-stream;base64,{}"}}],"#,
+                r#""bufferViews":[{}],"accessors":[{}]}}"#
+            ),
+            attributes,
+            accessors.len() - 1,
+            buffer.len(),
+            base64(&buffer),
+            views.join(","),
+            accessors.join(",")
+        )?;
+
+        writeln!(writer)
+    }
+
+    /// Returns the number of vertices across and down the mesh.
+    fn size(&self) -> (usize, usize) {
+        let width = self.heights.first().map_or(0, Vec::len);
+        (width, if width == 0 { 0 } else { self.heights.len() })
+    }
+
+    fn height(&self, x: usize, y: usize) -> f64 {
+        self.heights[y][x] * self.scale
+    }
+
+    fn colour(&self, index: usize) -> Option<[u8; 4]> {
+        let (width, _) = self.size();
+        let colours = self.colours.as_ref()?;
+
+        Some(colours[index / width][index % width])
+    }
+
+    fn positions(&self) -> Vec<[f32; 3]> {
+        let (width, depth) = self.size();
+
+        (0..depth)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                [
+                    (x as f64 * self.spacing) as f32,
+                    self.height(x, y) as f32,
+                    (y as f64 * self.spacing) as f32,
+                ]
+            })
+            .collect()
+    }
+
+    /// Returns the normal at each vertex, from the slope between the
+    /// vertices on either side of it.
+    fn normals(&self) -> Vec<[f32; 3]> {
+        let (width, depth) = self.size();
+
+        // The rate of change of the height along an axis, between the
+        // neighbours of a vertex, or itself at the edges.
+        let slope = |before: (usize, usize), after: (usize, usize)| {
+            let distance = (after.0 - before.0 + after.1 - before.1) as f64 * self.spacing;
+
+            if distance > 0.0 {
+                (self.height(after.0, after.1) - self.height(before.0, before.1)) / distance
+            } else {
+                0.0
+            }
+        };
+
+        (0..depth)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let dx = slope((x.saturating_sub(1), y), ((x + 1).min(width - 1), y));
+                let dz = slope((x, y.saturating_sub(1)), (x, (y + 1).min(depth - 1)));
+
+                let length = (dx * dx + 1.0 + dz * dz).sqrt();
+                [
+                    (-dx / length) as f32,
+                    (1.0 / length) as f32,
+                    (-dz / length) as f32,
+                ]
+            })
+            .collect()
+    }
+
+    /// Returns the vertices of each triangle, wound anticlockwise when
+    /// seen from above.
+    fn indices(&self) -> Vec<u32> {
+        let (width, depth) = self.size();
+        let mut indices = Vec::with_capacity(self.triangle_count() * 3);
+
+        for y in 0..depth.saturating_sub(1) {
+            for x in 0..width.saturating_sub(1) {
+                let top_left = (y * width + x) as u32;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + width as u32;
+                let bottom_right = bottom_left + 1;
+
+                indices.extend_from_slice(&[top_left, bottom_left, top_right]);
+                indices.extend_from_slice(&[top_right, bottom_left, bottom_right]);
+            }
+        }
+
+        indices
+    }
+}
+
+/// Returns a glTF buffer view of a range of the buffer.
+fn view(start: usize, end: usize, target: u32) -> String {
+    format!(
+        r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+        start,
+        end - start,
+        target
+    )
+}
+
+/// Returns a glTF accessor of a whole buffer view.
+fn accessor(view: usize, component: u32, count: usize, kind: &str, extra: &str) -> String {
+    format!(
+        r#"{{"bufferView":{},"componentType":{},"count":{},"type":"{}"{}}}"#,
+        view, component, count, kind, extra
+    )
+}
+
+/// Encodes bytes as base64, for a data URI.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for group in bytes.chunks(3) {
+        let value = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |value, (index, &byte)| {
+                value | (byte as u32) << (16 - 8 * index)
+            });
+
+        for index in 0..4 {
+            if index <= group.len() {
+                encoded.push(ALPHABET[(value >> (18 - 6 * index) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,72 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/mod.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Writing generated chunks in the formats of other tools.
+//!
+//! Each format has its own module:
+//!
+//! * `anvil` writes voxel columns as Minecraft region files, with the
+//!   `anvil` feature enabled.
+//! * `ansi` draws chunks in the terminal, in colour.
+//! * `csv` and `json` write single chunks, of noise or of tiles, for
+//!   spreadsheets and other tools.
+//! * `geotiff` writes noise as rasters for GIS tools, with the `geotiff`
+//!   feature enabled.
+//! * `legend` lists the names and colours of tiles beside rendered maps,
+//!   as an image and a JSON manifest.
+//! * `mesh` triangulates heightmaps into OBJ and glTF meshes.
+//! * `png` renders worlds and noise maps as images, with the `image`
+//!   feature enabled.
+//! * `raw` writes 16 bit RAW heightmaps for the terrain systems of game
+//!   engines.
+//! * `tmx` writes maps and tilesets for the Tiled editor.
+
+pub use self::csv::chunk_to_csv;
+pub use self::json::{chunk_to_json, JsonValue};
+
+pub mod ansi;
+#[cfg(feature = "anvil")]
+pub mod anvil;
+pub mod csv;
+#[cfg(feature = "geotiff")]
+pub mod geotiff;
+pub mod json;
+pub mod legend;
+pub mod mesh;
+#[cfg(feature = "image")]
+pub mod png;
+pub mod raw;
+pub mod tmx;
+
+/// Escapes the characters that can't appear in XML attribute values.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
@@ -0,0 +1,226 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/png.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Rendering of worlds and noise maps as PNG images.
+//!
+//! Worlds are rendered with a palette giving the colour of each tile, and
+//! noise maps with a `Gradient` of colours over their values, so previews
+//! of a whole map can be produced without an engine.
+//!
+//! ```no_run
+//! # use worldgen::export::png::{render_noise_png, Gradient};
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Size, Step};
+//! # use worldgen::world::{Tile, World};
+//! let nm = NoiseMap::new(PerlinNoise::new())
+//!     .set(Size::of(64, 64))
+//!     .set(Step::of(0.02, 0.02));
+//!
+//! let gradient = Gradient::new()
+//!     .stop(-1.0, [0, 0, 128, 255])
+//!     .stop(0.0, [240, 220, 160, 255])
+//!     .stop(1.0, [255, 255, 255, 255]);
+//!
+//! render_noise_png(&nm, "elevation.png", (-2, -2)..=(1, 1), &gradient).unwrap();
+//!
+//! let world = World::new()
+//!     .set(Size::of(64, 64))
+//!     .add(Tile::new('.'));
+//!
+//! world
+//!     .render_png("world.png", (-2, -2)..=(1, 1), |_| [40, 160, 40, 255])
+//!     .unwrap();
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use image::{ImageError, Rgba, RgbaImage};
+
+use crate::noisemap::NoiseMapGeneratorBase;
+use crate::world::{GenerateError, World};
+
+/// An RGBA colour.
+pub type Colour = [u8; 4];
+
+/// A gradient of colours, blending between stops at given values.
+///
+/// Values outside the stops take the colour of the nearest stop.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::export::png::Gradient;
+/// let gradient = Gradient::new()
+///     .stop(0.0, [0, 0, 0, 255])
+///     .stop(1.0, [200, 100, 0, 255]);
+///
+/// assert_eq!(gradient.colour_at(0.5), [100, 50, 0, 255]);
+/// assert_eq!(gradient.colour_at(2.0), [200, 100, 0, 255]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f64, Colour)>,
+}
+
+impl Gradient {
+    /// Construct a gradient without any stops, which is transparent.
+    pub fn new() -> Gradient {
+        Default::default()
+    }
+
+    /// Adds a stop to the gradient.
+    pub fn stop(self, value: f64, colour: Colour) -> Gradient {
+        let mut new = self;
+        let index = new.stops.partition_point(|&(other, _)| other <= value);
+        new.stops.insert(index, (value, colour));
+        new
+    }
+
+    /// Returns the colour of the gradient at a value.
+    pub fn colour_at(&self, value: f64) -> Colour {
+        let after = self.stops.partition_point(|&(other, _)| other <= value);
+
+        if after == 0 {
+            return self.stops.first().map_or([0; 4], |&(_, colour)| colour);
+        }
+
+        let (low, from) = self.stops[after - 1];
+        let (high, to) = match self.stops.get(after) {
+            Some(&stop) => stop,
+            None => return from,
+        };
+
+        let t = (value - low) / (high - low);
+        let mut colour = [0; 4];
+
+        for (channel, (&from, &to)) in colour.iter_mut().zip(from.iter().zip(to.iter())) {
+            *channel = (from as f64 + (to as f64 - from as f64) * t).round() as u8;
+        }
+
+        colour
+    }
+}
+
+/// The error returned when rendering an image fails.
+#[derive(Debug)]
+pub enum RenderError {
+    /// A chunk of the world could not be generated.
+    Generate(GenerateError),
+
+    /// The image could not be saved.
+    Image(ImageError),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RenderError::Generate(ref error) => write!(f, "failed to generate world: {}", error),
+            RenderError::Image(ref error) => write!(f, "failed to save image: {}", error),
+        }
+    }
+}
+
+impl Error for RenderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            RenderError::Generate(ref error) => Some(error),
+            RenderError::Image(ref error) => Some(error),
+        }
+    }
+}
+
+impl From<GenerateError> for RenderError {
+    fn from(error: GenerateError) -> RenderError {
+        RenderError::Generate(error)
+    }
+}
+
+impl From<ImageError> for RenderError {
+    fn from(error: ImageError) -> RenderError {
+        RenderError::Image(error)
+    }
+}
+
+impl<T: Clone + Send + Sync> World<T> {
+    /// Renders the chunks between two chunk coordinates (inclusive) as a
+    /// PNG image, with a pixel for each tile coloured by the palette.
+    ///
+    /// See the module documentation for an example.
+    pub fn render_png<P, F>(
+        &self,
+        path: P,
+        region: RangeInclusive<(i64, i64)>,
+        palette: F,
+    ) -> Result<(), RenderError>
+    where
+        P: AsRef<Path>,
+        F: Fn(&T) -> Colour,
+    {
+        let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+        let size = self.get_size();
+
+        let positions: Vec<_> = (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+            .collect();
+
+        let mut image = RgbaImage::new(
+            ((x1 - x0 + 1).max(0) * size.w) as u32,
+            ((y1 - y0 + 1).max(0) * size.h) as u32,
+        );
+
+        for (&(x, y), chunk) in positions.iter().zip(self.par_generate_chunks(&positions)) {
+            let (left, top) = ((x - x0) * size.w, (y - y0) * size.h);
+
+            for (j, row) in chunk?.iter().enumerate() {
+                for (i, tile) in row.iter().enumerate() {
+                    let (px, py) = (left as u32 + i as u32, top as u32 + j as u32);
+                    image.put_pixel(px, py, Rgba(palette(tile)));
+                }
+            }
+        }
+
+        image.save(path)?;
+        Ok(())
+    }
+}
+
+/// Renders the chunks of a noise map between two chunk coordinates
+/// (inclusive) as a PNG image, with a pixel for each value coloured by the
+/// gradient.
+///
+/// See the module documentation for an example.
+pub fn render_noise_png<P: AsRef<Path>>(
+    nm: &dyn NoiseMapGeneratorBase,
+    path: P,
+    region: RangeInclusive<(i64, i64)>,
+    gradient: &Gradient,
+) -> Result<(), RenderError> {
+    let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+    let values = nm.generate_area(x0, y0, x1, y1);
+
+    let width = values.first().map_or(0, Vec::len);
+    let image = RgbaImage::from_fn(width as u32, values.len() as u32, |x, y| {
+        Rgba(gradient.colour_at(values[y as usize][x as usize]))
+    });
+
+    image.save(path)?;
+    Ok(())
+}
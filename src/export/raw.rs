@@ -0,0 +1,261 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/raw.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Export of heightmaps as 16 bit RAW files.
+//!
+//! RAW heightmaps are what the terrain systems of Unity and Unreal import:
+//! a grid of unsigned 16 bit heights with no header, so the importer has to
+//! be told the resolution and byte order. `Raw16` resamples a region of a
+//! noise map (or any chunk of values) to the chosen resolution, and scales
+//! the values to the full range of heights:
+//!
+//! ```
+//! # use worldgen::export::raw::{ByteOrder, Raw16};
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Size, Step};
+//! let nm = NoiseMap::new(PerlinNoise::new())
+//!     .set(Size::of(64, 64))
+//!     .set(Step::of(0.01, 0.01));
+//!
+//! // Unity's terrains are a power of two plus one across
+//! let raw = Raw16::new(513, 513).byte_order(ByteOrder::LittleEndian);
+//!
+//! let mut file = Vec::new();
+//! raw.write_noise(&nm, (0, 0)..=(3, 3), &mut file).unwrap();
+//! assert_eq!(file.len(), 513 * 513 * 2);
+//! ```
+//!
+//! The first and last heights of each row and column are sampled at the
+//! edges of the region, so heightmaps of regions beside each other line up
+//! where they meet. Unity reads rows from the bottom of the terrain up, so
+//! the rows are written bottom up with `RowOrder::BottomUp` to keep the
+//! terrain the same way round as the noise map.
+
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+use crate::noisemap::NoiseMapGeneratorBase;
+use crate::world::Chunk;
+
+/// The order of the two bytes of each height.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// The low byte first, as Unity's "Windows" byte order.
+    LittleEndian,
+
+    /// The high byte first, as Unity's "Mac" byte order.
+    BigEndian,
+}
+
+/// The order the rows of heights are written in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RowOrder {
+    /// The first row of the values first.
+    TopDown,
+
+    /// The last row of the values first.
+    BottomUp,
+}
+
+/// The settings for writing a RAW heightmap.
+///
+/// The defaults are:
+///
+/// ```text
+/// byte_order = LittleEndian
+/// row_order = TopDown
+/// range = the noise map's bounds, or the lowest and highest values
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Raw16 {
+    width: usize,
+    height: usize,
+
+    byte_order: ByteOrder,
+    row_order: RowOrder,
+    range: Option<(f64, f64)>,
+}
+
+impl Raw16 {
+    /// Construct the settings for a heightmap of the given resolution.
+    pub fn new(width: usize, height: usize) -> Raw16 {
+        Raw16 {
+            width,
+            height,
+
+            byte_order: ByteOrder::LittleEndian,
+            row_order: RowOrder::TopDown,
+            range: None,
+        }
+    }
+
+    /// Set the order of the bytes of each height.
+    pub fn byte_order(self, byte_order: ByteOrder) -> Raw16 {
+        Raw16 { byte_order, ..self }
+    }
+
+    /// Set the order the rows are written in.
+    pub fn row_order(self, row_order: RowOrder) -> Raw16 {
+        Raw16 { row_order, ..self }
+    }
+
+    /// Set the values written as the lowest and highest heights. Values
+    /// outside of the range are clamped to it.
+    pub fn range(self, low: f64, high: f64) -> Raw16 {
+        Raw16 {
+            range: Some((low, high)),
+            ..self
+        }
+    }
+
+    /// Returns the heights for a chunk of values, resampled to the
+    /// heightmap's resolution, with the rows in the order they are written.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::export::raw::{Raw16, RowOrder};
+    /// let values = vec![vec![0.0, 1.0], vec![0.5, 0.25]];
+    ///
+    /// let heights = Raw16::new(3, 2)
+    ///     .row_order(RowOrder::BottomUp)
+    ///     .heights(&values);
+    ///
+    /// assert_eq!(heights, vec![vec![32768, 24576, 16384], vec![0, 32768, 65535]]);
+    /// ```
+    pub fn heights(&self, values: &Chunk<f64>) -> Chunk<u16> {
+        let (low, high) = self.range.unwrap_or_else(|| extent(values));
+        self.quantise(values, low, high)
+    }
+
+    /// Writes the heights for a chunk of values.
+    pub fn write<W: Write>(&self, values: &Chunk<f64>, writer: W) -> io::Result<()> {
+        let heights = self.heights(values);
+        self.write_heights(&heights, writer)
+    }
+
+    /// Writes the heights for the chunks of a noise map between two chunk
+    /// coordinates (inclusive).
+    ///
+    /// Unless a range is set, the noise map's bounds are used for it when
+    /// they are known, so that separately exported regions share the same
+    /// scale of heights.
+    pub fn write_noise<W: Write>(
+        &self,
+        nm: &dyn NoiseMapGeneratorBase,
+        region: RangeInclusive<(i64, i64)>,
+        writer: W,
+    ) -> io::Result<()> {
+        let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+        let values = nm.generate_area(x0, y0, x1, y1);
+
+        let (low, high) = match (self.range, nm.bounds()) {
+            (Some(range), _) => range,
+            (None, (low, high)) if low.is_finite() && high.is_finite() => (low, high),
+            (None, _) => extent(&values),
+        };
+
+        let heights = self.quantise(&values, low, high);
+        self.write_heights(&heights, writer)
+    }
+
+    fn quantise(&self, values: &Chunk<f64>, low: f64, high: f64) -> Chunk<u16> {
+        let source_height = values.len();
+        let source_width = values.iter().map(Vec::len).min().unwrap_or(0);
+
+        if source_width == 0 || source_height == 0 {
+            return vec![vec![0; self.width]; self.height];
+        }
+
+        let mut heights: Chunk<u16> = (0..self.height)
+            .map(|j| {
+                let y = position(j, self.height, source_height);
+
+                (0..self.width)
+                    .map(|i| {
+                        let x = position(i, self.width, source_width);
+                        let value = sample(values, x, y);
+
+                        let t = if high > low {
+                            ((value - low) / (high - low)).clamp(0.0, 1.0)
+                        } else {
+                            0.0
+                        };
+
+                        (t * u16::MAX as f64).round() as u16
+                    })
+                    .collect()
+            })
+            .collect();
+
+        if self.row_order == RowOrder::BottomUp {
+            heights.reverse();
+        }
+
+        heights
+    }
+
+    fn write_heights<W: Write>(&self, heights: &Chunk<u16>, mut writer: W) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.width * self.height * 2);
+
+        for &height in heights.iter().flatten() {
+            match self.byte_order {
+                ByteOrder::LittleEndian => bytes.extend_from_slice(&height.to_le_bytes()),
+                ByteOrder::BigEndian => bytes.extend_from_slice(&height.to_be_bytes()),
+            }
+        }
+
+        writer.write_all(&bytes)
+    }
+}
+
+/// Returns the position in the source values of a cell of the heightmap,
+/// so that the first and last cells fall on the first and last values.
+fn position(index: usize, size: usize, source: usize) -> f64 {
+    if size <= 1 {
+        0.0
+    } else {
+        index as f64 * (source - 1) as f64 / (size - 1) as f64
+    }
+}
+
+/// Returns the value between the cells of a chunk, blending the four
+/// around it.
+fn sample(values: &Chunk<f64>, x: f64, y: f64) -> f64 {
+    let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+    let (x1, y1) = (
+        (x0 + 1).min(values[y0].len() - 1),
+        (y0 + 1).min(values.len() - 1),
+    );
+    let (tx, ty) = (x - x0 as f64, y - y0 as f64);
+
+    let top = values[y0][x0] + (values[y0][x1] - values[y0][x0]) * tx;
+    let bottom = values[y1][x0] + (values[y1][x1] - values[y1][x0]) * tx;
+
+    top + (bottom - top) * ty
+}
+
+/// Returns the lowest and highest values in a chunk.
+fn extent(values: &Chunk<f64>) -> (f64, f64) {
+    values
+        .iter()
+        .flatten()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &value| {
+            (low.min(value), high.max(value))
+        })
+}
@@ -0,0 +1,246 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/export/tmx.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Export to the Tiled map editor.
+//!
+//! A `TmxMap` holds a layer for each generated chunk added to it, with the
+//! tiles turned into ids in a tileset by a given function, and is written
+//! as a TMX file. The tileset itself can be written as a TSX file with
+//! `Tileset`, so the output opens directly in Tiled, and in the engines
+//! that import its format.
+//!
+//! ```
+//! # use worldgen::export::tmx::{Tileset, TmxMap};
+//! # use worldgen::world::{Size, Tile, World};
+//! let world = World::new()
+//!     .set(Size::of(8, 4))
+//!     .add(Tile::new('.'));
+//!
+//! let tileset = Tileset::new("terrain", "terrain.png", 128, 128);
+//! let map = TmxMap::new("terrain.tsx", &tileset)
+//!     .layer("ground", &world.generate(0, 0).unwrap(), |&tile| match tile {
+//!         '.' => Some(3),
+//!         _ => None,
+//!     });
+//!
+//! let mut tmx = Vec::new();
+//! map.write(&mut tmx).unwrap();
+//!
+//! let mut tsx = Vec::new();
+//! tileset.write(&mut tsx).unwrap();
+//!
+//! let tmx = String::from_utf8(tmx).unwrap();
+//! assert!(tmx.contains(r#"<layer id="1" name="ground" width="8" height="4">"#));
+//! assert!(tmx.contains("4,4,4,4,4,4,4,4,\n"));
+//! ```
+
+use std::io::{self, Write};
+
+use super::escape_xml;
+use crate::world::Chunk;
+
+/// A tileset, cut from a single image into tiles of the same size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tileset {
+    name: String,
+    image: String,
+    image_width: u32,
+    image_height: u32,
+
+    tile_width: u32,
+    tile_height: u32,
+}
+
+impl Tileset {
+    /// Construct a tileset from the image at the given path, with the
+    /// given size in pixels, and tiles of 16 by 16 pixels.
+    pub fn new(name: &str, image: &str, image_width: u32, image_height: u32) -> Tileset {
+        Tileset {
+            name: name.to_string(),
+            image: image.to_string(),
+            image_width,
+            image_height,
+
+            tile_width: 16,
+            tile_height: 16,
+        }
+    }
+
+    /// Set the size of each tile in pixels.
+    pub fn tile_size(self, tile_width: u32, tile_height: u32) -> Tileset {
+        Tileset {
+            tile_width,
+            tile_height,
+            ..self
+        }
+    }
+
+    /// Returns the number of tiles in each row of the image.
+    pub fn columns(&self) -> u32 {
+        self.image_width / self.tile_width
+    }
+
+    /// Returns the number of tiles in the tileset. Tile ids run from zero
+    /// up to this number, in rows from the top left of the image.
+    pub fn tile_count(&self) -> u32 {
+        self.columns() * (self.image_height / self.tile_height)
+    }
+
+    /// Writes the tileset as a TSX file.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<tileset version="1.10" name="{}" tilewidth="{}" tileheight="{}" tilecount="{}" columns="{}">"#,
+            escape_xml(&self.name),
+            self.tile_width,
+            self.tile_height,
+            self.tile_count(),
+            self.columns()
+        )?;
+        writeln!(
+            writer,
+            r#" <image source="{}" width="{}" height="{}"/>"#,
+            escape_xml(&self.image),
+            self.image_width,
+            self.image_height
+        )?;
+        writeln!(writer, "</tileset>")
+    }
+}
+
+/// A layer of a map, as global tile ids, where zero is an empty tile.
+#[derive(Debug, Clone)]
+struct Layer {
+    name: String,
+    gids: Chunk<u32>,
+}
+
+/// An orthogonal map using a single tileset, with a layer for each chunk
+/// added to it.
+#[derive(Debug, Clone)]
+pub struct TmxMap {
+    tileset: String,
+    tile_width: u32,
+    tile_height: u32,
+
+    layers: Vec<Layer>,
+}
+
+impl TmxMap {
+    /// Construct an empty map using the tileset at the given path, relative
+    /// to where the map will be saved.
+    pub fn new(source: &str, tileset: &Tileset) -> TmxMap {
+        TmxMap {
+            tileset: source.to_string(),
+            tile_width: tileset.tile_width,
+            tile_height: tileset.tile_height,
+
+            layers: Vec::new(),
+        }
+    }
+
+    /// Add a layer holding a chunk of tiles, with each tile turned into the
+    /// id of a tile in the tileset by the given function. Tiles without an
+    /// id are left empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chunk is not the same size as the layers already
+    /// added.
+    pub fn layer<T, F>(self, name: &str, chunk: &Chunk<T>, ids: F) -> TmxMap
+    where
+        F: Fn(&T) -> Option<u32>,
+    {
+        let gids: Chunk<u32> = chunk
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|tile| ids(tile).map_or(0, |id| id + 1))
+                    .collect()
+            })
+            .collect();
+
+        if let Some(first) = self.layers.first() {
+            assert!(
+                size(&first.gids) == size(&gids),
+                "every layer of a map must be the same size"
+            );
+        }
+
+        let mut new = self;
+        new.layers.push(Layer {
+            name: name.to_string(),
+            gids,
+        });
+        new
+    }
+
+    /// Writes the map as a TMX file.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let (width, height) = self
+            .layers
+            .first()
+            .map_or((0, 0), |layer| size(&layer.gids));
+
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<map version="1.10" orientation="orthogonal" renderorder="right-down" width="{}" height="{}" tilewidth="{}" tileheight="{}" infinite="0" nextlayerid="{}" nextobjectid="1">"#,
+            width,
+            height,
+            self.tile_width,
+            self.tile_height,
+            self.layers.len() + 1
+        )?;
+        writeln!(
+            writer,
+            r#" <tileset firstgid="1" source="{}"/>"#,
+            escape_xml(&self.tileset)
+        )?;
+
+        for (index, layer) in self.layers.iter().enumerate() {
+            writeln!(
+                writer,
+                r#" <layer id="{}" name="{}" width="{}" height="{}">"#,
+                index + 1,
+                escape_xml(&layer.name),
+                width,
+                height
+            )?;
+            writeln!(writer, r#"  <data encoding="csv">"#)?;
+
+            for (y, row) in layer.gids.iter().enumerate() {
+                let ids: Vec<_> = row.iter().map(u32::to_string).collect();
+                let separator = if y + 1 < height { "," } else { "" };
+
+                writeln!(writer, "{}{}", ids.join(","), separator)?;
+            }
+
+            writeln!(writer, "</data>")?;
+            writeln!(writer, " </layer>")?;
+        }
+
+        writeln!(writer, "</map>")
+    }
+}
+
+/// Returns the width and height of a chunk.
+fn size<T>(chunk: &Chunk<T>) -> (usize, usize) {
+    (chunk.first().map_or(0, Vec::len), chunk.len())
+}
@@ -0,0 +1,363 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/factions.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Territories of factions, for political maps.
+//!
+//! `Factions` places the capitals of a number of factions over a region of
+//! tiles, and grows each faction's territory outwards from its capital.
+//! Growing costs more over some tiles than others, and tiles that can't be
+//! entered (such as seas or mountain ranges) are barriers, so borders tend
+//! to follow rivers, ridges and coasts rather than straight lines. Every
+//! cell goes to the faction that can reach it most cheaply:
+//!
+//! ```
+//! # use worldgen::factions::Factions;
+//! # use worldgen::noisemap::Seed;
+//! # use worldgen::presets::{self, Terrain};
+//! let world = presets::continents(Seed::of("earth")).build().unwrap();
+//! let tiles = world.generate_area((0, 0)..=(1, 1)).unwrap();
+//!
+//! let territories = Factions::new(6)
+//!     .set(Seed::of("kingdoms"))
+//!     .territories(&tiles, |&tile| match tile {
+//!         Terrain::DeepWater | Terrain::Water | Terrain::Snow => None,
+//!         Terrain::Mountains => Some(8.0),
+//!         Terrain::Hills | Terrain::Forest => Some(2.0),
+//!         _ => Some(1.0),
+//!     });
+//!
+//! for (faction, &(x, y)) in territories.capitals().iter().enumerate() {
+//!     assert_eq!(territories.owner(x, y), Some(faction));
+//! }
+//! ```
+//!
+//! Capitals are placed on cells that can be entered, each as far as it can
+//! be from the capitals before it, out of a number of random candidates.
+//! The territories only cover the region they are grown over, so a world
+//! is divided by growing them over an area of it, such as one from
+//! `World::generate_area`. The owners can be passed to `Components` to
+//! trace the borders.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::noisemap::Seed;
+use crate::rng::CellRng;
+use crate::world::Chunk;
+
+/// The number of random cells considered for each capital.
+const CANDIDATES: i64 = 16;
+
+/// The number of random cells tried when looking for one that can be
+/// entered, before giving up on a capital.
+const ATTEMPTS: i64 = 64;
+
+/// The settings for growing the territories of factions.
+///
+/// The defaults are:
+///
+/// ```text
+/// seed = 0
+/// reach = unlimited
+/// ```
+#[derive(Debug, Clone)]
+pub struct Factions {
+    count: usize,
+    seed: Seed,
+
+    capitals: Vec<(usize, usize)>,
+    reach: Option<f64>,
+}
+
+/// The territories grown by `Factions`.
+///
+/// Factions are identified by the index of their capital.
+#[derive(Debug, Clone)]
+pub struct Territories {
+    owners: Chunk<Option<usize>>,
+    capitals: Vec<(usize, usize)>,
+    areas: Vec<usize>,
+}
+
+/// A cell waiting to be claimed, ordered so that the binary heap gives the
+/// cheapest first.
+struct Claim {
+    cost: f64,
+    faction: usize,
+    position: (usize, usize),
+}
+
+impl PartialEq for Claim {
+    fn eq(&self, other: &Claim) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Claim {}
+
+impl PartialOrd for Claim {
+    fn partial_cmp(&self, other: &Claim) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Claim {
+    fn cmp(&self, other: &Claim) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.faction.cmp(&self.faction))
+            .then_with(|| other.position.cmp(&self.position))
+    }
+}
+
+impl Factions {
+    /// Construct the settings for the given number of factions.
+    pub fn new(count: usize) -> Factions {
+        Factions {
+            count,
+            seed: Seed::of_value(0),
+
+            capitals: Vec::new(),
+            reach: None,
+        }
+    }
+
+    /// Set the seed the capitals are placed with.
+    pub fn set(self, seed: Seed) -> Factions {
+        Factions { seed, ..self }
+    }
+
+    /// Add a capital at the given cell of the region, instead of placing it
+    /// randomly. Capitals that are added come first, and count towards the
+    /// number of factions.
+    pub fn capital(self, x: usize, y: usize) -> Factions {
+        let mut new = self;
+        new.capitals.push((x, y));
+        new
+    }
+
+    /// Set the highest cost a faction can reach from its capital, leaving
+    /// cells beyond the reach of every faction unclaimed.
+    pub fn reach(self, reach: f64) -> Factions {
+        Factions {
+            reach: Some(reach.max(0.0)),
+            ..self
+        }
+    }
+
+    /// Returns the capitals placed over a region of tiles, using the given
+    /// function for the cost of entering each tile, or `None` if it can't
+    /// be entered.
+    ///
+    /// Fewer capitals than factions are returned if there are too few
+    /// cells that can be entered, or they are too rare to be found.
+    pub fn capitals<T, F>(&self, grid: &[Vec<T>], cost: F) -> Vec<(usize, usize)>
+    where
+        F: Fn(&T) -> Option<f64>,
+    {
+        let entered = |(x, y): (usize, usize)| {
+            grid.get(y)
+                .and_then(|row| row.get(x))
+                .and_then(&cost)
+                .is_some()
+        };
+
+        let mut capitals: Vec<(usize, usize)> = self
+            .capitals
+            .iter()
+            .copied()
+            .filter(|&capital| entered(capital))
+            .take(self.count)
+            .collect();
+
+        let h = grid.len() as i64;
+        let w = grid.iter().map(|row| row.len()).max().unwrap_or(0) as i64;
+        if w == 0 || h == 0 {
+            return capitals;
+        }
+
+        for faction in capitals.len()..self.count {
+            let mut rng = CellRng::new(self.seed, faction as i64, 0, "capitals");
+            let mut best: Option<((usize, usize), i64)> = None;
+            let mut candidates = 0;
+
+            for _ in 0..CANDIDATES * ATTEMPTS {
+                if candidates == CANDIDATES {
+                    break;
+                }
+
+                let candidate = (rng.range(0..w) as usize, rng.range(0..h) as usize);
+                if !entered(candidate) || capitals.contains(&candidate) {
+                    continue;
+                }
+
+                candidates += 1;
+
+                // The squared distance to the nearest capital so far.
+                let distance = capitals
+                    .iter()
+                    .map(|&(x, y)| {
+                        let (dx, dy) =
+                            (x as i64 - candidate.0 as i64, y as i64 - candidate.1 as i64);
+                        dx * dx + dy * dy
+                    })
+                    .min()
+                    .unwrap_or(0);
+
+                if best.is_none_or(|(_, furthest)| distance > furthest) {
+                    best = Some((candidate, distance));
+                }
+            }
+
+            match best {
+                Some((capital, _)) => capitals.push(capital),
+                None => break,
+            }
+        }
+
+        capitals
+    }
+
+    /// Grows the territories of the factions over a region of tiles, using
+    /// the given function for the cost of entering each tile, or `None` if
+    /// it can't be entered.
+    ///
+    /// Territories grow between cells beside each other, not diagonally, so
+    /// a line of barriers meeting only at corners still holds them back.
+    /// Where two factions reach a cell at the same cost, the one with the
+    /// lower id takes it.
+    pub fn territories<T, F>(&self, grid: &[Vec<T>], cost: F) -> Territories
+    where
+        F: Fn(&T) -> Option<f64>,
+    {
+        let capitals = self.capitals(grid, &cost);
+
+        let mut owners: Chunk<Option<usize>> =
+            grid.iter().map(|row| vec![None; row.len()]).collect();
+        let mut areas = vec![0; capitals.len()];
+        let mut open = BinaryHeap::new();
+
+        for (faction, &position) in capitals.iter().enumerate() {
+            open.push(Claim {
+                cost: 0.0,
+                faction,
+                position,
+            });
+        }
+
+        while let Some(Claim {
+            cost: so_far,
+            faction,
+            position: (x, y),
+        }) = open.pop()
+        {
+            if owners[y][x].is_some() {
+                continue;
+            }
+
+            owners[y][x] = Some(faction);
+            areas[faction] += 1;
+
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+
+            for (nx, ny) in neighbours {
+                let step = match grid.get(ny).and_then(|row| row.get(nx)) {
+                    Some(tile) if owners[ny][nx].is_none() => match cost(tile) {
+                        Some(step) => step.max(0.0),
+                        None => continue,
+                    },
+                    _ => continue,
+                };
+
+                let total = so_far + step;
+                if self.reach.is_some_and(|reach| total > reach) {
+                    continue;
+                }
+
+                open.push(Claim {
+                    cost: total,
+                    faction,
+                    position: (nx, ny),
+                });
+            }
+        }
+
+        Territories {
+            owners,
+            capitals,
+            areas,
+        }
+    }
+}
+
+impl Territories {
+    /// Returns the id of the faction owning the given cell, if any.
+    pub fn owner(&self, x: usize, y: usize) -> Option<usize> {
+        self.owners[y][x]
+    }
+
+    /// Returns the faction owning every cell.
+    pub fn owners(&self) -> &Chunk<Option<usize>> {
+        &self.owners
+    }
+
+    /// Returns the capital of every faction.
+    pub fn capitals(&self) -> &[(usize, usize)] {
+        &self.capitals
+    }
+
+    /// Returns the number of factions.
+    pub fn len(&self) -> usize {
+        self.capitals.len()
+    }
+
+    /// Returns true if there are no factions.
+    pub fn is_empty(&self) -> bool {
+        self.capitals.is_empty()
+    }
+
+    /// Returns the number of cells a faction owns.
+    pub fn area(&self, faction: usize) -> usize {
+        self.areas[faction]
+    }
+
+    /// Returns true if the given cell is owned, and a cell beside it is
+    /// owned by another faction.
+    pub fn is_border(&self, x: usize, y: usize) -> bool {
+        let owner = match self.owner(x, y) {
+            Some(owner) => owner,
+            None => return false,
+        };
+
+        [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ]
+        .into_iter()
+        .filter_map(|(nx, ny)| self.owners.get(ny).and_then(|row| row.get(nx)))
+        .any(|&other| other.is_some_and(|other| other != owner))
+    }
+}
@@ -0,0 +1,301 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/gpu/mod.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Generation of noise on the GPU.
+//!
+//! With the `gpu` feature, `NoiseMap::generate_chunk_gpu` generates whole
+//! chunks of noise in a wgpu compute shader, with one invocation for each
+//! cell, which is much faster than the CPU for large chunks of noise with
+//! many octaves.
+//!
+//! Only noise made of layers of coherent noise can be generated this way,
+//! which is `CoherentNoise`, and `OctavedNoise` over it (such as
+//! `PerlinNoise`):
+//!
+//! ```
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGeneratorBase, NoiseMapGenerator, Seed, Size, Step};
+//! let nm = NoiseMap::new(PerlinNoise::new())
+//!     .set(Seed::of("gpu"))
+//!     .set(Size::of(64, 64))
+//!     .set(Step::of(0.01, 0.01));
+//!
+//! let gpu = nm.generate_chunk_gpu(3, -2);
+//! let cpu = nm.generate_chunk(3, -2);
+//!
+//! for (gpu, cpu) in gpu.iter().flatten().zip(cpu.iter().flatten()) {
+//!     assert!((gpu - cpu).abs() < 1e-3);
+//! }
+//! ```
+//!
+//! The shader works in single precision, so its values agree with those of
+//! the CPU to within the precision of an `f32` rather than exactly. When
+//! there is no GPU to use, or the chunk can't be generated on it, the chunk
+//! is generated on the CPU instead.
+
+use std::sync::{mpsc, OnceLock};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::DeviceExt;
+
+use crate::noise::coherent::CoherentNoise;
+use crate::noise::NoiseProvider;
+use crate::noisemap::Size;
+use crate::world::Chunk;
+
+/// The number of cells along each side of the shader's workgroups.
+const WORKGROUP: u32 = 8;
+
+/// A source of noise that can be generated on the GPU.
+pub trait GpuNoise: NoiseProvider {
+    /// Returns the layers of coherent noise that are added together to
+    /// make the noise.
+    fn layers(&self) -> Vec<Layer>;
+}
+
+/// A layer of coherent noise, as part of a `GpuNoise`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Layer {
+    /// The factor coordinates are multiplied by for the layer.
+    pub scale: f64,
+
+    /// The factor the layer's values are multiplied by.
+    pub weight: f64,
+
+    /// The amount added to the seed for the layer.
+    pub seed: u64,
+}
+
+impl GpuNoise for CoherentNoise {
+    fn layers(&self) -> Vec<Layer> {
+        vec![Layer {
+            scale: 1.0,
+            weight: 1.0,
+            seed: 0,
+        }]
+    }
+}
+
+/// The properties of the whole chunk, as the shader's `Params`.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Params {
+    width: u32,
+    height: u32,
+    octaves: u32,
+    amplitude: f32,
+}
+
+/// A layer positioned for a chunk, as the shader's `Octave`.
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+struct Octave {
+    cell: [i32; 2],
+    offset: [f32; 2],
+    delta: [f32; 2],
+    weight: f32,
+    seed: i32,
+}
+
+/// A GPU device ready to generate noise.
+#[derive(Debug)]
+pub struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    /// Requests a GPU device and compiles the noise shader for it,
+    /// returning `None` if there is no device to use.
+    pub fn new() -> Option<Gpu> {
+        pollster::block_on(Gpu::request())
+    }
+
+    /// Returns the device shared by every noise map, requested the first
+    /// time it is needed.
+    pub fn shared() -> Option<&'static Gpu> {
+        static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+        GPU.get_or_init(Gpu::new).as_ref()
+    }
+
+    async fn request() -> Option<Gpu> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions::default())
+            .await
+            .ok()?;
+
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor::default())
+            .await
+            .ok()?;
+
+        let module = device.create_shader_module(wgpu::include_wgsl!("noise.wgsl"));
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("worldgen noise"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Some(Gpu {
+            device,
+            queue,
+            pipeline,
+        })
+    }
+
+    /// Generates a chunk of noise made of the given layers, with the given
+    /// noise coordinate for its first cell and step between cells.
+    ///
+    /// Returns `None` if the chunk is empty, or too large for the device,
+    /// or the device fails, to be generated on the CPU instead.
+    pub(crate) fn chunk(
+        &self,
+        layers: &[Layer],
+        origin: (f64, f64),
+        step: (f64, f64),
+        seed: u64,
+        amplitude: f64,
+        size: Size,
+    ) -> Option<Chunk<f64>> {
+        let (w, h) = (u32::try_from(size.w).ok()?, u32::try_from(size.h).ok()?);
+        let bytes = u64::from(w) * u64::from(h) * 4;
+
+        let limits = self.device.limits();
+        if bytes == 0 || layers.is_empty() || bytes > limits.max_storage_buffer_binding_size {
+            return None;
+        }
+
+        // Each layer's first cell is split into its lattice cell and the
+        // offset within it, so that precision isn't lost far from the origin.
+        let octaves: Vec<Octave> = layers
+            .iter()
+            .map(|layer| {
+                let (x, y) = (origin.0 * layer.scale, origin.1 * layer.scale);
+                let (cell_x, cell_y) = (x.floor(), y.floor());
+
+                Octave {
+                    cell: [cell_x as i32, cell_y as i32],
+                    offset: [(x - cell_x) as f32, (y - cell_y) as f32],
+                    delta: [(step.0 * layer.scale) as f32, (step.1 * layer.scale) as f32],
+                    weight: layer.weight as f32,
+                    seed: seed.wrapping_add(layer.seed) as i32,
+                }
+            })
+            .collect();
+
+        let params = Params {
+            width: w,
+            height: h,
+            octaves: octaves.len() as u32,
+            amplitude: amplitude as f32,
+        };
+
+        let params = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("worldgen params"),
+                contents: bytemuck::bytes_of(&params),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+        let octaves = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("worldgen octaves"),
+                contents: bytemuck::cast_slice(&octaves),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("worldgen output"),
+            size: bytes,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("worldgen staging"),
+            size: bytes,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("worldgen noise"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: octaves.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("worldgen noise"),
+            });
+
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("worldgen noise"),
+                timestamp_writes: None,
+            });
+
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(w.div_ceil(WORKGROUP), h.div_ceil(WORKGROUP), 1);
+        }
+
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, bytes);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+        receiver.recv().ok()?.ok()?;
+
+        let values: Vec<f32> = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+
+        Some(
+            values
+                .chunks(w as usize)
+                .map(|row| row.iter().map(|&value| f64::from(value)).collect())
+                .collect(),
+        )
+    }
+}
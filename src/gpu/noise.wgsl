@@ -0,0 +1,85 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/gpu/noise.wgsl
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+// Octaved coherent noise for a whole chunk, one invocation per cell. This
+// follows `CoherentNoise` in the noise module, in single precision.
+
+struct Params {
+    width: u32,
+    height: u32,
+    octaves: u32,
+    amplitude: f32,
+}
+
+// The position of the chunk's first cell is split into a lattice cell and
+// the offset within it, so that cells far from the origin keep their
+// precision.
+struct Octave {
+    cell: vec2<i32>,
+    offset: vec2<f32>,
+    delta: vec2<f32>,
+    weight: f32,
+    seed: i32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> octaves: array<Octave>;
+@group(0) @binding(2) var<storage, read_write> output: array<f32>;
+
+// Integer arithmetic wraps, as `wrapping_mul` and `wrapping_add` do.
+fn random_value(x: i32, y: i32, seed: i32) -> f32 {
+    var n = (x * 157 + y * 31337 + seed * 2633) & 0x7fffffff;
+    n = (n << 13u) ^ n;
+
+    let m = (n * (n * n * 15731 + 789221) + 1376312579) & 0x7fffffff;
+    return 1.0 - f32(m) / 1073741824.0;
+}
+
+fn s_curve(a: f32) -> f32 {
+    return a * a * (3.0 - 2.0 * a);
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.width || id.y >= params.height) {
+        return;
+    }
+
+    var value = 0.0;
+
+    for (var i = 0u; i < params.octaves; i++) {
+        let octave = octaves[i];
+
+        let position = octave.offset + vec2<f32>(f32(id.x), f32(id.y)) * octave.delta;
+        let whole = floor(position);
+        let cell = octave.cell + vec2<i32>(whole);
+        let d = vec2<f32>(s_curve(position.x - whole.x), s_curve(position.y - whole.y));
+
+        let x0y0 = random_value(cell.x, cell.y, octave.seed);
+        let x1y0 = random_value(cell.x + 1, cell.y, octave.seed);
+        let x0y1 = random_value(cell.x, cell.y + 1, octave.seed);
+        let x1y1 = random_value(cell.x + 1, cell.y + 1, octave.seed);
+
+        let v1 = mix(x0y0, x1y0, d.x);
+        let v2 = mix(x0y1, x1y1, d.x);
+
+        value += mix(v1, v2, d.y) * octave.weight;
+    }
+
+    output[id.y * params.width + id.x] = value * params.amplitude;
+}
@@ -100,6 +100,7 @@
 //!
 //! use worldgen::noise::perlin::PerlinNoise;
 //! use worldgen::noisemap::{NoiseMapGenerator, NoiseMapGeneratorBase, NoiseMap, Seed, Step, Size};
+//! use worldgen::export::ansi::{render_ansi, Colour, Style};
 //! use worldgen::world::{World, Tile};
 //! use worldgen::world::tile::{Constraint, ConstraintType};
 //!
@@ -134,17 +135,14 @@
 //!         // Hills
 //!         .add(Tile::new('n'));
 //!
-//!     for row in world.generate(0, 0).iter() {
-//!         for val in row.iter() {
-//!             for c in val.iter() {
-//!                 print!("{}", c);
-//!             }
+//!     let chunk = world.generate(0, 0).unwrap();
 //!
-//!             println!("");
-//!         }
-//!
-//!         println!("");
-//!     }
+//!     print!("{}", render_ansi(&chunk, |&tile| match tile {
+//!         '~' => Style::new('~').fg(Colour::Blue),
+//!         ',' => Style::new(',').fg(Colour::Green),
+//!         '^' => Style::new('^').fg(Colour::White).bold(),
+//!         tile => Style::new(tile).fg(Colour::Yellow),
+//!     }));
 //! }
 //! ```
 //!
@@ -161,11 +159,50 @@ use world::{Tile, World};
 #[cfg(test)]
 use world::tile::{Constraint, ConstraintType};
 
+pub mod biome;
+pub mod cancel;
+pub mod climate;
+pub mod components;
+pub mod definition;
+pub mod dungeon;
+pub mod export;
+pub mod factions;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod lsystem;
+pub mod names;
+#[cfg(feature = "net")]
+pub mod net;
 pub mod noise;
 pub mod noisemap;
+pub mod path;
+pub mod pipeline;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod pool;
+pub mod presets;
+pub mod region;
+pub mod resources;
+pub mod rivers;
+pub mod rng;
+pub mod routes;
+pub mod spawn;
+pub mod store;
+pub mod structures;
+pub mod tectonics;
+pub mod terrain;
+pub mod vegetation;
+pub mod version;
+pub mod voxel;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod weather;
+pub mod wfc;
+
+mod progress;
 
 #[macro_use]
 pub mod world;
+pub mod zoom;
 
 #[test]
 fn it_works() {
@@ -192,7 +229,7 @@ fn it_works() {
         // Hills
         .add(Tile::new('n'));
 
-    if let Some(chunk) = world.generate(0, 0) {
+    if let Ok(chunk) = world.generate(0, 0) {
         for row in chunk {
             for tile in row {
                 print!("{}", tile);
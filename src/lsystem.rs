@@ -0,0 +1,350 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/lsystem.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Branching structures grown by L-systems.
+//!
+//! An `LSystem` starts from a string of symbols, the axiom, and rewrites
+//! every symbol with a rule for it at each iteration. The final string is
+//! then followed by a turtle, which draws the structure as line segments:
+//!
+//! ```text
+//! F   move forward, drawing a segment
+//! f   move forward without drawing
+//! +   turn clockwise (as seen on the map) by the angle
+//! -   turn anticlockwise by the angle
+//! [   start a branch, remembering the position and heading
+//! ]   end the branch, returning to where it started
+//! ```
+//!
+//! Other symbols are only used by the rules. A symbol with several rules
+//! picks one at random, in proportion to their weights, and each turn can
+//! be jittered randomly, so one system grows many different trees, river
+//! deltas, or coral reefs.
+//!
+//! The random choices depend only on the seed and the position a structure
+//! is grown from, so every chunk a structure crosses can grow it again and
+//! rasterize its own part:
+//!
+//! ```
+//! # use worldgen::lsystem::{self, LSystem};
+//! # use worldgen::noisemap::Seed;
+//! let tree = LSystem::new("X")
+//!     .set(Seed::of("trees"))
+//!     .rule('X', "F[+X][-X]FX")
+//!     .weighted_rule('X', "F[-X]FX", 0.5)
+//!     .rule('F', "FF")
+//!     .iterations(3)
+//!     .angle(25.0)
+//!     .jitter(5.0);
+//!
+//! // Grown upwards from the bottom of a 32x32 chunk
+//! let segments = tree.grow(16.0, 31.0, -90.0);
+//! let mask = lsystem::mask(&segments, 0, 0, 32, 32);
+//!
+//! assert!(mask[31][16]);
+//! assert_eq!(segments, tree.grow(16.0, 31.0, -90.0));
+//! ```
+
+use crate::noisemap::Seed;
+use crate::rng::CellRng;
+use crate::world::Chunk;
+
+/// A line segment drawn by an `LSystem`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Segment {
+    /// The position the segment starts from.
+    pub start: (f64, f64),
+
+    /// The position the segment ends at.
+    pub end: (f64, f64),
+
+    /// How many branches deep the segment is, with 0 for the trunk.
+    pub depth: usize,
+}
+
+/// An L-system, with its rules and how its turtle draws.
+///
+/// The defaults are:
+///
+/// ```text
+/// seed = 0
+/// iterations = 4
+/// angle = 30
+/// length = 1
+/// shrink = 1
+/// jitter = 0
+/// ```
+#[derive(Debug, Clone)]
+pub struct LSystem {
+    axiom: String,
+    rules: Vec<(char, String, f64)>,
+    seed: Seed,
+
+    iterations: usize,
+    angle: f64,
+    length: f64,
+    shrink: f64,
+    jitter: f64,
+}
+
+impl LSystem {
+    /// Construct an L-system starting from the given axiom, without any
+    /// rules.
+    pub fn new(axiom: &str) -> LSystem {
+        LSystem {
+            axiom: axiom.to_string(),
+            rules: Vec::new(),
+            seed: Seed::of_value(0),
+
+            iterations: 4,
+            angle: 30.0,
+            length: 1.0,
+            shrink: 1.0,
+            jitter: 0.0,
+        }
+    }
+
+    /// Set the seed of every random choice.
+    pub fn set(self, seed: Seed) -> LSystem {
+        LSystem { seed, ..self }
+    }
+
+    /// Add a rule rewriting the symbol with the replacement, with a weight
+    /// of 1.
+    pub fn rule(self, symbol: char, replacement: &str) -> LSystem {
+        self.weighted_rule(symbol, replacement, 1.0)
+    }
+
+    /// Add a rule rewriting the symbol with the replacement, chosen in
+    /// proportion to its weight from the rules for the same symbol.
+    pub fn weighted_rule(self, symbol: char, replacement: &str, weight: f64) -> LSystem {
+        let mut new = self;
+        new.rules
+            .push((symbol, replacement.to_string(), weight.max(0.0)));
+        new
+    }
+
+    /// Set the number of times the rules are applied.
+    pub fn iterations(self, iterations: usize) -> LSystem {
+        LSystem { iterations, ..self }
+    }
+
+    /// Set the angle, in degrees, the turtle turns by.
+    pub fn angle(self, angle: f64) -> LSystem {
+        LSystem { angle, ..self }
+    }
+
+    /// Set the length of the trunk's segments.
+    pub fn length(self, length: f64) -> LSystem {
+        LSystem { length, ..self }
+    }
+
+    /// Set the factor the length of segments is multiplied by in each
+    /// level of branches, so that branches get shorter as they divide.
+    pub fn shrink(self, shrink: f64) -> LSystem {
+        LSystem { shrink, ..self }
+    }
+
+    /// Set the most, in degrees, each turn is randomly changed by.
+    pub fn jitter(self, jitter: f64) -> LSystem {
+        LSystem {
+            jitter: jitter.abs(),
+            ..self
+        }
+    }
+
+    /// Returns the string of symbols after applying the rules, taking
+    /// random choices from the given stream.
+    pub fn expand(&self, rng: &mut CellRng) -> String {
+        let mut current = self.axiom.clone();
+
+        for _ in 0..self.iterations {
+            let mut next = String::with_capacity(current.len() * 2);
+
+            for symbol in current.chars() {
+                match self.choose_rule(symbol, rng) {
+                    Some(replacement) => next.push_str(replacement),
+                    None => next.push(symbol),
+                }
+            }
+
+            current = next;
+        }
+
+        current
+    }
+
+    /// Grows the structure from the given position, with the turtle
+    /// heading at the given angle in degrees clockwise from east (so -90
+    /// heads up the map), returning the segments it draws.
+    pub fn grow(&self, x: f64, y: f64, heading: f64) -> Vec<Segment> {
+        let mut rng = CellRng::new(self.seed, x.floor() as i64, y.floor() as i64, "lsystem");
+        let symbols = self.expand(&mut rng);
+
+        let mut segments = Vec::new();
+        let mut stack = Vec::new();
+        let (mut position, mut heading, mut depth) = ((x, y), heading, 0);
+
+        for symbol in symbols.chars() {
+            match symbol {
+                'F' | 'f' => {
+                    let length = self.length * self.shrink.powi(depth as i32);
+                    let radians = heading.to_radians();
+                    let end = (
+                        position.0 + length * radians.cos(),
+                        position.1 + length * radians.sin(),
+                    );
+
+                    if symbol == 'F' {
+                        segments.push(Segment {
+                            start: position,
+                            end,
+                            depth,
+                        });
+                    }
+
+                    position = end;
+                }
+
+                '+' | '-' => {
+                    let jitter = (rng.next_f64() * 2.0 - 1.0) * self.jitter;
+                    let turn = if symbol == '+' {
+                        self.angle
+                    } else {
+                        -self.angle
+                    };
+
+                    heading += turn + jitter;
+                }
+
+                '[' => {
+                    stack.push((position, heading));
+                    depth += 1;
+                }
+
+                ']' => {
+                    if let Some((saved_position, saved_heading)) = stack.pop() {
+                        position = saved_position;
+                        heading = saved_heading;
+                        depth -= 1;
+                    }
+                }
+
+                _ => (),
+            }
+        }
+
+        segments
+    }
+
+    /// Chooses one of the rules for the symbol, if it has any.
+    fn choose_rule(&self, symbol: char, rng: &mut CellRng) -> Option<&str> {
+        let rules: Vec<&(char, String, f64)> = self
+            .rules
+            .iter()
+            .filter(|&&(rule, _, _)| rule == symbol)
+            .collect();
+
+        match rules.len() {
+            0 => None,
+            1 => Some(&rules[0].1),
+            _ => {
+                let total: f64 = rules.iter().map(|&&(_, _, weight)| weight).sum();
+                let mut choice = rng.next_f64() * total;
+
+                for &(_, replacement, weight) in rules.iter() {
+                    if choice < *weight {
+                        return Some(replacement);
+                    }
+
+                    choice -= weight;
+                }
+
+                rules
+                    .last()
+                    .map(|&(_, replacement, _)| replacement.as_str())
+            }
+        }
+    }
+}
+
+/// Rasterizes segments into a mask of the area with the given top left
+/// position and size, which is true for each cell a segment crosses.
+pub fn mask(segments: &[Segment], x: i64, y: i64, w: i64, h: i64) -> Chunk<bool> {
+    depths(segments, x, y, w, h)
+        .into_iter()
+        .map(|row| row.into_iter().map(|depth| depth.is_some()).collect())
+        .collect()
+}
+
+/// Rasterizes segments into an overlay of tiles for the area with the
+/// given top left position and size, such as for a `Prefab` or for
+/// `Overrides::stamp`.
+///
+/// Each cell a segment crosses takes the tile given for the depth of the
+/// shallowest segment crossing it, so trunks can be drawn differently to
+/// their branches. Other cells hold `None`.
+pub fn overlay<T, F>(
+    segments: &[Segment],
+    x: i64,
+    y: i64,
+    w: i64,
+    h: i64,
+    tile: F,
+) -> Chunk<Option<T>>
+where
+    F: Fn(usize) -> T,
+{
+    depths(segments, x, y, w, h)
+        .into_iter()
+        .map(|row| row.into_iter().map(|depth| depth.map(&tile)).collect())
+        .collect()
+}
+
+/// Returns the depth of the shallowest segment crossing each cell of an
+/// area, with cell centres on whole numbers.
+fn depths(segments: &[Segment], x0: i64, y0: i64, w: i64, h: i64) -> Chunk<Option<usize>> {
+    let (w, h) = (w.max(0), h.max(0));
+    let mut depths = vec![vec![None; w as usize]; h as usize];
+
+    for segment in segments {
+        let (dx, dy) = (
+            segment.end.0 - segment.start.0,
+            segment.end.1 - segment.start.1,
+        );
+
+        // Steps of at most half a cell, so no cell along the way is missed.
+        let steps = ((dx.abs().max(dy.abs())) * 2.0).ceil().max(1.0) as usize;
+
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = (segment.start.0 + dx * t).round() as i64 - x0;
+            let y = (segment.start.1 + dy * t).round() as i64 - y0;
+
+            if (0..w).contains(&x) && (0..h).contains(&y) {
+                let cell: &mut Option<usize> = &mut depths[y as usize][x as usize];
+
+                if cell.is_none_or(|depth| segment.depth < depth) {
+                    *cell = Some(segment.depth);
+                }
+            }
+        }
+    }
+
+    depths
+}
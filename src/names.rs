@@ -0,0 +1,216 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/names.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Names for generated places, from a Markov chain.
+//!
+//! A `NameGenerator` is trained on a list of example words, and learns
+//! which letters follow each short run of letters. New names are built a
+//! letter at a time from those, so they sound like the examples without
+//! (usually) being copies of them.
+//!
+//! Names depend only on the generator's seed and a position, so a town or
+//! river is given the same name every time the world is generated:
+//!
+//! ```
+//! # use worldgen::names::NameGenerator;
+//! # use worldgen::world::Seed;
+//! let names = NameGenerator::new()
+//!     .seed(Seed::of("towns"))
+//!     .train(&["ashford", "bramley", "crawley", "dunsford", "elmley", "farnham"]);
+//!
+//! let name = names.name_at(120, -45).unwrap();
+//! assert_eq!(names.name_at(120, -45).unwrap(), name);
+//! assert!(name.chars().next().unwrap().is_uppercase());
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use crate::noisemap::Seed;
+use crate::rng::CellRng;
+
+/// Marks the start and end of a word in the chain.
+const BOUNDARY: char = '\0';
+
+/// The number of names tried before giving up on one that fits.
+const ATTEMPTS: usize = 100;
+
+/// A generator of names, trained on example words.
+///
+/// The defaults are:
+///
+/// ```text
+/// order = 2
+/// length = 4..10
+/// ```
+#[derive(Debug, Clone)]
+pub struct NameGenerator {
+    seed: Seed,
+    order: usize,
+    length: Range<usize>,
+
+    transitions: BTreeMap<Vec<char>, Vec<(char, u32)>>,
+    words: BTreeSet<String>,
+}
+
+impl Default for NameGenerator {
+    fn default() -> NameGenerator {
+        NameGenerator::new()
+    }
+}
+
+impl NameGenerator {
+    /// Construct an untrained name generator.
+    pub fn new() -> NameGenerator {
+        NameGenerator {
+            seed: Default::default(),
+            order: 2,
+            length: 4..10,
+
+            transitions: BTreeMap::new(),
+            words: BTreeSet::new(),
+        }
+    }
+
+    /// Set the seed of the generator.
+    pub fn seed(self, seed: Seed) -> NameGenerator {
+        NameGenerator { seed, ..self }
+    }
+
+    /// Set the number of letters considered when choosing the next letter.
+    /// Higher orders give names closer to the examples.
+    ///
+    /// This forgets any training, so should be set first.
+    pub fn order(self, order: usize) -> NameGenerator {
+        NameGenerator {
+            order: order.max(1),
+            transitions: BTreeMap::new(),
+            words: BTreeSet::new(),
+            ..self
+        }
+    }
+
+    /// Set the range of lengths (in letters) of generated names.
+    pub fn length(self, length: Range<usize>) -> NameGenerator {
+        NameGenerator { length, ..self }
+    }
+
+    /// Train the generator on the given example words. Examples are not
+    /// case sensitive.
+    pub fn train<S: AsRef<str>>(self, words: &[S]) -> NameGenerator {
+        let mut new = self;
+
+        for word in words.iter() {
+            let word = word.as_ref().trim().to_lowercase();
+            if word.is_empty() {
+                continue;
+            }
+
+            let letters: Vec<char> = std::iter::repeat_n(BOUNDARY, new.order)
+                .chain(word.chars())
+                .chain(Some(BOUNDARY))
+                .collect();
+
+            for window in letters.windows(new.order + 1) {
+                let (&next, context) = window.split_last().unwrap();
+                let choices = new.transitions.entry(context.to_vec()).or_default();
+
+                match choices.iter_mut().find(|&&mut (letter, _)| letter == next) {
+                    Some(choice) => choice.1 += 1,
+                    None => choices.push((next, 1)),
+                }
+            }
+
+            new.words.insert(word);
+        }
+
+        new
+    }
+
+    /// Returns the name for the given world position.
+    ///
+    /// Returns `None` if the generator hasn't been trained, or no name of
+    /// the right length could be found.
+    pub fn name_at(&self, x: i64, y: i64) -> Option<String> {
+        self.name_with(&mut CellRng::new(self.seed, x, y, "name"))
+    }
+
+    /// Returns a name using the given random stream.
+    ///
+    /// Names that are copies of the examples are avoided where possible.
+    pub fn name_with(&self, rng: &mut CellRng) -> Option<String> {
+        let mut fallback = None;
+
+        for _ in 0..ATTEMPTS {
+            let name = match self.build(rng) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if !self.words.contains(&name) {
+                return Some(capitalise(&name));
+            }
+
+            fallback = fallback.or(Some(name));
+        }
+
+        fallback.map(|name| capitalise(&name))
+    }
+
+    /// Builds a single name from the chain, if it finishes at an allowed
+    /// length.
+    fn build(&self, rng: &mut CellRng) -> Option<String> {
+        let mut context = vec![BOUNDARY; self.order];
+        let mut name = String::new();
+
+        loop {
+            let choices = self.transitions.get(&context)?;
+            let total: u32 = choices.iter().map(|&(_, count)| count).sum();
+
+            let mut roll = rng.range(0..total as i64) as u32;
+            let &(letter, _) = choices.iter().find(|&&(_, count)| {
+                let found = roll < count;
+                roll = roll.saturating_sub(count);
+                found
+            })?;
+
+            if letter == BOUNDARY {
+                let length = name.chars().count();
+                return Some(name).filter(|_| self.length.contains(&length));
+            }
+
+            name.push(letter);
+            if name.chars().count() >= self.length.end {
+                return None;
+            }
+
+            context.remove(0);
+            context.push(letter);
+        }
+    }
+}
+
+/// Returns the word with its first letter in upper case.
+fn capitalise(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
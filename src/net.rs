@@ -0,0 +1,301 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/net.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! A compact binary encoding of chunks, for sending them over a network,
+//! with the `net` feature enabled.
+//!
+//! A server that generates the authoritative chunks of a world can send
+//! them to its clients as `ChunkMessage`s. A message holds either the whole
+//! chunk, as runs of the same tile, or only the tiles that changed since a
+//! chunk the client already has, and is encoded with bincode:
+//!
+//! ```
+//! # use worldgen::net::ChunkMessage;
+//! # use worldgen::world::{Size, Tile, World};
+//! let world = World::new()
+//!     .set(Size::of(32, 32))
+//!     .add(Tile::new('.'));
+//!
+//! // On the server
+//! let chunk = world.generate(0, 0).unwrap();
+//! let bytes = ChunkMessage::full(0, 0, &chunk).encode().unwrap();
+//!
+//! // A single run of grass takes a handful of bytes, not 1024
+//! assert!(bytes.len() < 16);
+//!
+//! // On the client
+//! let message = ChunkMessage::<char>::decode(&bytes).unwrap();
+//! assert_eq!((message.x, message.y), (0, 0));
+//! assert_eq!(message.into_chunk(None).unwrap(), chunk);
+//! ```
+//!
+//! When a chunk changes, as when a player builds on it, a delta against
+//! the chunk the client was sent before carries only the changed tiles:
+//!
+//! ```
+//! # use worldgen::net::ChunkMessage;
+//! let before = vec![vec!['.'; 32]; 32];
+//!
+//! let mut after = before.clone();
+//! after[4][4..8].copy_from_slice(&['#'; 4]);
+//!
+//! let message = ChunkMessage::delta(0, 0, &before, &after);
+//! assert!(message.is_delta());
+//!
+//! let bytes = message.encode().unwrap();
+//! let decoded = ChunkMessage::<char>::decode(&bytes).unwrap();
+//! assert_eq!(decoded.into_chunk(Some(&before)).unwrap(), after);
+//! ```
+//!
+//! Each message records the `GENERATION_VERSION` of the server, and
+//! decoding a message from a server with another version fails, so clients
+//! never mix tiles generated by different versions.
+
+use std::error::Error;
+use std::fmt;
+
+use bincode::config;
+use bincode::error::{DecodeError, EncodeError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::version::{self, VersionMismatch, GENERATION_VERSION};
+use crate::world::Chunk;
+
+/// A chunk, or the changes to one, to be sent over a network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkMessage<T> {
+    version: u32,
+
+    /// The chunk coordinate of the chunk.
+    pub x: i64,
+    pub y: i64,
+
+    /// The number of tiles in each row of the chunk.
+    pub width: u32,
+
+    /// The number of rows in the chunk.
+    pub height: u32,
+
+    body: Body<T>,
+}
+
+/// The tiles of a message, in order along each row, one row after another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Body<T> {
+    /// Every tile, as runs of the same tile with their lengths.
+    Full(Vec<(u32, T)>),
+
+    /// The changed tiles, as runs of the same tile with the number of
+    /// unchanged tiles before them and their lengths.
+    Delta(Vec<(u32, u32, T)>),
+}
+
+/// A problem encoding or decoding a `ChunkMessage`.
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// The message couldn't be encoded by bincode.
+    Encode(EncodeError),
+
+    /// The bytes couldn't be decoded by bincode.
+    Decode(DecodeError),
+
+    /// The message was sent by a server with another version of the
+    /// generation algorithms.
+    Version(VersionMismatch),
+
+    /// The message holds changes, but no chunk of its size was given to
+    /// apply them to.
+    MissingBase,
+
+    /// The runs of the message don't cover the chunk exactly.
+    Malformed,
+}
+
+impl<T: Clone + PartialEq> ChunkMessage<T> {
+    /// Construct a message holding the whole of a chunk.
+    ///
+    /// Rows longer than the first row are cut down to its length.
+    pub fn full(x: i64, y: i64, chunk: &Chunk<T>) -> ChunkMessage<T> {
+        let width = chunk.first().map_or(0, Vec::len);
+        let mut runs: Vec<(u32, T)> = Vec::new();
+
+        for tile in chunk.iter().flat_map(|row| row[..width].iter()) {
+            match runs.last_mut() {
+                Some((count, last)) if last == tile => *count += 1,
+                _ => runs.push((1, tile.clone())),
+            }
+        }
+
+        ChunkMessage::new(x, y, width, chunk.len(), Body::Full(runs))
+    }
+
+    /// Construct a message holding the changes from one version of a chunk
+    /// to another.
+    ///
+    /// If the chunks are different sizes, or the changes would take more
+    /// runs than the whole chunk, the message holds the whole chunk.
+    pub fn delta(x: i64, y: i64, previous: &Chunk<T>, chunk: &Chunk<T>) -> ChunkMessage<T> {
+        let full = ChunkMessage::full(x, y, chunk);
+
+        let same_size = previous.len() == chunk.len()
+            && previous.iter().all(|row| row.len() >= full.width as usize);
+
+        if !same_size {
+            return full;
+        }
+
+        let width = full.width as usize;
+        let pairs = previous
+            .iter()
+            .zip(chunk.iter())
+            .flat_map(|(old, new)| old[..width].iter().zip(new[..width].iter()));
+
+        let mut runs: Vec<(u32, u32, T)> = Vec::new();
+        let mut skipped = 0;
+
+        for (old, new) in pairs {
+            if old == new {
+                skipped += 1;
+                continue;
+            }
+
+            match runs.last_mut() {
+                Some((_, count, last)) if skipped == 0 && last == new => *count += 1,
+                _ => runs.push((skipped, 1, new.clone())),
+            }
+
+            skipped = 0;
+        }
+
+        match full.body {
+            Body::Full(ref full_runs) if full_runs.len() <= runs.len() => full,
+            _ => ChunkMessage {
+                body: Body::Delta(runs),
+                ..full
+            },
+        }
+    }
+
+    fn new(x: i64, y: i64, width: usize, height: usize, body: Body<T>) -> ChunkMessage<T> {
+        ChunkMessage {
+            version: GENERATION_VERSION,
+
+            x,
+            y,
+            width: width as u32,
+            height: height as u32,
+
+            body,
+        }
+    }
+
+    /// Returns whether the message holds only the changes to a chunk.
+    pub fn is_delta(&self) -> bool {
+        matches!(self.body, Body::Delta(_))
+    }
+
+    /// Returns the chunk the message describes. A message holding changes
+    /// needs the chunk they were made from, which is ignored otherwise.
+    pub fn into_chunk(self, previous: Option<&Chunk<T>>) -> Result<Chunk<T>, ProtocolError> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut tiles = Vec::with_capacity(width * height);
+
+        match self.body {
+            Body::Full(runs) => {
+                for (count, tile) in runs {
+                    tiles.extend(std::iter::repeat_n(tile, count as usize));
+                }
+            }
+            Body::Delta(runs) => {
+                let previous = previous
+                    .filter(|chunk| {
+                        chunk.len() == height && chunk.iter().all(|row| row.len() >= width)
+                    })
+                    .ok_or(ProtocolError::MissingBase)?;
+
+                let mut unchanged = previous.iter().flat_map(|row| row[..width].iter());
+
+                for (skipped, count, tile) in runs {
+                    for _ in 0..skipped {
+                        tiles.push(unchanged.next().ok_or(ProtocolError::Malformed)?.clone());
+                    }
+
+                    for _ in 0..count {
+                        unchanged.next().ok_or(ProtocolError::Malformed)?;
+                        tiles.push(tile.clone());
+                    }
+                }
+
+                tiles.extend(unchanged.cloned());
+            }
+        }
+
+        if tiles.len() != width * height {
+            return Err(ProtocolError::Malformed);
+        }
+
+        let mut tiles = tiles.into_iter();
+        Ok((0..height)
+            .map(|_| tiles.by_ref().take(width).collect())
+            .collect())
+    }
+}
+
+impl<T: Serialize> ChunkMessage<T> {
+    /// Returns the bytes of the message.
+    pub fn encode(&self) -> Result<Vec<u8>, ProtocolError> {
+        bincode::serde::encode_to_vec(self, config::standard()).map_err(ProtocolError::Encode)
+    }
+}
+
+impl<T: DeserializeOwned> ChunkMessage<T> {
+    /// Reads a message from its bytes, checking it was sent with the same
+    /// version of the generation algorithms.
+    pub fn decode(bytes: &[u8]) -> Result<ChunkMessage<T>, ProtocolError> {
+        let (message, _): (ChunkMessage<T>, _) =
+            bincode::serde::decode_from_slice(bytes, config::standard())
+                .map_err(ProtocolError::Decode)?;
+
+        version::check(message.version).map_err(ProtocolError::Version)?;
+        Ok(message)
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProtocolError::Encode(ref error) => write!(f, "couldn't encode the message: {}", error),
+            ProtocolError::Decode(ref error) => write!(f, "couldn't decode the message: {}", error),
+            ProtocolError::Version(ref mismatch) => mismatch.fmt(f),
+            ProtocolError::MissingBase => write!(f, "no chunk to apply the changes to"),
+            ProtocolError::Malformed => write!(f, "the message doesn't cover the chunk"),
+        }
+    }
+}
+
+impl Error for ProtocolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ProtocolError::Encode(ref error) => Some(error),
+            ProtocolError::Decode(ref error) => Some(error),
+            ProtocolError::Version(ref mismatch) => Some(mismatch),
+            _ => None,
+        }
+    }
+}
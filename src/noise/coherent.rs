@@ -47,6 +47,7 @@ fn interpolate(v1: f64, v2: f64, a: f64) -> f64 {
 }
 
 #[derive(Default, Debug, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CoherentNoise;
 
 impl NoiseProvider for CoherentNoise {
@@ -70,4 +71,8 @@ impl NoiseProvider for CoherentNoise {
 
         interpolate(v1, v2, yd)
     }
+
+    fn bounds(&self) -> (f64, f64) {
+        (-1.0, 1.0)
+    }
 }
@@ -0,0 +1,118 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/noise/fault.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! A provider of fault-formation noise.
+//!
+//! Fault formation builds terrain by repeatedly splitting the plane along a
+//! random line, raising one side and lowering the other, with each fault
+//! displacing the terrain less than the one before. The result is the
+//! blocky, plateau-heavy terrain of old strategy games.
+//!
+//! The fault lines are derived only from the seed, so every point of the
+//! plane is generated independently and chunks line up exactly. Wrapped in
+//! a `NoiseMap` this can be used anywhere a noise map can:
+//!
+//! ```
+//! # use worldgen::noise::fault::FaultNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+//! let nm = NoiseMap::new(FaultNoise::new().faults(100).extent(4.0))
+//!     .set(Size::of(80, 50))
+//!     .set(Step::of(0.05, 0.05));
+//!
+//! let chunk = nm.generate_chunk(0, 0);
+//! ```
+
+use std::default::Default;
+use std::f64::consts::PI;
+
+use super::NoiseProvider;
+use crate::rng::{hash, unit};
+
+/// The fault-formation noise source.
+///
+/// The defaults are:
+///
+/// ```text
+/// faults = 64
+/// extent = 8.0
+/// ```
+///
+/// The extent is the distance from the origin (in noise coordinates) that
+/// fault lines can pass through; beyond it the terrain flattens out into
+/// a few large blocks.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct FaultNoise {
+    faults: u32,
+    extent: f64,
+}
+
+impl Default for FaultNoise {
+    fn default() -> FaultNoise {
+        FaultNoise {
+            faults: 64,
+            extent: 8.0,
+        }
+    }
+}
+
+impl FaultNoise {
+    /// Construct the default fault noise source.
+    pub fn new() -> FaultNoise {
+        Default::default()
+    }
+
+    /// Set the number of faults.
+    pub fn faults(self, faults: u32) -> FaultNoise {
+        FaultNoise { faults, ..self }
+    }
+
+    /// Set the distance from the origin that fault lines can pass through.
+    pub fn extent(self, extent: f64) -> FaultNoise {
+        FaultNoise { extent, ..self }
+    }
+}
+
+impl NoiseProvider for FaultNoise {
+    fn generate(&self, x: f64, y: f64, seed: u64) -> f64 {
+        let (value, total) = (0..self.faults as u64).fold((0.0, 0.0), |(value, total), fault| {
+            let angle = unit(hash(seed, fault * 2)) * 2.0 * PI;
+            let offset = (unit(hash(seed, fault * 2 + 1)) * 2.0 - 1.0) * self.extent;
+
+            let displacement = 1.0 - fault as f64 / self.faults as f64;
+            let side = if x * angle.cos() + y * angle.sin() > offset {
+                1.0
+            } else {
+                -1.0
+            };
+
+            (value + side * displacement, total + displacement)
+        });
+
+        if total > 0.0 {
+            value / total
+        } else {
+            0.0
+        }
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (-1.0, 1.0)
+    }
+}
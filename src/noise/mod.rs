@@ -18,20 +18,31 @@
 
 //! The Noise module provides generators for different kinds of noise.
 //!
-//! There are currently two different sources for noise: coherent and perlin.
-//! The coherent noise source provides no customisation and is very simple, and
-//! mainly exists to be used by the perlin source, which is the recommended one
-//! to use at the moment.
+//! There are currently three different sources for noise: coherent, perlin,
+//! and fault. The coherent noise source provides no customisation and is very
+//! simple, and mainly exists to be used by the perlin source, which is the
+//! recommended one to use at the moment. The fault source produces blocky
+//! fault-formation terrain instead of smooth noise.
 //!
 //! These generators provide a method for generating a noise value at a specific
 //! location, however are best used in combination with a `NoiseMap`
 
 pub mod coherent;
+pub mod fault;
 pub mod octaved;
 pub mod perlin;
 
 /// The trait for a noise generator.
-pub trait NoiseProvider: Default + Clone + Copy {
+pub trait NoiseProvider: Default + Clone + Copy + Send + Sync {
     /// This method generates a value of noise at the given location, using a given seed.
     fn generate(&self, x: f64, y: f64, seed: u64) -> f64;
+
+    /// Returns the lowest and highest values the generator can produce.
+    ///
+    /// This is used to detect constraints that can never be satisfied, so
+    /// it should never be narrower than the actual range. By default the
+    /// range is unbounded.
+    fn bounds(&self) -> (f64, f64) {
+        (f64::NEG_INFINITY, f64::INFINITY)
+    }
 }
@@ -29,6 +29,9 @@ use std::default::Default;
 
 use super::NoiseProvider;
 
+#[cfg(feature = "gpu")]
+use crate::gpu::{GpuNoise, Layer};
+
 use self::property::Property;
 pub use self::property::{Frequency, Lacunarity, Octaves, Persistence};
 
@@ -48,11 +51,17 @@ mod property;
 /// let value = noise.generate(1.5, 2.5, 15);
 /// ```
 #[derive(Default, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct OctavedNoise<Noise> {
     octaves: Octaves,
+    #[cfg_attr(feature = "serde", serde(rename = "frequency"))]
     freq: Frequency,
+    #[cfg_attr(feature = "serde", serde(rename = "persistence"))]
     pers: Persistence,
+    #[cfg_attr(feature = "serde", serde(rename = "lacunarity"))]
     lacu: Lacunarity,
+    #[cfg_attr(feature = "serde", serde(skip))]
     noise: Noise,
 }
 
@@ -115,4 +124,35 @@ impl<Noise: NoiseProvider> NoiseProvider for OctavedNoise<Noise> {
             value
         })
     }
+
+    fn bounds(&self) -> (f64, f64) {
+        let (low, high) = self.noise.bounds();
+        let magnitude = low.abs().max(high.abs());
+
+        let total: f64 = (0..self.octaves.value)
+            .map(|octave| self.pers.value.abs().powi(octave as i32))
+            .sum();
+
+        (-magnitude * total, magnitude * total)
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl<Noise: GpuNoise> GpuNoise for OctavedNoise<Noise> {
+    fn layers(&self) -> Vec<Layer> {
+        let layers = self.noise.layers();
+
+        (0..self.octaves.value)
+            .flat_map(|octave| {
+                let scale = self.freq.value * self.lacu.value.powi(octave as i32);
+                let weight = self.pers.value.powi(octave as i32);
+
+                layers.iter().map(move |layer| Layer {
+                    scale: layer.scale * scale,
+                    weight: layer.weight * weight,
+                    seed: layer.seed + octave as u64,
+                })
+            })
+            .collect()
+    }
 }
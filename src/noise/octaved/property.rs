@@ -31,6 +31,8 @@ pub trait Property: Default + Copy {
 ///
 /// The default value for this is 8.
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Octaves {
     pub value: u32,
 }
@@ -59,6 +61,8 @@ impl Property for Octaves {
 ///
 /// The default value for this is 1.0
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Frequency {
     pub value: f64,
 }
@@ -86,6 +90,8 @@ impl Property for Frequency {
 ///
 /// The default value for this is 0.5
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Persistence {
     pub value: f64,
 }
@@ -113,6 +119,8 @@ impl Property for Persistence {
 ///
 /// The default value for this is 2.0
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Lacunarity {
     pub value: f64,
 }
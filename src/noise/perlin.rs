@@ -39,6 +39,7 @@ use super::{coherent::CoherentNoise, octaved::OctavedNoise};
 pub enum PerlinNoise {}
 
 impl PerlinNoise {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new() -> OctavedNoise<CoherentNoise> {
         OctavedNoise::new(CoherentNoise)
     }
@@ -0,0 +1,441 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/noisemap/cave.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::{
+    next_id, Amplitude, NoiseMapGenerator, NoiseMapGeneratorBase, Property, ScaledNoiseMap, Seed,
+    Size, Step,
+};
+use crate::rng::{hash_all, unit};
+
+use std::ops::Mul;
+
+/// A map of caves grown by a cellular automaton, with a value of 1 for open
+/// cells and 0 for walls.
+///
+/// Every cell starts as a wall at random, with the chance given by the fill.
+/// Then, for each iteration, a cell becomes a wall when at least five of its
+/// eight neighbours are walls, and opens up when at most three are.
+///
+/// The random fill depends only on the seed, and each chunk is grown along
+/// with enough of its surroundings that caves continue seamlessly across
+/// chunk borders.
+///
+/// Unless it is turned off, caves are then joined so that every open cell
+/// can be reached from every other. The world is divided into squares,
+/// `tunnel_spacing` cells across, each with an anchor at a random position
+/// within it, and each anchor is joined to the anchors of the squares to
+/// its right and below by a tunnel with a single bend. Each square is then
+/// flood filled from its anchor, and every cave in the square that isn't
+/// reached is joined to the anchor by a tunnel of its own. As the tunnels
+/// depend only on the seed and the contents of their squares, every cell
+/// depends only on its position, whatever the size of the chunks it is
+/// generated in.
+///
+/// The walls are grown one cell at a time, so the `Step` property has no
+/// effect on this map.
+///
+/// The defaults are:
+///
+/// ```text
+/// fill = 0.45
+/// iterations = 4
+/// connected = true
+/// tunnel_spacing = 16
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noisemap::{CaveMap, NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size};
+/// let caves = CaveMap::new()
+///     .set(Seed::of("caves"))
+///     .set(Size::of(32, 32));
+///
+/// let mask = caves.generate_chunk(0, 0);
+/// assert!(mask.iter().flatten().all(|&v| v == 0.0 || v == 1.0));
+///
+/// let tiles = caves.generate_tiles(0, 0, '#', '.');
+/// assert_eq!(tiles[5][7] == '.', mask[5][7] == 1.0);
+///
+/// // Each cell is the same however it is generated
+/// assert_eq!(caves.get_value(7, 5), mask[5][7]);
+///
+/// let quarter = caves.generate_sized_chunk(Size::of(16, 16), 1, 1);
+/// assert_eq!(quarter[3][2], mask[19][18]);
+///
+/// let whole = caves.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = caves.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+///
+/// Chunks made of whole squares hold the tunnels joining their caves, so
+/// every open cell of such a chunk can be reached from any other:
+///
+/// ```
+/// # use worldgen::noisemap::{CaveMap, NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size};
+/// let caves = CaveMap::new().set(Seed::of("caves")).set(Size::of(64, 64));
+/// let mask = caves.generate_chunk(-1, 2);
+///
+/// let open: Vec<(usize, usize)> = (0..64)
+///     .flat_map(|y| (0..64).map(move |x| (x, y)))
+///     .filter(|&(x, y)| mask[y][x] == 1.0)
+///     .collect();
+///
+/// let mut reached = vec![vec![false; 64]; 64];
+/// let mut stack = vec![open[0]];
+/// while let Some((x, y)) = stack.pop() {
+///     if x < 64 && y < 64 && mask[y][x] == 1.0 && !reached[y][x] {
+///         reached[y][x] = true;
+///         stack.extend([(x + 1, y), (x, y + 1), (x.wrapping_sub(1), y), (x, y.wrapping_sub(1))]);
+///     }
+/// }
+///
+/// assert!(open.iter().all(|&(x, y)| reached[y][x]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CaveMap {
+    seed: Seed,
+    size: Size,
+    amplitude: Amplitude,
+
+    fill: f64,
+    iterations: u32,
+    connected: bool,
+    tunnel_spacing: i64,
+
+    id: u64,
+}
+
+impl Default for CaveMap {
+    fn default() -> CaveMap {
+        CaveMap {
+            seed: Default::default(),
+            size: Default::default(),
+            amplitude: Default::default(),
+
+            fill: 0.45,
+            iterations: 4,
+            connected: true,
+            tunnel_spacing: 16,
+
+            id: next_id(),
+        }
+    }
+}
+
+impl CaveMap {
+    /// Construct a new cave map with the default properties.
+    pub fn new() -> CaveMap {
+        Default::default()
+    }
+
+    /// Set the chance (between 0 and 1) of each cell starting as a wall.
+    pub fn fill(self, fill: f64) -> CaveMap {
+        CaveMap { fill, ..self }
+    }
+
+    /// Set the number of iterations of the cellular automaton.
+    pub fn iterations(self, iterations: u32) -> CaveMap {
+        CaveMap { iterations, ..self }
+    }
+
+    /// Set whether caves are joined together by tunnels.
+    pub fn connected(self, connected: bool) -> CaveMap {
+        CaveMap { connected, ..self }
+    }
+
+    /// Set the distance between the anchors the tunnels join (at least 1).
+    pub fn tunnel_spacing(self, tunnel_spacing: i64) -> CaveMap {
+        CaveMap {
+            tunnel_spacing: tunnel_spacing.max(1),
+            ..self
+        }
+    }
+
+    /// Generates a chunk of caves as tiles, using the given tiles for walls
+    /// and for open cells.
+    pub fn generate_tiles<T: Clone>(&self, x: i64, y: i64, wall: T, open: T) -> Vec<Vec<T>> {
+        self.walls(self.size, x, y)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|is_wall| if is_wall { wall.clone() } else { open.clone() })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns which cells of a chunk are walls.
+    fn walls(&self, size: Size, x: i64, y: i64) -> Vec<Vec<bool>> {
+        let (x0, y0) = (x * size.w, y * size.h);
+        if size.w <= 0 || size.h <= 0 || !self.connected {
+            return self.grow(x0, y0, size.w.max(0), size.h.max(0));
+        }
+
+        // Tunnels are carved a square at a time, so the whole of every
+        // square the chunk overlaps is grown and carved, then cut down to
+        // the chunk.
+        let spacing = self.tunnel_spacing;
+        let (ax, ay) = (x0.div_euclid(spacing), y0.div_euclid(spacing));
+        let (bx, by) = (
+            (x0 + size.w - 1).div_euclid(spacing),
+            (y0 + size.h - 1).div_euclid(spacing),
+        );
+
+        let origin = (ax * spacing, ay * spacing);
+        let mut walls = self.grow(
+            origin.0,
+            origin.1,
+            (bx - ax + 1) * spacing,
+            (by - ay + 1) * spacing,
+        );
+
+        self.carve(&mut walls, origin);
+        for sy in ay..=by {
+            for sx in ax..=bx {
+                self.join(&mut walls, origin, (sx, sy));
+            }
+        }
+
+        let (dx, dy) = ((x0 - origin.0) as usize, (y0 - origin.1) as usize);
+        walls
+            .into_iter()
+            .skip(dy)
+            .take(size.h as usize)
+            .map(|row| row.into_iter().skip(dx).take(size.w as usize).collect())
+            .collect()
+    }
+
+    /// Grows the walls of an area, given the world position of its first
+    /// cell and its size, before any tunnels are carved.
+    fn grow(&self, x0: i64, y0: i64, w: i64, h: i64) -> Vec<Vec<bool>> {
+        // Each iteration needs one more ring of cells around the area.
+        let margin = self.iterations as i64;
+        let (x0, y0) = (x0 - margin, y0 - margin);
+
+        let mut walls: Vec<Vec<bool>> = (y0..y0 + h + 2 * margin)
+            .map(|wy| {
+                (x0..x0 + w + 2 * margin)
+                    .map(|wx| unit(hash_all(self.seed.value, &[wx as u64, wy as u64])) < self.fill)
+                    .collect()
+            })
+            .collect();
+
+        for _ in 0..self.iterations {
+            walls = (1..walls.len() - 1)
+                .map(|cy| {
+                    (1..walls[cy].len() - 1)
+                        .map(|cx| {
+                            let count = (cy - 1..=cy + 1)
+                                .flat_map(|ny| (cx - 1..=cx + 1).map(move |nx| (nx, ny)))
+                                .filter(|&(nx, ny)| (nx, ny) != (cx, cy) && walls[ny][nx])
+                                .count();
+
+                            match count {
+                                5.. => true,
+                                4 => walls[cy][cx],
+                                _ => false,
+                            }
+                        })
+                        .collect()
+                })
+                .collect();
+        }
+
+        walls
+    }
+
+    /// Opens the cells of an area, whose first cell is at the given world
+    /// position, that are crossed by the tunnels between anchors.
+    fn carve(&self, walls: &mut [Vec<bool>], (x0, y0): (i64, i64)) {
+        let (h, w) = (walls.len() as i64, walls.first().map_or(0, Vec::len) as i64);
+        let spacing = self.tunnel_spacing;
+
+        let mut open = |(x, y): (i64, i64)| {
+            if (x0..x0 + w).contains(&x) && (y0..y0 + h).contains(&y) {
+                walls[(y - y0) as usize][(x - x0) as usize] = false;
+            }
+        };
+
+        // A tunnel stays within the squares of the two anchors it joins, so
+        // only the squares overlapping the area, and those just above and
+        // to the left of it, have tunnels crossing it.
+        for sy in y0.div_euclid(spacing) - 1..=(y0 + h - 1).div_euclid(spacing) {
+            for sx in x0.div_euclid(spacing) - 1..=(x0 + w - 1).div_euclid(spacing) {
+                let from = self.anchor(sx, sy);
+
+                for (to, direction) in [(self.anchor(sx + 1, sy), 0), (self.anchor(sx, sy + 1), 1)]
+                {
+                    let bend = hash_all(self.seed.value, &[sx as u64, sy as u64, direction, 1]);
+                    let corner = if bend & 1 == 0 {
+                        (to.0, from.1)
+                    } else {
+                        (from.0, to.1)
+                    };
+
+                    for (a, b) in [(from, corner), (corner, to)] {
+                        for y in a.1.min(b.1)..=a.1.max(b.1) {
+                            for x in a.0.min(b.0)..=a.0.max(b.0) {
+                                open((x, y));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Joins every open cell of a square to its anchor, given the square
+    /// and the walls of an area holding it, whose first cell is at the
+    /// given world position.
+    ///
+    /// The cells reached from the anchor within the square are flood
+    /// filled, and each cave that isn't reached is joined by a tunnel from
+    /// its cell nearest the anchor. Everything used lies within the square,
+    /// so the square is joined the same way in every chunk.
+    fn join(&self, walls: &mut [Vec<bool>], (x0, y0): (i64, i64), (sx, sy): (i64, i64)) {
+        let spacing = self.tunnel_spacing;
+        let (left, top) = ((sx * spacing - x0) as usize, (sy * spacing - y0) as usize);
+        let cells = left..left + spacing as usize;
+        let rows = top..top + spacing as usize;
+
+        let anchor = self.anchor(sx, sy);
+        let anchor = ((anchor.0 - x0) as usize, (anchor.1 - y0) as usize);
+
+        let mut reached = vec![vec![false; walls[0].len()]; walls.len()];
+        let fill = |walls: &[Vec<bool>], reached: &mut [Vec<bool>], start| {
+            let mut found = Vec::new();
+            let mut stack = vec![start];
+
+            while let Some((x, y)) = stack.pop() {
+                if !cells.contains(&x) || !rows.contains(&y) || walls[y][x] || reached[y][x] {
+                    continue;
+                }
+
+                reached[y][x] = true;
+                found.push((x, y));
+                stack.extend([
+                    (x + 1, y),
+                    (x, y + 1),
+                    (x.wrapping_sub(1), y),
+                    (x, y.wrapping_sub(1)),
+                ]);
+            }
+
+            found
+        };
+
+        fill(walls, &mut reached, anchor);
+
+        for y in rows.clone() {
+            for x in cells.clone() {
+                if walls[y][x] || reached[y][x] {
+                    continue;
+                }
+
+                let cave = fill(walls, &mut reached, (x, y));
+                let &(cx, cy) = cave
+                    .iter()
+                    .min_by_key(|&&(cx, cy)| cx.abs_diff(anchor.0) + cy.abs_diff(anchor.1))
+                    .unwrap();
+
+                // Along the cave's row to below or above the anchor, then
+                // up or down to it, reaching any caves passed through.
+                let tunnel = (cx.min(anchor.0)..=cx.max(anchor.0))
+                    .map(|tx| (tx, cy))
+                    .chain((cy.min(anchor.1)..=cy.max(anchor.1)).map(|ty| (anchor.0, ty)));
+
+                for (tx, ty) in tunnel {
+                    walls[ty][tx] = false;
+                    fill(walls, &mut reached, (tx, ty));
+                }
+            }
+        }
+    }
+
+    /// Returns the world position of the anchor of a square of tunnels.
+    fn anchor(&self, sx: i64, sy: i64) -> (i64, i64) {
+        let spacing = self.tunnel_spacing;
+        let offset = |axis: u64| {
+            let hash = hash_all(self.seed.value, &[sx as u64, sy as u64, axis, 0]);
+            (unit(hash) * spacing as f64) as i64 % spacing
+        };
+
+        (sx * spacing + offset(0), sy * spacing + offset(1))
+    }
+}
+
+impl NoiseMapGeneratorBase for CaveMap {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.size;
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        self.walls(size, x, y)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|wall| if wall { 0.0 } else { self.amplitude.value })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (self.amplitude.value.min(0.0), self.amplitude.value.max(0.0))
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl NoiseMapGenerator for CaveMap {
+    fn set<P: Property>(self, property: P) -> CaveMap {
+        property.set_to(self)
+    }
+
+    fn get_size(&self) -> Size {
+        self.size
+    }
+
+    fn set_seed(self, seed: Seed) -> CaveMap {
+        CaveMap { seed, ..self }
+    }
+
+    fn set_step(self, _step: Step) -> CaveMap {
+        self
+    }
+
+    fn set_size(self, size: Size) -> CaveMap {
+        CaveMap { size, ..self }
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> CaveMap {
+        CaveMap { amplitude, ..self }
+    }
+}
+
+impl Mul<i64> for CaveMap {
+    type Output = ScaledNoiseMap<CaveMap>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<CaveMap> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
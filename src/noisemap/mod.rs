@@ -22,21 +22,22 @@
 //! of noise.
 //!
 //! They have properties that can be set to specify the seed used for
-//! noise generation, the size of the generated map, and the scale of the
-//! coordinates used for generation.
+//! noise generation, the size of the generated map, the scale of the
+//! coordinates used for generation, and the amplitude of the generated values.
 //!
 //! A simple noise map is created by wrapping a source of noise, and then
 //! setting the required properties:
 //!
 //! ```
 //! # use worldgen::noise::perlin::PerlinNoise;
-//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed, Size, Step};
+//! # use worldgen::noisemap::{Amplitude, NoiseMap, NoiseMapGenerator, Seed, Size, Step};
 //! let noise = PerlinNoise::new();
 //!
 //! let nm = NoiseMap::new(noise)
 //!     .set(Seed::of("Hello!"))
 //!     .set(Size::of(10, 10))
-//!     .set(Step::of(0.02, 0.02));
+//!     .set(Step::of(0.02, 0.02))
+//!     .set(Amplitude::of(0.5));
 //! ```
 //!
 //! By default, a noise map will generate values between -1 and 1, however
@@ -66,6 +67,25 @@
 //!
 //! This final result will be normalised back between -1 and 1.
 //!
+//! Scaled, modified and combined noise maps work out each value of a chunk
+//! through the whole expression at once, with their samplers, rather than
+//! generating a chunk for every part of it, so deeply nested combinations
+//! only allocate the chunk they return. `generate_chunk_const` generates
+//! chunks of a size fixed at compile time as arrays, and `fill_sized_chunk`
+//! generates them into an existing buffer, so they needn't allocate at all.
+//!
+//! The values of a noise map can also be divided, or raised to a power,
+//! producing a `ModifiedNoiseMap`:
+//!
+//! ```
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator};
+//! # let noise = PerlinNoise::new();
+//! # let nm1 = NoiseMap::new(noise);
+//! # let nm2 = NoiseMap::new(noise);
+//! let nm = nm1.pow(2.0) + nm2 / 2.0;
+//! ```
+//!
 //! Once you have the noise map you want, you can then use it to produce
 //! a vector of rows of values:
 //!
@@ -87,16 +107,31 @@
 //! `generate_chunk` method to generate specific chunks and produce infinite
 //! maps.
 
+#[cfg(feature = "gpu")]
+use crate::gpu::{Gpu, GpuNoise};
 use crate::noise::NoiseProvider;
 
 use std::cmp;
 use std::default::Default;
-use std::ops::{Add, Mul};
+use std::ops::{Add, Div, Mul};
 use std::sync::atomic::{AtomicUsize, Ordering};
-
-pub use self::property::{Property, Seed, Size, Step};
-
+use std::sync::Arc;
+
+pub use self::cave::CaveMap;
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::prefetch::ChunkPrefetcher;
+pub use self::property::{Amplitude, Property, Seed, Size, Step, Time};
+pub use self::sampled::{Interpolation, SampledMap};
+pub use self::symmetry::{SymmetricNoiseMap, Symmetry};
+pub use self::walk::RandomWalkMap;
+
+mod cave;
+#[cfg(not(target_arch = "wasm32"))]
+mod prefetch;
 mod property;
+mod sampled;
+mod symmetry;
+mod walk;
 
 static NEXT_NM_ID: AtomicUsize = AtomicUsize::new(0);
 
@@ -106,13 +141,54 @@ pub fn next_id() -> u64 {
     NEXT_NM_ID.fetch_add(1, Ordering::SeqCst) as u64
 }
 
+/// Generates a chunk of the given size by reading every value from a
+/// sampler.
+fn sample_chunk<F: Fn(usize, usize) -> f64>(size: Size, sampler: F) -> Vec<Vec<f64>> {
+    (0..size.h as usize)
+        .map(|j| (0..size.w as usize).map(|i| sampler(i, j)).collect())
+        .collect()
+}
+
+/// Generates a chunk of the given size with single precision by reading
+/// every value from a sampler, so that no chunk of doubles is built first.
+fn sample_chunk_f32<F: Fn(usize, usize) -> f64>(size: Size, sampler: F) -> Vec<Vec<f32>> {
+    (0..size.h as usize)
+        .map(|j| (0..size.w as usize).map(|i| sampler(i, j) as f32).collect())
+        .collect()
+}
+
+/// Multiplies a range by a factor, keeping the lower bound first.
+fn scale_bounds(low: f64, high: f64, factor: f64) -> (f64, f64) {
+    let (low, high) = (low * factor, high * factor);
+
+    if low <= high {
+        (low, high)
+    } else {
+        (high, low)
+    }
+}
+
 /// Base trait for noise maps. This trait containts functions relevent to
 /// the actual map generation, and is all that is required for constraints
 /// to generate a world.
 ///
-/// `NoiseMap`, `ScaledNoiseMap`, and `NoiseMapCombination` all implement
-/// this trait.
-pub trait NoiseMapGeneratorBase {
+/// `NoiseMap`, `ScaledNoiseMap`, `ModifiedNoiseMap`, and `NoiseMapCombination`
+/// all implement this trait.
+///
+/// Noise maps are shared between the threads generating a world, so they
+/// must be `Send` and `Sync`.
+///
+/// # Chunk sizes
+///
+/// A noise map should give the same value at a position whatever the size
+/// and position of the chunk it is generated in, so that chunks fit
+/// together without seams and `get_value` agrees with `generate_chunk`.
+/// Every noise map in this crate keeps to this, as its example checks.
+///
+/// A world's `rows` and `find` sample noise maps in chunks of the world's
+/// size, but `tile_at`, `trace` and guarantees sample single positions, so
+/// a noise map that doesn't keep to this needs `World::sample_whole_chunks`.
+pub trait NoiseMapGeneratorBase: Send + Sync {
     /// Generates a specific chunk of the noise map.
     ///
     /// This can be used to generate a larger map in smaller parts.
@@ -135,11 +211,112 @@ pub trait NoiseMapGeneratorBase {
     /// ```
     fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>>;
 
+    /// Generates the rectangle of chunks between two chunk coordinates
+    /// (inclusive), stitched together into a single map.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+    /// # let noise = PerlinNoise::new();
+    /// let nm = NoiseMap::new(noise)
+    ///     .set(Size::of(10, 10))
+    ///     .set(Step::of(0.05, 0.05));
+    ///
+    /// // A 3x2 grid of chunks
+    /// let area = nm.generate_area(-1, 0, 1, 1);
+    ///
+    /// assert_eq!(area.len(), 20);
+    /// assert_eq!(area[0].len(), 30);
+    /// assert_eq!(area[12][15], nm.generate_chunk(0, 1)[2][5]);
+    /// ```
+    fn generate_area(&self, x0: i64, y0: i64, x1: i64, y1: i64) -> Vec<Vec<f64>> {
+        let mut area = Vec::new();
+
+        for y in y0..=y1 {
+            let chunks: Vec<_> = (x0..=x1).map(|x| self.generate_chunk(x, y)).collect();
+            let rows = chunks.first().map_or(0, |chunk| chunk.len());
+
+            area.extend((0..rows).map(|row| {
+                chunks
+                    .iter()
+                    .flat_map(|chunk| chunk[row].iter().cloned())
+                    .collect()
+            }));
+        }
+
+        area
+    }
+
     /// Generate a chunk with a given size instead of the noisemap's size.
     ///
     /// This is used when generating a world.
     fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>>;
 
+    /// Generate the single value at the given world coordinate.
+    ///
+    /// This produces the same value as the matching position in a generated
+    /// chunk, without generating the rest of the chunk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+    /// # let noise = PerlinNoise::new();
+    /// let nm = NoiseMap::new(noise)
+    ///     .set(Size::of(10, 10))
+    ///     .set(Step::of(0.05, 0.05));
+    ///
+    /// let value = nm.get_value(13, 4);
+    /// assert_eq!(value, nm.generate_chunk(1, 0)[4][3]);
+    /// ```
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        self.generate_sized_chunk(Size::of(1, 1), world_x, world_y)[0][0]
+    }
+
+    /// Generates a specific chunk of the noise map, storing the values with
+    /// single precision.
+    ///
+    /// This halves the memory needed to keep generated chunks around, which
+    /// is usually more than enough precision for visual terrain.
+    ///
+    /// By default the chunk is generated with double precision and then
+    /// converted, so it briefly needs the memory of both. Noise maps and
+    /// the scaled, modified and combined maps built from them override this
+    /// to read each value from their sampler instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size};
+    /// # let noise = PerlinNoise::new();
+    /// let nm = NoiseMap::new(noise).set(Size::of(10, 10));
+    ///
+    /// let chunk: Vec<Vec<f32>> = nm.generate_chunk_f32(0, 0);
+    ///
+    /// let nm = nm + nm.pow(2.0) * 3;
+    /// let chunk = nm.generate_chunk_f32(1, -2);
+    /// assert_eq!(chunk[7][4], nm.generate_chunk(1, -2)[7][4] as f32);
+    /// ```
+    fn generate_chunk_f32(&self, x: i64, y: i64) -> Vec<Vec<f32>> {
+        self.generate_chunk(x, y)
+            .into_iter()
+            .map(|row| row.into_iter().map(|value| value as f32).collect())
+            .collect()
+    }
+
+    /// Returns the lowest and highest values the noise map can produce.
+    ///
+    /// This is used to detect constraints that can never be satisfied, so
+    /// it should never be narrower than the actual range. By default the
+    /// range is unbounded.
+    fn bounds(&self) -> (f64, f64) {
+        (f64::NEG_INFINITY, f64::INFINITY)
+    }
+
     /// Return the unique id of the noisemap.
     fn id(&self) -> u64;
 }
@@ -166,23 +343,179 @@ pub trait NoiseMapGenerator:
     where
         Self: Sized;
 
+    /// Noise maps with no amplitude of their own keep this default, which
+    /// leaves them unchanged.
+    fn set_amplitude(self, _amplitude: Amplitude) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+
+    /// Noise maps whose values don't change over the year keep this
+    /// default, which leaves them unchanged.
+    fn set_time(self, _time: Time) -> Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+
     /// Returns the size of the noise map.
     fn get_size(&self) -> Size
     where
         Self: Sized;
+
+    /// Returns a sampler reading the values of the chunk of the given size
+    /// at the given chunk coordinate, one at a time. Each value is exactly
+    /// the matching value of `generate_sized_chunk`.
+    ///
+    /// Scaled, modified and combined noise maps build their chunks from
+    /// the samplers of the maps inside them, so that no intermediate chunks
+    /// are needed. By default the chunk is generated once and read from, so
+    /// noise maps that need the whole chunk to work out any of it (such as
+    /// caves) are still correct, while noise maps that can work out each
+    /// value on its own override this to avoid generating the chunk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+    /// # let noise = PerlinNoise::new();
+    /// let nm = NoiseMap::new(noise)
+    ///     .set(Size::of(8, 8))
+    ///     .set(Step::of(0.1, 0.1));
+    /// let nm = nm + nm.pow(2.0) * 3;
+    ///
+    /// let sampler = nm.sampler(Size::of(8, 8), 1, 2);
+    /// assert_eq!(sampler(3, 5), nm.generate_chunk(1, 2)[5][3]);
+    /// ```
+    fn sampler(&self, size: Size, x: i64, y: i64) -> impl Fn(usize, usize) -> f64 + '_
+    where
+        Self: Sized,
+    {
+        let chunk = self.generate_sized_chunk(size, x, y);
+        move |i: usize, j: usize| chunk[j][i]
+    }
+
+    /// Generates a chunk with dimensions fixed at compile time, as an array
+    /// of rows, instead of the noise map's size. The chunk coordinate
+    /// counts chunks of that size.
+    ///
+    /// Small chunks can be kept on the stack, or in an arena, without
+    /// allocating, and loops over them have bounds the compiler knows.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+    /// # let noise = PerlinNoise::new();
+    /// let nm = NoiseMap::new(noise).set(Step::of(0.05, 0.05));
+    ///
+    /// let chunk: [[f64; 16]; 8] = nm.generate_chunk_const(2, -1);
+    /// assert_eq!(chunk[5][3], nm.generate_sized_chunk(Size::of(16, 8), 2, -1)[5][3]);
+    /// ```
+    fn generate_chunk_const<const W: usize, const H: usize>(&self, x: i64, y: i64) -> [[f64; W]; H]
+    where
+        Self: Sized,
+    {
+        let sampler = self.sampler(Size::of(W as i64, H as i64), x, y);
+        std::array::from_fn(|j| std::array::from_fn(|i| sampler(i, j)))
+    }
+
+    /// Generates a chunk of the given size into a slice, one row after
+    /// another, such as part of a larger buffer that is reused.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice is too short to hold the chunk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+    /// # let noise = PerlinNoise::new();
+    /// let nm = NoiseMap::new(noise).set(Step::of(0.05, 0.05));
+    ///
+    /// let mut buffer = [0.0; 32 * 32];
+    /// nm.fill_sized_chunk(Size::of(32, 32), 0, 0, &mut buffer);
+    ///
+    /// assert_eq!(buffer[32 * 4 + 7], nm.get_value(7, 4));
+    /// ```
+    fn fill_sized_chunk(&self, size: Size, x: i64, y: i64, out: &mut [f64])
+    where
+        Self: Sized,
+    {
+        let width = size.w.max(0) as usize;
+        let height = size.h.max(0) as usize;
+        assert!(
+            out.len() >= width * height,
+            "the slice is too short to hold the chunk"
+        );
+
+        if width == 0 {
+            return;
+        }
+
+        let sampler = self.sampler(size, x, y);
+        for (j, row) in out[..width * height].chunks_mut(width).enumerate() {
+            for (i, value) in row.iter_mut().enumerate() {
+                *value = sampler(i, j);
+            }
+        }
+    }
+
+    /// Raise every value of the noise map to the given power.
+    ///
+    /// The sign of each value is preserved, so negative values do not
+    /// produce `NaN` for fractional exponents, and values at zero stay at
+    /// zero even for negative exponents.
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator};
+    /// # let noise = PerlinNoise::new();
+    /// # let nm = NoiseMap::new(noise);
+    /// let mnm = nm.pow(3.0);
+    /// ```
+    fn pow(self, exponent: f64) -> ModifiedNoiseMap<Self>
+    where
+        Self: Sized,
+    {
+        ModifiedNoiseMap::new(self, Modifier::Pow(exponent))
+    }
 }
 
 /// The standard noise map.
 ///
 /// This is the base noise map, and is created by wrapping a
 /// noise source. It has properties that allow the setting of the
-/// generation seed, the size of the generated chunks, and the coordinate
-/// scale.
+/// generation seed, the size of the generated chunks, the coordinate
+/// scale, and the amplitude of the generated values.
+///
+/// Each value depends only on its position, so chunks of any size fit
+/// together:
+///
+/// ```
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+/// # let noise = PerlinNoise::new();
+/// let nm = NoiseMap::new(noise).set(Step::of(0.05, 0.05));
+///
+/// let whole = nm.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = nm.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
 #[derive(Default, Debug, Clone, Copy)]
 pub struct NoiseMap<T> {
     seed: Seed,
     step: Step,
     size: Size,
+    amplitude: Amplitude,
+    time: Time,
 
     noise: T,
 
@@ -195,11 +528,16 @@ pub struct NoiseMap<T> {
 ///
 /// ```
 /// # use worldgen::noise::perlin::PerlinNoise;
-/// # use worldgen::noisemap::NoiseMap;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGeneratorBase, Size};
 /// # let noise = PerlinNoise::new();
 /// # let nm = NoiseMap::new(noise);
 ///
 /// let snm = nm * 5;
+///
+/// // The same values whatever the size of the chunk
+/// let whole = snm.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = snm.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
 /// ```
 #[derive(Debug, Clone, Copy)]
 pub struct ScaledNoiseMap<T> {
@@ -209,6 +547,104 @@ pub struct ScaledNoiseMap<T> {
     id: u64,
 }
 
+/// A modification applied to the values of a `ModifiedNoiseMap`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Modifier {
+    /// Divide each value by the given divisor.
+    ///
+    /// A divisor closer to zero than `f64::EPSILON` is taken as `EPSILON`
+    /// with the same sign, so the values stay finite.
+    Div(f64),
+
+    /// Raise each value to the given power, preserving its sign.
+    ///
+    /// With a positive power, values closer to zero than `f64::EPSILON`
+    /// become zero. With a negative power they are taken as `EPSILON` with
+    /// the same sign, so the values stay finite.
+    Pow(f64),
+}
+
+impl Modifier {
+    /// Apply the modification to a single noise value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noisemap::Modifier;
+    /// assert_eq!(Modifier::Div(2.0).apply(3.0), 1.5);
+    /// assert_eq!(Modifier::Div(0.0).apply(1.0), 1.0 / f64::EPSILON);
+    /// assert_eq!(Modifier::Pow(-1.0).apply(0.0), 1.0 / f64::EPSILON);
+    /// assert_eq!(Modifier::Pow(2.0).apply(-0.5), -0.25);
+    /// ```
+    pub fn apply(self, value: f64) -> f64 {
+        match self {
+            Modifier::Div(divisor) => value / Modifier::away_from_zero(divisor),
+            Modifier::Pow(exponent) if exponent < 0.0 => {
+                let value = Modifier::away_from_zero(value);
+                value.signum() * value.abs().powf(exponent)
+            }
+            Modifier::Pow(_) if value.abs() < f64::EPSILON => 0.0,
+            Modifier::Pow(exponent) => value.signum() * value.abs().powf(exponent),
+        }
+    }
+
+    /// Returns the range of the modified values, given the range of the
+    /// values before they are modified.
+    pub(crate) fn bounds(self, low: f64, high: f64) -> (f64, f64) {
+        match self {
+            Modifier::Div(divisor) => {
+                scale_bounds(low, high, 1.0 / Modifier::away_from_zero(divisor))
+            }
+            Modifier::Pow(exponent) if exponent >= 0.0 => (self.apply(low), self.apply(high)),
+            Modifier::Pow(exponent) => {
+                let furthest = f64::EPSILON.powf(exponent);
+                (-furthest, furthest)
+            }
+        }
+    }
+
+    /// Returns the value, or `EPSILON` with its sign if it is closer to
+    /// zero than that.
+    fn away_from_zero(value: f64) -> f64 {
+        if value.abs() < f64::EPSILON {
+            f64::EPSILON.copysign(value)
+        } else {
+            value
+        }
+    }
+}
+
+/// A modified noise map.
+///
+/// Created when a noise map is divided, or raised to a power:
+///
+/// ```
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size};
+/// # let noise = PerlinNoise::new();
+/// # let nm1 = NoiseMap::new(noise);
+/// # let nm2 = NoiseMap::new(noise);
+///
+/// let mnm1 = nm1 / 2.0;
+/// let mnm2 = nm2.pow(0.5);
+///
+/// // The same values whatever the size of the chunk
+/// let whole = mnm2.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = mnm2.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+///
+/// In a combination a modified noise map has the same weight as a plain
+/// `NoiseMap`.
+#[derive(Debug, Clone, Copy)]
+pub struct ModifiedNoiseMap<T> {
+    nm: T,
+    modifier: Modifier,
+
+    id: u64,
+}
+
 /// A combination of noise maps.
 ///
 /// Created when two noise maps are added together. The resulting noise
@@ -218,12 +654,17 @@ pub struct ScaledNoiseMap<T> {
 ///
 /// ```
 /// # use worldgen::noise::perlin::PerlinNoise;
-/// # use worldgen::noisemap::NoiseMap;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size};
 /// # let noise = PerlinNoise::new();
 /// # let nm1 = NoiseMap::new(noise);
-/// # let nm2 = NoiseMap::new(noise);
+/// # let nm2 = NoiseMap::new(noise).set(Seed::of("detail"));
 ///
 /// let nmc = nm1 + nm2 * 5;
+///
+/// // The same values whatever the size of the chunk
+/// let whole = nmc.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = nmc.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
 /// ```
 #[derive(Debug, Clone, Copy)]
 pub struct NoiseMapCombination<T1, T2> {
@@ -236,6 +677,35 @@ pub struct NoiseMapCombination<T1, T2> {
     id: u64,
 }
 
+/// A shared noise map generates the same values, and has the same id, as
+/// the noise map it shares. This lets one instance be used by many
+/// constraints while only being generated once per chunk.
+impl<NM: NoiseMapGeneratorBase + ?Sized> NoiseMapGeneratorBase for Arc<NM> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        (**self).generate_chunk(x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        (**self).generate_sized_chunk(size, x, y)
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        (**self).get_value(world_x, world_y)
+    }
+
+    fn generate_chunk_f32(&self, x: i64, y: i64) -> Vec<Vec<f32>> {
+        (**self).generate_chunk_f32(x, y)
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (**self).bounds()
+    }
+
+    fn id(&self) -> u64 {
+        (**self).id()
+    }
+}
+
 impl<T: NoiseProvider> NoiseMap<T> {
     /// Construct a new noise map with the default properties.
     pub fn new(noise: T) -> NoiseMap<T> {
@@ -247,6 +717,51 @@ impl<T: NoiseProvider> NoiseMap<T> {
             ..Default::default()
         }
     }
+
+    /// Returns the value at a world position between cells, for maps
+    /// sampled along paths that don't follow the grid.
+    pub fn sample(&self, world_x: f64, world_y: f64) -> f64 {
+        let (dx, dy) = self.time.offset();
+
+        self.noise.generate(
+            world_x * self.step.x + dx,
+            world_y * self.step.y + dy,
+            self.seed.value,
+        ) * self.amplitude.value
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl<T: GpuNoise> NoiseMap<T> {
+    /// Generates a chunk on the GPU, falling back to the CPU if there is
+    /// no GPU to use. See the `gpu` module.
+    pub fn generate_chunk_gpu(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.size;
+        self.generate_sized_chunk_gpu(size, x, y)
+    }
+
+    /// Generates a chunk of the given size on the GPU, falling back to the
+    /// CPU if there is no GPU to use.
+    pub fn generate_sized_chunk_gpu(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let (dx, dy) = self.time.offset();
+        let origin = (
+            (x * size.w) as f64 * self.step.x + dx,
+            (y * size.h) as f64 * self.step.y + dy,
+        );
+
+        Gpu::shared()
+            .and_then(|gpu| {
+                gpu.chunk(
+                    &self.noise.layers(),
+                    origin,
+                    (self.step.x, self.step.y),
+                    self.seed.value,
+                    self.amplitude.value,
+                    size,
+                )
+            })
+            .unwrap_or_else(|| self.generate_sized_chunk(size, x, y))
+    }
 }
 
 impl<T: NoiseProvider> NoiseMapGeneratorBase for NoiseMap<T> {
@@ -255,18 +770,41 @@ impl<T: NoiseProvider> NoiseMapGeneratorBase for NoiseMap<T> {
         self.generate_sized_chunk(size, x, y)
     }
 
+    fn generate_chunk_f32(&self, x: i64, y: i64) -> Vec<Vec<f32>> {
+        let size = self.size;
+
+        (y * size.h..(y + 1) * size.h)
+            .map(|y| {
+                (x * size.w..(x + 1) * size.w)
+                    .map(|x| self.get_value(x, y) as f32)
+                    .collect()
+            })
+            .collect()
+    }
+
     fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let (dx, dy) = self.time.offset();
+
         (y * size.h..(y + 1) * size.h)
-            .map(|y| y as f64 * self.step.y)
+            .map(|y| y as f64 * self.step.y + dy)
             .map(|y| {
                 (x * size.w..(x + 1) * size.w)
-                    .map(|x| x as f64 * self.step.x)
-                    .map(|x| self.noise.generate(x, y, self.seed.value))
+                    .map(|x| x as f64 * self.step.x + dx)
+                    .map(|x| self.noise.generate(x, y, self.seed.value) * self.amplitude.value)
                     .collect()
             })
             .collect()
     }
 
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        self.sample(world_x as f64, world_y as f64)
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        let (low, high) = self.noise.bounds();
+        scale_bounds(low, high, self.amplitude.value)
+    }
+
     fn id(&self) -> u64 {
         self.id
     }
@@ -294,6 +832,26 @@ impl<T: NoiseProvider> NoiseMapGenerator for NoiseMap<T> {
     fn set_size(self, size: Size) -> NoiseMap<T> {
         NoiseMap { size, ..self }
     }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> NoiseMap<T> {
+        NoiseMap { amplitude, ..self }
+    }
+
+    fn set_time(self, time: Time) -> NoiseMap<T> {
+        NoiseMap { time, ..self }
+    }
+
+    fn sampler(&self, size: Size, x: i64, y: i64) -> impl Fn(usize, usize) -> f64 + '_ {
+        let (dx, dy) = self.time.offset();
+        let (left, top) = (x * size.w, y * size.h);
+
+        move |i: usize, j: usize| {
+            let x = (left + i as i64) as f64 * self.step.x + dx;
+            let y = (top + j as i64) as f64 * self.step.y + dy;
+
+            self.noise.generate(x, y, self.seed.value) * self.amplitude.value
+        }
+    }
 }
 
 impl<T: NoiseMapGenerator> NoiseMapGeneratorBase for ScaledNoiseMap<T> {
@@ -303,11 +861,21 @@ impl<T: NoiseMapGenerator> NoiseMapGeneratorBase for ScaledNoiseMap<T> {
     }
 
     fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
-        self.nm
-            .generate_sized_chunk(size, x, y)
-            .iter()
-            .map(|row| row.iter().map(|value| value * self.scale as f64).collect())
-            .collect()
+        sample_chunk(size, self.sampler(size, x, y))
+    }
+
+    fn generate_chunk_f32(&self, x: i64, y: i64) -> Vec<Vec<f32>> {
+        let size = self.get_size();
+        sample_chunk_f32(size, self.sampler(size, x, y))
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        self.nm.get_value(world_x, world_y) * self.scale as f64
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        let (low, high) = self.nm.bounds();
+        scale_bounds(low, high, self.scale as f64)
     }
 
     fn id(&self) -> u64 {
@@ -340,6 +908,19 @@ impl<T: NoiseMapGenerator> NoiseMapGenerator for ScaledNoiseMap<T> {
     fn set_size(self, size: Size) -> ScaledNoiseMap<T> {
         self.set(size)
     }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> ScaledNoiseMap<T> {
+        self.set(amplitude)
+    }
+
+    fn set_time(self, time: Time) -> ScaledNoiseMap<T> {
+        self.set(time)
+    }
+
+    fn sampler(&self, size: Size, x: i64, y: i64) -> impl Fn(usize, usize) -> f64 + '_ {
+        let inner = self.nm.sampler(size, x, y);
+        move |i: usize, j: usize| inner(i, j) * self.scale as f64
+    }
 }
 
 impl<T> ScaledNoiseMap<T> {
@@ -357,6 +938,106 @@ impl<T> ScaledNoiseMap<T> {
     }
 }
 
+impl<T: NoiseMapGenerator> NoiseMapGeneratorBase for ModifiedNoiseMap<T> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.nm.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        sample_chunk(size, self.sampler(size, x, y))
+    }
+
+    fn generate_chunk_f32(&self, x: i64, y: i64) -> Vec<Vec<f32>> {
+        let size = self.get_size();
+        sample_chunk_f32(size, self.sampler(size, x, y))
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        self.modifier.apply(self.nm.get_value(world_x, world_y))
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        let (low, high) = self.nm.bounds();
+        self.modifier.bounds(low, high)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<T: NoiseMapGenerator> NoiseMapGenerator for ModifiedNoiseMap<T> {
+    fn set<P: Property>(self, property: P) -> ModifiedNoiseMap<T> {
+        ModifiedNoiseMap {
+            nm: self.nm.set(property),
+            ..self
+        }
+    }
+
+    fn get_size(&self) -> Size {
+        self.nm.get_size()
+    }
+
+    fn set_seed(self, seed: Seed) -> ModifiedNoiseMap<T> {
+        self.set(seed)
+    }
+
+    fn set_step(self, step: Step) -> ModifiedNoiseMap<T> {
+        self.set(step)
+    }
+
+    fn set_size(self, size: Size) -> ModifiedNoiseMap<T> {
+        self.set(size)
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> ModifiedNoiseMap<T> {
+        self.set(amplitude)
+    }
+
+    fn set_time(self, time: Time) -> ModifiedNoiseMap<T> {
+        self.set(time)
+    }
+
+    fn sampler(&self, size: Size, x: i64, y: i64) -> impl Fn(usize, usize) -> f64 + '_ {
+        let inner = self.nm.sampler(size, x, y);
+        move |i: usize, j: usize| self.modifier.apply(inner(i, j))
+    }
+}
+
+impl<T> ModifiedNoiseMap<T> {
+    /// Construct a new modified noise map.
+    ///
+    /// Dividing by zero, or raising values near zero to a negative power,
+    /// gives large but finite values rather than infinities. See `Modifier`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase};
+    /// let nm = NoiseMap::new(PerlinNoise::new());
+    ///
+    /// let divided = nm / 0.0;
+    /// assert!(divided.generate_chunk(0, 0).iter().flatten().all(|v| v.is_finite()));
+    ///
+    /// let inverse = nm.pow(-2.0);
+    /// assert!(inverse.generate_chunk(0, 0).iter().flatten().all(|v| v.is_finite()));
+    /// ```
+    pub fn new(nm: T, modifier: Modifier) -> ModifiedNoiseMap<T> {
+        ModifiedNoiseMap {
+            nm,
+            modifier,
+
+            id: next_id(),
+        }
+    }
+
+    pub fn modifier(&self) -> Modifier {
+        self.modifier
+    }
+}
+
 impl<T1: NoiseMapGenerator, T2: NoiseMapGenerator> NoiseMapGeneratorBase
     for NoiseMapCombination<T1, T2>
 {
@@ -366,27 +1047,32 @@ impl<T1: NoiseMapGenerator, T2: NoiseMapGenerator> NoiseMapGeneratorBase
     }
 
     fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        sample_chunk(size, self.sampler(size, x, y))
+    }
+
+    fn generate_chunk_f32(&self, x: i64, y: i64) -> Vec<Vec<f32>> {
+        let size = self.get_size();
+        sample_chunk_f32(size, self.sampler(size, x, y))
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        let value = self.nm1.get_value(world_x, world_y) + self.nm2.get_value(world_x, world_y);
+
+        if self.outer {
+            value / self.total_scale as f64
+        } else {
+            value
+        }
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        let (low1, high1) = self.nm1.bounds();
+        let (low2, high2) = self.nm2.bounds();
+
         if self.outer {
-            let nm1_map = self.nm1.generate_sized_chunk(size, x, y);
-            let nm2_map = self.nm2.generate_sized_chunk(size, x, y);
-            nm1_map
-                .iter()
-                .zip(nm2_map.iter())
-                .map(|(lr, rr)| {
-                    lr.iter()
-                        .zip(rr.iter())
-                        .map(|(lv, rv)| (lv + rv) / self.total_scale as f64)
-                        .collect()
-                })
-                .collect()
+            scale_bounds(low1 + low2, high1 + high2, 1.0 / self.total_scale as f64)
         } else {
-            let nm1_map = self.nm1.generate_sized_chunk(size, x, y);
-            let nm2_map = self.nm2.generate_sized_chunk(size, x, y);
-            nm1_map
-                .iter()
-                .zip(nm2_map.iter())
-                .map(|(lr, rr)| lr.iter().zip(rr.iter()).map(|(lv, rv)| lv + rv).collect())
-                .collect()
+            (low1 + low2, high1 + high2)
         }
     }
 
@@ -423,6 +1109,28 @@ impl<T1: NoiseMapGenerator, T2: NoiseMapGenerator> NoiseMapGenerator
     fn set_size(self, size: Size) -> NoiseMapCombination<T1, T2> {
         self.set(size)
     }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> NoiseMapCombination<T1, T2> {
+        self.set(amplitude)
+    }
+
+    fn set_time(self, time: Time) -> NoiseMapCombination<T1, T2> {
+        self.set(time)
+    }
+
+    fn sampler(&self, size: Size, x: i64, y: i64) -> impl Fn(usize, usize) -> f64 + '_ {
+        let (nm1, nm2) = (self.nm1.sampler(size, x, y), self.nm2.sampler(size, x, y));
+
+        move |i: usize, j: usize| {
+            let value = nm1(i, j) + nm2(i, j);
+
+            if self.outer {
+                value / self.total_scale as f64
+            } else {
+                value
+            }
+        }
+    }
 }
 
 impl<T1, T2> NoiseMapCombination<T1, T2> {
@@ -458,6 +1166,46 @@ impl<T1: NoiseMapGenerator, T2: NoiseMapGenerator> Mul<i64> for NoiseMapCombinat
     }
 }
 
+impl<T: NoiseMapGenerator> Mul<i64> for ModifiedNoiseMap<T> {
+    type Output = ScaledNoiseMap<ModifiedNoiseMap<T>>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<ModifiedNoiseMap<T>> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
+
+impl<T: NoiseProvider> Div<f64> for NoiseMap<T> {
+    type Output = ModifiedNoiseMap<NoiseMap<T>>;
+
+    fn div(self, divisor: f64) -> ModifiedNoiseMap<NoiseMap<T>> {
+        ModifiedNoiseMap::new(self, Modifier::Div(divisor))
+    }
+}
+
+impl<T: NoiseMapGenerator> Div<f64> for ScaledNoiseMap<T> {
+    type Output = ModifiedNoiseMap<ScaledNoiseMap<T>>;
+
+    fn div(self, divisor: f64) -> ModifiedNoiseMap<ScaledNoiseMap<T>> {
+        ModifiedNoiseMap::new(self, Modifier::Div(divisor))
+    }
+}
+
+impl<T: NoiseMapGenerator> Div<f64> for ModifiedNoiseMap<T> {
+    type Output = ModifiedNoiseMap<ModifiedNoiseMap<T>>;
+
+    fn div(self, divisor: f64) -> ModifiedNoiseMap<ModifiedNoiseMap<T>> {
+        ModifiedNoiseMap::new(self, Modifier::Div(divisor))
+    }
+}
+
+impl<T1: NoiseMapGenerator, T2: NoiseMapGenerator> Div<f64> for NoiseMapCombination<T1, T2> {
+    type Output = ModifiedNoiseMap<NoiseMapCombination<T1, T2>>;
+
+    fn div(self, divisor: f64) -> ModifiedNoiseMap<NoiseMapCombination<T1, T2>> {
+        ModifiedNoiseMap::new(self, Modifier::Div(divisor))
+    }
+}
+
 impl<T1: NoiseProvider, T2: NoiseProvider> Add<NoiseMap<T2>> for NoiseMap<T1> {
     type Output = NoiseMapCombination<NoiseMap<T1>, NoiseMap<T2>>;
 
@@ -630,3 +1378,121 @@ impl<
         .set(cmp::max(s1, s2))
     }
 }
+
+impl<T1: NoiseProvider, T2: NoiseMapGenerator> Add<ModifiedNoiseMap<T2>> for NoiseMap<T1> {
+    type Output = NoiseMapCombination<NoiseMap<T1>, ModifiedNoiseMap<T2>>;
+
+    fn add(self, rhs: ModifiedNoiseMap<T2>) -> Self::Output {
+        rhs + self
+    }
+}
+
+impl<T1: NoiseMapGenerator, T2: NoiseMapGenerator> Add<ModifiedNoiseMap<T2>>
+    for ScaledNoiseMap<T1>
+{
+    type Output = NoiseMapCombination<ModifiedNoiseMap<T2>, ScaledNoiseMap<T1>>;
+
+    fn add(self, rhs: ModifiedNoiseMap<T2>) -> Self::Output {
+        rhs + self
+    }
+}
+
+impl<T: NoiseMapGenerator, T1: NoiseMapGenerator, T2: NoiseMapGenerator> Add<ModifiedNoiseMap<T>>
+    for NoiseMapCombination<T1, T2>
+{
+    type Output = NoiseMapCombination<ModifiedNoiseMap<T>, NoiseMapCombination<T1, T2>>;
+
+    fn add(self, rhs: ModifiedNoiseMap<T>) -> Self::Output {
+        rhs + self
+    }
+}
+
+impl<T1: NoiseMapGenerator, T2: NoiseProvider> Add<NoiseMap<T2>> for ModifiedNoiseMap<T1> {
+    type Output = NoiseMapCombination<NoiseMap<T2>, ModifiedNoiseMap<T1>>;
+
+    fn add(self, rhs: NoiseMap<T2>) -> Self::Output {
+        let s1 = self.get_size();
+        let s2 = rhs.get_size();
+
+        NoiseMapCombination {
+            nm1: rhs,
+            nm2: self,
+
+            outer: true,
+            total_scale: 2,
+
+            id: next_id(),
+        }
+        .set(cmp::max(s1, s2))
+    }
+}
+
+impl<T1: NoiseMapGenerator, T2: NoiseMapGenerator> Add<ModifiedNoiseMap<T2>>
+    for ModifiedNoiseMap<T1>
+{
+    type Output = NoiseMapCombination<ModifiedNoiseMap<T1>, ModifiedNoiseMap<T2>>;
+
+    fn add(self, rhs: ModifiedNoiseMap<T2>) -> Self::Output {
+        let s1 = self.get_size();
+        let s2 = rhs.get_size();
+
+        NoiseMapCombination {
+            nm1: self,
+            nm2: rhs,
+
+            outer: true,
+            total_scale: 2,
+
+            id: next_id(),
+        }
+        .set(cmp::max(s1, s2))
+    }
+}
+
+impl<T1: NoiseMapGenerator, T2: NoiseMapGenerator> Add<ScaledNoiseMap<T2>>
+    for ModifiedNoiseMap<T1>
+{
+    type Output = NoiseMapCombination<ModifiedNoiseMap<T1>, ScaledNoiseMap<T2>>;
+
+    fn add(self, rhs: ScaledNoiseMap<T2>) -> Self::Output {
+        let scale = rhs.scale();
+
+        let s1 = self.get_size();
+        let s2 = rhs.get_size();
+
+        NoiseMapCombination {
+            nm1: self,
+            nm2: rhs,
+
+            outer: true,
+            total_scale: 1 + scale,
+
+            id: next_id(),
+        }
+        .set(cmp::max(s1, s2))
+    }
+}
+
+impl<T: NoiseMapGenerator, T1: NoiseMapGenerator, T2: NoiseMapGenerator>
+    Add<NoiseMapCombination<T1, T2>> for ModifiedNoiseMap<T>
+{
+    type Output = NoiseMapCombination<ModifiedNoiseMap<T>, NoiseMapCombination<T1, T2>>;
+
+    fn add(self, rhs: NoiseMapCombination<T1, T2>) -> Self::Output {
+        let scale = rhs.total_scale;
+
+        let s1 = self.get_size();
+        let s2 = rhs.get_size();
+
+        NoiseMapCombination {
+            nm1: self,
+            nm2: rhs.inner(),
+
+            outer: true,
+            total_scale: 1 + scale,
+
+            id: next_id(),
+        }
+        .set(cmp::max(s1, s2))
+    }
+}
@@ -0,0 +1,385 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/noisemap/prefetch.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::NoiseMapGeneratorBase;
+use crate::pool::GenPool;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Coord = (i64, i64);
+
+/// The largest prefetch radius, at which a request queues 289 chunks.
+const MAX_RADIUS: i64 = 8;
+
+struct State {
+    queue: VecDeque<Coord>,
+    pending: HashSet<Coord>,
+    chunks: HashMap<Coord, Arc<Vec<Vec<f64>>>>,
+    order: VecDeque<Coord>,
+
+    radius: i64,
+    capacity: Option<usize>,
+    running: bool,
+}
+
+struct Shared<NM> {
+    nm: NM,
+
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+/// Generates the chunks of a noise map on a pool of background threads.
+///
+/// Requesting a chunk queues it for generation, along with the chunks
+/// surrounding it (within the prefetch radius, which defaults to 1), so that
+/// a caller moving through the map will usually find the next chunk already
+/// generated. Generated chunks are cached until they are evicted, or pushed
+/// out of a cache with a capacity set by `set_capacity`.
+///
+/// Chunks can also be generated on a `GenPool` shared with the rest of the
+/// application, with `with_pool`. As it needs threads, the prefetcher isn't
+/// available on wasm32.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{ChunkPrefetcher, NoiseMap, NoiseMapGenerator, Size, Step};
+/// # let noise = PerlinNoise::new();
+/// let nm = NoiseMap::new(noise)
+///     .set(Size::of(16, 16))
+///     .set(Step::of(0.05, 0.05));
+///
+/// let prefetcher = ChunkPrefetcher::new(nm, 2);
+/// prefetcher.request(0, 0);
+///
+/// // Later on, without blocking:
+/// if let Some(chunk) = prefetcher.try_get(0, 0) {
+///     assert_eq!(chunk.len(), 16);
+/// }
+///
+/// // Or wait for the chunk to be ready:
+/// let chunk = prefetcher.get(0, 0);
+/// assert_eq!(chunk.len(), 16);
+/// ```
+pub struct ChunkPrefetcher<NM> {
+    shared: Arc<Shared<NM>>,
+    workers: Vec<JoinHandle<()>>,
+    pool: Option<GenPool>,
+}
+
+impl<NM: NoiseMapGeneratorBase + Send + Sync + 'static> ChunkPrefetcher<NM> {
+    /// Construct a prefetcher generating chunks of the given noise map
+    /// on the given number of threads (at least one thread is always used).
+    pub fn new(nm: NM, threads: usize) -> ChunkPrefetcher<NM> {
+        let shared = ChunkPrefetcher::share(nm);
+
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || work(&shared))
+            })
+            .collect();
+
+        ChunkPrefetcher {
+            shared,
+            workers,
+            pool: None,
+        }
+    }
+
+    /// Construct a prefetcher generating chunks of the given noise map as
+    /// jobs on the given pool, which may be shared with other generators.
+    ///
+    /// Each chunk queued is a job of its own, so requesting chunks blocks
+    /// while the queue of the pool is full.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{ChunkPrefetcher, NoiseMap, NoiseMapGenerator, Size};
+    /// # use worldgen::pool::GenPool;
+    /// # let noise = PerlinNoise::new();
+    /// let pool = GenPool::new().threads(2);
+    ///
+    /// let nm = NoiseMap::new(noise).set(Size::of(16, 16));
+    /// let prefetcher = ChunkPrefetcher::with_pool(nm, pool.clone());
+    ///
+    /// assert_eq!(prefetcher.get(0, 0).len(), 16);
+    /// ```
+    pub fn with_pool(nm: NM, pool: GenPool) -> ChunkPrefetcher<NM> {
+        ChunkPrefetcher {
+            shared: ChunkPrefetcher::share(nm),
+            workers: Vec::new(),
+            pool: Some(pool),
+        }
+    }
+
+    fn share(nm: NM) -> Arc<Shared<NM>> {
+        Arc::new(Shared {
+            nm,
+
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                pending: HashSet::new(),
+                chunks: HashMap::new(),
+                order: VecDeque::new(),
+
+                radius: 1,
+                capacity: None,
+                running: true,
+            }),
+            changed: Condvar::new(),
+        })
+    }
+
+    /// Queue a chunk for generation, along with its neighbours.
+    ///
+    /// The requested chunk is generated before any chunks that are
+    /// only being prefetched.
+    pub fn request(&self, x: i64, y: i64) {
+        let queued = {
+            let mut state = self.state();
+            let radius = state.radius;
+            let mut queued = 0;
+
+            for ny in y - radius..=y + radius {
+                for nx in x - radius..=x + radius {
+                    if (nx, ny) != (x, y) {
+                        queued += enqueue(&mut state, (nx, ny), false) as usize;
+                    }
+                }
+            }
+
+            queued + enqueue(&mut state, (x, y), true) as usize
+        };
+
+        self.shared.changed.notify_all();
+        self.schedule(queued);
+    }
+
+    /// Returns the chunk, requesting it and blocking until it has been
+    /// generated if necessary.
+    pub fn get(&self, x: i64, y: i64) -> Arc<Vec<Vec<f64>>> {
+        if let Some(chunk) = self.try_get(x, y) {
+            return chunk;
+        }
+
+        self.request(x, y);
+
+        let mut state = self.state();
+        loop {
+            if let Some(chunk) = state.chunks.get(&(x, y)) {
+                return chunk.clone();
+            }
+
+            // The request may have been dropped by a call to `clear`.
+            if !state.pending.contains(&(x, y)) && enqueue(&mut state, (x, y), true) {
+                self.shared.changed.notify_all();
+
+                drop(state);
+                self.schedule(1);
+                state = self.state();
+                continue;
+            }
+
+            state = self.shared.changed.wait(state).unwrap();
+        }
+    }
+
+    /// Queues a job on the pool for each of the chunks that were queued,
+    /// if the prefetcher uses one.
+    fn schedule(&self, queued: usize) {
+        if let Some(ref pool) = self.pool {
+            for _ in 0..queued {
+                let shared = self.shared.clone();
+                pool.execute(move || work_once(&shared));
+            }
+        }
+    }
+}
+
+impl<NM> ChunkPrefetcher<NM> {
+    /// Set the radius (in chunks) of the neighbours that are generated
+    /// speculatively whenever a chunk is requested, between 0 and 8.
+    pub fn set_radius(self, radius: i64) -> ChunkPrefetcher<NM> {
+        self.state().radius = radius.clamp(0, MAX_RADIUS);
+        self
+    }
+
+    /// Set the number of chunks the cache holds, beyond which the chunks
+    /// generated longest ago are evicted. By default the cache is unbounded.
+    ///
+    /// The cache always has room for the chunks of one request, the
+    /// requested chunk and its neighbours within the prefetch radius.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{ChunkPrefetcher, NoiseMap, NoiseMapGenerator};
+    /// # let noise = PerlinNoise::new();
+    /// let prefetcher = ChunkPrefetcher::new(NoiseMap::new(noise), 1)
+    ///     .set_radius(0)
+    ///     .set_capacity(2);
+    ///
+    /// for x in 0..3 {
+    ///     prefetcher.get(x, 0);
+    /// }
+    ///
+    /// assert!(prefetcher.try_get(0, 0).is_none());
+    /// assert!(prefetcher.try_get(2, 0).is_some());
+    /// ```
+    pub fn set_capacity(self, chunks: usize) -> ChunkPrefetcher<NM> {
+        {
+            let mut state = self.state();
+            state.capacity = Some(chunks);
+            trim(&mut state);
+        }
+
+        self
+    }
+
+    /// Returns the chunk if it has already been generated.
+    pub fn try_get(&self, x: i64, y: i64) -> Option<Arc<Vec<Vec<f64>>>> {
+        self.state().chunks.get(&(x, y)).cloned()
+    }
+
+    /// Remove a chunk from the cache.
+    pub fn evict(&self, x: i64, y: i64) -> Option<Arc<Vec<Vec<f64>>>> {
+        let mut state = self.state();
+        state.order.retain(|&coord| coord != (x, y));
+        state.chunks.remove(&(x, y))
+    }
+
+    /// Remove every chunk from the cache, and drop any queued requests.
+    pub fn clear(&self) {
+        let mut state = self.state();
+
+        state.chunks.clear();
+        state.order.clear();
+        state.queue.clear();
+        state.pending.clear();
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.shared.state.lock().unwrap()
+    }
+}
+
+impl<NM> Drop for ChunkPrefetcher<NM> {
+    fn drop(&mut self) {
+        self.state().running = false;
+        self.shared.changed.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// Queues a chunk, returning whether it wasn't already generated or queued.
+fn enqueue(state: &mut State, coord: Coord, urgent: bool) -> bool {
+    if state.chunks.contains_key(&coord) {
+        return false;
+    }
+
+    if state.pending.insert(coord) {
+        if urgent {
+            state.queue.push_front(coord);
+        } else {
+            state.queue.push_back(coord);
+        }
+
+        return true;
+    }
+
+    if urgent {
+        if let Some(index) = state.queue.iter().position(|&queued| queued == coord) {
+            state.queue.remove(index);
+            state.queue.push_front(coord);
+        }
+    }
+
+    false
+}
+
+fn work<NM: NoiseMapGeneratorBase>(shared: &Shared<NM>) {
+    let mut state = shared.state.lock().unwrap();
+
+    while state.running {
+        let (x, y) = match state.queue.pop_front() {
+            Some(coord) => coord,
+            None => {
+                state = shared.changed.wait(state).unwrap();
+                continue;
+            }
+        };
+
+        drop(state);
+        generate(shared, (x, y));
+        state = shared.state.lock().unwrap();
+    }
+}
+
+/// Generates the next queued chunk, as a job on a pool.
+fn work_once<NM: NoiseMapGeneratorBase>(shared: &Shared<NM>) {
+    let coord = {
+        let mut state = shared.state.lock().unwrap();
+
+        match state.queue.pop_front() {
+            Some(coord) if state.running => coord,
+            _ => return,
+        }
+    };
+
+    generate(shared, coord);
+}
+
+fn generate<NM: NoiseMapGeneratorBase>(shared: &Shared<NM>, (x, y): Coord) {
+    let chunk = Arc::new(shared.nm.generate_chunk(x, y));
+    let mut state = shared.state.lock().unwrap();
+
+    // A chunk that was cleared while it was being generated is discarded.
+    if state.pending.remove(&(x, y)) {
+        state.chunks.insert((x, y), chunk);
+        state.order.push_back((x, y));
+        trim(&mut state);
+
+        shared.changed.notify_all();
+    }
+}
+
+/// Evicts the chunks generated longest ago until the cache is within its
+/// capacity, leaving room for the chunks of one request.
+fn trim(state: &mut State) {
+    if let Some(capacity) = state.capacity {
+        let capacity = capacity.max((2 * state.radius + 1).pow(2) as usize);
+
+        while state.chunks.len() > capacity {
+            match state.order.pop_front() {
+                Some(coord) => state.chunks.remove(&coord),
+                None => break,
+            };
+        }
+    }
+}
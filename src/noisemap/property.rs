@@ -19,9 +19,10 @@
 use super::NoiseMapGenerator;
 
 use std::cmp::{Ord, Ordering, PartialOrd};
-use std::collections::hash_map::DefaultHasher;
+use std::convert::Infallible;
 use std::default::Default;
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
 /// A property is an option that can be set on a noise map.
 pub trait Property: Default + Copy {
@@ -29,7 +30,12 @@ pub trait Property: Default + Copy {
 }
 
 /// Sets the seed that is used for generating the noise.
+///
+/// When deserialized, a seed can be given either as its integer value or as
+/// text, which is hashed as by `Seed::of`.
 #[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Seed {
     pub value: u64,
 }
@@ -41,8 +47,22 @@ impl Seed {
     }
 
     /// Sets the seed to the hash of whatever is provided.
+    ///
+    /// The hash is SipHash-1-3 with keys of zero, implemented here rather
+    /// than taken from the standard library, whose hash may change between
+    /// Rust releases. It is the same on every platform and with every
+    /// version of Rust, including wasm32, so a seed of text gives the same
+    /// world in a browser, or in a definition saved long ago, as anywhere
+    /// else.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noisemap::Seed;
+    /// assert_eq!(Seed::of("island").value, 0x3fb167614d2e9ba3);
+    /// ```
     pub fn of<T: Hash>(value: T) -> Seed {
-        let mut hasher = DefaultHasher::new();
+        let mut hasher = PortableHasher::new();
         value.hash(&mut hasher);
 
         Seed {
@@ -51,6 +71,146 @@ impl Seed {
     }
 }
 
+/// Reads a seed from text, as given on a command line or from JavaScript.
+/// Text made only of digits is used as the seed's exact value, and any
+/// other text is hashed as by `Seed::of`.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noisemap::Seed;
+/// assert_eq!("42".parse(), Ok(Seed::of_value(42)));
+/// assert_eq!("hello".parse(), Ok(Seed::of("hello")));
+/// ```
+impl FromStr for Seed {
+    type Err = Infallible;
+
+    fn from_str(text: &str) -> Result<Seed, Infallible> {
+        Ok(text
+            .parse()
+            .map(Seed::of_value)
+            .unwrap_or_else(|_| Seed::of(text)))
+    }
+}
+
+/// SipHash-1-3 with keys of zero, which hashes integers as little endian
+/// and `usize` and `isize` values (such as the lengths of slices) as 64 bit
+/// values, so hashes don't depend on the platform.
+struct PortableHasher {
+    v: [u64; 4],
+    tail: u64,
+    length: usize,
+}
+
+impl PortableHasher {
+    fn new() -> PortableHasher {
+        PortableHasher {
+            v: [
+                0x736f6d6570736575,
+                0x646f72616e646f6d,
+                0x6c7967656e657261,
+                0x7465646279746573,
+            ],
+            tail: 0,
+            length: 0,
+        }
+    }
+
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    fn compress(v: &mut [u64; 4], word: u64) {
+        v[3] ^= word;
+        PortableHasher::round(v);
+        v[0] ^= word;
+    }
+}
+
+impl Hasher for PortableHasher {
+    fn finish(&self) -> u64 {
+        let mut v = self.v;
+        PortableHasher::compress(&mut v, self.tail | (self.length as u64) << 56);
+
+        v[2] ^= 0xff;
+        for _ in 0..3 {
+            PortableHasher::round(&mut v);
+        }
+
+        v[0] ^ v[1] ^ v[2] ^ v[3]
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.tail |= (byte as u64) << (8 * (self.length % 8));
+            self.length += 1;
+
+            if self.length.is_multiple_of(8) {
+                PortableHasher::compress(&mut self.v, self.tail);
+                self.tail = 0;
+            }
+        }
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, value: u128) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Seed {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Seed, D::Error> {
+        struct SeedVisitor;
+
+        impl serde::de::Visitor<'_> for SeedVisitor {
+            type Value = Seed;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "an integer or a string")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Seed, E> {
+                Ok(Seed::of_value(value))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Seed, E> {
+                Ok(Seed::of_value(value as u64))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Seed, E> {
+                Ok(Seed::of(value))
+            }
+        }
+
+        deserializer.deserialize_any(SeedVisitor)
+    }
+}
+
 impl Property for Seed {
     fn set_to<NM: NoiseMapGenerator>(self, nm: NM) -> NM {
         nm.set_seed(self)
@@ -63,6 +223,7 @@ impl Property for Seed {
 /// The default values of this are 0, so if you do not set this then
 /// every value will be the same.
 #[derive(Default, Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Step {
     pub x: f64,
     pub y: f64,
@@ -72,6 +233,31 @@ impl Step {
     pub fn of(x: f64, y: f64) -> Step {
         Step { x, y }
     }
+
+    /// Calculates the step needed for a chunk of the given size to cover
+    /// the given width and height of noise space.
+    ///
+    /// A dimension of the size that is zero results in a step of zero.
+    ///
+    /// ```
+    /// # use worldgen::noisemap::{Size, Step};
+    /// let step = Step::spanning(4.0, 1.0, Size::of(80, 50));
+    /// assert_eq!(step, Step::of(0.05, 0.02));
+    /// ```
+    pub fn spanning(width: f64, height: f64, size: Size) -> Step {
+        let span = |extent: f64, samples: i64| {
+            if samples == 0 {
+                0.0
+            } else {
+                extent / samples as f64
+            }
+        };
+
+        Step {
+            x: span(width, size.w),
+            y: span(height, size.h),
+        }
+    }
 }
 
 impl Property for Step {
@@ -87,6 +273,7 @@ impl Property for Step {
 /// one of the member noisemaps, because the size will be set to whichever is
 /// largest when the combination is created.
 #[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Size {
     pub w: i64,
     pub h: i64,
@@ -104,14 +291,103 @@ impl Property for Size {
     }
 }
 
+/// Sets the amplitude of the noise map, which every generated value is
+/// multiplied by.
+///
+/// Unlike scaling a noise map with the `*` operator, this accepts any
+/// factor and leaves the type of the noise map unchanged.
+///
+/// The default value for this is 1.0
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Amplitude {
+    pub value: f64,
+}
+
+impl Amplitude {
+    pub fn of(value: f64) -> Amplitude {
+        Amplitude { value }
+    }
+}
+
+impl Default for Amplitude {
+    fn default() -> Amplitude {
+        Amplitude { value: 1.0 }
+    }
+}
+
+impl Property for Amplitude {
+    fn set_to<NM: NoiseMapGenerator>(self, nm: NM) -> NM {
+        nm.set_amplitude(self)
+    }
+}
+
+/// Sets the time of year the noise is generated for, as a fraction of the
+/// year between 0 and 1, so that values such as snow cover or rainfall can
+/// change with the seasons.
+///
+/// Over a year the noise is sampled around a loop, ending where it started,
+/// so the values change smoothly and the end of one year runs into the
+/// next. The drift is how far the loop reaches in noise coordinates: the
+/// further it reaches, the more the values change. A time of 0 generates
+/// the same values as a map without one, so maps that shouldn't change,
+/// such as the terrain, can be left alone.
+///
+/// The default values of this are 0, so if you do not set this then the
+/// noise is the same all year.
+///
+/// ```
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Step, Time};
+/// let snow = NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05));
+///
+/// let winter = snow.set(Time::of(0.0));
+/// let summer = snow.set(Time::of(0.5).drift(2.0));
+///
+/// assert_eq!(winter.get_value(3, 4), snow.get_value(3, 4));
+/// assert_ne!(summer.get_value(3, 4), snow.get_value(3, 4));
+/// ```
+#[derive(Default, Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Time {
+    pub value: f64,
+    pub drift: f64,
+}
+
+impl Time {
+    /// Construct a time of year, with a drift of 1.0.
+    pub fn of(value: f64) -> Time {
+        Time { value, drift: 1.0 }
+    }
+
+    /// Set how far the noise moves over a year.
+    pub fn drift(self, drift: f64) -> Time {
+        Time { drift, ..self }
+    }
+
+    /// Returns how far the noise has moved from where it is at the start
+    /// of the year.
+    pub fn offset(&self) -> (f64, f64) {
+        let angle = self.value * 2.0 * std::f64::consts::PI;
+        (self.drift * (angle.cos() - 1.0), self.drift * angle.sin())
+    }
+}
+
+impl Property for Time {
+    fn set_to<NM: NoiseMapGenerator>(self, nm: NM) -> NM {
+        nm.set_time(self)
+    }
+}
+
 impl PartialOrd for Size {
     fn partial_cmp(&self, other: &Size) -> Option<Ordering> {
-        (self.w * self.h).partial_cmp(&(other.w * other.h))
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Size {
     fn cmp(&self, other: &Size) -> Ordering {
-        self.partial_cmp(other).unwrap()
+        (self.w * self.h).cmp(&(other.w * other.h))
     }
 }
@@ -0,0 +1,353 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/noisemap/sampled.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::{
+    next_id, scale_bounds, Amplitude, NoiseMapGenerator, NoiseMapGeneratorBase, Property,
+    ScaledNoiseMap, Seed, Size, Step,
+};
+
+use std::ops::Mul;
+use std::sync::Arc;
+
+/// How a `SampledMap` finds the values between its samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpolation {
+    /// Takes the value of the nearest sample, giving a blocky map.
+    Nearest,
+
+    /// Blends linearly between the four nearest samples.
+    Bilinear,
+
+    /// Fits a smooth curve through the sixteen nearest samples, which can
+    /// overshoot the samples slightly.
+    Bicubic,
+}
+
+/// A noise map of values given in a buffer, such as a heightmap drawn by
+/// hand or exported from another tool, so that it can be used in tile
+/// constraints alongside generated noise.
+///
+/// Each sample of the buffer covers a square of `scale` cells, with its
+/// value at the square's centre, and the values between samples are
+/// interpolated. The buffer's top left corner is placed at the origin, so
+/// a buffer of the same size as a chunk, at a scale of 1, lines up with
+/// the chunk exactly. Outside the buffer the nearest edge value is used,
+/// unless a value for outside it is set.
+///
+/// The buffer is shared between clones of the map. As the values are
+/// given, the `Seed` and `Step` properties have no effect on this map.
+///
+/// The defaults are:
+///
+/// ```text
+/// origin = (0, 0)
+/// scale = 1
+/// interpolation = Bilinear
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # #[macro_use] extern crate worldgen;
+/// # use worldgen::noisemap::{Interpolation, NoiseMapGenerator, NoiseMapGeneratorBase};
+/// # use worldgen::noisemap::{SampledMap, Size};
+/// # use worldgen::world::{Tile, World};
+/// # use worldgen::world::tile::{Constraint, ConstraintType};
+/// # fn main() {
+/// // A 4x4 heightmap, stretched over 4 chunks of 16x16
+/// let heights = vec![
+///     vec![0.0, 0.2, 0.4, 0.2],
+///     vec![0.2, 0.6, 0.8, 0.4],
+///     vec![0.2, 0.8, 1.0, 0.6],
+///     vec![0.0, 0.2, 0.4, 0.2],
+/// ];
+///
+/// let nm = SampledMap::new(heights)
+///     .scale(8.0)
+///     .interpolation(Interpolation::Bicubic)
+///     .set(Size::of(16, 16));
+///
+/// // The centre of the third sample of the third row
+/// assert!((nm.get_value(20, 20) - 1.0).abs() < 0.1);
+///
+/// // The same values whatever the size of the chunk
+/// let whole = nm.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = nm.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+///
+/// let nm = Box::new(nm);
+/// let world = World::new()
+///     .set(Size::of(16, 16))
+///     .add(Tile::new('^').when(constraint!(nm.clone(), > 0.7)))
+///     .add(Tile::new('.').when(constraint!(nm, > 0.1)))
+///     .add(Tile::new('~'));
+///
+/// assert_eq!(world.generate(1, 1).unwrap()[4][4], '^');
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SampledMap {
+    values: Arc<Vec<f64>>,
+    width: usize,
+    height: usize,
+
+    origin: (i64, i64),
+    scale: f64,
+    interpolation: Interpolation,
+    outside: Option<f64>,
+
+    size: Size,
+    amplitude: Amplitude,
+
+    id: u64,
+}
+
+impl SampledMap {
+    /// Construct a map of the given rows of values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no values, or the rows are of different lengths.
+    pub fn new(rows: Vec<Vec<f64>>) -> SampledMap {
+        let width = rows.first().map_or(0, Vec::len);
+        assert!(
+            rows.iter().all(|row| row.len() == width),
+            "the rows of a sampled map must all be the same length"
+        );
+
+        SampledMap::from_flat(width, rows.into_iter().flatten().collect())
+    }
+
+    /// Construct a map of the given values, one row after another, with
+    /// the given number of values in each row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no values, or they don't fill a whole number of
+    /// rows.
+    pub fn from_flat(width: usize, values: Vec<f64>) -> SampledMap {
+        assert!(
+            width > 0 && !values.is_empty() && values.len().is_multiple_of(width),
+            "a sampled map needs at least one value, and only whole rows"
+        );
+
+        SampledMap {
+            width,
+            height: values.len() / width,
+            values: Arc::new(values),
+
+            origin: (0, 0),
+            scale: 1.0,
+            interpolation: Interpolation::Bilinear,
+            outside: None,
+
+            size: Default::default(),
+            amplitude: Default::default(),
+
+            id: next_id(),
+        }
+    }
+
+    /// Set the world position of the buffer's top left corner.
+    pub fn origin(self, x: i64, y: i64) -> SampledMap {
+        SampledMap {
+            origin: (x, y),
+            ..self
+        }
+    }
+
+    /// Set the number of cells across each sample, for buffers at a coarser
+    /// (above 1) or finer (below 1) resolution than the world.
+    pub fn scale(self, scale: f64) -> SampledMap {
+        SampledMap {
+            scale: scale.max(f64::EPSILON),
+            ..self
+        }
+    }
+
+    /// Set how the values between samples are found.
+    pub fn interpolation(self, interpolation: Interpolation) -> SampledMap {
+        SampledMap {
+            interpolation,
+            ..self
+        }
+    }
+
+    /// Set the value of every cell outside the buffer, rather than carrying
+    /// on its edges.
+    pub fn outside(self, value: f64) -> SampledMap {
+        SampledMap {
+            outside: Some(value),
+            ..self
+        }
+    }
+
+    /// Returns the number of samples across and down the buffer.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Returns the sample at the given position in the buffer, carrying on
+    /// the edges beyond it.
+    fn sample(&self, i: i64, j: i64) -> f64 {
+        let i = i.clamp(0, self.width as i64 - 1) as usize;
+        let j = j.clamp(0, self.height as i64 - 1) as usize;
+
+        self.values[j * self.width + i]
+    }
+
+    /// Returns the interpolated value at the given world position, before
+    /// the amplitude is applied.
+    fn interpolate(&self, x: i64, y: i64) -> f64 {
+        // The position in samples, with each sample's centre on a whole
+        // number.
+        let u = (x - self.origin.0) as f64 / self.scale + 0.5 / self.scale - 0.5;
+        let v = (y - self.origin.1) as f64 / self.scale + 0.5 / self.scale - 0.5;
+
+        if let Some(outside) = self.outside {
+            let (w, h) = (self.width as f64, self.height as f64);
+
+            if u < -0.5 || v < -0.5 || u >= w - 0.5 || v >= h - 0.5 {
+                return outside;
+            }
+        }
+
+        // Beyond the outermost samples' centres, the edges carry on.
+        let u = u.clamp(0.0, (self.width - 1) as f64);
+        let v = v.clamp(0.0, (self.height - 1) as f64);
+
+        match self.interpolation {
+            Interpolation::Nearest => self.sample(u.round() as i64, v.round() as i64),
+
+            Interpolation::Bilinear => {
+                let (i, j) = (u.floor() as i64, v.floor() as i64);
+                let (tx, ty) = (u - u.floor(), v - v.floor());
+
+                let top = lerp(self.sample(i, j), self.sample(i + 1, j), tx);
+                let bottom = lerp(self.sample(i, j + 1), self.sample(i + 1, j + 1), tx);
+
+                lerp(top, bottom, ty)
+            }
+
+            Interpolation::Bicubic => {
+                let (i, j) = (u.floor() as i64, v.floor() as i64);
+                let (tx, ty) = (u - u.floor(), v - v.floor());
+
+                let rows: Vec<f64> = (-1..=2)
+                    .map(|dj| {
+                        let row: Vec<f64> =
+                            (-1..=2).map(|di| self.sample(i + di, j + dj)).collect();
+                        cubic(&row, tx)
+                    })
+                    .collect();
+
+                cubic(&rows, ty)
+            }
+        }
+    }
+}
+
+impl NoiseMapGeneratorBase for SampledMap {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.size;
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        (y * size.h..(y + 1) * size.h)
+            .map(|y| {
+                (x * size.w..(x + 1) * size.w)
+                    .map(|x| self.get_value(x, y))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        self.interpolate(world_x, world_y) * self.amplitude.value
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        if self.interpolation == Interpolation::Bicubic {
+            return (f64::NEG_INFINITY, f64::INFINITY);
+        }
+
+        let (low, high) = self
+            .values
+            .iter()
+            .chain(self.outside.iter())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &value| {
+                (low.min(value), high.max(value))
+            });
+
+        scale_bounds(low, high, self.amplitude.value)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl NoiseMapGenerator for SampledMap {
+    fn set<P: Property>(self, property: P) -> SampledMap {
+        property.set_to(self)
+    }
+
+    fn get_size(&self) -> Size {
+        self.size
+    }
+
+    fn set_seed(self, _seed: Seed) -> SampledMap {
+        self
+    }
+
+    fn set_step(self, _step: Step) -> SampledMap {
+        self
+    }
+
+    fn set_size(self, size: Size) -> SampledMap {
+        SampledMap { size, ..self }
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> SampledMap {
+        SampledMap { amplitude, ..self }
+    }
+}
+
+impl Mul<i64> for SampledMap {
+    type Output = ScaledNoiseMap<SampledMap>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<SampledMap> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+/// Interpolates between the middle two of four evenly spaced values with
+/// a Catmull-Rom spline.
+fn cubic(values: &[f64], t: f64) -> f64 {
+    let (p0, p1, p2, p3) = (values[0], values[1], values[2], values[3]);
+
+    p1 + 0.5
+        * t
+        * (p2 - p0 + t * (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3 + t * (3.0 * (p1 - p2) + p3 - p0)))
+}
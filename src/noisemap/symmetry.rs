@@ -0,0 +1,247 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/noisemap/symmetry.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::{
+    next_id, Amplitude, NoiseMapGenerator, NoiseMapGeneratorBase, Property, ScaledNoiseMap, Seed,
+    Size, Step, Time,
+};
+
+use std::ops::Mul;
+
+/// The ways a `SymmetricNoiseMap` repeats its values about its centre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Symmetry {
+    /// Mirrors the west half onto the east half.
+    LeftRight,
+
+    /// Mirrors the north half onto the south half.
+    TopBottom,
+
+    /// Mirrors the north-west quarter onto the other three.
+    FourWay,
+
+    /// Mirrors the half below the diagonal running from north-west to
+    /// south-east onto the half above it.
+    Diagonal,
+
+    /// Repeats the map rotated by half a turn, for two sides.
+    HalfTurn,
+
+    /// Repeats the map rotated by each quarter turn, for four sides.
+    QuarterTurn,
+}
+
+/// A noise map made symmetric, so that maps for competitive games give
+/// every side the same ground.
+///
+/// Every position is reflected or rotated about the centre into one part of
+/// the plane, and takes the value of the wrapped noise map there. The
+/// centre is given in cells, and can fall between two cells by ending in a
+/// half. For the quarter turn, the centre should be on a cell in both
+/// directions, or between cells in both.
+///
+/// The defaults are:
+///
+/// ```text
+/// centre = (-0.5, -0.5)
+/// ```
+///
+/// which puts the centre on the corner between the four chunks around the
+/// origin.
+///
+/// Worlds built from symmetric noise maps choose symmetric tiles, though
+/// post-processing passes and anything else random still break the
+/// symmetry.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase};
+/// # use worldgen::noisemap::{Size, Step, Symmetry, SymmetricNoiseMap};
+/// # let noise = PerlinNoise::new();
+/// # let nm = NoiseMap::new(noise).set(Step::of(0.05, 0.05));
+/// // A 64x64 map for two players, with one corner each
+/// let nm = SymmetricNoiseMap::new(nm, Symmetry::HalfTurn)
+///     .centre(31.5, 31.5)
+///     .set(Size::of(64, 64));
+///
+/// let map = nm.generate_chunk(0, 0);
+/// assert_eq!(map[5][10], map[58][53]);
+///
+/// // The same values whatever the size of the chunk
+/// let whole = nm.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = nm.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct SymmetricNoiseMap<NM> {
+    nm: NM,
+    symmetry: Symmetry,
+
+    // Twice the centre, so that it can fall between cells.
+    centre: (i64, i64),
+
+    id: u64,
+}
+
+impl<NM: NoiseMapGenerator> SymmetricNoiseMap<NM> {
+    /// Construct a noise map repeating the values of the given noise map
+    /// with the given symmetry.
+    pub fn new(nm: NM, symmetry: Symmetry) -> SymmetricNoiseMap<NM> {
+        SymmetricNoiseMap {
+            nm,
+            symmetry,
+
+            centre: (-1, -1),
+
+            id: next_id(),
+        }
+    }
+
+    /// Set the point the values are reflected or rotated about. Each
+    /// coordinate is rounded to the nearest half.
+    pub fn centre(self, x: f64, y: f64) -> SymmetricNoiseMap<NM> {
+        SymmetricNoiseMap {
+            centre: ((x * 2.0).round() as i64, (y * 2.0).round() as i64),
+            ..self
+        }
+    }
+
+    pub fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    /// Returns the position whose value the given position takes.
+    pub fn source(&self, x: i64, y: i64) -> (i64, i64) {
+        let (cx, cy) = self.centre;
+
+        // Offsets from the centre, doubled along with it.
+        let (dx, dy) = (2 * x - cx, 2 * y - cy);
+
+        let (dx, dy) = match self.symmetry {
+            Symmetry::LeftRight => (-dx.abs(), dy),
+            Symmetry::TopBottom => (dx, -dy.abs()),
+            Symmetry::FourWay => (-dx.abs(), -dy.abs()),
+            Symmetry::Diagonal if dx > dy => (dy, dx),
+            Symmetry::Diagonal => (dx, dy),
+            Symmetry::HalfTurn if dx > 0 || (dx == 0 && dy > 0) => (-dx, -dy),
+            Symmetry::HalfTurn => (dx, dy),
+
+            // Turns the offset until it points into the north-west quarter,
+            // which holds exactly one of its four turns.
+            Symmetry::QuarterTurn => {
+                let (mut dx, mut dy) = (dx, dy);
+                for _ in 0..3 {
+                    if (dx < 0 && dy <= 0) || (dx == 0 && dy == 0) {
+                        break;
+                    }
+                    (dx, dy) = (-dy, dx);
+                }
+                (dx, dy)
+            }
+        };
+
+        ((dx + cx).div_euclid(2), (dy + cy).div_euclid(2))
+    }
+}
+
+impl<NM: NoiseMapGenerator> NoiseMapGeneratorBase for SymmetricNoiseMap<NM> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.nm.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let chunk = self.nm.generate_sized_chunk(size, x, y);
+        let (x0, y0) = (x * size.w, y * size.h);
+
+        // Positions that take their own value come from the chunk, and the
+        // rest are sampled one by one.
+        (0..size.h)
+            .map(|j| {
+                (0..size.w)
+                    .map(|i| {
+                        let (sx, sy) = self.source(x0 + i, y0 + j);
+
+                        if (sx, sy) == (x0 + i, y0 + j) {
+                            chunk[j as usize][i as usize]
+                        } else {
+                            self.nm.get_value(sx, sy)
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        let (x, y) = self.source(world_x, world_y);
+        self.nm.get_value(x, y)
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        self.nm.bounds()
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<NM: NoiseMapGenerator> NoiseMapGenerator for SymmetricNoiseMap<NM> {
+    fn set<P: Property>(self, property: P) -> SymmetricNoiseMap<NM> {
+        SymmetricNoiseMap {
+            nm: self.nm.set(property),
+            ..self
+        }
+    }
+
+    fn get_size(&self) -> Size {
+        self.nm.get_size()
+    }
+
+    fn set_seed(self, seed: Seed) -> SymmetricNoiseMap<NM> {
+        self.set(seed)
+    }
+
+    fn set_step(self, step: Step) -> SymmetricNoiseMap<NM> {
+        self.set(step)
+    }
+
+    fn set_size(self, size: Size) -> SymmetricNoiseMap<NM> {
+        self.set(size)
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> SymmetricNoiseMap<NM> {
+        self.set(amplitude)
+    }
+
+    fn set_time(self, time: Time) -> SymmetricNoiseMap<NM> {
+        self.set(time)
+    }
+}
+
+impl<NM: NoiseMapGenerator> Mul<i64> for SymmetricNoiseMap<NM> {
+    type Output = ScaledNoiseMap<SymmetricNoiseMap<NM>>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<SymmetricNoiseMap<NM>> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
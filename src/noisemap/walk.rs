@@ -0,0 +1,210 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/noisemap/walk.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::{
+    next_id, Amplitude, NoiseMapGenerator, NoiseMapGeneratorBase, Property, ScaledNoiseMap, Seed,
+    Size, Step,
+};
+use crate::rng::{hash, hash_all, unit};
+
+use std::ops::Mul;
+
+/// A map of the cells visited by random walks (also known as drunkard's
+/// walks), with a value of 1 for visited cells and 0 everywhere else.
+///
+/// The plane is divided into square regions, and each region has a chance
+/// of being the starting point of a walker, which then takes a number of
+/// steps in random directions. The walkers depend only on the seed, so a
+/// walk that crosses the border of a chunk continues seamlessly into the
+/// next one. The result is winding caves or lakes.
+///
+/// The walk moves one cell at a time, so the `Step` property has no effect
+/// on this map.
+///
+/// The defaults are:
+///
+/// ```text
+/// spacing = 32
+/// steps = 256
+/// density = 0.5
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noisemap::{NoiseMapGenerator, NoiseMapGeneratorBase, RandomWalkMap, Seed, Size};
+/// let nm = RandomWalkMap::new()
+///     .steps(400)
+///     .set(Seed::of("caves"))
+///     .set(Size::of(64, 64));
+///
+/// let chunk = nm.generate_chunk(0, 0);
+/// assert!(chunk.iter().flatten().all(|&v| v == 0.0 || v == 1.0));
+///
+/// // Walks continue across chunk borders
+/// assert_eq!(nm.get_value(70, 3), nm.generate_chunk(1, 0)[3][6]);
+///
+/// let whole = nm.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = nm.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RandomWalkMap {
+    seed: Seed,
+    size: Size,
+    amplitude: Amplitude,
+
+    spacing: i64,
+    steps: u32,
+    density: f64,
+
+    id: u64,
+}
+
+impl Default for RandomWalkMap {
+    fn default() -> RandomWalkMap {
+        RandomWalkMap {
+            seed: Default::default(),
+            size: Default::default(),
+            amplitude: Default::default(),
+
+            spacing: 32,
+            steps: 256,
+            density: 0.5,
+
+            id: next_id(),
+        }
+    }
+}
+
+impl RandomWalkMap {
+    /// Construct a new random walk map with the default properties.
+    pub fn new() -> RandomWalkMap {
+        Default::default()
+    }
+
+    /// Set the width of the square regions that each start at most one
+    /// walker.
+    pub fn spacing(self, spacing: i64) -> RandomWalkMap {
+        RandomWalkMap {
+            spacing: spacing.max(1),
+            ..self
+        }
+    }
+
+    /// Set the number of steps each walker takes.
+    pub fn steps(self, steps: u32) -> RandomWalkMap {
+        RandomWalkMap { steps, ..self }
+    }
+
+    /// Set the chance (between 0 and 1) of a region starting a walker.
+    pub fn density(self, density: f64) -> RandomWalkMap {
+        RandomWalkMap { density, ..self }
+    }
+}
+
+impl NoiseMapGeneratorBase for RandomWalkMap {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.size;
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let mut chunk = vec![vec![0.0; size.w as usize]; size.h as usize];
+
+        let (x0, y0) = (x * size.w, y * size.h);
+        let reach = self.steps as i64;
+
+        // Only walkers starting within reach of the chunk can visit it.
+        let regions = |start: i64, length: i64| {
+            (start - reach).div_euclid(self.spacing)
+                ..=(start + length + reach).div_euclid(self.spacing)
+        };
+
+        for ry in regions(y0, size.h) {
+            for rx in regions(x0, size.w) {
+                let walker = hash_all(self.seed.value, &[rx as u64, ry as u64]);
+
+                if unit(walker) >= self.density {
+                    continue;
+                }
+
+                let mut wx = rx * self.spacing + (hash(walker, 0) % self.spacing as u64) as i64;
+                let mut wy = ry * self.spacing + (hash(walker, 1) % self.spacing as u64) as i64;
+
+                for step in 0..=self.steps {
+                    let (cx, cy) = (wx - x0, wy - y0);
+                    if (0..size.w).contains(&cx) && (0..size.h).contains(&cy) {
+                        chunk[cy as usize][cx as usize] = self.amplitude.value;
+                    }
+
+                    match hash(walker, step as u64 + 2) % 4 {
+                        0 => wx += 1,
+                        1 => wx -= 1,
+                        2 => wy += 1,
+                        _ => wy -= 1,
+                    }
+                }
+            }
+        }
+
+        chunk
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (self.amplitude.value.min(0.0), self.amplitude.value.max(0.0))
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl NoiseMapGenerator for RandomWalkMap {
+    fn set<P: Property>(self, property: P) -> RandomWalkMap {
+        property.set_to(self)
+    }
+
+    fn get_size(&self) -> Size {
+        self.size
+    }
+
+    fn set_seed(self, seed: Seed) -> RandomWalkMap {
+        RandomWalkMap { seed, ..self }
+    }
+
+    fn set_step(self, _step: Step) -> RandomWalkMap {
+        self
+    }
+
+    fn set_size(self, size: Size) -> RandomWalkMap {
+        RandomWalkMap { size, ..self }
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> RandomWalkMap {
+        RandomWalkMap { amplitude, ..self }
+    }
+}
+
+impl Mul<i64> for RandomWalkMap {
+    type Output = ScaledNoiseMap<RandomWalkMap>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<RandomWalkMap> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
@@ -0,0 +1,338 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/path.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Movement costs and pathfinding over generated tiles.
+//!
+//! `cost_map` turns a generated chunk (or a region of noise values) into
+//! the cost of moving into each cell, with `None` for cells that can't be
+//! entered. `PathFinder` searches for the cheapest path between two world
+//! positions with A*, asking for the cost of each cell as it is reached, so
+//! paths can cross any number of chunks. `ChunkCosts` provides those costs
+//! from a world, generating and caching each chunk the search reaches:
+//!
+//! ```
+//! # #[macro_use] extern crate worldgen;
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+//! # use worldgen::path::{ChunkCosts, PathFinder};
+//! # use worldgen::world::{Size, Tile, World};
+//! # use worldgen::world::tile::{Constraint, ConstraintType};
+//! # fn main() {
+//! # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05)));
+//! let world = World::new()
+//!     .set(Size::of(16, 16))
+//!     .add(Tile::new('~').when(constraint!(nm.clone(), < -0.3)))
+//!     .add(Tile::new('^').when(constraint!(nm, > 0.4)))
+//!     .add(Tile::new(','));
+//!
+//! // Water can't be crossed, and mountains are slow going
+//! let mut costs = ChunkCosts::new(&world, |&tile| match tile {
+//!     '~' => None,
+//!     '^' => Some(5.0),
+//!     _ => Some(1.0),
+//! });
+//!
+//! let path = PathFinder::new().find((0, 0), (40, 10), |x, y| costs.cost_at(x, y));
+//!
+//! if let Some(path) = path {
+//!     assert_eq!(path.points.first(), Some(&(0, 0)));
+//!     assert_eq!(path.points.last(), Some(&(40, 10)));
+//! }
+//! # }
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+use std::f64::consts::SQRT_2;
+
+use crate::world::{Chunk, World};
+
+/// The cheapest known cost of reaching each position, along with the
+/// position it is reached from.
+type Reached = HashMap<(i64, i64), (f64, (i64, i64))>;
+
+/// Builds a grid of movement costs from a grid of tiles (or values), as a
+/// vector of rows. The cost function gives the cost of moving into a cell,
+/// or `None` if it can't be entered.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::path::cost_map;
+/// let tiles = vec![vec!['.', '~'], vec!['^', '.']];
+///
+/// let costs = cost_map(&tiles, |&tile| match tile {
+///     '~' => None,
+///     '^' => Some(3.0),
+///     _ => Some(1.0),
+/// });
+///
+/// assert_eq!(costs, vec![vec![Some(1.0), None], vec![Some(3.0), Some(1.0)]]);
+/// ```
+pub fn cost_map<C, F>(grid: &[Vec<C>], cost: F) -> Chunk<Option<f64>>
+where
+    F: Fn(&C) -> Option<f64>,
+{
+    grid.iter()
+        .map(|row| row.iter().map(&cost).collect())
+        .collect()
+}
+
+/// The movement costs of a world's tiles, generated a chunk at a time as
+/// they are asked for.
+///
+/// Positions in chunks that fail to generate can't be entered.
+pub struct ChunkCosts<'a, T, F> {
+    world: &'a World<T>,
+    cost: F,
+    chunks: HashMap<(i64, i64), Option<Chunk<Option<f64>>>>,
+}
+
+impl<'a, T, F> ChunkCosts<'a, T, F>
+where
+    T: Clone + Send + Sync,
+    F: Fn(&T) -> Option<f64>,
+{
+    /// Construct the costs of the given world's tiles, using the given
+    /// cost function.
+    pub fn new(world: &'a World<T>, cost: F) -> ChunkCosts<'a, T, F> {
+        ChunkCosts {
+            world,
+            cost,
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// Returns the cost of moving into the given world position, or `None`
+    /// if it can't be entered.
+    pub fn cost_at(&mut self, x: i64, y: i64) -> Option<f64> {
+        let size = self.world.get_size();
+        let (chunk_x, chunk_y) = (x.div_euclid(size.w), y.div_euclid(size.h));
+
+        let chunk = match self.chunks.entry((chunk_x, chunk_y)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let chunk = self.world.generate(chunk_x, chunk_y).ok();
+                entry.insert(chunk.map(|chunk| cost_map(&chunk, &self.cost)))
+            }
+        };
+
+        chunk.as_ref()?[y.rem_euclid(size.h) as usize][x.rem_euclid(size.w) as usize]
+    }
+
+    /// Returns the number of chunks generated so far.
+    pub fn chunks_generated(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+/// A path between two positions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    /// The positions along the path, including the start and the goal.
+    pub points: Vec<(i64, i64)>,
+
+    /// The total cost of moving along the path.
+    pub cost: f64,
+}
+
+/// An A* path finder.
+///
+/// The defaults are:
+///
+/// ```text
+/// diagonal = true
+/// min cost = 1.0
+/// max nodes = 100000
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct PathFinder {
+    diagonal: bool,
+    min_cost: f64,
+    max_nodes: usize,
+}
+
+impl Default for PathFinder {
+    fn default() -> PathFinder {
+        PathFinder::new()
+    }
+}
+
+/// A position waiting to be visited, ordered so that the binary heap gives
+/// the lowest estimated total cost first.
+struct Open {
+    estimate: f64,
+    position: (i64, i64),
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Open) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Open) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Open) -> Ordering {
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| other.position.cmp(&self.position))
+    }
+}
+
+impl PathFinder {
+    /// Construct a path finder with the default settings.
+    pub fn new() -> PathFinder {
+        PathFinder {
+            diagonal: true,
+            min_cost: 1.0,
+            max_nodes: 100_000,
+        }
+    }
+
+    /// Set whether paths may move diagonally, at `sqrt(2)` times the cost
+    /// of the cell moved into.
+    pub fn diagonal(self, diagonal: bool) -> PathFinder {
+        PathFinder { diagonal, ..self }
+    }
+
+    /// Set the lowest cost of any cell. Paths are only guaranteed to be the
+    /// cheapest if no cell costs less than this, while higher values make
+    /// the search faster.
+    pub fn min_cost(self, min_cost: f64) -> PathFinder {
+        PathFinder {
+            min_cost: min_cost.max(0.0),
+            ..self
+        }
+    }
+
+    /// Set the number of positions visited before giving up.
+    pub fn max_nodes(self, max_nodes: usize) -> PathFinder {
+        PathFinder { max_nodes, ..self }
+    }
+
+    /// Finds the cheapest path from the start to the goal, using the given
+    /// function for the cost of moving into each world position.
+    ///
+    /// Returns `None` if the goal can't be reached, or the search visits too
+    /// many positions first.
+    pub fn find<F>(&self, start: (i64, i64), goal: (i64, i64), mut cost: F) -> Option<Path>
+    where
+        F: FnMut(i64, i64) -> Option<f64>,
+    {
+        let mut open = BinaryHeap::new();
+        let mut best: Reached = HashMap::new();
+        let mut visited = 0;
+
+        best.insert(start, (0.0, start));
+        open.push(Open {
+            estimate: self.heuristic(start, goal),
+            position: start,
+        });
+
+        while let Some(Open { estimate, position }) = open.pop() {
+            let so_far = best[&position].0;
+
+            // Skip positions that were reached more cheaply after being
+            // pushed.
+            if estimate > so_far + self.heuristic(position, goal) {
+                continue;
+            }
+
+            if position == goal {
+                return Some(self.path(&best, start, goal));
+            }
+
+            visited += 1;
+            if visited > self.max_nodes {
+                return None;
+            }
+
+            for (dx, dy) in self.moves() {
+                let next = (position.0 + dx, position.1 + dy);
+                let step = match cost(next.0, next.1) {
+                    Some(step) if dx != 0 && dy != 0 => step * SQRT_2,
+                    Some(step) => step,
+                    None => continue,
+                };
+
+                let total = so_far + step;
+                if best.get(&next).is_some_and(|&(known, _)| known <= total) {
+                    continue;
+                }
+
+                best.insert(next, (total, position));
+                open.push(Open {
+                    estimate: total + self.heuristic(next, goal),
+                    position: next,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Returns the offsets of the positions a path may move to next.
+    fn moves(&self) -> impl Iterator<Item = (i64, i64)> {
+        let diagonal = self.diagonal;
+
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(move |&(dx, dy)| (dx, dy) != (0, 0) && (diagonal || dx == 0 || dy == 0))
+    }
+
+    /// Returns the lowest possible cost of moving between two positions.
+    fn heuristic(&self, (x0, y0): (i64, i64), (x1, y1): (i64, i64)) -> f64 {
+        let (dx, dy) = ((x1 - x0).abs() as f64, (y1 - y0).abs() as f64);
+
+        let distance = if self.diagonal {
+            dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy)
+        } else {
+            dx + dy
+        };
+
+        distance * self.min_cost
+    }
+
+    /// Follows the cheapest moves back from the goal to build the path.
+    fn path(&self, best: &Reached, start: (i64, i64), goal: (i64, i64)) -> Path {
+        let mut points = vec![goal];
+        let mut position = goal;
+
+        while position != start {
+            position = best[&position].1;
+            points.push(position);
+        }
+
+        points.reverse();
+
+        Path {
+            points,
+            cost: best[&goal].0,
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/pipeline/incremental.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::mem;
+use std::ops::RangeInclusive;
+
+use super::{Grid, Inputs, Kind, Layers, PipelineError, WorldPipeline};
+use crate::noisemap::NoiseMapGeneratorBase;
+
+/// The first and last chunk coordinates of a region.
+type Region = ((i64, i64), (i64, i64));
+
+/// A pipeline that keeps the layers of each region it generates, and only
+/// generates stages again once they have changed.
+///
+/// Replacing a stage, or marking it as changed with `invalidate`, throws
+/// away the layers of that stage and of every stage that reads it, in
+/// every region. Generating a region again runs only those stages, reusing
+/// the layers of the rest. Changing the time of year throws away every
+/// stage but plain noise map stages, as any derived stage may read it.
+///
+/// The layers are kept until they are thrown away, or the cache is
+/// cleared with `clear`.
+///
+/// # Example
+///
+/// ```
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// # use std::sync::Arc;
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+/// # use worldgen::pipeline::{IncrementalPipeline, WorldPipeline};
+/// # use worldgen::terrain::FlowMap;
+/// # use worldgen::world::Size;
+/// let heights = NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05));
+///
+/// let flows = Arc::new(AtomicUsize::new(0));
+/// let counter = flows.clone();
+///
+/// let pipeline = WorldPipeline::new(Size::of(16, 16))
+///     .noise("height", Box::new(heights))
+///     .stage("flow", &["height"], move |inputs| {
+///         counter.fetch_add(1, Ordering::Relaxed);
+///         Ok(FlowMap::new(inputs.get("height")?).accumulation().clone())
+///     })
+///     .stage("lakes", &["flow"], |inputs| {
+///         let flow = inputs.get("flow")?;
+///         Ok(inputs.map(|x, y| (flow[y][x] > 10.0) as u8 as f64))
+///     });
+///
+/// let mut editor = IncrementalPipeline::new(pipeline);
+/// editor.generate((0, 0)..=(1, 1)).unwrap();
+///
+/// // Tweak the threshold of the lakes, without running the flow again
+/// editor
+///     .replace_stage("lakes", &["flow"], |inputs| {
+///         let flow = inputs.get("flow")?;
+///         Ok(inputs.map(|x, y| (flow[y][x] > 20.0) as u8 as f64))
+///     })
+///     .unwrap();
+///
+/// let layers = editor.generate((0, 0)..=(1, 1)).unwrap();
+/// assert_eq!(layers.get("lakes").unwrap().len(), 32);
+/// assert_eq!(flows.load(Ordering::Relaxed), 1);
+/// ```
+pub struct IncrementalPipeline {
+    pipeline: WorldPipeline,
+    regions: BTreeMap<Region, Layers>,
+}
+
+impl IncrementalPipeline {
+    /// Construct an incremental pipeline, with nothing generated yet.
+    pub fn new(pipeline: WorldPipeline) -> IncrementalPipeline {
+        IncrementalPipeline {
+            pipeline,
+            regions: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the pipeline being generated.
+    pub fn pipeline(&self) -> &WorldPipeline {
+        &self.pipeline
+    }
+
+    /// Returns the pipeline, dropping the layers that have been generated.
+    pub fn into_pipeline(self) -> WorldPipeline {
+        self.pipeline
+    }
+
+    /// Generates every stage over the chunks between two chunk coordinates
+    /// (inclusive), reusing the layers that haven't changed since the region
+    /// was last generated.
+    pub fn generate(
+        &mut self,
+        region: RangeInclusive<(i64, i64)>,
+    ) -> Result<&Layers, PipelineError> {
+        let names: Vec<String> = self
+            .pipeline
+            .stages
+            .iter()
+            .map(|stage| stage.name.clone())
+            .collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+
+        self.generate_stages(region, &names)
+    }
+
+    /// Generates the named stages, along with the stages they read, over
+    /// the chunks between two chunk coordinates (inclusive), reusing the
+    /// layers that haven't changed since the region was last generated.
+    ///
+    /// The layers returned also hold any other stages still kept for the
+    /// region.
+    pub fn generate_stages(
+        &mut self,
+        region: RangeInclusive<(i64, i64)>,
+        names: &[&str],
+    ) -> Result<&Layers, PipelineError> {
+        let pipeline = &self.pipeline;
+        let layers = self
+            .regions
+            .entry((*region.start(), *region.end()))
+            .or_insert_with(|| pipeline.layers(&region));
+
+        pipeline.fill(&region, names, layers)?;
+        Ok(layers)
+    }
+
+    /// Marks the named stage as changed, such as when a value its function
+    /// reads has been tweaked, so that it and the stages reading it are
+    /// generated again.
+    pub fn invalidate(&mut self, name: &str) -> Result<(), PipelineError> {
+        if !self.pipeline.stages.iter().any(|stage| stage.name == name) {
+            return Err(PipelineError::UnknownStage(name.to_string()));
+        }
+
+        let stale = self.readers(name);
+        self.discard(&stale);
+        Ok(())
+    }
+
+    /// Replaces the noise map of the named stage, which becomes a noise map
+    /// stage if it wasn't already.
+    pub fn replace_noise(
+        &mut self,
+        name: &str,
+        nm: Box<dyn NoiseMapGeneratorBase>,
+    ) -> Result<(), PipelineError> {
+        self.replace(name, Kind::Noise(nm))
+    }
+
+    /// Replaces the function building the noise map of the named stage,
+    /// which becomes a seasonal stage if it wasn't already.
+    pub fn replace_seasonal<F>(&mut self, name: &str, nm: F) -> Result<(), PipelineError>
+    where
+        F: Fn(f64) -> Box<dyn NoiseMapGeneratorBase> + Send + Sync + 'static,
+    {
+        self.replace(name, Kind::Seasonal(Box::new(nm)))
+    }
+
+    /// Replaces the inputs and function of the named stage, which becomes
+    /// a derived stage if it wasn't already.
+    ///
+    /// If the stage would read an unknown stage, or depend on itself, the
+    /// stage is left as it was and the problem is returned.
+    pub fn replace_stage<F>(
+        &mut self,
+        name: &str,
+        inputs: &[&str],
+        stage: F,
+    ) -> Result<(), PipelineError>
+    where
+        F: Fn(&Inputs) -> Result<Grid, PipelineError> + Send + Sync + 'static,
+    {
+        self.replace(
+            name,
+            Kind::Derived(
+                inputs.iter().map(|input| input.to_string()).collect(),
+                Box::new(stage),
+            ),
+        )
+    }
+
+    /// Set the time of year regions are generated for, throwing away the
+    /// stages that may change with it if it's a new time.
+    pub fn set_time(&mut self, time: f64) {
+        if time == self.pipeline.time {
+            return;
+        }
+
+        self.pipeline.time = time;
+
+        let stale: BTreeSet<String> = self
+            .pipeline
+            .stages
+            .iter()
+            .filter(|stage| !matches!(stage.kind, Kind::Noise(_)))
+            .map(|stage| stage.name.clone())
+            .collect();
+        self.discard(&stale);
+    }
+
+    /// Throws away every layer that has been generated.
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    fn replace(&mut self, name: &str, kind: Kind) -> Result<(), PipelineError> {
+        let index = self
+            .pipeline
+            .stages
+            .iter()
+            .position(|stage| stage.name == name)
+            .ok_or_else(|| PipelineError::UnknownStage(name.to_string()))?;
+
+        let old = mem::replace(&mut self.pipeline.stages[index].kind, kind);
+
+        if let Err(error) = self.pipeline.validate() {
+            self.pipeline.stages[index].kind = old;
+            return Err(error);
+        }
+
+        self.invalidate(name)
+    }
+
+    /// Returns the named stage, along with every stage that reads it
+    /// directly or through other stages.
+    fn readers(&self, name: &str) -> BTreeSet<String> {
+        let mut readers = BTreeSet::new();
+        readers.insert(name.to_string());
+
+        loop {
+            let found = readers.len();
+
+            for stage in self.pipeline.stages.iter() {
+                if stage.inputs().iter().any(|input| readers.contains(input)) {
+                    readers.insert(stage.name.clone());
+                }
+            }
+
+            if readers.len() == found {
+                return readers;
+            }
+        }
+    }
+
+    fn discard(&mut self, stale: &BTreeSet<String>) {
+        for layers in self.regions.values_mut() {
+            layers.grids.retain(|name, _| !stale.contains(name));
+        }
+    }
+}
@@ -0,0 +1,629 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/pipeline/mod.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Multi-stage generation of a region.
+//!
+//! Passes such as those in the `terrain` module work on a region of values
+//! that has already been generated, and often feed into each other: a
+//! heightmap is generated, water flow is derived from it, and moisture from
+//! the flow. A `WorldPipeline` holds these stages by name, along with the
+//! names of the stages each one reads, and runs them in order over a
+//! region. Every stage is run at most once per region, however many other
+//! stages read it.
+//!
+//! ```
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed, Step};
+//! # use worldgen::pipeline::WorldPipeline;
+//! # use worldgen::terrain::FlowMap;
+//! # use worldgen::world::Size;
+//! let heights = NoiseMap::new(PerlinNoise::new())
+//!     .set(Seed::of("heights"))
+//!     .set(Step::of(0.05, 0.05));
+//!
+//! let pipeline = WorldPipeline::new(Size::of(16, 16))
+//!     .noise("height", Box::new(heights))
+//!     .stage("flow", &["height"], |inputs| {
+//!         Ok(FlowMap::new(inputs.get("height")?).accumulation().clone())
+//!     })
+//!     .stage("wetness", &["height", "flow"], |inputs| {
+//!         let (heights, flows) = (inputs.get("height")?, inputs.get("flow")?);
+//!         Ok(inputs.map(|x, y| {
+//!             let (height, flow) = (heights[y][x], flows[y][x]);
+//!             if height < 0.0 { 1.0 } else { (flow.ln() / 5.0).min(1.0) }
+//!         }))
+//!     });
+//!
+//! // Two chunks across, one down
+//! let layers = pipeline.generate((0, 0)..=(1, 0)).unwrap();
+//! let wetness = layers.get("wetness").unwrap();
+//! assert_eq!(wetness.len(), 16);
+//! assert_eq!(wetness[0].len(), 32);
+//! ```
+//!
+//! Stages return a `Result`, so that reading a stage that wasn't listed as
+//! an input can be passed on with `?`, and generation stops with an error
+//! rather than a panic.
+//!
+//! The stages a region is generated with can be limited with
+//! `generate_stages`, in which case only those stages and the stages they
+//! read are run.
+//!
+//! A pipeline can be given a time of year, between 0 and 1, so that stages
+//! such as snow cover or river levels change with the seasons while the
+//! terrain stays the same. Seasonal stages build their noise map for the
+//! time being generated, and derived stages can read it from their inputs:
+//!
+//! ```
+//! # use worldgen::climate::TemperatureMap;
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed, Step, Time};
+//! # use worldgen::pipeline::{Layers, WorldPipeline};
+//! # use worldgen::world::Size;
+//! let heights = NoiseMap::new(PerlinNoise::new())
+//!     .set(Seed::of("heights"))
+//!     .set(Step::of(0.05, 0.05));
+//!
+//! let rain = heights.set(Seed::of("rain"));
+//!
+//! let pipeline = WorldPipeline::new(Size::of(16, 16))
+//!     .noise("height", Box::new(heights))
+//!     .seasonal("temperature", move |time| {
+//!         Box::new(
+//!             TemperatureMap::new(heights)
+//!                 .pole_distance(64.0)
+//!                 .season(time)
+//!                 .seasonal_swing(0.5),
+//!         )
+//!     })
+//!     .seasonal("rain", move |time| Box::new(rain.set(Time::of(time))))
+//!     .stage("river level", &["rain"], |inputs| {
+//!         let (rain, wet_season) = (
+//!             inputs.get("rain")?,
+//!             (inputs.time() * std::f64::consts::TAU).sin().max(0.0),
+//!         );
+//!         Ok(inputs.map(|x, y| rain[y][x] + wet_season))
+//!     });
+//!
+//! let winter = pipeline.generate((0, -1)..=(0, -1)).unwrap();
+//! let summer = pipeline.time(0.5).generate((0, -1)..=(0, -1)).unwrap();
+//!
+//! assert_eq!(winter.get("height"), summer.get("height"));
+//!
+//! let temperature = |layers: &Layers| layers.get("temperature").unwrap()[8][8];
+//! assert!(temperature(&summer) > temperature(&winter));
+//! ```
+//!
+//! An `IncrementalPipeline` keeps the layers it has generated, so that when
+//! one stage is changed, as when tweaking a map in an editor, only that
+//! stage and the stages reading it are generated again.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::cancel::{CancelToken, Cancelled};
+use crate::noisemap::{NoiseMapGeneratorBase, Size};
+use crate::progress::Progress;
+
+pub use self::incremental::IncrementalPipeline;
+
+mod incremental;
+
+/// A region of values, as a vector of rows.
+pub type Grid = Vec<Vec<f64>>;
+
+/// A function deriving a stage's values from those of the stages it reads.
+type StageFn = Box<dyn Fn(&Inputs) -> Result<Grid, PipelineError> + Send + Sync>;
+
+/// A function building a stage's noise map for a time of year.
+type SeasonalFn = Box<dyn Fn(f64) -> Box<dyn NoiseMapGeneratorBase> + Send + Sync>;
+
+enum Kind {
+    Noise(Box<dyn NoiseMapGeneratorBase>),
+    Seasonal(SeasonalFn),
+    Derived(Vec<String>, StageFn),
+}
+
+struct Stage {
+    name: String,
+    kind: Kind,
+}
+
+impl Stage {
+    fn inputs(&self) -> &[String] {
+        match self.kind {
+            Kind::Noise(_) | Kind::Seasonal(_) => &[],
+            Kind::Derived(ref inputs, _) => inputs,
+        }
+    }
+}
+
+/// A problem with the stages of a pipeline.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineError {
+    /// More than one stage has the given name.
+    DuplicateStage(String),
+
+    /// The first stage reads the second, which doesn't exist.
+    UnknownInput(String, String),
+
+    /// The given stage was asked for, but doesn't exist.
+    UnknownStage(String),
+
+    /// The given stage depends on itself, through the stages it reads.
+    Cycle(String),
+
+    /// The first stage read the second, which it doesn't list as an input.
+    NotAnInput(String, String),
+
+    /// The values of the given stage were asked for, but weren't generated.
+    NotGenerated(String),
+
+    /// Generation was stopped by the pipeline's `CancelToken`.
+    Cancelled,
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PipelineError::DuplicateStage(ref name) => {
+                write!(f, "there is more than one stage named {:?}", name)
+            }
+            PipelineError::UnknownInput(ref stage, ref input) => {
+                write!(f, "stage {:?} reads an unknown stage {:?}", stage, input)
+            }
+            PipelineError::UnknownStage(ref name) => {
+                write!(f, "there is no stage named {:?}", name)
+            }
+            PipelineError::Cycle(ref name) => write!(f, "stage {:?} depends on itself", name),
+            PipelineError::NotAnInput(ref stage, ref input) => {
+                write!(
+                    f,
+                    "stage {:?} reads {:?}, which is not an input",
+                    stage, input
+                )
+            }
+            PipelineError::NotGenerated(ref name) => {
+                write!(f, "stage {:?} was not generated", name)
+            }
+            PipelineError::Cancelled => Cancelled.fmt(f),
+        }
+    }
+}
+
+impl Error for PipelineError {}
+
+impl From<Cancelled> for PipelineError {
+    fn from(_: Cancelled) -> PipelineError {
+        PipelineError::Cancelled
+    }
+}
+
+/// A chain of named generation stages. See the module documentation.
+pub struct WorldPipeline {
+    size: Size,
+    stages: Vec<Stage>,
+    time: f64,
+    progress: Progress,
+    cancel: CancelToken,
+}
+
+impl WorldPipeline {
+    /// Construct an empty pipeline, whose regions are made of chunks of the
+    /// given size.
+    pub fn new(size: Size) -> WorldPipeline {
+        WorldPipeline {
+            size,
+            stages: Vec::new(),
+            time: 0.0,
+            progress: Default::default(),
+            cancel: Default::default(),
+        }
+    }
+
+    /// Add a stage whose values are generated by a noise map.
+    pub fn noise(self, name: &str, nm: Box<dyn NoiseMapGeneratorBase>) -> WorldPipeline {
+        let mut new = self;
+        new.stages.push(Stage {
+            name: name.to_string(),
+            kind: Kind::Noise(nm),
+        });
+        new
+    }
+
+    /// Add a stage whose values are generated by a noise map that changes
+    /// with the seasons.
+    ///
+    /// The function is given the pipeline's time of year, and builds the
+    /// noise map for it each time the stage is generated.
+    pub fn seasonal<F>(self, name: &str, nm: F) -> WorldPipeline
+    where
+        F: Fn(f64) -> Box<dyn NoiseMapGeneratorBase> + Send + Sync + 'static,
+    {
+        let mut new = self;
+        new.stages.push(Stage {
+            name: name.to_string(),
+            kind: Kind::Seasonal(Box::new(nm)),
+        });
+        new
+    }
+
+    /// Add a stage whose values are derived from those of the named stages
+    /// by the given function.
+    ///
+    /// The function is given the values of the named stages over the whole
+    /// region, and should return values of the same dimensions. Any error
+    /// it returns stops generation. Stages may be added in any order.
+    pub fn stage<F>(self, name: &str, inputs: &[&str], stage: F) -> WorldPipeline
+    where
+        F: Fn(&Inputs) -> Result<Grid, PipelineError> + Send + Sync + 'static,
+    {
+        let mut new = self;
+        new.stages.push(Stage {
+            name: name.to_string(),
+            kind: Kind::Derived(
+                inputs.iter().map(|input| input.to_string()).collect(),
+                Box::new(stage),
+            ),
+        });
+        new
+    }
+
+    /// Set the time of year regions are generated for, as a fraction of
+    /// the year between 0 and 1. The default is 0.
+    pub fn time(self, time: f64) -> WorldPipeline {
+        WorldPipeline { time, ..self }
+    }
+
+    /// Set a callback to be told how far generation has got, for example
+    /// to drive a progress bar.
+    ///
+    /// The callback is given the name of the stage being run, and the
+    /// fraction of it that is done. Noise map stages report as each row of
+    /// chunks is generated, and other stages as they start and finish.
+    pub fn on_progress<F>(self, callback: F) -> WorldPipeline
+    where
+        F: Fn(&str, f64) + Send + Sync + 'static,
+    {
+        WorldPipeline {
+            progress: Progress::new(callback),
+            ..self
+        }
+    }
+
+    /// Set a token that stops generation when it is cancelled. See the
+    /// `cancel` module.
+    ///
+    /// The token is checked before each stage, and before each row of
+    /// chunks of noise map stages.
+    pub fn cancel_on(self, token: CancelToken) -> WorldPipeline {
+        WorldPipeline {
+            cancel: token,
+            ..self
+        }
+    }
+
+    /// Checks that stage names are unique, and that every stage reads
+    /// existing stages without depending on itself.
+    pub fn validate(&self) -> Result<(), PipelineError> {
+        let names: Vec<&str> = self
+            .stages
+            .iter()
+            .map(|stage| stage.name.as_str())
+            .collect();
+        self.order(&names).map(|_| ())
+    }
+
+    /// Generates every stage over the chunks between two chunk coordinates
+    /// (inclusive).
+    pub fn generate(&self, region: RangeInclusive<(i64, i64)>) -> Result<Layers, PipelineError> {
+        let names: Vec<&str> = self
+            .stages
+            .iter()
+            .map(|stage| stage.name.as_str())
+            .collect();
+        self.generate_stages(region, &names)
+    }
+
+    /// Generates the named stages, along with the stages they read, over
+    /// the chunks between two chunk coordinates (inclusive).
+    pub fn generate_stages(
+        &self,
+        region: RangeInclusive<(i64, i64)>,
+        names: &[&str],
+    ) -> Result<Layers, PipelineError> {
+        let mut layers = self.layers(&region);
+        self.fill(&region, names, &mut layers)?;
+        Ok(layers)
+    }
+
+    /// Returns the layers of a region, before any stage is generated.
+    fn layers(&self, region: &RangeInclusive<(i64, i64)>) -> Layers {
+        let &(x0, y0) = region.start();
+
+        Layers {
+            origin: (x0 * self.size.w, y0 * self.size.h),
+            grids: BTreeMap::new(),
+        }
+    }
+
+    /// Generates the named stages, along with the stages they read, that
+    /// are missing from the layers of a region.
+    fn fill(
+        &self,
+        region: &RangeInclusive<(i64, i64)>,
+        names: &[&str],
+        layers: &mut Layers,
+    ) -> Result<(), PipelineError> {
+        let order = self.order(names)?;
+        let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+
+        let width = ((x1 - x0 + 1).max(0) * self.size.w) as usize;
+        let height = ((y1 - y0 + 1).max(0) * self.size.h) as usize;
+
+        for index in order {
+            let stage = &self.stages[index];
+            if layers.grids.contains_key(&stage.name) {
+                continue;
+            }
+
+            let grid = match stage.kind {
+                Kind::Noise(ref nm) => {
+                    self.generate_noise(&stage.name, &**nm, (x0, y0), (x1, y1))?
+                }
+
+                Kind::Seasonal(ref build) => {
+                    let nm = build(self.time);
+                    self.generate_noise(&stage.name, &*nm, (x0, y0), (x1, y1))?
+                }
+
+                Kind::Derived(ref inputs, ref run) => {
+                    self.progress.report(&stage.name, 0, 1);
+
+                    let grid = run(&Inputs {
+                        stage: &stage.name,
+                        origin: layers.origin,
+                        width,
+                        height,
+                        time: self.time,
+                        grids: inputs
+                            .iter()
+                            .map(|input| (input.as_str(), &layers.grids[input]))
+                            .collect(),
+                    })?;
+
+                    self.progress.report(&stage.name, 1, 1);
+                    grid
+                }
+            };
+
+            layers.grids.insert(stage.name.clone(), grid);
+        }
+
+        Ok(())
+    }
+
+    /// Generates a noise map over a region, a chunk at a time.
+    fn generate_noise(
+        &self,
+        name: &str,
+        nm: &dyn NoiseMapGeneratorBase,
+        (x0, y0): (i64, i64),
+        (x1, y1): (i64, i64),
+    ) -> Result<Grid, Cancelled> {
+        let mut grid = Vec::new();
+
+        for y in y0..=y1 {
+            self.cancel.check()?;
+
+            let chunks: Vec<_> = (x0..=x1)
+                .map(|x| nm.generate_sized_chunk(self.size, x, y))
+                .collect();
+
+            grid.extend((0..self.size.h as usize).map(|row| {
+                chunks
+                    .iter()
+                    .flat_map(|chunk| chunk[row].iter().cloned())
+                    .collect()
+            }));
+
+            self.progress
+                .report(name, (y - y0 + 1) as usize, (y1 - y0 + 1) as usize);
+        }
+
+        Ok(grid)
+    }
+
+    /// Returns the indices of the named stages and the stages they read,
+    /// with every stage after the stages it reads.
+    fn order(&self, names: &[&str]) -> Result<Vec<usize>, PipelineError> {
+        let mut indices = BTreeMap::new();
+        for (index, stage) in self.stages.iter().enumerate() {
+            if indices.insert(stage.name.as_str(), index).is_some() {
+                return Err(PipelineError::DuplicateStage(stage.name.clone()));
+            }
+        }
+
+        for stage in self.stages.iter() {
+            if let Some(input) = stage
+                .inputs()
+                .iter()
+                .find(|input| !indices.contains_key(input.as_str()))
+            {
+                return Err(PipelineError::UnknownInput(
+                    stage.name.clone(),
+                    input.clone(),
+                ));
+            }
+        }
+
+        // Depth first, marking stages while their inputs are visited so
+        // that cycles are noticed.
+        let mut visiting = vec![false; self.stages.len()];
+        let mut done = vec![false; self.stages.len()];
+        let mut order = Vec::new();
+
+        fn visit(
+            stages: &[Stage],
+            indices: &BTreeMap<&str, usize>,
+            index: usize,
+            visiting: &mut [bool],
+            done: &mut [bool],
+            order: &mut Vec<usize>,
+        ) -> Result<(), PipelineError> {
+            if done[index] {
+                return Ok(());
+            }
+
+            if visiting[index] {
+                return Err(PipelineError::Cycle(stages[index].name.clone()));
+            }
+
+            visiting[index] = true;
+            for input in stages[index].inputs() {
+                visit(
+                    stages,
+                    indices,
+                    indices[input.as_str()],
+                    visiting,
+                    done,
+                    order,
+                )?;
+            }
+
+            done[index] = true;
+            order.push(index);
+            Ok(())
+        }
+
+        for &name in names {
+            let index = *indices
+                .get(name)
+                .ok_or_else(|| PipelineError::UnknownStage(name.to_string()))?;
+
+            visit(
+                &self.stages,
+                &indices,
+                index,
+                &mut visiting,
+                &mut done,
+                &mut order,
+            )?;
+        }
+
+        Ok(order)
+    }
+}
+
+/// The values of the stages read by a stage, over the region being
+/// generated.
+///
+pub struct Inputs<'a> {
+    stage: &'a str,
+    origin: (i64, i64),
+    width: usize,
+    height: usize,
+    time: f64,
+    grids: BTreeMap<&'a str, &'a Grid>,
+}
+
+impl Inputs<'_> {
+    /// Returns the world position of the first value of the region.
+    pub fn origin(&self) -> (i64, i64) {
+        self.origin
+    }
+
+    /// Returns the width and height of the region.
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Returns the time of year the region is being generated for.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Returns the values of the named stage, or `NotAnInput` if it wasn't
+    /// listed as an input.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::NoiseMap;
+    /// # use worldgen::pipeline::{PipelineError, WorldPipeline};
+    /// # use worldgen::world::Size;
+    /// let pipeline = WorldPipeline::new(Size::of(8, 8))
+    ///     .noise("height", Box::new(NoiseMap::new(PerlinNoise::new())))
+    ///     .noise("rain", Box::new(NoiseMap::new(PerlinNoise::new())))
+    ///     .stage("wetness", &["rain"], |inputs| Ok(inputs.get("height")?.clone()));
+    ///
+    /// assert_eq!(
+    ///     pipeline.generate((0, 0)..=(0, 0)),
+    ///     Err(PipelineError::NotAnInput("wetness".into(), "height".into()))
+    /// );
+    /// ```
+    pub fn get(&self, name: &str) -> Result<&Grid, PipelineError> {
+        self.grids
+            .get(name)
+            .copied()
+            .ok_or_else(|| PipelineError::NotAnInput(self.stage.to_string(), name.to_string()))
+    }
+
+    /// Builds a region of values by calling the given function with each
+    /// position in the region.
+    pub fn map<F: Fn(usize, usize) -> f64>(&self, f: F) -> Grid {
+        (0..self.height)
+            .map(|y| (0..self.width).map(|x| f(x, y)).collect())
+            .collect()
+    }
+}
+
+/// The values of each generated stage of a pipeline, over a region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layers {
+    origin: (i64, i64),
+    grids: BTreeMap<String, Grid>,
+}
+
+impl Layers {
+    /// Returns the world position of the first value of the region.
+    pub fn origin(&self) -> (i64, i64) {
+        self.origin
+    }
+
+    /// Returns the values of the named stage, or `NotGenerated` if it
+    /// wasn't generated.
+    pub fn get(&self, name: &str) -> Result<&Grid, PipelineError> {
+        self.grids
+            .get(name)
+            .ok_or_else(|| PipelineError::NotGenerated(name.to_string()))
+    }
+
+    /// Returns the names of the generated stages, in alphabetical order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.grids.keys().map(String::as_str)
+    }
+
+    /// Returns the values of each generated stage by name.
+    pub fn into_grids(self) -> BTreeMap<String, Grid> {
+        self.grids
+    }
+}
@@ -0,0 +1,461 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/pool.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! A pool of threads for generation, so an application can bound how much
+//! of the machine worldgen uses.
+//!
+//! By default, parallel generation runs on rayon's global pool, which has a
+//! thread for every core, and the background generators start threads of
+//! their own. A `GenPool` can be given to each of them instead:
+//!
+//! * `World::pool` runs the parallel work of a world on the pool, with the
+//!   `parallel` feature enabled.
+//! * `AsyncWorld::with_pool` generates chunks on the pool.
+//! * `ChunkPrefetcher::with_pool` prefetches noise on the pool.
+//!
+//! ```
+//! # use worldgen::pool::GenPool;
+//! # use worldgen::world::{Size, Tile, World};
+//! let pool = GenPool::new().threads(2).queue_depth(64);
+//!
+//! let world = World::new()
+//!     .set(Size::of(16, 16))
+//!     .add(Tile::new(','))
+//!     .pool(pool.clone());
+//!
+//! let chunks = world.par_generate_chunks(&[(0, 0), (1, 0)]);
+//! assert_eq!(chunks.len(), 2);
+//! ```
+//!
+//! A pool and its clones share the same threads, which are started the
+//! first time they are needed and stopped when the last clone is dropped.
+//! With the `pinning` feature, the threads can also be pinned to cores.
+//! As it needs threads, the pool isn't available on wasm32.
+
+#[cfg(not(feature = "parallel"))]
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+use std::thread;
+#[cfg(not(feature = "parallel"))]
+use std::thread::JoinHandle;
+
+/// A piece of work run on a pool.
+type Job = Box<dyn FnOnce() + Send>;
+
+#[cfg(not(feature = "parallel"))]
+thread_local! {
+    /// The queue of the pool the current thread works for, if any.
+    static WORKER: Cell<*const Queue> = const { Cell::new(std::ptr::null()) };
+}
+
+/// A bounded pool of threads for generation. See the module documentation.
+///
+/// The defaults are:
+///
+/// ```text
+/// Threads: the available parallelism of the machine
+/// Pinned: false
+/// Queue depth: unbounded
+/// ```
+#[derive(Clone)]
+pub struct GenPool {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    threads: usize,
+    pinned: bool,
+    queue_depth: Option<usize>,
+}
+
+/// The threads of a pool and its clones, stopped when they are all dropped.
+struct Shared {
+    config: Config,
+    queue: Arc<Queue>,
+
+    #[cfg(not(feature = "parallel"))]
+    workers: OnceLock<Vec<JoinHandle<()>>>,
+
+    #[cfg(feature = "parallel")]
+    rayon: OnceLock<rayon::ThreadPool>,
+}
+
+/// The jobs waiting for a thread.
+struct Queue {
+    state: Mutex<State>,
+    ready: Condvar,
+    space: Condvar,
+}
+
+struct State {
+    jobs: VecDeque<Job>,
+    running: bool,
+}
+
+impl GenPool {
+    /// Construct a pool with the default configuration.
+    pub fn new() -> GenPool {
+        let threads = thread::available_parallelism().map_or(1, usize::from);
+
+        GenPool::start(Config {
+            threads,
+            pinned: false,
+            queue_depth: None,
+        })
+    }
+
+    /// Set the number of threads in the pool (at least one thread is
+    /// always used).
+    ///
+    /// Configuring a pool gives a new pool, which doesn't share its threads
+    /// with any clones of the old one.
+    pub fn threads(self, threads: usize) -> GenPool {
+        GenPool::start(Config {
+            threads: threads.max(1),
+            ..self.shared.config
+        })
+    }
+
+    /// Set whether each thread of the pool is pinned to a core of its own,
+    /// so that generation stays on the same cores as far as possible, with
+    /// the `pinning` feature enabled.
+    ///
+    /// Threads are only pinned on Linux, and only to the cores the process
+    /// is allowed to run on. Elsewhere this has no effect.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::mpsc;
+    /// # use std::thread;
+    /// # use worldgen::pool::GenPool;
+    /// let pool = GenPool::new().threads(2).pinned(true);
+    /// let (sender, receiver) = mpsc::channel();
+    ///
+    /// pool.execute(move || {
+    ///     let cores = thread::available_parallelism().unwrap().get();
+    ///     sender.send(cores).unwrap();
+    /// });
+    ///
+    /// // The job can only run on the core its thread is pinned to
+    /// let cores = receiver.recv().unwrap();
+    /// # #[cfg(target_os = "linux")]
+    /// assert_eq!(cores, 1);
+    /// ```
+    #[cfg(feature = "pinning")]
+    pub fn pinned(self, pinned: bool) -> GenPool {
+        GenPool::start(Config {
+            pinned,
+            ..self.shared.config
+        })
+    }
+
+    /// Set the number of jobs that can wait for a thread (at least one job
+    /// can always wait).
+    ///
+    /// Once the queue is full, queueing more work blocks until one of the
+    /// waiting jobs is started, so a producer can't get far ahead of the
+    /// pool.
+    /// Jobs queueing work on their own pool don't block, and run the work
+    /// themselves instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::mpsc;
+    /// # use worldgen::pool::GenPool;
+    /// let pool = GenPool::new().threads(1).queue_depth(1);
+    /// let (sender, receiver) = mpsc::channel();
+    ///
+    /// let inner = pool.clone();
+    /// pool.execute(move || {
+    ///     for i in 0..4 {
+    ///         let sender = sender.clone();
+    ///         inner.execute(move || sender.send(i).unwrap());
+    ///     }
+    /// });
+    ///
+    /// let mut jobs: Vec<i32> = receiver.iter().take(4).collect();
+    /// jobs.sort();
+    /// assert_eq!(jobs, [0, 1, 2, 3]);
+    /// ```
+    pub fn queue_depth(self, depth: usize) -> GenPool {
+        GenPool::start(Config {
+            queue_depth: Some(depth.max(1)),
+            ..self.shared.config
+        })
+    }
+
+    fn start(config: Config) -> GenPool {
+        let queue = Arc::new(Queue {
+            state: Mutex::new(State {
+                jobs: VecDeque::new(),
+                running: true,
+            }),
+            ready: Condvar::new(),
+            space: Condvar::new(),
+        });
+
+        GenPool {
+            shared: Arc::new(Shared {
+                config,
+                queue,
+
+                #[cfg(not(feature = "parallel"))]
+                workers: OnceLock::new(),
+
+                #[cfg(feature = "parallel")]
+                rayon: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns the number of threads in the pool.
+    pub fn thread_count(&self) -> usize {
+        self.shared.config.threads
+    }
+
+    /// Queue a job to be run on one of the threads of the pool, blocking
+    /// while the queue is full.
+    ///
+    /// Jobs are started in the order they were queued. A job that queues
+    /// another job on its own pool while the queue is full runs it straight
+    /// away instead, as waiting could leave every thread of the pool
+    /// waiting for itself.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::mpsc;
+    /// # use worldgen::pool::GenPool;
+    /// let pool = GenPool::new().threads(2);
+    /// let (sender, receiver) = mpsc::channel();
+    ///
+    /// for i in 0..4 {
+    ///     let sender = sender.clone();
+    ///     pool.execute(move || sender.send(i * i).unwrap());
+    /// }
+    ///
+    /// let mut squares: Vec<i32> = receiver.iter().take(4).collect();
+    /// squares.sort();
+    /// assert_eq!(squares, [0, 1, 4, 9]);
+    /// ```
+    pub fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        let queue = &self.shared.queue;
+
+        {
+            let mut state = queue.state();
+
+            if let Some(depth) = self.shared.config.queue_depth {
+                while state.jobs.len() >= depth {
+                    if self.shared.is_worker() {
+                        drop(state);
+                        return job();
+                    }
+
+                    state = queue.space.wait(state).unwrap();
+                }
+            }
+
+            state.jobs.push_back(Box::new(job));
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            self.shared.workers();
+            queue.ready.notify_one();
+        }
+
+        #[cfg(feature = "parallel")]
+        {
+            // Each task runs whichever job is next, so jobs start in order.
+            let queue = queue.clone();
+
+            self.shared.rayon().spawn(move || {
+                if let Some(job) = queue.next() {
+                    job()
+                }
+            });
+        }
+    }
+
+    /// Runs a function, with any parallel work it does using rayon run on
+    /// the threads of the pool rather than rayon's global pool.
+    ///
+    /// Without the `parallel` feature the function is just called.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::pool::GenPool;
+    /// let pool = GenPool::new().threads(3);
+    /// assert_eq!(pool.install(|| 6 * 7), 42);
+    /// ```
+    pub fn install<R: Send, F: FnOnce() -> R + Send>(&self, f: F) -> R {
+        #[cfg(feature = "parallel")]
+        {
+            self.shared.rayon().install(f)
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            f()
+        }
+    }
+}
+
+impl Default for GenPool {
+    fn default() -> GenPool {
+        GenPool::new()
+    }
+}
+
+impl Shared {
+    #[cfg(not(feature = "parallel"))]
+    fn workers(&self) -> &[JoinHandle<()>] {
+        self.workers.get_or_init(|| {
+            (0..self.config.threads)
+                .map(|index| {
+                    let (queue, pinned) = (self.queue.clone(), self.config.pinned);
+
+                    thread::spawn(move || {
+                        if pinned {
+                            pin(index);
+                        }
+
+                        WORKER.with(|worker| worker.set(Arc::as_ptr(&queue)));
+
+                        while let Some(job) = queue.next() {
+                            job();
+                        }
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Returns whether the current thread is one of the pool's threads.
+    fn is_worker(&self) -> bool {
+        #[cfg(not(feature = "parallel"))]
+        {
+            WORKER.with(|worker| worker.get() == Arc::as_ptr(&self.queue))
+        }
+
+        #[cfg(feature = "parallel")]
+        {
+            self.rayon
+                .get()
+                .is_some_and(|pool| pool.current_thread_index().is_some())
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    fn rayon(&self) -> &rayon::ThreadPool {
+        self.rayon.get_or_init(|| {
+            let pinned = self.config.pinned;
+
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(self.config.threads)
+                .start_handler(move |index| {
+                    if pinned {
+                        pin(index);
+                    }
+                })
+                .build()
+                .expect("couldn't start the threads of the pool")
+        })
+    }
+}
+
+impl Drop for Shared {
+    fn drop(&mut self) {
+        // The threads finish the jobs still queued before they stop.
+        self.queue.state().running = false;
+        self.queue.ready.notify_all();
+
+        #[cfg(not(feature = "parallel"))]
+        if let Some(workers) = self.workers.take() {
+            let current = thread::current().id();
+
+            // A job may drop the last clone of its own pool.
+            for worker in workers {
+                if worker.thread().id() != current {
+                    let _ = worker.join();
+                }
+            }
+        }
+    }
+}
+
+impl Queue {
+    /// Returns the next job, waiting for one while the pool is running.
+    fn next(&self) -> Option<Job> {
+        let mut state = self.state();
+
+        loop {
+            if let Some(job) = state.jobs.pop_front() {
+                self.space.notify_one();
+                return Some(job);
+            }
+
+            if !state.running || cfg!(feature = "parallel") {
+                return None;
+            }
+
+            state = self.ready.wait(state).unwrap();
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Pins the current thread to one of the cores the process may run on.
+#[cfg(all(feature = "pinning", target_os = "linux"))]
+fn pin(index: usize) {
+    use std::mem;
+
+    // SAFETY: `cpu_set_t` is a plain bitmask, for which all zeroes is the
+    // empty set, and both calls are given its real size.
+    unsafe {
+        let size = mem::size_of::<libc::cpu_set_t>();
+
+        let mut allowed: libc::cpu_set_t = mem::zeroed();
+        if libc::sched_getaffinity(0, size, &mut allowed) != 0 {
+            return;
+        }
+
+        let cores: Vec<usize> = (0..libc::CPU_SETSIZE as usize)
+            .filter(|&core| libc::CPU_ISSET(core, &allowed))
+            .collect();
+
+        if cores.is_empty() {
+            return;
+        }
+
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        libc::CPU_SET(cores[index % cores.len()], &mut set);
+        libc::sched_setaffinity(0, size, &set);
+    }
+}
+
+#[cfg(not(all(feature = "pinning", target_os = "linux")))]
+fn pin(_index: usize) {}
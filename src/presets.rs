@@ -0,0 +1,309 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/presets.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Ready-made worlds to start from.
+//!
+//! Each preset is a `WorldDef` of `Terrain` tiles, with its noise maps and
+//! tiles already tuned, so a good looking world takes a few lines:
+//!
+//! ```
+//! # use worldgen::export::ansi::render_ansi;
+//! # use worldgen::noisemap::Seed;
+//! # use worldgen::presets::{self, Terrain};
+//! let world = presets::archipelago(Seed::of("islands")).build().unwrap();
+//! let chunk = world.generate(0, 0).unwrap();
+//!
+//! print!("{}", render_ansi(&chunk, Terrain::style));
+//! ```
+//!
+//! As the presets are plain data, they can be tweaked before they are
+//! built, for example by changing the chunk size or the noise maps, or by
+//! adding tiles of their own. Every preset but the cave world has an
+//! `"elevation"` map and a `"moisture"` map:
+//!
+//! ```
+//! # use worldgen::definition::{ConstraintDef, TileDef};
+//! # use worldgen::noisemap::Seed;
+//! # use worldgen::presets::{self, Terrain};
+//! # use worldgen::world::Size;
+//! # use worldgen::world::tile::ConstraintType;
+//! let mut continents = presets::continents(Seed::of("earth"));
+//! continents.size = Size::of(128, 128);
+//!
+//! // Swamps on low, wet ground, ahead of the preset's own land tiles
+//! let swamp = TileDef::new(Terrain::Forest)
+//!     .when(ConstraintDef::Threshold {
+//!         map: "elevation".to_string(),
+//!         constraint: ConstraintType::LT(0.1),
+//!     })
+//!     .when(ConstraintDef::Threshold {
+//!         map: "moisture".to_string(),
+//!         constraint: ConstraintType::GT(0.3),
+//!     });
+//! continents.tiles.insert(3, swamp);
+//!
+//! let world = continents.build().unwrap();
+//! ```
+
+use std::collections::BTreeMap;
+
+use crate::definition::{ConstraintDef, NoiseDef, NoiseMapDef, TileDef, WorldDef};
+use crate::export::ansi::{Colour, Style};
+use crate::noise::fault::FaultNoise;
+use crate::noisemap::{Amplitude, Seed, Size, Step};
+use crate::version::GENERATION_VERSION;
+use crate::world::tile::ConstraintType;
+use crate::world::SmoothingRule;
+
+/// The tiles of the preset worlds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Terrain {
+    DeepWater,
+    Water,
+    Beach,
+    Grassland,
+    Forest,
+    Hills,
+    Mountains,
+    Snow,
+
+    /// Solid rock, in the cave world.
+    Wall,
+
+    /// Open ground, in the cave world.
+    Floor,
+}
+
+impl Terrain {
+    /// Returns a character to draw the tile with.
+    pub fn glyph(self) -> char {
+        match self {
+            Terrain::DeepWater => '≈',
+            Terrain::Water => '~',
+            Terrain::Beach => '.',
+            Terrain::Grassland => ',',
+            Terrain::Forest => '♣',
+            Terrain::Hills => 'n',
+            Terrain::Mountains => '^',
+            Terrain::Snow => '*',
+            Terrain::Wall => '#',
+            Terrain::Floor => '.',
+        }
+    }
+
+    /// Returns the style to draw the tile with in a terminal, for
+    /// `render_ansi`.
+    pub fn style(&self) -> Style {
+        let style = Style::new(self.glyph());
+
+        match *self {
+            Terrain::DeepWater => style.fg(Colour::Blue),
+            Terrain::Water => style.fg(Colour::Fixed(12)),
+            Terrain::Beach => style.fg(Colour::Fixed(11)),
+            Terrain::Grassland => style.fg(Colour::Fixed(10)),
+            Terrain::Forest => style.fg(Colour::Green),
+            Terrain::Hills => style.fg(Colour::Yellow),
+            Terrain::Mountains => style.fg(Colour::White).bold(),
+            Terrain::Snow => style.fg(Colour::Fixed(15)).bold(),
+            Terrain::Wall => style.fg(Colour::Fixed(8)),
+            Terrain::Floor => style.fg(Colour::Yellow),
+        }
+    }
+}
+
+/// Large landmasses with coasts, mountain ranges and forests, split by
+/// wide oceans.
+pub fn continents(seed: Seed) -> WorldDef<Terrain> {
+    let elevation = NoiseMapDef::Sum(vec![
+        (4, perlin(seed, "continents", 0.004)),
+        (1, perlin(seed, "coasts", 0.04)),
+    ]);
+
+    surface(seed, elevation, Levels::default())
+}
+
+/// Scattered islands, large and small, in a shallow sea.
+pub fn archipelago(seed: Seed) -> WorldDef<Terrain> {
+    let elevation = NoiseMapDef::Sum(vec![
+        (2, perlin(seed, "islands", 0.015)),
+        (1, perlin(seed, "islets", 0.06)),
+    ]);
+
+    surface(
+        seed,
+        elevation,
+        Levels {
+            sea: 0.2,
+            deep: -0.2,
+            beach: 0.24,
+            hills: 0.42,
+            mountains: 0.55,
+            snow: 0.7,
+        },
+    )
+}
+
+/// A single sprawling landmass, broken only by inland seas and lakes.
+pub fn pangea(seed: Seed) -> WorldDef<Terrain> {
+    let elevation = NoiseMapDef::Sum(vec![
+        (4, perlin(seed, "pangea", 0.002)),
+        (1, perlin(seed, "coasts", 0.03)),
+    ]);
+
+    surface(
+        seed,
+        elevation,
+        Levels {
+            sea: -0.55,
+            deep: -0.8,
+            beach: -0.5,
+            hills: 0.2,
+            mountains: 0.35,
+            snow: 0.5,
+        },
+    )
+}
+
+/// Rugged land of hills and long mountain ridges, with little water.
+pub fn highlands(seed: Seed) -> WorldDef<Terrain> {
+    let elevation = NoiseMapDef::Sum(vec![
+        (2, perlin(seed, "highlands", 0.01)),
+        (
+            1,
+            NoiseMapDef::Noise {
+                noise: NoiseDef::Fault(FaultNoise::new()),
+                seed: Seed::of((seed.value, "ridges")),
+                step: Step::of(0.01, 0.01),
+                amplitude: Amplitude::of(1.0),
+            },
+        ),
+        (1, perlin(seed, "crags", 0.08)),
+    ]);
+
+    surface(
+        seed,
+        elevation,
+        Levels {
+            sea: -0.35,
+            deep: -0.45,
+            beach: -0.33,
+            hills: 0.05,
+            mountains: 0.3,
+            snow: 0.45,
+        },
+    )
+}
+
+/// An underground world of winding tunnels, open caverns, and lakes, with
+/// rock everywhere else.
+///
+/// Its maps are `"tunnels"`, which are open close to 0, and `"caverns"`
+/// and `"lakes"`, which are open above their thresholds.
+pub fn cave_world(seed: Seed) -> WorldDef<Terrain> {
+    let mut maps = BTreeMap::new();
+    maps.insert("tunnels".to_string(), perlin(seed, "tunnels", 0.04));
+    maps.insert("caverns".to_string(), perlin(seed, "caverns", 0.03));
+    maps.insert("lakes".to_string(), perlin(seed, "lakes", 0.02));
+
+    WorldDef {
+        size: Size::of(64, 64),
+        seed,
+        maps,
+        tiles: vec![
+            TileDef::new(Terrain::Water)
+                .when(threshold("caverns", ConstraintType::GT(0.4)))
+                .when(threshold("lakes", ConstraintType::GT(0.2))),
+            TileDef::new(Terrain::Floor).when(threshold("caverns", ConstraintType::GT(0.3))),
+            TileDef::new(Terrain::Floor)
+                .when(threshold("tunnels", ConstraintType::Between(-0.1, 0.1))),
+            TileDef::new(Terrain::Wall),
+        ],
+        smoothing: Some((1, SmoothingRule::Majority)),
+        version: GENERATION_VERSION,
+    }
+}
+
+/// The elevations separating the tiles of a surface world.
+struct Levels {
+    sea: f64,
+    deep: f64,
+    beach: f64,
+    hills: f64,
+    mountains: f64,
+    snow: f64,
+}
+
+impl Default for Levels {
+    fn default() -> Levels {
+        Levels {
+            sea: 0.0,
+            deep: -0.4,
+            beach: 0.04,
+            hills: 0.45,
+            mountains: 0.6,
+            snow: 0.7,
+        }
+    }
+}
+
+/// Builds a world of land and sea over the given elevation.
+fn surface(seed: Seed, elevation: NoiseMapDef, levels: Levels) -> WorldDef<Terrain> {
+    let mut maps = BTreeMap::new();
+    maps.insert("elevation".to_string(), elevation);
+    maps.insert("moisture".to_string(), perlin(seed, "moisture", 0.02));
+
+    let below = |level| threshold("elevation", ConstraintType::LT(level));
+    let above = |level| threshold("elevation", ConstraintType::GT(level));
+
+    WorldDef {
+        size: Size::of(64, 64),
+        seed,
+        maps,
+        tiles: vec![
+            TileDef::new(Terrain::DeepWater).when(below(levels.deep)),
+            TileDef::new(Terrain::Water).when(below(levels.sea)),
+            TileDef::new(Terrain::Beach).when(below(levels.beach)),
+            TileDef::new(Terrain::Snow).when(above(levels.snow)),
+            TileDef::new(Terrain::Mountains).when(above(levels.mountains)),
+            TileDef::new(Terrain::Hills).when(above(levels.hills)),
+            TileDef::new(Terrain::Forest).when(threshold("moisture", ConstraintType::GT(0.1))),
+            TileDef::new(Terrain::Grassland),
+        ],
+        smoothing: Some((1, SmoothingRule::Majority)),
+        version: GENERATION_VERSION,
+    }
+}
+
+/// A map of Perlin noise, seeded from the world's seed and the map's
+/// purpose.
+fn perlin(seed: Seed, purpose: &str, step: f64) -> NoiseMapDef {
+    NoiseMapDef::Noise {
+        noise: NoiseDef::Perlin(Default::default()),
+        seed: Seed::of((seed.value, purpose)),
+        step: Step::of(step, step),
+        amplitude: Amplitude::of(1.0),
+    }
+}
+
+fn threshold(map: &str, constraint: ConstraintType) -> ConstraintDef {
+    ConstraintDef::Threshold {
+        map: map.to_string(),
+        constraint,
+    }
+}
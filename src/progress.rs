@@ -0,0 +1,66 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/progress.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Progress reporting for long generations.
+
+use std::fmt;
+use std::sync::Arc;
+
+type Callback = Arc<dyn Fn(&str, f64) + Send + Sync>;
+
+/// A callback told how far generation has got, set with an `on_progress`
+/// method. It is given the name of the current stage, and the fraction
+/// (between 0 and 1) of that stage that is done.
+///
+/// With the `parallel` feature enabled the callback may be called from
+/// several threads.
+#[derive(Clone, Default)]
+pub(crate) struct Progress(Option<Callback>);
+
+impl Progress {
+    pub(crate) fn new<F: Fn(&str, f64) + Send + Sync + 'static>(callback: F) -> Progress {
+        Progress(Some(Arc::new(callback)))
+    }
+
+    /// Returns true if there is a callback to report to.
+    pub(crate) fn is_set(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// Reports that the given number of steps of a stage are done.
+    pub(crate) fn report(&self, stage: &str, done: usize, total: usize) {
+        if let Some(ref callback) = self.0 {
+            let fraction = if total == 0 {
+                1.0
+            } else {
+                done as f64 / total as f64
+            };
+
+            callback(stage, fraction);
+        }
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "Progress(..)"),
+            None => write!(f, "Progress(None)"),
+        }
+    }
+}
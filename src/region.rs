@@ -0,0 +1,412 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/region.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Partitioning of the world into regions.
+//!
+//! A `RegionMap` scatters seeded sites over the plane, and divides it into
+//! the Voronoi cells around them, so every position belongs to the region
+//! of its nearest site. Regions suit political maps, biome plates, and
+//! areas for quests.
+//!
+//! Sites are scattered one to each square of a grid, which keeps regions
+//! roughly the same size. Lloyd relaxation can be applied to make them more
+//! even still, by moving each site to the centre of its region a number of
+//! times.
+//!
+//! ```
+//! # use worldgen::noisemap::{NoiseMapGenerator, Seed, Size};
+//! # use worldgen::region::RegionMap;
+//! let regions = RegionMap::new()
+//!     .spacing(16)
+//!     .relax(1)
+//!     .set(Seed::of("provinces"))
+//!     .set(Size::of(32, 32));
+//!
+//! let chunk = regions.generate_regions(0, 0);
+//! assert_eq!(chunk[3][30], regions.region_at(30, 3));
+//! assert_eq!(regions.generate_regions(1, 0)[3][0], regions.region_at(32, 3));
+//! ```
+
+use std::collections::HashMap;
+use std::ops::Mul;
+
+use crate::noisemap::{
+    next_id, Amplitude, NoiseMapGenerator, NoiseMapGeneratorBase, Property, ScaledNoiseMap, Seed,
+    Size, Step,
+};
+use crate::rng::{hash, hash_all, unit};
+
+/// The number of samples taken across each square of the grid when
+/// finding the centre of a region.
+const SAMPLES: i64 = 4;
+
+/// A region, identified by the square of the grid holding its site.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Region {
+    pub x: i64,
+    pub y: i64,
+}
+
+/// The nearest boundary of a position's region, with the region beyond it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Boundary {
+    /// The region of the position.
+    pub region: Region,
+
+    /// The region on the other side of the boundary.
+    pub neighbour: Region,
+
+    /// The distance from the position to the boundary.
+    pub distance: f64,
+
+    /// The unit vector pointing from the region's site to the neighbour's
+    /// site, across the boundary.
+    pub normal: (f64, f64),
+}
+
+/// The positions of sites, keyed by the squares of the grid holding them.
+type Sites = HashMap<(i64, i64), (f64, f64)>;
+
+/// A map of the regions of the plane.
+///
+/// As a noise map, each region has a random value between 0 and the
+/// amplitude, the same for every position in the region, so tiles can be
+/// chosen per region. The regions themselves are found with `region_at` and
+/// `generate_regions`.
+///
+/// The `Step` property has no effect on this map.
+///
+/// The defaults are:
+///
+/// ```text
+/// spacing = 32
+/// relaxation = 0
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noisemap::{NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size};
+/// # use worldgen::region::RegionMap;
+/// let nm = RegionMap::new().spacing(8).set(Seed::of("regions"));
+///
+/// let whole = nm.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = nm.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RegionMap {
+    seed: Seed,
+    size: Size,
+    amplitude: Amplitude,
+
+    spacing: i64,
+    relaxation: u32,
+
+    id: u64,
+}
+
+impl Default for RegionMap {
+    fn default() -> RegionMap {
+        RegionMap {
+            seed: Default::default(),
+            size: Default::default(),
+            amplitude: Default::default(),
+
+            spacing: 32,
+            relaxation: 0,
+
+            id: next_id(),
+        }
+    }
+}
+
+impl RegionMap {
+    /// Construct a new region map with the default properties.
+    pub fn new() -> RegionMap {
+        Default::default()
+    }
+
+    /// Set the width of the squares of the grid that each hold one site.
+    pub fn spacing(self, spacing: i64) -> RegionMap {
+        RegionMap {
+            spacing: spacing.max(1),
+            ..self
+        }
+    }
+
+    /// Set the number of iterations of Lloyd relaxation.
+    pub fn relax(self, relaxation: u32) -> RegionMap {
+        RegionMap { relaxation, ..self }
+    }
+
+    /// Returns the region of the given world position.
+    pub fn region_at(&self, x: i64, y: i64) -> Region {
+        let cell = (x.div_euclid(self.spacing), y.div_euclid(self.spacing));
+        let sites = self.sites(cell, cell);
+
+        nearest(&sites, self.spacing, (x as f64 + 0.5, y as f64 + 0.5))
+    }
+
+    /// Returns the nearest boundary of the region of the given world
+    /// position.
+    pub fn boundary_at(&self, x: i64, y: i64) -> Boundary {
+        let cell = (x.div_euclid(self.spacing), y.div_euclid(self.spacing));
+        let sites = self.sites(cell, cell);
+
+        boundary(&sites, self.spacing, (x as f64 + 0.5, y as f64 + 0.5))
+    }
+
+    /// Generates a chunk of the nearest boundaries of the regions of each
+    /// position.
+    pub fn generate_boundaries(&self, x: i64, y: i64) -> Vec<Vec<Boundary>> {
+        self.sized_boundaries(self.size, x, y)
+    }
+
+    pub(crate) fn sized_boundaries(&self, size: Size, x: i64, y: i64) -> Vec<Vec<Boundary>> {
+        self.sized_chunk(size, x, y, boundary)
+    }
+
+    /// Returns the position of the site of a region.
+    pub fn site(&self, region: Region) -> (f64, f64) {
+        let cell = (region.x, region.y);
+        self.sites(cell, cell)[&cell]
+    }
+
+    /// Generates a chunk of the regions of each position.
+    pub fn generate_regions(&self, x: i64, y: i64) -> Vec<Vec<Region>> {
+        self.sized_regions(self.size, x, y)
+    }
+
+    fn sized_regions(&self, size: Size, x: i64, y: i64) -> Vec<Vec<Region>> {
+        self.sized_chunk(size, x, y, nearest)
+    }
+
+    /// Generates a chunk by applying a function to the sites around each
+    /// position.
+    fn sized_chunk<U, F>(&self, size: Size, x: i64, y: i64, f: F) -> Vec<Vec<U>>
+    where
+        F: Fn(&Sites, i64, (f64, f64)) -> U,
+    {
+        let (x0, y0) = (x * size.w, y * size.h);
+        let (x1, y1) = (x0 + size.w - 1, y0 + size.h - 1);
+
+        let sites = self.sites(
+            (x0.div_euclid(self.spacing), y0.div_euclid(self.spacing)),
+            (x1.div_euclid(self.spacing), y1.div_euclid(self.spacing)),
+        );
+
+        (y0..=y1)
+            .map(|py| {
+                (x0..=x1)
+                    .map(|px| f(&sites, self.spacing, (px as f64 + 0.5, py as f64 + 0.5)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the sites that may be nearest to the positions in the given
+    /// squares of the grid, after relaxation.
+    fn sites(&self, (gx0, gy0): (i64, i64), (gx1, gy1): (i64, i64)) -> Sites {
+        let spacing = self.spacing as f64;
+
+        // Finding the nearest site looks two squares away, and relaxing a
+        // site samples the squares beside it, so each iteration of
+        // relaxation needs sites three squares further out.
+        let mut margin = 2 + 3 * self.relaxation as i64;
+
+        let mut sites: Sites = (gy0 - margin..=gy1 + margin)
+            .flat_map(|gy| (gx0 - margin..=gx1 + margin).map(move |gx| (gx, gy)))
+            .map(|(gx, gy)| {
+                let site = hash_all(self.seed.value, &[gx as u64, gy as u64]);
+                let position = (
+                    (gx as f64 + unit(hash(site, 0))) * spacing,
+                    (gy as f64 + unit(hash(site, 1))) * spacing,
+                );
+
+                ((gx, gy), position)
+            })
+            .collect();
+
+        for _ in 0..self.relaxation {
+            margin -= 3;
+
+            sites = (gy0 - margin..=gy1 + margin)
+                .flat_map(|gy| (gx0 - margin..=gx1 + margin).map(move |gx| (gx, gy)))
+                .map(|cell| (cell, centroid(&sites, self.spacing, cell)))
+                .collect();
+        }
+
+        sites
+    }
+}
+
+/// Returns the squares holding the sites that could be nearest to the given
+/// position, along with the squared distances to their sites, nearest
+/// first.
+fn candidates(sites: &Sites, spacing: i64, (x, y): (f64, f64)) -> Vec<((i64, i64), f64)> {
+    let (gx, gy) = (
+        (x / spacing as f64).floor() as i64,
+        (y / spacing as f64).floor() as i64,
+    );
+
+    let mut candidates: Vec<_> = (gy - 2..=gy + 2)
+        .flat_map(|ny| (gx - 2..=gx + 2).map(move |nx| (nx, ny)))
+        .map(|cell| {
+            let (sx, sy) = sites[&cell];
+            (cell, (sx - x) * (sx - x) + (sy - y) * (sy - y))
+        })
+        .collect();
+
+    candidates.sort_by(|(a, da), (b, db)| da.total_cmp(db).then(a.cmp(b)));
+    candidates
+}
+
+/// Returns the region whose site is nearest to the given position.
+fn nearest(sites: &Sites, spacing: i64, position: (f64, f64)) -> Region {
+    let ((x, y), _) = candidates(sites, spacing, position)[0];
+    Region { x, y }
+}
+
+/// Returns the nearest boundary of the region of the given position.
+fn boundary(sites: &Sites, spacing: i64, (x, y): (f64, f64)) -> Boundary {
+    let candidates = candidates(sites, spacing, (x, y));
+    let (own, own_distance) = candidates[0];
+    let (ax, ay) = sites[&own];
+
+    // The distance to the boundary with another site is the distance to
+    // the line bisecting the two sites.
+    let ((other, distance), (bx, by)) = candidates[1..]
+        .iter()
+        .map(|&(cell, other_distance)| {
+            let (bx, by) = sites[&cell];
+            let between = ((bx - ax) * (bx - ax) + (by - ay) * (by - ay)).sqrt();
+            let distance = (other_distance - own_distance) / (2.0 * between.max(f64::EPSILON));
+
+            ((cell, distance), (bx, by))
+        })
+        .min_by(|((_, a), _), ((_, b), _)| a.total_cmp(b))
+        .unwrap();
+
+    let length = ((bx - ax) * (bx - ax) + (by - ay) * (by - ay))
+        .sqrt()
+        .max(f64::EPSILON);
+
+    Boundary {
+        region: Region { x: own.0, y: own.1 },
+        neighbour: Region {
+            x: other.0,
+            y: other.1,
+        },
+        distance,
+        normal: ((bx - ax) / length, (by - ay) / length),
+    }
+}
+
+/// Returns the approximate centre of the region of the site in the given
+/// square, sampling the squares around it.
+fn centroid(sites: &Sites, spacing: i64, (gx, gy): (i64, i64)) -> (f64, f64) {
+    let step = spacing as f64 / SAMPLES as f64;
+    let region = Region { x: gx, y: gy };
+
+    let (mut total_x, mut total_y, mut count) = (0.0, 0.0, 0.0);
+
+    for j in 0..3 * SAMPLES {
+        for i in 0..3 * SAMPLES {
+            let point = (
+                (gx - 1) as f64 * spacing as f64 + (i as f64 + 0.5) * step,
+                (gy - 1) as f64 * spacing as f64 + (j as f64 + 0.5) * step,
+            );
+
+            if nearest(sites, spacing, point) == region {
+                total_x += point.0;
+                total_y += point.1;
+                count += 1.0;
+            }
+        }
+    }
+
+    if count > 0.0 {
+        (total_x / count, total_y / count)
+    } else {
+        sites[&(gx, gy)]
+    }
+}
+
+impl NoiseMapGeneratorBase for RegionMap {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.size;
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        self.sized_regions(size, x, y)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|region| {
+                        let value = hash_all(self.seed.value, &[region.x as u64, region.y as u64]);
+                        unit(hash(value, 2)) * self.amplitude.value
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (self.amplitude.value.min(0.0), self.amplitude.value.max(0.0))
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl NoiseMapGenerator for RegionMap {
+    fn set<P: Property>(self, property: P) -> RegionMap {
+        property.set_to(self)
+    }
+
+    fn get_size(&self) -> Size {
+        self.size
+    }
+
+    fn set_seed(self, seed: Seed) -> RegionMap {
+        RegionMap { seed, ..self }
+    }
+
+    fn set_step(self, _step: Step) -> RegionMap {
+        self
+    }
+
+    fn set_size(self, size: Size) -> RegionMap {
+        RegionMap { size, ..self }
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> RegionMap {
+        RegionMap { amplitude, ..self }
+    }
+}
+
+impl Mul<i64> for RegionMap {
+    type Output = ScaledNoiseMap<RegionMap>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<RegionMap> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
@@ -0,0 +1,231 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/resources.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Placement of resources such as ore veins.
+//!
+//! Each resource has its own noise map, and is found wherever that noise
+//! rises above a threshold. How common a resource is can vary with the
+//! elevation (or depth) through a rarity curve, which scales the resource's
+//! noise before the threshold is applied, so veins thin out and vanish
+//! where the resource is rare.
+//!
+//! A `ResourceLayer` produces either a secondary layer of tiles, to sit
+//! alongside the tiles of a `World`, or a list of the veins in a chunk.
+//!
+//! ```
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed, Step};
+//! # use worldgen::resources::{Resource, ResourceLayer};
+//! # use worldgen::world::Size;
+//! # let noise = PerlinNoise::new();
+//! # let elevation = Box::new(NoiseMap::new(noise).set(Seed::of("elevation")).set(Step::of(0.02, 0.02)));
+//! # let iron_noise = Box::new(NoiseMap::new(noise).set(Seed::of("iron")).set(Step::of(0.2, 0.2)));
+//! # let gold_noise = Box::new(NoiseMap::new(noise).set(Seed::of("gold")).set(Step::of(0.3, 0.3)));
+//! let layer = ResourceLayer::new(elevation)
+//!     // Gold only appears deep down, getting more common with depth
+//!     .add(Resource::new("gold", gold_noise, 0.5).rarity(vec![(-1.0, 1.0), (0.0, 0.0)]))
+//!     .add(Resource::new("iron", iron_noise, 0.3));
+//!
+//! let tiles = layer.generate(Size::of(16, 16), 0, 0);
+//! let veins = layer.veins(Size::of(16, 16), 0, 0);
+//!
+//! for vein in veins {
+//!     let (x, y) = vein.cells[0];
+//!     assert_eq!(tiles[y as usize][x as usize], Some(vein.value));
+//! }
+//! ```
+
+use crate::noisemap::{NoiseMapGeneratorBase, Size};
+use crate::world::Chunk;
+
+/// A resource, found where its noise rises above a threshold.
+pub struct Resource<R> {
+    value: R,
+
+    noise: Box<dyn NoiseMapGeneratorBase>,
+    threshold: f64,
+    rarity: Vec<(f64, f64)>,
+}
+
+impl<R> Resource<R> {
+    /// Construct a resource represented by the given value, found where the
+    /// given noise map is above the threshold.
+    pub fn new(value: R, noise: Box<dyn NoiseMapGeneratorBase>, threshold: f64) -> Resource<R> {
+        Resource {
+            value,
+
+            noise,
+            threshold,
+            rarity: Vec::new(),
+        }
+    }
+
+    /// Set the rarity curve of the resource, as points of elevation and the
+    /// factor (between 0 and 1) the resource's noise is scaled by at that
+    /// elevation.
+    ///
+    /// The factor is interpolated linearly between points, and held at the
+    /// first and last points beyond them. Without a curve, the factor is
+    /// always 1.
+    pub fn rarity(self, mut rarity: Vec<(f64, f64)>) -> Resource<R> {
+        rarity.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        Resource { rarity, ..self }
+    }
+
+    /// Returns the factor the resource's noise is scaled by at the given
+    /// elevation.
+    pub fn rarity_at(&self, elevation: f64) -> f64 {
+        if self.rarity.is_empty() {
+            return 1.0;
+        }
+
+        match self.rarity.iter().position(|&(e, _)| e > elevation) {
+            Some(0) => self.rarity[0].1,
+            None => self.rarity[self.rarity.len() - 1].1,
+            Some(index) => {
+                let (e0, f0) = self.rarity[index - 1];
+                let (e1, f1) = self.rarity[index];
+                f0 + (f1 - f0) * (elevation - e0) / (e1 - e0)
+            }
+        }
+    }
+}
+
+/// A connected group of cells holding the same resource within a chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vein<R> {
+    /// The value of the resource.
+    pub value: R,
+
+    /// The positions of the cells in the vein, relative to the chunk.
+    pub cells: Vec<(i64, i64)>,
+}
+
+/// A layer of resources placed over an elevation map.
+///
+/// Where several resources are found at the same position, the first one
+/// that was added is chosen.
+pub struct ResourceLayer<R> {
+    elevation: Box<dyn NoiseMapGeneratorBase>,
+    resources: Vec<Resource<R>>,
+}
+
+impl<R: Clone> ResourceLayer<R> {
+    /// Construct an empty resource layer over the given elevation map.
+    pub fn new(elevation: Box<dyn NoiseMapGeneratorBase>) -> ResourceLayer<R> {
+        ResourceLayer {
+            elevation,
+            resources: Vec::new(),
+        }
+    }
+
+    /// Add a resource to the layer.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, resource: Resource<R>) -> ResourceLayer<R> {
+        let mut new = self;
+        new.resources.push(resource);
+        new
+    }
+
+    /// Generates a chunk of the layer, holding the resource found at each
+    /// position, if any.
+    pub fn generate(&self, size: Size, x: i64, y: i64) -> Chunk<Option<R>> {
+        self.indices(size, x, y)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|index| index.map(|index| self.resources[index].value.clone()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the veins of resources found in a chunk.
+    ///
+    /// Cells are connected to the four cells beside them. Veins that cross
+    /// the border of the chunk are cut at the border.
+    pub fn veins(&self, size: Size, x: i64, y: i64) -> Vec<Vein<R>> {
+        let indices = self.indices(size, x, y);
+        let mut visited = vec![vec![false; size.w as usize]; size.h as usize];
+        let mut veins = Vec::new();
+
+        for sy in 0..size.h {
+            for sx in 0..size.w {
+                let index = match indices[sy as usize][sx as usize] {
+                    Some(index) if !visited[sy as usize][sx as usize] => index,
+                    _ => continue,
+                };
+
+                let mut cells = Vec::new();
+                let mut stack = vec![(sx, sy)];
+                visited[sy as usize][sx as usize] = true;
+
+                while let Some((cx, cy)) = stack.pop() {
+                    cells.push((cx, cy));
+
+                    for (nx, ny) in [(cx - 1, cy), (cx + 1, cy), (cx, cy - 1), (cx, cy + 1)] {
+                        if (0..size.w).contains(&nx)
+                            && (0..size.h).contains(&ny)
+                            && !visited[ny as usize][nx as usize]
+                            && indices[ny as usize][nx as usize] == Some(index)
+                        {
+                            visited[ny as usize][nx as usize] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+
+                cells.sort_by_key(|&(cx, cy)| (cy, cx));
+                veins.push(Vein {
+                    value: self.resources[index].value.clone(),
+                    cells,
+                });
+            }
+        }
+
+        veins
+    }
+
+    /// Returns the index of the resource found at each position of a chunk.
+    fn indices(&self, size: Size, x: i64, y: i64) -> Chunk<Option<usize>> {
+        let elevation = self.elevation.generate_sized_chunk(size, x, y);
+        let noise: Vec<_> = self
+            .resources
+            .iter()
+            .map(|resource| resource.noise.generate_sized_chunk(size, x, y))
+            .collect();
+
+        elevation
+            .iter()
+            .enumerate()
+            .map(|(cy, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(cx, &height)| {
+                        self.resources
+                            .iter()
+                            .zip(noise.iter())
+                            .position(|(resource, noise)| {
+                                noise[cy][cx] * resource.rarity_at(height) > resource.threshold
+                            })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
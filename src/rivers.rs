@@ -0,0 +1,307 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/rivers.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! River networks traced over heightmaps.
+//!
+//! Rivers start at springs scattered over high ground, and flow downhill
+//! until they reach the sea, a pit, or their maximum length. Every river is
+//! traced from its spring using only the values of the heightmap, so a
+//! river that crosses the border of a chunk continues seamlessly into the
+//! next one, and rivers that meet join and flow on together.
+//!
+//! `Rivers` is itself a noise map, whose value at each cell is the number of
+//! rivers flowing through it, so rivers can be overlaid onto a world by
+//! giving them a tile ahead of the others:
+//!
+//! ```
+//! # #[macro_use] extern crate worldgen;
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size, Step};
+//! # use worldgen::rivers::Rivers;
+//! # use worldgen::world::{World, Tile};
+//! # use worldgen::world::tile::{Constraint, ConstraintType};
+//! # fn main() {
+//! # let noise = PerlinNoise::new();
+//! let heights = NoiseMap::new(noise)
+//!     .set(Seed::of("valleys"))
+//!     .set(Size::of(32, 32))
+//!     .set(Step::of(0.05, 0.05));
+//!
+//! let rivers = Rivers::new(heights.clone())
+//!     .source_height(0.2)
+//!     .length(64);
+//!
+//! // Rivers continue across chunk borders
+//! assert_eq!(rivers.get_value(32, 7), rivers.generate_chunk(1, 0)[7][0]);
+//!
+//! let world = World::new()
+//!     .set(Size::of(32, 32))
+//!     .add(Tile::new('~').when(constraint!(Box::new(rivers), > 0.5)))
+//!     .add(Tile::new('~').when(constraint!(Box::new(heights.clone()), < 0.0)))
+//!     .add(Tile::new(','));
+//! # }
+//! ```
+
+use crate::noisemap::{next_id, NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size};
+use crate::rng::{hash, hash_all, unit};
+
+/// The offsets of the eight neighbours of a cell.
+const NEIGHBOURS: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// The path of a single river, as a polyline of world cells.
+///
+/// The first point is the river's spring, and each following point is one
+/// of the eight neighbours of the point before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct River {
+    pub points: Vec<(i64, i64)>,
+}
+
+/// A network of rivers flowing over a heightmap.
+///
+/// The plane is divided into square regions, and each region has a chance
+/// of holding a spring. Springs below the source height are discarded, and
+/// the rest flow down the steepest slope (out of the eight neighbouring
+/// cells) until they fall below the sea level, reach a cell with no lower
+/// neighbour, or have flowed the maximum length.
+///
+/// The defaults are:
+///
+/// ```text
+/// spacing = 16
+/// length = 128
+/// density = 0.5
+/// source height = 0.5
+/// sea level = 0.0
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+/// # use worldgen::rivers::Rivers;
+/// # let noise = PerlinNoise::new();
+/// let heights = NoiseMap::new(noise)
+///     .set(Size::of(32, 32))
+///     .set(Step::of(0.05, 0.05));
+///
+/// let rivers = Rivers::new(heights).source_height(0.2).length(64);
+///
+/// for river in rivers.rivers_in(Size::of(32, 32), 0, 0) {
+///     assert!(river.points.windows(2).all(|pair| {
+///         (pair[0].0 - pair[1].0).abs() <= 1 && (pair[0].1 - pair[1].1).abs() <= 1
+///     }));
+/// }
+///
+/// // The same values whatever the size of the chunk
+/// let whole = rivers.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = rivers.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Rivers<NM> {
+    heights: NM,
+    seed: Seed,
+
+    spacing: i64,
+    length: u32,
+    density: f64,
+    source_height: f64,
+    sea_level: f64,
+
+    id: u64,
+}
+
+impl<NM: NoiseMapGenerator> Rivers<NM> {
+    /// Construct a river network over the given heightmap.
+    pub fn new(heights: NM) -> Rivers<NM> {
+        Rivers {
+            heights,
+            seed: Default::default(),
+
+            spacing: 16,
+            length: 128,
+            density: 0.5,
+            source_height: 0.5,
+            sea_level: 0.0,
+
+            id: next_id(),
+        }
+    }
+
+    /// Set the seed used to place springs.
+    pub fn seed(self, seed: Seed) -> Rivers<NM> {
+        Rivers { seed, ..self }
+    }
+
+    /// Set the width of the square regions that each hold at most one
+    /// spring.
+    pub fn spacing(self, spacing: i64) -> Rivers<NM> {
+        Rivers {
+            spacing: spacing.max(1),
+            ..self
+        }
+    }
+
+    /// Set the maximum number of cells a river flows through.
+    pub fn length(self, length: u32) -> Rivers<NM> {
+        Rivers { length, ..self }
+    }
+
+    /// Set the chance (between 0 and 1) of a region holding a spring.
+    pub fn density(self, density: f64) -> Rivers<NM> {
+        Rivers { density, ..self }
+    }
+
+    /// Set the lowest height at which a spring can appear.
+    pub fn source_height(self, source_height: f64) -> Rivers<NM> {
+        Rivers {
+            source_height,
+            ..self
+        }
+    }
+
+    /// Set the height below which rivers have reached the sea.
+    pub fn sea_level(self, sea_level: f64) -> Rivers<NM> {
+        Rivers { sea_level, ..self }
+    }
+
+    /// Returns every river that flows through the given chunk.
+    ///
+    /// Rivers are returned in full, including the parts of them outside
+    /// the chunk.
+    pub fn rivers_in(&self, size: Size, x: i64, y: i64) -> Vec<River> {
+        let (x0, y0) = (x * size.w, y * size.h);
+        let inside = |&(px, py): &(i64, i64)| {
+            (x0..x0 + size.w).contains(&px) && (y0..y0 + size.h).contains(&py)
+        };
+
+        self.rivers_near(size, x, y)
+            .filter(|river| river.points.iter().any(inside))
+            .collect()
+    }
+
+    /// Returns the rivers whose springs are close enough to the chunk that
+    /// they could reach it.
+    fn rivers_near(&self, size: Size, x: i64, y: i64) -> impl Iterator<Item = River> + '_ {
+        let (x0, y0) = (x * size.w, y * size.h);
+        let reach = self.length as i64;
+
+        let regions = |start: i64, length: i64| {
+            (start - reach).div_euclid(self.spacing)
+                ..=(start + length + reach).div_euclid(self.spacing)
+        };
+
+        let ys = regions(y0, size.h);
+        let xs = regions(x0, size.w);
+
+        ys.flat_map(move |ry| xs.clone().map(move |rx| (rx, ry)))
+            .filter_map(move |(rx, ry)| self.spring(rx, ry))
+            .map(move |(sx, sy)| self.trace(sx, sy))
+    }
+
+    /// Returns the position of the spring in the given region, if it has
+    /// one.
+    fn spring(&self, rx: i64, ry: i64) -> Option<(i64, i64)> {
+        let spring = hash_all(self.seed.value, &[rx as u64, ry as u64]);
+
+        if unit(spring) >= self.density {
+            return None;
+        }
+
+        let sx = rx * self.spacing + (hash(spring, 0) % self.spacing as u64) as i64;
+        let sy = ry * self.spacing + (hash(spring, 1) % self.spacing as u64) as i64;
+
+        if self.heights.get_value(sx, sy) >= self.source_height {
+            Some((sx, sy))
+        } else {
+            None
+        }
+    }
+
+    /// Follows the steepest descent from a spring.
+    fn trace(&self, sx: i64, sy: i64) -> River {
+        let mut points = vec![(sx, sy)];
+        let (mut x, mut y) = (sx, sy);
+        let mut height = self.heights.get_value(x, y);
+
+        while points.len() < self.length as usize && height >= self.sea_level {
+            let lowest = NEIGHBOURS
+                .iter()
+                .map(|&(dx, dy)| {
+                    let neighbour = self.heights.get_value(x + dx, y + dy);
+                    let distance = ((dx * dx + dy * dy) as f64).sqrt();
+                    ((x + dx, y + dy), neighbour, (height - neighbour) / distance)
+                })
+                .filter(|&(_, _, slope)| slope > 0.0)
+                .max_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+
+            match lowest {
+                Some((next, next_height, _)) => {
+                    (x, y) = next;
+                    height = next_height;
+                    points.push(next);
+                }
+                None => break,
+            }
+        }
+
+        River { points }
+    }
+}
+
+impl<NM: NoiseMapGenerator> NoiseMapGeneratorBase for Rivers<NM> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.heights.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let mut chunk = vec![vec![0.0; size.w as usize]; size.h as usize];
+        let (x0, y0) = (x * size.w, y * size.h);
+
+        for river in self.rivers_near(size, x, y) {
+            for &(px, py) in &river.points {
+                let (cx, cy) = (px - x0, py - y0);
+                if (0..size.w).contains(&cx) && (0..size.h).contains(&cy) {
+                    chunk[cy as usize][cx as usize] += 1.0;
+                }
+            }
+        }
+
+        chunk
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (0.0, f64::INFINITY)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
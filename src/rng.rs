@@ -0,0 +1,136 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/rng.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Random values that depend only on a seed and a position.
+//!
+//! Generation never keeps random state between cells, so the same world is
+//! generated no matter which chunks are generated first, or on which
+//! threads. A `CellRng` gives passes a stream of random values for a single
+//! cell in the same way:
+//!
+//! ```
+//! # use worldgen::noisemap::Seed;
+//! # use worldgen::rng::CellRng;
+//! let seed = Seed::of("world");
+//!
+//! let mut rng = CellRng::new(seed, 10, -4, "flowers");
+//! let petals = rng.range(3..7);
+//! let colour = rng.choose(&["red", "yellow", "white"]);
+//!
+//! // The same cell and purpose always give the same values...
+//! let mut again = CellRng::new(seed, 10, -4, "flowers");
+//! assert_eq!(again.range(3..7), petals);
+//! assert_eq!(again.choose(&["red", "yellow", "white"]), colour);
+//!
+//! // ...while other purposes get their own streams.
+//! let mut other = CellRng::new(seed, 10, -4, "rocks");
+//! # let _ = other.next_f64();
+//! ```
+
+use std::ops::Range;
+
+use crate::noisemap::Seed;
+
+/// Mixes a value into a seed, producing a well distributed hash.
+pub(crate) fn hash(seed: u64, value: u64) -> u64 {
+    // SplitMix64 finaliser
+    let mut z = seed
+        .wrapping_add(value.wrapping_mul(0x9e3779b97f4a7c15))
+        .wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Hashes a seed with each of the given values in turn.
+pub(crate) fn hash_all(seed: u64, values: &[u64]) -> u64 {
+    values.iter().fold(seed, |seed, &value| hash(seed, value))
+}
+
+/// Converts a hash into a value in `[0, 1)`.
+pub(crate) fn unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Hashes a purpose tag. Unlike the standard library's hasher, this is
+/// guaranteed to stay the same between Rust versions, so worlds do too.
+fn hash_tag(tag: &str) -> u64 {
+    // FNV-1a
+    tag.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A reproducible stream of random values for a single cell.
+///
+/// The stream is derived from the world's seed, the cell's world position
+/// and a purpose tag, so different features of the same cell (for example
+/// choosing a tile and placing a tree) get independent values. Constructing
+/// the same `CellRng` again always gives the same values.
+///
+/// Inside a post-processing pass, `ChunkContext::rng` constructs one using
+/// the world's seed.
+#[derive(Debug, Clone)]
+pub struct CellRng {
+    state: u64,
+}
+
+impl CellRng {
+    /// Constructs the stream for the given cell and purpose.
+    pub fn new(seed: Seed, x: i64, y: i64, purpose: &str) -> CellRng {
+        CellRng {
+            state: hash_all(seed.value, &[hash_tag(purpose), x as u64, y as u64]),
+        }
+    }
+
+    /// Returns the next random integer.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(1);
+        hash(self.state, 0)
+    }
+
+    /// Returns the next random value in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        unit(self.next_u64())
+    }
+
+    /// Returns true with the given probability (between 0 and 1).
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    /// Returns a random integer in the given range.
+    ///
+    /// Panics if the range is empty.
+    pub fn range(&mut self, range: Range<i64>) -> i64 {
+        assert!(range.start < range.end, "empty range");
+
+        let span = range.end.wrapping_sub(range.start) as u64;
+        range.start.wrapping_add((self.next_u64() % span) as i64)
+    }
+
+    /// Returns a random element of the given slice, or `None` if it is
+    /// empty.
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            None
+        } else {
+            Some(&items[(self.next_u64() % items.len() as u64) as usize])
+        }
+    }
+}
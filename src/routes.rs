@@ -0,0 +1,338 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/routes.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Networks of trade routes between settlements.
+//!
+//! `Routes` links a list of settlements with roads, found with a
+//! `PathFinder` over the same movement costs used for any other path. The
+//! most important settlements, the hubs, are joined by major routes, along
+//! the shortest tree that connects them all. Every other settlement is then
+//! joined by a minor route to the nearest settlement listed before it, so
+//! the whole network hangs off the hubs.
+//!
+//! Routes are found one at a time, major routes first, and moving along a
+//! road that is already built costs less than the ground under it, so
+//! later routes join and share the corridors of earlier ones instead of
+//! running alongside them:
+//!
+//! ```
+//! # use worldgen::routes::{Rank, Routes};
+//! // A river down the middle, crossed by a single bridge
+//! let cost = |x: i64, _y: i64| if x == 10 { None } else { Some(1.0) };
+//! let bridge = |x: i64, y: i64| if (x, y) == (10, 5) { Some(1.0) } else { cost(x, y) };
+//!
+//! let settlements = [(0, 0), (20, 0), (2, 12), (18, 12)];
+//! let network = Routes::new().hubs(2).build(&settlements, bridge);
+//!
+//! assert_eq!(network.routes().len(), 3);
+//! assert_eq!(network.routes()[0].rank, Rank::Major);
+//! assert_eq!(network.rank_at(10, 5), Some(Rank::Major));
+//!
+//! // Roads as tiles, ready to stamp into a world's overrides
+//! let overlay = network.overlay(0, 0, 21, 13, |rank| match rank {
+//!     Rank::Major => '#',
+//!     Rank::Minor => '=',
+//! });
+//! assert_eq!(overlay[5][10], Some('#'));
+//! ```
+
+use std::collections::HashMap;
+
+use crate::path::PathFinder;
+use crate::world::Chunk;
+
+/// The number of nearest earlier settlements tried when looking for a
+/// minor route, before leaving a settlement unconnected.
+const ATTEMPTS: usize = 3;
+
+/// The importance of a route, from most to least important.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Rank {
+    /// A route between two hubs.
+    Major,
+
+    /// A route from a settlement into the rest of the network.
+    Minor,
+}
+
+/// A route between two settlements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    /// The index of the settlement the route starts from.
+    pub from: usize,
+
+    /// The index of the settlement the route leads to.
+    pub to: usize,
+
+    /// The importance of the route.
+    pub rank: Rank,
+
+    /// The positions along the route, including both settlements.
+    pub points: Vec<(i64, i64)>,
+
+    /// The cost of moving along the route, with the discount for any part
+    /// of it that follows earlier roads.
+    pub cost: f64,
+}
+
+impl Route {
+    /// Returns the route as a line through the centres of its cells,
+    /// keeping only its ends and the points where it turns.
+    pub fn polyline(&self) -> Vec<(f64, f64)> {
+        let mut line: Vec<(f64, f64)> = Vec::new();
+
+        for (index, &(x, y)) in self.points.iter().enumerate() {
+            if let (Some(&previous), Some(&next)) = (
+                self.points.get(index.wrapping_sub(1)),
+                self.points.get(index + 1),
+            ) {
+                let before = (x - previous.0, y - previous.1);
+                let after = (next.0 - x, next.1 - y);
+
+                if before == after {
+                    continue;
+                }
+            }
+
+            line.push((x as f64 + 0.5, y as f64 + 0.5));
+        }
+
+        line
+    }
+}
+
+/// The settings for building a network of routes.
+///
+/// The defaults are:
+///
+/// ```text
+/// hubs = 1
+/// reuse = 0.5
+/// ```
+///
+/// and the default `PathFinder`. As roads make moving cheaper, the path
+/// finder's minimum cost should be lowered by the reuse discount for routes
+/// to be the cheapest possible.
+#[derive(Debug, Copy, Clone)]
+pub struct Routes {
+    finder: PathFinder,
+
+    hubs: usize,
+    reuse: f64,
+}
+
+impl Default for Routes {
+    fn default() -> Routes {
+        Routes::new()
+    }
+}
+
+/// A network of routes, along with the rank of the road at each position.
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    routes: Vec<Route>,
+    roads: HashMap<(i64, i64), Rank>,
+}
+
+impl Routes {
+    /// Construct the settings with the defaults.
+    pub fn new() -> Routes {
+        Routes {
+            finder: PathFinder::new(),
+
+            hubs: 1,
+            reuse: 0.5,
+        }
+    }
+
+    /// Set the path finder used to find each route.
+    pub fn finder(self, finder: PathFinder) -> Routes {
+        Routes { finder, ..self }
+    }
+
+    /// Set the number of settlements, from the start of the list, that are
+    /// hubs joined by major routes.
+    pub fn hubs(self, hubs: usize) -> Routes {
+        Routes { hubs, ..self }
+    }
+
+    /// Set the factor (between 0 and 1) the cost of moving along an
+    /// existing road is multiplied by. Lower values make routes share more
+    /// of their length.
+    pub fn reuse(self, reuse: f64) -> Routes {
+        Routes {
+            reuse: reuse.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Builds the network between the given settlements, which are listed
+    /// from most to least important, using the given function for the cost
+    /// of moving into each world position.
+    ///
+    /// Settlements that can't be reached are left out of the network.
+    pub fn build<F>(&self, settlements: &[(i64, i64)], mut cost: F) -> Network
+    where
+        F: FnMut(i64, i64) -> Option<f64>,
+    {
+        let mut network = Network::default();
+        let hubs = self.hubs.min(settlements.len());
+
+        for (from, to) in spanning_tree(&settlements[..hubs]) {
+            self.link(&mut network, settlements, from, to, Rank::Major, &mut cost);
+        }
+
+        for from in hubs.max(1)..settlements.len() {
+            let mut nearest: Vec<usize> = (0..from).collect();
+            nearest.sort_by_key(|&to| distance(settlements[from], settlements[to]));
+
+            for &to in nearest.iter().take(ATTEMPTS) {
+                if self.link(&mut network, settlements, from, to, Rank::Minor, &mut cost) {
+                    break;
+                }
+            }
+        }
+
+        network
+    }
+
+    /// Finds the route between two settlements and adds it to the network,
+    /// returning false if there isn't one.
+    fn link<F>(
+        &self,
+        network: &mut Network,
+        settlements: &[(i64, i64)],
+        from: usize,
+        to: usize,
+        rank: Rank,
+        cost: &mut F,
+    ) -> bool
+    where
+        F: FnMut(i64, i64) -> Option<f64>,
+    {
+        let roads = &network.roads;
+        let path = self
+            .finder
+            .find(settlements[from], settlements[to], |x, y| {
+                let step = cost(x, y)?;
+
+                if roads.contains_key(&(x, y)) {
+                    Some(step * self.reuse)
+                } else {
+                    Some(step)
+                }
+            });
+
+        let path = match path {
+            Some(path) => path,
+            None => return false,
+        };
+
+        for &point in path.points.iter() {
+            let road = network.roads.entry(point).or_insert(rank);
+            *road = (*road).min(rank);
+        }
+
+        network.routes.push(Route {
+            from,
+            to,
+            rank,
+            points: path.points,
+            cost: path.cost,
+        });
+
+        true
+    }
+}
+
+impl Network {
+    /// Returns every route, in the order they were found: major routes
+    /// first, then minor routes.
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// Returns the rank of the most important road at the given world
+    /// position, if there is one.
+    pub fn rank_at(&self, x: i64, y: i64) -> Option<Rank> {
+        self.roads.get(&(x, y)).copied()
+    }
+
+    /// Returns an overlay of tiles for the area with the given top left
+    /// position and size, such as for `Overrides::stamp`.
+    ///
+    /// Each position on a road takes the tile given for the road's rank,
+    /// and other positions hold `None`.
+    pub fn overlay<T, F>(&self, x: i64, y: i64, w: i64, h: i64, tile: F) -> Chunk<Option<T>>
+    where
+        F: Fn(Rank) -> T,
+    {
+        (y..y + h.max(0))
+            .map(|y| {
+                (x..x + w.max(0))
+                    .map(|x| self.rank_at(x, y).map(&tile))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Returns the squared distance between two positions.
+fn distance((x0, y0): (i64, i64), (x1, y1): (i64, i64)) -> i64 {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    dx * dx + dy * dy
+}
+
+/// Returns the edges of the minimum spanning tree of the given positions,
+/// by straight line distance, each from a position to one already in the
+/// tree.
+fn spanning_tree(positions: &[(i64, i64)]) -> Vec<(usize, usize)> {
+    let mut edges = Vec::new();
+
+    if positions.is_empty() {
+        return edges;
+    }
+
+    // The nearest position in the tree to each position outside of it.
+    let mut nearest: Vec<Option<(i64, usize)>> = positions
+        .iter()
+        .map(|&position| Some((distance(position, positions[0]), 0)))
+        .collect();
+    nearest[0] = None;
+
+    while let Some((next, (_, to))) = nearest
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &nearest)| nearest.map(|nearest| (index, nearest)))
+        .min_by_key(|&(index, (distance, _))| (distance, index))
+    {
+        edges.push((next, to));
+        nearest[next] = None;
+
+        for (index, entry) in nearest.iter_mut().enumerate() {
+            if let Some((known, _)) = *entry {
+                let through = distance(positions[index], positions[next]);
+                if through < known {
+                    *entry = Some((through, next));
+                }
+            }
+        }
+    }
+
+    edges
+}
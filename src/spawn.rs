@@ -0,0 +1,204 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/spawn.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Population of generated tiles with creatures and items.
+//!
+//! A `SpawnTable` maps tiles (or biomes) to a `SpawnList`: the chance of
+//! anything spawning in a cell, and a weighted list of what it can be. The
+//! roll for each cell depends only on the table's seed and the cell's world
+//! position, so what spawns where is as much a part of the world as its
+//! tiles:
+//!
+//! ```
+//! # use worldgen::noisemap::Seed;
+//! # use worldgen::spawn::{SpawnList, SpawnTable};
+//! # use worldgen::world::{Size, Tile, World};
+//! let world = World::new()
+//!     .set(Size::of(16, 16))
+//!     .add(Tile::new(','));
+//!
+//! let spawns = SpawnTable::new()
+//!     .set(Seed::of("creatures"))
+//!     .add(',', SpawnList::new(0.05).entry("rabbit", 4.0).entry("wolf", 1.0))
+//!     .add('~', SpawnList::new(0.1).entry("fish", 1.0));
+//!
+//! let chunk = world.generate(2, 3).unwrap();
+//!
+//! for spawn in spawns.populate(&chunk, 2, 3) {
+//!     assert!(spawn.entity == "rabbit" || spawn.entity == "wolf");
+//!     assert_eq!(spawns.roll(&',', spawn.x, spawn.y), Some(&spawn.entity));
+//! }
+//! ```
+
+use crate::noisemap::Seed;
+use crate::rng::CellRng;
+use crate::world::Chunk;
+
+/// The entities that can spawn on a tile, with their weights.
+#[derive(Debug, Clone)]
+pub struct SpawnList<E> {
+    chance: f64,
+    entries: Vec<(E, f64)>,
+    total: f64,
+}
+
+impl<E> SpawnList<E> {
+    /// Construct an empty list, where something spawns in each cell with
+    /// the given probability (between 0 and 1).
+    pub fn new(chance: f64) -> SpawnList<E> {
+        SpawnList {
+            chance,
+            entries: Vec::new(),
+            total: 0.0,
+        }
+    }
+
+    /// Add an entity to the list, chosen in proportion to its weight when
+    /// something spawns. Entities without a positive weight never spawn.
+    pub fn entry(self, entity: E, weight: f64) -> SpawnList<E> {
+        let weight = weight.max(0.0);
+
+        let mut new = self;
+        new.entries.push((entity, weight));
+        new.total += weight;
+        new
+    }
+
+    /// Returns what spawns using the given stream of random values, if
+    /// anything does.
+    pub fn roll_with(&self, rng: &mut CellRng) -> Option<&E> {
+        if self.total <= 0.0 || !rng.chance(self.chance) {
+            return None;
+        }
+
+        let mut target = rng.next_f64() * self.total;
+
+        for (entity, weight) in self.entries.iter() {
+            if target < *weight {
+                return Some(entity);
+            }
+
+            target -= weight;
+        }
+
+        // Rounding can leave the target just past the last weight.
+        self.entries
+            .iter()
+            .rev()
+            .find(|(_, weight)| *weight > 0.0)
+            .map(|(entity, _)| entity)
+    }
+}
+
+/// An entity spawned at a world position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spawn<E> {
+    /// The entity spawned.
+    pub entity: E,
+
+    /// The world position of the cell it spawned in.
+    pub x: i64,
+
+    /// The world position of the cell it spawned in.
+    pub y: i64,
+}
+
+/// A table of what spawns on each kind of tile.
+///
+/// The defaults are:
+///
+/// ```text
+/// seed = 0
+/// ```
+#[derive(Debug, Clone)]
+pub struct SpawnTable<K, E> {
+    seed: Seed,
+    lists: Vec<(K, SpawnList<E>)>,
+}
+
+impl<K: PartialEq, E> Default for SpawnTable<K, E> {
+    fn default() -> SpawnTable<K, E> {
+        SpawnTable::new()
+    }
+}
+
+impl<K: PartialEq, E> SpawnTable<K, E> {
+    /// Construct an empty spawn table.
+    pub fn new() -> SpawnTable<K, E> {
+        SpawnTable {
+            seed: Seed::of_value(0),
+            lists: Vec::new(),
+        }
+    }
+
+    /// Set the seed spawns are rolled with.
+    pub fn set(self, seed: Seed) -> SpawnTable<K, E> {
+        SpawnTable { seed, ..self }
+    }
+
+    /// Set what spawns on the given tile, replacing any list added for it
+    /// earlier.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, key: K, list: SpawnList<E>) -> SpawnTable<K, E> {
+        let mut new = self;
+        new.lists.retain(|(other, _)| *other != key);
+        new.lists.push((key, list));
+        new
+    }
+
+    /// Returns the spawn list for the given tile, if it has one.
+    pub fn list(&self, key: &K) -> Option<&SpawnList<E>> {
+        self.lists
+            .iter()
+            .find(|(other, _)| other == key)
+            .map(|(_, list)| list)
+    }
+
+    /// Returns what spawns in the cell at the given world position, if it
+    /// holds the given tile.
+    pub fn roll(&self, key: &K, x: i64, y: i64) -> Option<&E> {
+        let list = self.list(key)?;
+        list.roll_with(&mut CellRng::new(self.seed, x, y, "spawn"))
+    }
+
+    /// Returns everything that spawns in a generated chunk, given the
+    /// chunk's coordinates, in row order.
+    pub fn populate(&self, chunk: &Chunk<K>, x: i64, y: i64) -> Vec<Spawn<E>>
+    where
+        E: Clone,
+    {
+        let h = chunk.len() as i64;
+        let w = chunk.first().map_or(0, Vec::len) as i64;
+
+        chunk
+            .iter()
+            .enumerate()
+            .flat_map(|(j, row)| {
+                row.iter().enumerate().filter_map(move |(i, key)| {
+                    let (x, y) = (x * w + i as i64, y * h + j as i64);
+
+                    self.roll(key, x, y).map(|entity| Spawn {
+                        entity: entity.clone(),
+                        x,
+                        y,
+                    })
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,285 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/store.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Persistence of generated chunks.
+//!
+//! A `ChunkStore` saves and loads chunks by their coordinates. Given to a
+//! `WorldStreamer`, chunks that are expensive to generate, or that have been
+//! changed since, are only generated once, then read back from the store:
+//!
+//! ```
+//! # use worldgen::store::FileStore;
+//! # use worldgen::world::{Size, Tile, World, WorldStreamer};
+//! # let dir = std::env::temp_dir().join("worldgen-store-example");
+//! # let _ = std::fs::remove_dir_all(&dir);
+//! let world = World::new()
+//!     .set(Size::of(16, 16))
+//!     .add(Tile::new('.'));
+//!
+//! let mut streamer = WorldStreamer::new(world, 1 << 20)
+//!     .store(FileStore::new(&dir).unwrap());
+//!
+//! // Build a wall, and keep it
+//! let mut chunk = streamer.get_or_generate(0, 0).unwrap().clone();
+//! chunk[4][4] = '#';
+//! streamer.insert(0, 0, chunk);
+//! streamer.flush();
+//!
+//! assert_eq!(streamer.get_or_generate(0, 0).unwrap()[4][4], '#');
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+//!
+//! `FileStore` keeps a file for each chunk in a directory, with tiles
+//! written by their `TileCodec` implementation. Each file records the
+//! `GENERATION_VERSION` it was saved with, and loading a chunk saved with
+//! another version fails with an error of kind `InvalidData`, holding a
+//! `VersionMismatch`.
+
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+
+use crate::version::{self, GENERATION_VERSION};
+use crate::world::Chunk;
+
+/// Somewhere chunks can be saved, and loaded again later.
+pub trait ChunkStore<T> {
+    /// Saves a chunk, replacing any saved earlier with the same
+    /// coordinates.
+    fn save(&mut self, x: i64, y: i64, chunk: &Chunk<T>) -> io::Result<()>;
+
+    /// Loads the chunk with the given coordinates, or returns `None` if
+    /// none has been saved.
+    fn load(&mut self, x: i64, y: i64) -> io::Result<Option<Chunk<T>>>;
+}
+
+/// A tile that can be written to and read from bytes, for saving chunks.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::store::TileCodec;
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Terrain {
+///     Water,
+///     Grass,
+/// }
+///
+/// impl TileCodec for Terrain {
+///     fn encode(&self, out: &mut Vec<u8>) {
+///         out.push(*self as u8);
+///     }
+///
+///     fn decode(input: &mut &[u8]) -> Option<Terrain> {
+///         match u8::decode(input)? {
+///             0 => Some(Terrain::Water),
+///             1 => Some(Terrain::Grass),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let mut bytes = Vec::new();
+/// Terrain::Grass.encode(&mut bytes);
+/// assert_eq!(Terrain::decode(&mut &bytes[..]), Some(Terrain::Grass));
+/// ```
+pub trait TileCodec: Sized {
+    /// Appends the tile's bytes to the output.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Reads a tile from the start of the input, advancing past it, or
+    /// returns `None` if the input doesn't hold one.
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+/// Splits the given number of bytes off the start of the input.
+fn take<'a>(input: &mut &'a [u8], count: usize) -> Option<&'a [u8]> {
+    if input.len() < count {
+        return None;
+    }
+
+    let (taken, rest) = input.split_at(count);
+    *input = rest;
+    Some(taken)
+}
+
+macro_rules! number_codec {
+    ($($ty:ty),*) => {
+        $(
+            impl TileCodec for $ty {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(input: &mut &[u8]) -> Option<$ty> {
+                    let bytes = take(input, std::mem::size_of::<$ty>())?;
+                    Some(<$ty>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+number_codec!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl TileCodec for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<bool> {
+        match u8::decode(input)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl TileCodec for char {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u32).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<char> {
+        char::from_u32(u32::decode(input)?)
+    }
+}
+
+impl TileCodec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (self.len() as u32).encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<String> {
+        let len = u32::decode(input)? as usize;
+        String::from_utf8(take(input, len)?.to_vec()).ok()
+    }
+}
+
+impl<T: TileCodec> TileCodec for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match *self {
+            Some(ref value) => {
+                out.push(1);
+                value.encode(out);
+            }
+            None => out.push(0),
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Option<T>> {
+        match u8::decode(input)? {
+            0 => Some(None),
+            1 => T::decode(input).map(Some),
+            _ => None,
+        }
+    }
+}
+
+impl<A: TileCodec, B: TileCodec> TileCodec for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<(A, B)> {
+        Some((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+/// The bytes every chunk file starts with.
+const MAGIC: &[u8; 4] = b"WGCH";
+
+/// Stores each chunk as a file in a directory.
+///
+/// Files are written to a temporary name and then renamed, so a chunk file
+/// is never left half written.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// Construct a store keeping its files in the given directory, which
+    /// is created if it doesn't exist.
+    pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<FileStore> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        Ok(FileStore { dir })
+    }
+
+    /// Returns the path of the file holding the given chunk.
+    pub fn path(&self, x: i64, y: i64) -> PathBuf {
+        self.dir.join(format!("{}_{}.chunk", x, y))
+    }
+}
+
+impl<T: TileCodec> ChunkStore<T> for FileStore {
+    fn save(&mut self, x: i64, y: i64, chunk: &Chunk<T>) -> io::Result<()> {
+        let mut bytes = MAGIC.to_vec();
+        GENERATION_VERSION.encode(&mut bytes);
+        (chunk.len() as u32).encode(&mut bytes);
+        (chunk.first().map_or(0, Vec::len) as u32).encode(&mut bytes);
+
+        for tile in chunk.iter().flatten() {
+            tile.encode(&mut bytes);
+        }
+
+        let path = self.path(x, y);
+        let temporary = path.with_extension("chunk.tmp");
+
+        fs::write(&temporary, bytes)?;
+        fs::rename(temporary, path)
+    }
+
+    fn load(&mut self, x: i64, y: i64) -> io::Result<Option<Chunk<T>>> {
+        let bytes = match fs::read(self.path(x, y)) {
+            Ok(bytes) => bytes,
+            Err(ref error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        let mut input = &bytes[..];
+        let invalid = || io::Error::new(ErrorKind::InvalidData, "invalid chunk file");
+
+        if take(&mut input, MAGIC.len()) != Some(&MAGIC[..]) {
+            return Err(invalid());
+        }
+
+        let found = u32::decode(&mut input).ok_or_else(invalid)?;
+        version::check(found)
+            .map_err(|mismatch| io::Error::new(ErrorKind::InvalidData, mismatch))?;
+
+        decode_chunk(input).map(Some).ok_or_else(invalid)
+    }
+}
+
+/// Reads a chunk written by `FileStore::save`, after its header.
+fn decode_chunk<T: TileCodec>(mut input: &[u8]) -> Option<Chunk<T>> {
+    let h = u32::decode(&mut input)? as usize;
+    let w = u32::decode(&mut input)? as usize;
+
+    let chunk = (0..h)
+        .map(|_| (0..w).map(|_| T::decode(&mut input)).collect())
+        .collect::<Option<Chunk<T>>>()?;
+
+    Some(chunk).filter(|_| input.is_empty())
+}
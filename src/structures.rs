@@ -0,0 +1,362 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/structures.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Placement of structures over the tiles of a world.
+//!
+//! A structure is a prefab (a small grid of tiles stamped over the world)
+//! along with the rules for where it may be placed: the tiles it must be
+//! built on, and how far apart copies of it must be. Placement depends only
+//! on the seed and the world's tiles, so a structure that crosses the
+//! border of a chunk is stamped consistently into both chunks.
+//!
+//! ```
+//! # #[macro_use] extern crate worldgen;
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+//! # use worldgen::structures::{Prefab, Structure, Structures};
+//! # use worldgen::world::{Seed, Size, Tile, World};
+//! # use worldgen::world::tile::{Constraint, ConstraintType};
+//! # fn main() {
+//! # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05)));
+//! let world = World::new()
+//!     .set(Size::of(32, 32))
+//!     .add(Tile::new('~').when(constraint!(nm, < -0.2)))
+//!     .add(Tile::new(','));
+//!
+//! let village = Prefab::new(vec![
+//!     vec![None, Some('#'), None],
+//!     vec![Some('#'), Some('+'), Some('#')],
+//!     vec![None, Some('#'), None],
+//! ]);
+//!
+//! // Villages on grass, at least 30 tiles apart
+//! let structures = Structures::new()
+//!     .seed(Seed::of("villages"))
+//!     .add(Structure::new(village).spacing(30).on(|&tile| tile == ','));
+//!
+//! let chunk = structures.generate(&world, 0, 0).unwrap();
+//! # }
+//! ```
+
+use crate::rng::{hash, hash_all, unit};
+use crate::world::{Chunk, ChunkContext, GenerateError, Seed, World};
+
+/// A predicate on the tiles a structure is placed over.
+type TilePredicate<T> = Box<dyn Fn(&T) -> bool>;
+
+/// A grid of tiles to stamp over the world, as a vector of rows.
+///
+/// Positions holding `None` are left as they are, so prefabs need not be
+/// rectangular.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prefab<T> {
+    stamp: Vec<Vec<Option<T>>>,
+}
+
+impl<T> Prefab<T> {
+    /// Construct a prefab from its rows of tiles.
+    pub fn new(stamp: Vec<Vec<Option<T>>>) -> Prefab<T> {
+        Prefab { stamp }
+    }
+
+    /// Returns the width of the prefab.
+    pub fn width(&self) -> i64 {
+        self.stamp.iter().map(Vec::len).max().unwrap_or(0) as i64
+    }
+
+    /// Returns the height of the prefab.
+    pub fn height(&self) -> i64 {
+        self.stamp.len() as i64
+    }
+
+    /// Returns the positions covered by the prefab, relative to its top
+    /// left corner, along with their tiles.
+    fn footprint(&self) -> impl Iterator<Item = (i64, i64, &T)> {
+        self.stamp.iter().zip(0..).flat_map(|(row, y)| {
+            row.iter()
+                .zip(0..)
+                .filter_map(move |(tile, x)| tile.as_ref().map(|tile| (x, y, tile)))
+        })
+    }
+}
+
+/// A prefab, along with the rules for placing it.
+///
+/// The defaults are:
+///
+/// ```text
+/// spacing = 32
+/// chance = 1.0
+/// ```
+///
+/// and a structure can be placed over any tiles.
+pub struct Structure<T> {
+    prefab: Prefab<T>,
+    predicates: Vec<TilePredicate<T>>,
+
+    spacing: i64,
+    chance: f64,
+}
+
+impl<T> Structure<T> {
+    /// Construct a structure stamping the given prefab.
+    pub fn new(prefab: Prefab<T>) -> Structure<T> {
+        Structure {
+            prefab,
+            predicates: Vec::new(),
+
+            spacing: 32,
+            chance: 1.0,
+        }
+    }
+
+    /// Set the minimum distance between the top left corners of any two
+    /// copies of the structure.
+    pub fn spacing(self, spacing: i64) -> Structure<T> {
+        Structure {
+            spacing: spacing.max(1),
+            ..self
+        }
+    }
+
+    /// Set the chance (between 0 and 1) of the structure being placed at
+    /// each position that allows it.
+    pub fn chance(self, chance: f64) -> Structure<T> {
+        Structure { chance, ..self }
+    }
+
+    /// Requires every tile the prefab covers to satisfy the given predicate
+    /// before the structure is placed over it.
+    pub fn on<F: Fn(&T) -> bool + 'static>(self, predicate: F) -> Structure<T> {
+        let mut new = self;
+        new.predicates.push(Box::new(predicate));
+        new
+    }
+}
+
+/// The position of a placed structure.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Placement {
+    /// The index of the structure, in the order structures were added.
+    pub structure: usize,
+
+    /// The world position of the structure's top left corner.
+    pub x: i64,
+    pub y: i64,
+}
+
+/// A set of structures to place over a world.
+///
+/// Candidate positions are scattered with blue noise: the plane is divided
+/// into squares the size of a structure's spacing, each holding one
+/// candidate, and a candidate is rejected if any other allowed candidate of
+/// the same structure within the spacing outranks it. Structures are
+/// stamped in the order they were added, so later structures are stamped
+/// over earlier ones where they overlap.
+pub struct Structures<T> {
+    structures: Vec<Structure<T>>,
+    seed: Seed,
+}
+
+impl<T> Default for Structures<T> {
+    fn default() -> Structures<T> {
+        Structures {
+            structures: Vec::new(),
+            seed: Default::default(),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync> Structures<T> {
+    /// Construct an empty set of structures.
+    pub fn new() -> Structures<T> {
+        Default::default()
+    }
+
+    /// Add a structure to the set.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, structure: Structure<T>) -> Structures<T> {
+        let mut new = self;
+        new.structures.push(structure);
+        new
+    }
+
+    /// Set the seed used to place structures.
+    pub fn seed(self, seed: Seed) -> Structures<T> {
+        Structures { seed, ..self }
+    }
+
+    /// Generates a chunk of the world with the structures stamped over it.
+    pub fn generate(
+        &self,
+        world: &World<T>,
+        chunk_x: i64,
+        chunk_y: i64,
+    ) -> Result<Chunk<T>, GenerateError> {
+        let mut chunk = world.generate(chunk_x, chunk_y)?;
+        let mut context = ChunkContext::new(world.get_size(), world.get_seed(), chunk_x, chunk_y);
+
+        self.stamp(world, &mut chunk, &mut context);
+        Ok(chunk)
+    }
+
+    /// Stamps the structures that overlap a chunk of the world, described
+    /// by the given context, over the chunk's tiles.
+    pub fn stamp(&self, world: &World<T>, chunk: &mut Chunk<T>, context: &mut ChunkContext) {
+        let size = context.size;
+        let (x0, y0) = (context.chunk_x * size.w, context.chunk_y * size.h);
+
+        for placement in self.placements(world, context, x0, y0, size.w, size.h) {
+            let prefab = &self.structures[placement.structure].prefab;
+
+            for (dx, dy, tile) in prefab.footprint() {
+                let (cx, cy) = (placement.x + dx - x0, placement.y + dy - y0);
+                if (0..size.w).contains(&cx) && (0..size.h).contains(&cy) {
+                    chunk[cy as usize][cx as usize] = tile.clone();
+                }
+            }
+        }
+    }
+
+    /// Returns every structure overlapping the given area of the world,
+    /// using the given context to choose the world's tiles.
+    pub fn placements(
+        &self,
+        world: &World<T>,
+        context: &mut ChunkContext,
+        x: i64,
+        y: i64,
+        width: i64,
+        height: i64,
+    ) -> Vec<Placement> {
+        let mut placements = Vec::new();
+
+        for (index, structure) in self.structures.iter().enumerate() {
+            let spacing = structure.spacing;
+            let cells = |start: i64, length: i64, extent: i64| {
+                (start - extent).div_euclid(spacing)..=(start + length - 1).div_euclid(spacing)
+            };
+
+            for gy in cells(y, height, structure.prefab.height()) {
+                for gx in cells(x, width, structure.prefab.width()) {
+                    if self.placed(world, context, index, gx, gy) {
+                        let (px, py) = self.candidate(index, gx, gy);
+                        placements.push(Placement {
+                            structure: index,
+                            x: px,
+                            y: py,
+                        });
+                    }
+                }
+            }
+        }
+
+        placements
+    }
+
+    /// Returns the candidate position of a structure in the given square.
+    fn candidate(&self, index: usize, gx: i64, gy: i64) -> (i64, i64) {
+        let spacing = self.structures[index].spacing;
+        let candidate = hash_all(self.seed.value, &[index as u64, gx as u64, gy as u64]);
+
+        (
+            gx * spacing + (hash(candidate, 0) % spacing as u64) as i64,
+            gy * spacing + (hash(candidate, 1) % spacing as u64) as i64,
+        )
+    }
+
+    /// Returns the priority of the candidate in the given square, higher
+    /// priorities winning over lower ones.
+    fn priority(&self, index: usize, gx: i64, gy: i64) -> u64 {
+        hash(
+            hash_all(self.seed.value, &[index as u64, gx as u64, gy as u64]),
+            2,
+        )
+    }
+
+    /// Returns true if the candidate in the given square is allowed by its
+    /// structure's chance and tile predicates.
+    fn allowed(
+        &self,
+        world: &World<T>,
+        context: &mut ChunkContext,
+        index: usize,
+        gx: i64,
+        gy: i64,
+    ) -> bool {
+        let structure = &self.structures[index];
+        let roll = hash(
+            hash_all(self.seed.value, &[index as u64, gx as u64, gy as u64]),
+            3,
+        );
+
+        if unit(roll) >= structure.chance {
+            return false;
+        }
+
+        let (px, py) = self.candidate(index, gx, gy);
+        structure.prefab.footprint().all(|(dx, dy, _)| {
+            match world.tile_in(px + dx, py + dy, context) {
+                Some(tile) => structure
+                    .predicates
+                    .iter()
+                    .all(|predicate| predicate(&tile)),
+                None => false,
+            }
+        })
+    }
+
+    /// Returns true if the candidate in the given square is allowed, and no
+    /// allowed candidate within the spacing outranks it.
+    fn placed(
+        &self,
+        world: &World<T>,
+        context: &mut ChunkContext,
+        index: usize,
+        gx: i64,
+        gy: i64,
+    ) -> bool {
+        if !self.allowed(world, context, index, gx, gy) {
+            return false;
+        }
+
+        let spacing = self.structures[index].spacing;
+        let (px, py) = self.candidate(index, gx, gy);
+        let priority = self.priority(index, gx, gy);
+
+        for ny in gy - 1..=gy + 1 {
+            for nx in gx - 1..=gx + 1 {
+                if (nx, ny) == (gx, gy) {
+                    continue;
+                }
+
+                let (ox, oy) = self.candidate(index, nx, ny);
+                let (dx, dy) = (ox - px, oy - py);
+
+                if dx * dx + dy * dy < spacing * spacing
+                    && self.priority(index, nx, ny) > priority
+                    && self.allowed(world, context, index, nx, ny)
+                {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
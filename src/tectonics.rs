@@ -0,0 +1,275 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/tectonics.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Elevation shaped by plate tectonics.
+//!
+//! The surface is divided into plates, which are the regions of a
+//! `RegionMap`. Each plate is either continental, sitting above sea level,
+//! or oceanic, sitting below it, and drifts in a random direction. Where
+//! plates meet, their relative motion shapes the land along the boundary:
+//!
+//! * Two continental plates pushing together raise mountain ranges.
+//! * An oceanic plate pushing under another plate sinks into a trench,
+//!   while the plate above it is raised into volcanic mountains or islands.
+//! * Plates pulling apart form rift valleys on land, and ridges at sea.
+//!
+//! The result is an elevation map between -1 and 1 with continents and
+//! mountain ranges laid out far more plausibly than noise alone, which can
+//! be used as the base for more detailed noise:
+//!
+//! ```
+//! # #[macro_use] extern crate worldgen;
+//! # use worldgen::noisemap::{NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size};
+//! # use worldgen::tectonics::TectonicMap;
+//! # use worldgen::world::{World, Tile};
+//! # use worldgen::world::tile::{Constraint, ConstraintType};
+//! # fn main() {
+//! let elevation = TectonicMap::new()
+//!     .plate_size(64)
+//!     .set(Seed::of("pangaea"))
+//!     .set(Size::of(32, 32));
+//!
+//! let chunk = elevation.generate_chunk(0, 0);
+//! assert!(chunk.iter().flatten().all(|&v| (-1.0..=1.0).contains(&v)));
+//!
+//! let world = World::new()
+//!     .set(Size::of(32, 32))
+//!     .add(Tile::new('~').when(constraint!(Box::new(elevation), < 0.0)))
+//!     .add(Tile::new('^').when(constraint!(Box::new(elevation), > 0.6)))
+//!     .add(Tile::new(','));
+//! # }
+//! ```
+
+use std::f64::consts::PI;
+use std::ops::Mul;
+
+use crate::noisemap::{
+    next_id, Amplitude, NoiseMapGenerator, NoiseMapGeneratorBase, Property, ScaledNoiseMap, Seed,
+    Size, Step,
+};
+use crate::region::{Region, RegionMap};
+use crate::rng::{hash, hash_all, unit};
+
+/// The elevation of continental plates away from their boundaries.
+const CONTINENTAL: f64 = 0.2;
+
+/// The elevation of oceanic plates away from their boundaries.
+const OCEANIC: f64 = -0.5;
+
+/// The properties of a single plate.
+struct Plate {
+    oceanic: bool,
+    drift: (f64, f64),
+    density: f64,
+}
+
+/// An elevation map built from a simulation of plate tectonics.
+///
+/// The defaults are:
+///
+/// ```text
+/// plate size = 128
+/// oceanic = 0.6
+/// boundary width = 16.0
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noisemap::{NoiseMapGeneratorBase, Size};
+/// # use worldgen::tectonics::TectonicMap;
+/// let nm = TectonicMap::new().plate_size(32);
+///
+/// let whole = nm.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = nm.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TectonicMap {
+    plates: RegionMap,
+    seed: Seed,
+    amplitude: Amplitude,
+
+    oceanic: f64,
+    width: f64,
+
+    id: u64,
+}
+
+impl Default for TectonicMap {
+    fn default() -> TectonicMap {
+        TectonicMap {
+            plates: RegionMap::new().spacing(128),
+            seed: Default::default(),
+            amplitude: Default::default(),
+
+            oceanic: 0.6,
+            width: 16.0,
+
+            id: next_id(),
+        }
+    }
+}
+
+impl TectonicMap {
+    /// Construct a new tectonic map with the default properties.
+    pub fn new() -> TectonicMap {
+        Default::default()
+    }
+
+    /// Set the average distance between the centres of plates.
+    pub fn plate_size(self, plate_size: i64) -> TectonicMap {
+        TectonicMap {
+            plates: self.plates.spacing(plate_size),
+            ..self
+        }
+    }
+
+    /// Set the chance (between 0 and 1) of a plate being oceanic.
+    pub fn oceanic(self, oceanic: f64) -> TectonicMap {
+        TectonicMap { oceanic, ..self }
+    }
+
+    /// Set the distance over which the effects of plate boundaries fade.
+    pub fn boundary_width(self, width: f64) -> TectonicMap {
+        TectonicMap {
+            width: width.max(f64::EPSILON),
+            ..self
+        }
+    }
+
+    /// Returns the plate of the given world position.
+    pub fn plate_at(&self, x: i64, y: i64) -> Region {
+        self.plates.region_at(x, y)
+    }
+
+    fn plate(&self, region: Region) -> Plate {
+        let plate = hash_all(self.seed.value, &[region.x as u64, region.y as u64, 0]);
+        let angle = unit(hash(plate, 1)) * 2.0 * PI;
+        let speed = unit(hash(plate, 2));
+
+        Plate {
+            oceanic: unit(hash(plate, 0)) < self.oceanic,
+            drift: (angle.cos() * speed, angle.sin() * speed),
+            density: unit(hash(plate, 3)),
+        }
+    }
+}
+
+impl NoiseMapGeneratorBase for TectonicMap {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.plates.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        self.plates
+            .sized_boundaries(size, x, y)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|boundary| {
+                        let plate = self.plate(boundary.region);
+                        let other = self.plate(boundary.neighbour);
+
+                        // Positive where the plates are moving towards each
+                        // other, and negative where they are moving apart.
+                        let convergence = (plate.drift.0 - other.drift.0) * boundary.normal.0
+                            + (plate.drift.1 - other.drift.1) * boundary.normal.1;
+
+                        let base = if plate.oceanic { OCEANIC } else { CONTINENTAL };
+                        let effect = if convergence > 0.0 {
+                            // The denser plate sinks beneath the other.
+                            let subducting = match (plate.oceanic, other.oceanic) {
+                                (true, false) => true,
+                                (false, true) => false,
+                                _ => plate.density > other.density,
+                            };
+
+                            match (plate.oceanic, other.oceanic, subducting) {
+                                (false, false, _) => 0.8,
+                                (_, _, true) => -0.5,
+                                (false, true, false) => 0.6,
+                                (true, _, false) => 0.4,
+                            }
+                        } else if plate.oceanic {
+                            0.2
+                        } else {
+                            -0.2
+                        };
+
+                        let falloff = (-boundary.distance / self.width).exp();
+                        let elevation = base + effect * convergence.abs().min(1.0) * falloff;
+
+                        elevation.clamp(-1.0, 1.0) * self.amplitude.value
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        let amplitude = self.amplitude.value.abs();
+        (-amplitude, amplitude)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl NoiseMapGenerator for TectonicMap {
+    fn set<P: Property>(self, property: P) -> TectonicMap {
+        property.set_to(self)
+    }
+
+    fn get_size(&self) -> Size {
+        self.plates.get_size()
+    }
+
+    fn set_seed(self, seed: Seed) -> TectonicMap {
+        TectonicMap {
+            plates: self.plates.set_seed(seed),
+            seed,
+            ..self
+        }
+    }
+
+    fn set_step(self, _step: Step) -> TectonicMap {
+        self
+    }
+
+    fn set_size(self, size: Size) -> TectonicMap {
+        TectonicMap {
+            plates: self.plates.set_size(size),
+            ..self
+        }
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> TectonicMap {
+        TectonicMap { amplitude, ..self }
+    }
+}
+
+impl Mul<i64> for TectonicMap {
+    type Output = ScaledNoiseMap<TectonicMap>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<TectonicMap> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
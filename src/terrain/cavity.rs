@@ -0,0 +1,165 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/terrain/cavity.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::f64::consts::PI;
+
+use super::sample_with_margin;
+use crate::noisemap::{next_id, NoiseMapGenerator, NoiseMapGeneratorBase, Size};
+
+/// A map of how exposed each cell of a heightmap is, approximating ambient
+/// occlusion.
+///
+/// For each cell, the horizon is sampled in a number of directions out to
+/// a radius, and the value is how much of the sky is visible: 1 for a flat
+/// plain or a peak, falling towards 0 at the bottom of narrow crevices.
+///
+/// As this is itself a noise map, it can be used in tile constraints:
+///
+/// ```
+/// # #[macro_use] extern crate worldgen;
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+/// # use worldgen::terrain::CavityMap;
+/// # use worldgen::world::{World, Tile};
+/// # use worldgen::world::tile::{Constraint, ConstraintType};
+/// # fn main() {
+/// # let noise = PerlinNoise::new();
+/// let nm = NoiseMap::new(noise)
+///     .set(Size::of(16, 16))
+///     .set(Step::of(0.1, 0.1));
+///
+/// let cavity = CavityMap::new(nm).radius(3).z_factor(10.0);
+///
+/// // The same values whatever the size of the chunk
+/// let whole = cavity.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = cavity.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+///
+/// let world = World::new()
+///     .set(Size::of(16, 16))
+///     .add(Tile::new('%').when(constraint!(Box::new(cavity), < 0.8)))
+///     .add(Tile::new('.'));
+/// # }
+/// ```
+///
+/// The defaults are:
+///
+/// ```text
+/// radius = 4
+/// directions = 8
+/// z factor = 1.0
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CavityMap<NM> {
+    nm: NM,
+
+    radius: i64,
+    directions: usize,
+    z_factor: f64,
+
+    id: u64,
+}
+
+impl<NM: NoiseMapGenerator> CavityMap<NM> {
+    /// Construct the default cavity map over the given heightmap.
+    pub fn new(nm: NM) -> CavityMap<NM> {
+        CavityMap {
+            nm,
+
+            radius: 4,
+            directions: 8,
+            z_factor: 1.0,
+
+            id: next_id(),
+        }
+    }
+
+    /// Set how far, in cells, the horizon is sampled.
+    pub fn radius(self, radius: i64) -> CavityMap<NM> {
+        CavityMap {
+            radius: radius.max(1),
+            ..self
+        }
+    }
+
+    /// Set the number of directions the horizon is sampled in.
+    pub fn directions(self, directions: usize) -> CavityMap<NM> {
+        CavityMap {
+            directions: directions.max(1),
+            ..self
+        }
+    }
+
+    /// Set the factor heights are multiplied by before sampling.
+    pub fn z_factor(self, z_factor: f64) -> CavityMap<NM> {
+        CavityMap { z_factor, ..self }
+    }
+
+    fn exposure(&self, heights: &[Vec<f64>], x: i64, y: i64) -> f64 {
+        let height = |x: i64, y: i64| heights[y as usize][x as usize] * self.z_factor;
+        let centre = height(x, y);
+
+        let occlusion: f64 = (0..self.directions)
+            .map(|direction| {
+                let angle = 2.0 * PI * direction as f64 / self.directions as f64;
+                let (dx, dy) = (angle.cos(), angle.sin());
+
+                let horizon = (1..=self.radius)
+                    .map(|step| {
+                        let sx = x + (dx * step as f64).round() as i64;
+                        let sy = y + (dy * step as f64).round() as i64;
+                        let distance = (((sx - x).pow(2) + (sy - y).pow(2)) as f64).sqrt();
+
+                        ((height(sx, sy) - centre) / distance).atan()
+                    })
+                    .fold(0.0, f64::max);
+
+                horizon.sin()
+            })
+            .sum();
+
+        1.0 - occlusion / self.directions as f64
+    }
+}
+
+impl<NM: NoiseMapGenerator> NoiseMapGeneratorBase for CavityMap<NM> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.nm.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let heights = sample_with_margin(&self.nm, size, x, y, self.radius);
+
+        (self.radius..size.h + self.radius)
+            .map(|y| {
+                (self.radius..size.w + self.radius)
+                    .map(|x| self.exposure(&heights, x, y))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (0.0, 1.0)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
@@ -0,0 +1,189 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/terrain/contour.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+
+/// An edge between two adjacent cells: the cell it starts at, and whether
+/// it runs horizontally (to the right) or vertically (downwards).
+type Edge = (usize, usize, bool);
+
+/// A line following a single elevation level.
+///
+/// The points are in the coordinates of the region the contour was
+/// extracted from, so `(2.5, 1.0)` lies halfway between the cells at
+/// `(2, 1)` and `(3, 1)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contour {
+    /// The elevation level the contour follows.
+    pub level: f64,
+
+    /// The points of the line, in order.
+    pub points: Vec<(f64, f64)>,
+
+    /// Whether the line forms a loop, in which case the first and last
+    /// points are the same.
+    pub closed: bool,
+}
+
+/// Extract the contour lines of the given elevation levels from a region
+/// of a heightmap, using marching squares.
+///
+/// Lines that reach the edge of the region are left open.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::terrain::contours;
+/// let heights = vec![
+///     vec![0.0, 0.0, 0.0],
+///     vec![0.0, 1.0, 0.0],
+///     vec![0.0, 0.0, 0.0],
+/// ];
+///
+/// let lines = contours(&heights, &[0.5]);
+///
+/// assert_eq!(lines.len(), 1);
+/// assert!(lines[0].closed);
+/// assert_eq!(lines[0].points.len(), 5);
+/// ```
+pub fn contours(heights: &[Vec<f64>], levels: &[f64]) -> Vec<Contour> {
+    levels
+        .iter()
+        .flat_map(|&level| trace_level(heights, level))
+        .collect()
+}
+
+fn trace_level(heights: &[Vec<f64>], level: f64) -> Vec<Contour> {
+    let above = |x: usize, y: usize| heights[y][x] >= level;
+
+    let mut points: HashMap<Edge, (f64, f64)> = HashMap::new();
+    let mut crossing = |(x, y, horizontal): Edge| {
+        let (x2, y2) = if horizontal { (x + 1, y) } else { (x, y + 1) };
+
+        if above(x, y) == above(x2, y2) {
+            return None;
+        }
+
+        let t = (level - heights[y][x]) / (heights[y2][x2] - heights[y][x]);
+        let point = if horizontal {
+            (x as f64 + t, y as f64)
+        } else {
+            (x as f64, y as f64 + t)
+        };
+
+        points.insert((x, y, horizontal), point);
+        Some((x, y, horizontal))
+    };
+
+    let mut segments: Vec<(Edge, Edge)> = Vec::new();
+
+    for y in 0..heights.len().saturating_sub(1) {
+        let width = heights[y].len().min(heights[y + 1].len());
+
+        for x in 0..width.saturating_sub(1) {
+            let top = crossing((x, y, true));
+            let right = crossing((x + 1, y, false));
+            let bottom = crossing((x, y + 1, true));
+            let left = crossing((x, y, false));
+
+            match (top, right, bottom, left) {
+                (Some(top), Some(right), Some(bottom), Some(left)) => {
+                    // A saddle: use the centre of the cell to decide which
+                    // pair of opposite corners is connected.
+                    let centre = (heights[y][x]
+                        + heights[y][x + 1]
+                        + heights[y + 1][x]
+                        + heights[y + 1][x + 1])
+                        / 4.0;
+
+                    if (centre >= level) == above(x, y) {
+                        segments.push((top, right));
+                        segments.push((bottom, left));
+                    } else {
+                        segments.push((left, top));
+                        segments.push((right, bottom));
+                    }
+                }
+
+                _ => {
+                    let mut edges = [top, right, bottom, left].into_iter().flatten();
+                    if let (Some(a), Some(b)) = (edges.next(), edges.next()) {
+                        segments.push((a, b));
+                    }
+                }
+            }
+        }
+    }
+
+    join_segments(&segments)
+        .into_iter()
+        .map(|edges| Contour {
+            level,
+            closed: edges.len() > 2 && edges.first() == edges.last(),
+            points: edges.iter().map(|edge| points[edge]).collect(),
+        })
+        .collect()
+}
+
+fn join_segments(segments: &[(Edge, Edge)]) -> Vec<Vec<Edge>> {
+    let mut at_edge: HashMap<Edge, Vec<usize>> = HashMap::new();
+    for (index, &(a, b)) in segments.iter().enumerate() {
+        at_edge.entry(a).or_default().push(index);
+        at_edge.entry(b).or_default().push(index);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let next_segment = |edge: Edge, used: &mut Vec<bool>| {
+        let index = *at_edge[&edge].iter().find(|&&index| !used[index])?;
+        used[index] = true;
+
+        let (a, b) = segments[index];
+        Some(if a == edge { b } else { a })
+    };
+
+    let mut lines = Vec::new();
+
+    for index in 0..segments.len() {
+        if used[index] {
+            continue;
+        }
+
+        used[index] = true;
+        let (start, end) = segments[index];
+
+        let mut forward = vec![start, end];
+        while let Some(edge) = next_segment(*forward.last().unwrap(), &mut used) {
+            forward.push(edge);
+        }
+
+        let mut backward = Vec::new();
+        if forward.last() != forward.first() {
+            let mut edge = start;
+            while let Some(previous) = next_segment(edge, &mut used) {
+                backward.push(previous);
+                edge = previous;
+            }
+        }
+
+        backward.reverse();
+        backward.extend(forward);
+        lines.push(backward);
+    }
+
+    lines
+}
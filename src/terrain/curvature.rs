@@ -0,0 +1,111 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/terrain/curvature.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::sample_with_margin;
+use crate::noisemap::{next_id, NoiseMapGenerator, NoiseMapGeneratorBase, Size};
+
+/// A map of the curvature of a heightmap.
+///
+/// The curvature is the negated second derivative of the heights: positive
+/// where the terrain is convex (ridges and peaks), negative where it is
+/// concave (valleys and gullies), and zero on flat ground or even slopes.
+///
+/// As this is itself a noise map, it can be used in tile constraints:
+///
+/// ```
+/// # #[macro_use] extern crate worldgen;
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+/// # use worldgen::terrain::CurvatureMap;
+/// # use worldgen::world::{World, Tile};
+/// # use worldgen::world::tile::{Constraint, ConstraintType};
+/// # fn main() {
+/// # let noise = PerlinNoise::new();
+/// let nm = NoiseMap::new(noise)
+///     .set(Size::of(16, 16))
+///     .set(Step::of(0.1, 0.1));
+///
+/// let curvature = Box::new(CurvatureMap::new(nm).z_factor(10.0));
+///
+/// // The same values whatever the size of the chunk
+/// let whole = curvature.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = curvature.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+///
+/// let world = World::new()
+///     .set(Size::of(16, 16))
+///     .add(Tile::new('^').when(constraint!(curvature.clone(), > 0.05)))
+///     .add(Tile::new('v').when(constraint!(curvature, < -0.05)))
+///     .add(Tile::new('.'));
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CurvatureMap<NM> {
+    nm: NM,
+    z_factor: f64,
+
+    id: u64,
+}
+
+impl<NM: NoiseMapGenerator> CurvatureMap<NM> {
+    /// Construct a curvature map over the given heightmap.
+    pub fn new(nm: NM) -> CurvatureMap<NM> {
+        CurvatureMap {
+            nm,
+            z_factor: 1.0,
+
+            id: next_id(),
+        }
+    }
+
+    /// Set the factor heights are multiplied by, which defaults to 1.0.
+    pub fn z_factor(self, z_factor: f64) -> CurvatureMap<NM> {
+        CurvatureMap { z_factor, ..self }
+    }
+}
+
+impl<NM: NoiseMapGenerator> NoiseMapGeneratorBase for CurvatureMap<NM> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.nm.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let heights = sample_with_margin(&self.nm, size, x, y, 1);
+
+        (1..=size.h as usize)
+            .map(|y| {
+                (1..=size.w as usize)
+                    .map(|x| {
+                        let laplacian = heights[y][x - 1]
+                            + heights[y][x + 1]
+                            + heights[y - 1][x]
+                            + heights[y + 1][x]
+                            - 4.0 * heights[y][x];
+
+                        -laplacian * self.z_factor
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
@@ -0,0 +1,116 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/terrain/flow.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::{neighbour, NEIGHBOURS};
+
+/// The flow of water over a region of a heightmap.
+///
+/// Flow is calculated with the D8 method: each cell drains into whichever
+/// of its eight neighbours has the steepest downhill slope. Cells with no
+/// lower neighbour (pits, and cells at the edge sloping out of the region)
+/// do not drain anywhere.
+///
+/// The accumulation of a cell is the number of cells that drain through it,
+/// including itself. High accumulation values follow the paths rivers
+/// would take.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::terrain::FlowMap;
+/// let heights = vec![
+///     vec![3.0, 2.0, 3.0],
+///     vec![3.0, 1.0, 3.0],
+///     vec![3.0, 0.0, 3.0],
+/// ];
+///
+/// let flow = FlowMap::new(&heights);
+///
+/// assert_eq!(flow.downstream(1, 0), Some((1, 1)));
+/// assert_eq!(flow.accumulation()[2][1], 9.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FlowMap {
+    downstream: Vec<Vec<Option<(usize, usize)>>>,
+    accumulation: Vec<Vec<f64>>,
+}
+
+impl FlowMap {
+    /// Calculate the flow over the given rows of heights.
+    pub fn new(heights: &[Vec<f64>]) -> FlowMap {
+        let downstream: Vec<Vec<_>> = heights
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                (0..row.len())
+                    .map(|x| steepest_descent(heights, x, y))
+                    .collect()
+            })
+            .collect();
+
+        let mut accumulation: Vec<Vec<f64>> =
+            heights.iter().map(|row| vec![1.0; row.len()]).collect();
+
+        // Every cell drains into a strictly lower one, so visiting cells from
+        // the highest down passes each total on only once it is complete.
+        let mut cells: Vec<(usize, usize)> = heights
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| (0..row.len()).map(move |x| (x, y)))
+            .collect();
+
+        cells.sort_by(|&(ax, ay), &(bx, by)| heights[by][bx].total_cmp(&heights[ay][ax]));
+
+        for (x, y) in cells {
+            if let Some((dx, dy)) = downstream[y][x] {
+                accumulation[dy][dx] += accumulation[y][x];
+            }
+        }
+
+        FlowMap {
+            downstream,
+            accumulation,
+        }
+    }
+
+    /// Returns the cell that the given cell drains into, if any.
+    pub fn downstream(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        self.downstream[y][x]
+    }
+
+    /// Returns the accumulated flow of every cell.
+    pub fn accumulation(&self) -> &Vec<Vec<f64>> {
+        &self.accumulation
+    }
+}
+
+fn steepest_descent(heights: &[Vec<f64>], x: usize, y: usize) -> Option<(usize, usize)> {
+    let height = heights[y][x];
+
+    NEIGHBOURS
+        .iter()
+        .filter_map(|&offset| {
+            neighbour(heights, x, y, offset).map(|(nx, ny)| {
+                let distance = ((offset.0 * offset.0 + offset.1 * offset.1) as f64).sqrt();
+                ((nx, ny), (height - heights[ny][nx]) / distance)
+            })
+        })
+        .filter(|&(_, slope)| slope > 0.0)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(cell, _)| cell)
+}
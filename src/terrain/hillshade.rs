@@ -0,0 +1,159 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/terrain/hillshade.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::default::Default;
+
+use super::{clamped, sample_with_margin};
+use crate::noisemap::{NoiseMapGeneratorBase, Size};
+
+/// Shades a heightmap as if lit by the sun, producing a layer of values
+/// between 0 (in shadow) and 255 (facing the sun).
+///
+/// The sun position is given by its azimuth (the compass direction it
+/// shines from, in degrees clockwise from north, where north is the top of
+/// the map) and its altitude (in degrees above the horizon). Since noise
+/// values are small compared to the distance between cells, the heights can
+/// be exaggerated with a z factor.
+///
+/// The defaults are:
+///
+/// ```text
+/// azimuth = 315.0
+/// altitude = 45.0
+/// z factor = 1.0
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Size, Step};
+/// # use worldgen::terrain::Hillshade;
+/// # let noise = PerlinNoise::new();
+/// let nm = NoiseMap::new(noise)
+///     .set(Size::of(16, 16))
+///     .set(Step::of(0.05, 0.05));
+///
+/// let shading = Hillshade::new()
+///     .azimuth(270.0)
+///     .z_factor(20.0)
+///     .shade_chunk(&nm, Size::of(16, 16), 0, 0);
+///
+/// assert_eq!(shading.len(), 16);
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct Hillshade {
+    azimuth: f64,
+    altitude: f64,
+    z_factor: f64,
+}
+
+impl Default for Hillshade {
+    fn default() -> Hillshade {
+        Hillshade {
+            azimuth: 315.0,
+            altitude: 45.0,
+            z_factor: 1.0,
+        }
+    }
+}
+
+impl Hillshade {
+    /// Construct the default hillshade.
+    pub fn new() -> Hillshade {
+        Default::default()
+    }
+
+    /// Set the compass direction the sun shines from, in degrees.
+    pub fn azimuth(self, azimuth: f64) -> Hillshade {
+        Hillshade { azimuth, ..self }
+    }
+
+    /// Set the height of the sun above the horizon, in degrees.
+    pub fn altitude(self, altitude: f64) -> Hillshade {
+        Hillshade { altitude, ..self }
+    }
+
+    /// Set the factor heights are multiplied by before shading.
+    pub fn z_factor(self, z_factor: f64) -> Hillshade {
+        Hillshade { z_factor, ..self }
+    }
+
+    /// Shade a region of a heightmap.
+    ///
+    /// Cells at the edge of the region are shaded as if the terrain
+    /// continued flat beyond it.
+    pub fn shade(&self, heights: &[Vec<f64>]) -> Vec<Vec<u8>> {
+        heights
+            .iter()
+            .enumerate()
+            .map(|(y, row)| {
+                (0..row.len())
+                    .map(|x| self.shade_cell(|dx, dy| clamped(heights, x, y, dx, dy)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Shade a chunk of a noise map.
+    ///
+    /// The values around the chunk are sampled too, so the shading of
+    /// neighbouring chunks lines up without seams.
+    pub fn shade_chunk<NM: NoiseMapGeneratorBase + ?Sized>(
+        &self,
+        nm: &NM,
+        size: Size,
+        x: i64,
+        y: i64,
+    ) -> Vec<Vec<u8>> {
+        let heights = sample_with_margin(nm, size, x, y, 1);
+
+        (0..size.h as usize)
+            .map(|y| {
+                (0..size.w as usize)
+                    .map(|x| {
+                        self.shade_cell(|dx, dy| {
+                            heights[(y as i64 + 1 + dy) as usize][(x as i64 + 1 + dx) as usize]
+                        })
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn shade_cell<F: Fn(i64, i64) -> f64>(&self, height: F) -> u8 {
+        let h = |dx, dy| height(dx, dy) * self.z_factor;
+
+        // Horn's method for the gradient of the 3x3 neighbourhood.
+        let dz_dx =
+            ((h(1, -1) + 2.0 * h(1, 0) + h(1, 1)) - (h(-1, -1) + 2.0 * h(-1, 0) + h(-1, 1))) / 8.0;
+        let dz_dy =
+            ((h(-1, 1) + 2.0 * h(0, 1) + h(1, 1)) - (h(-1, -1) + 2.0 * h(0, -1) + h(1, -1))) / 8.0;
+
+        let slope = (dz_dx * dz_dx + dz_dy * dz_dy).sqrt().atan();
+        let aspect = dz_dy.atan2(-dz_dx);
+
+        let zenith = (90.0 - self.altitude).to_radians();
+        let azimuth = (450.0 - self.azimuth).rem_euclid(360.0).to_radians();
+
+        let shade =
+            zenith.cos() * slope.cos() + zenith.sin() * slope.sin() * (azimuth - aspect).cos();
+
+        (shade.max(0.0) * 255.0).round() as u8
+    }
+}
@@ -0,0 +1,122 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/terrain/mod.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Analysis of heightmaps.
+//!
+//! The terrain module treats the values of a noise map as elevations, and
+//! provides passes that derive more information from them, such as where
+//! water would flow.
+//!
+//! These passes work on a region of values that has already been generated,
+//! for example with `generate_area`:
+//!
+//! ```
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+//! # use worldgen::terrain::FlowMap;
+//! # let noise = PerlinNoise::new();
+//! let nm = NoiseMap::new(noise)
+//!     .set(Size::of(16, 16))
+//!     .set(Step::of(0.05, 0.05));
+//!
+//! let heights = nm.generate_area(0, 0, 1, 1);
+//! let flow = FlowMap::new(&heights);
+//! ```
+
+pub use self::cavity::CavityMap;
+pub use self::contour::{contours, Contour};
+pub use self::curvature::CurvatureMap;
+pub use self::flow::FlowMap;
+pub use self::hillshade::Hillshade;
+pub use self::watershed::Watersheds;
+
+mod cavity;
+mod contour;
+mod curvature;
+mod flow;
+mod hillshade;
+mod watershed;
+
+use crate::noisemap::{NoiseMapGeneratorBase, Size};
+
+/// The offsets of the eight neighbours of a cell.
+const NEIGHBOURS: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Returns the coordinates of the neighbour of a cell at the given offset,
+/// if it lies within the region.
+fn neighbour(
+    heights: &[Vec<f64>],
+    x: usize,
+    y: usize,
+    (dx, dy): (i64, i64),
+) -> Option<(usize, usize)> {
+    let nx = x as i64 + dx;
+    let ny = y as i64 + dy;
+
+    if ny < 0 || ny as usize >= heights.len() || nx < 0 || nx as usize >= heights[ny as usize].len()
+    {
+        None
+    } else {
+        Some((nx as usize, ny as usize))
+    }
+}
+
+/// Returns the height of the cell at the given offset, clamping the
+/// coordinates to the region so cells beyond the edge repeat the edge.
+fn clamped(heights: &[Vec<f64>], x: usize, y: usize, dx: i64, dy: i64) -> f64 {
+    let ny = (y as i64 + dy).clamp(0, heights.len() as i64 - 1) as usize;
+    let nx = (x as i64 + dx).clamp(0, heights[ny].len() as i64 - 1) as usize;
+
+    heights[ny][nx]
+}
+
+/// Generates a chunk of a noise map along with a margin of the values
+/// surrounding it, so passes that look at neighbouring cells produce the
+/// same results at the edges of a chunk as in its middle.
+pub(crate) fn sample_with_margin<NM: NoiseMapGeneratorBase + ?Sized>(
+    nm: &NM,
+    size: Size,
+    x: i64,
+    y: i64,
+    margin: i64,
+) -> Vec<Vec<f64>> {
+    let chunk = nm.generate_sized_chunk(size, x, y);
+
+    (-margin..size.h + margin)
+        .map(|cy| {
+            (-margin..size.w + margin)
+                .map(|cx| {
+                    if (0..size.h).contains(&cy) && (0..size.w).contains(&cx) {
+                        chunk[cy as usize][cx as usize]
+                    } else {
+                        nm.get_value(x * size.w + cx, y * size.h + cy)
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,140 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/terrain/watershed.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::FlowMap;
+
+/// The drainage basins of a region of a heightmap.
+///
+/// Every cell is labelled with the id of the basin it drains into. A basin
+/// is identified by its outlet: the cell at the end of the flow, where the
+/// water would pool into a lake or leave the region.
+///
+/// Basin ids count up from 0, in the order the basins are first reached
+/// when reading the region row by row: basin 0 holds the first cell, and
+/// each new id goes to the basin of the first cell not in an earlier one.
+/// An outlet may come later in the region than cells draining into it.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::terrain::{FlowMap, Watersheds};
+/// let heights = vec![
+///     vec![0.0, 1.0, 2.0, 1.0, 0.0],
+///     vec![0.5, 1.0, 2.0, 1.0, 0.5],
+/// ];
+///
+/// let basins = Watersheds::new(&FlowMap::new(&heights));
+///
+/// assert_eq!(basins.len(), 2);
+/// assert_eq!(basins.basin(0, 0), 0);
+/// assert_eq!(basins.basin(1, 0), basins.basin(0, 1));
+/// assert_ne!(basins.basin(1, 0), basins.basin(3, 0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Watersheds {
+    labels: Vec<Vec<usize>>,
+
+    outlets: Vec<(usize, usize)>,
+    areas: Vec<usize>,
+}
+
+impl Watersheds {
+    /// Label the basins of the given flow.
+    pub fn new(flow: &FlowMap) -> Watersheds {
+        let mut labels: Vec<Vec<Option<usize>>> = flow
+            .accumulation()
+            .iter()
+            .map(|row| vec![None; row.len()])
+            .collect();
+
+        let mut outlets = Vec::new();
+        let mut areas = Vec::new();
+
+        for y in 0..labels.len() {
+            for x in 0..labels[y].len() {
+                // Follow the flow until reaching a labelled cell or an outlet.
+                let mut path = vec![(x, y)];
+                let mut basin = None;
+
+                while let Some(&(px, py)) = path.last() {
+                    if let Some(label) = labels[py][px] {
+                        basin = Some(label);
+                        path.pop();
+                        break;
+                    }
+
+                    match flow.downstream(px, py) {
+                        Some(next) => path.push(next),
+                        None => break,
+                    }
+                }
+
+                let basin = basin.unwrap_or_else(|| {
+                    outlets.push(*path.last().unwrap());
+                    areas.push(0);
+                    outlets.len() - 1
+                });
+
+                areas[basin] += path.len();
+                for (px, py) in path {
+                    labels[py][px] = Some(basin);
+                }
+            }
+        }
+
+        Watersheds {
+            labels: labels
+                .into_iter()
+                .map(|row| row.into_iter().map(Option::unwrap).collect())
+                .collect(),
+
+            outlets,
+            areas,
+        }
+    }
+
+    /// Returns the id of the basin the given cell drains into.
+    pub fn basin(&self, x: usize, y: usize) -> usize {
+        self.labels[y][x]
+    }
+
+    /// Returns the basin id of every cell.
+    pub fn labels(&self) -> &Vec<Vec<usize>> {
+        &self.labels
+    }
+
+    /// Returns the number of basins.
+    pub fn len(&self) -> usize {
+        self.outlets.len()
+    }
+
+    /// Returns true if there are no basins (the region was empty).
+    pub fn is_empty(&self) -> bool {
+        self.outlets.is_empty()
+    }
+
+    /// Returns the outlet cell of a basin.
+    pub fn outlet(&self, basin: usize) -> (usize, usize) {
+        self.outlets[basin]
+    }
+
+    /// Returns the number of cells in a basin.
+    pub fn area(&self, basin: usize) -> usize {
+        self.areas[basin]
+    }
+}
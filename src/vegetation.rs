@@ -0,0 +1,450 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/vegetation.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Plant cover and the placement of individual plants.
+//!
+//! `Vegetation` derives how densely plants grow from a heightmap, a
+//! moisture map and a temperature map, such as those in the `climate`
+//! module: plants grow thickest where it is wet, warm and flat. Each
+//! species has a habitat, the range of conditions it grows in, and is
+//! scattered with blue noise like structures are, at most one plant to
+//! each square of its spacing and never two within the spacing. Where the
+//! plants are thinner, fewer of the scattered positions are used.
+//!
+//! Placement depends only on the seed and the maps, so plants along the
+//! border of a chunk are placed the same way from either side.
+//!
+//! ```
+//! # use worldgen::climate::{MoistureMap, TemperatureMap};
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed, Size, Step};
+//! # use worldgen::vegetation::{Species, Vegetation};
+//! let heights = NoiseMap::new(PerlinNoise::new())
+//!     .set(Seed::of("heights"))
+//!     .set(Size::of(32, 32))
+//!     .set(Step::of(0.03, 0.03));
+//!
+//! let vegetation = Vegetation::new(
+//!     Box::new(heights),
+//!     Box::new(MoistureMap::new(heights)),
+//!     Box::new(TemperatureMap::new(heights)),
+//! )
+//! .seed(Seed::of("plants"))
+//! .add(Species::new("oak").spacing(6).moisture(0.4, 1.0).temperature(0.0, 1.0))
+//! .add(Species::new("pine").spacing(4).temperature(-0.8, 0.3).max_slope(0.05))
+//! .add(Species::new("grass").spacing(2));
+//!
+//! let density = vegetation.density(Size::of(32, 32), 0, 0);
+//! let plants = vegetation.generate(Size::of(32, 32), 0, 0);
+//!
+//! for plant in plants.iter().filter(|plant| plant.value == "oak") {
+//!     assert!(density[plant.y as usize][plant.x as usize] > 0.0);
+//! }
+//! ```
+
+use crate::noisemap::{NoiseMapGeneratorBase, Seed, Size};
+use crate::rng::{hash, hash_all, unit};
+use crate::terrain::sample_with_margin;
+use crate::world::Chunk;
+
+/// The conditions plants grow in at a position.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Conditions {
+    /// The moisture, between 0 (arid) and 1 (wet).
+    pub moisture: f64,
+
+    /// The temperature, between -1 (polar) and 1 (tropical).
+    pub temperature: f64,
+
+    /// The steepness of the ground, as the rise in height for each cell
+    /// across.
+    pub slope: f64,
+
+    /// How densely plants grow, between 0 and 1.
+    pub density: f64,
+}
+
+/// A species of plant, along with the habitat it grows in.
+///
+/// The defaults are:
+///
+/// ```text
+/// spacing = 4
+/// abundance = 1.0
+/// ```
+///
+/// and a species grows in any conditions with some plant cover.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Species<S> {
+    value: S,
+
+    spacing: i64,
+    abundance: f64,
+
+    moisture: (f64, f64),
+    temperature: (f64, f64),
+    max_slope: f64,
+    min_density: f64,
+}
+
+impl<S> Species<S> {
+    /// Construct a species represented by the given value.
+    pub fn new(value: S) -> Species<S> {
+        Species {
+            value,
+
+            spacing: 4,
+            abundance: 1.0,
+
+            moisture: (f64::NEG_INFINITY, f64::INFINITY),
+            temperature: (f64::NEG_INFINITY, f64::INFINITY),
+            max_slope: f64::INFINITY,
+            min_density: 0.0,
+        }
+    }
+
+    /// Set the minimum distance between any two plants of the species.
+    pub fn spacing(self, spacing: i64) -> Species<S> {
+        Species {
+            spacing: spacing.max(1),
+            ..self
+        }
+    }
+
+    /// Set the factor the chance of a plant growing at each scattered
+    /// position is multiplied by, on top of the plant cover there.
+    pub fn abundance(self, abundance: f64) -> Species<S> {
+        Species { abundance, ..self }
+    }
+
+    /// Set the range of moisture the species grows in.
+    pub fn moisture(self, min: f64, max: f64) -> Species<S> {
+        Species {
+            moisture: (min, max),
+            ..self
+        }
+    }
+
+    /// Set the range of temperature the species grows in.
+    pub fn temperature(self, min: f64, max: f64) -> Species<S> {
+        Species {
+            temperature: (min, max),
+            ..self
+        }
+    }
+
+    /// Set the steepest slope the species grows on.
+    pub fn max_slope(self, max_slope: f64) -> Species<S> {
+        Species { max_slope, ..self }
+    }
+
+    /// Set the thinnest plant cover the species grows in, so that it is
+    /// only found in dense forest, for example.
+    pub fn min_density(self, min_density: f64) -> Species<S> {
+        Species {
+            min_density,
+            ..self
+        }
+    }
+
+    /// Returns true if the species grows in the given conditions.
+    pub fn grows_in(&self, conditions: &Conditions) -> bool {
+        let within = |(min, max): (f64, f64), value: f64| min <= value && value <= max;
+
+        within(self.moisture, conditions.moisture)
+            && within(self.temperature, conditions.temperature)
+            && conditions.slope <= self.max_slope
+            && conditions.density > 0.0
+            && conditions.density >= self.min_density
+    }
+}
+
+/// A placed plant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plant<S> {
+    /// The value of the plant's species.
+    pub value: S,
+
+    /// The index of the species, in the order species were added.
+    pub species: usize,
+
+    /// The position of the plant, relative to the area it was placed in.
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Plant cover over a heightmap, and the species growing in it. See the
+/// module documentation.
+///
+/// The plant cover at each position is the moisture, multiplied by the
+/// warmth (the temperature scaled to between 0 and 1) and by the
+/// flatness, which falls from 1 on flat ground to 0 at the maximum slope.
+/// Below sea level there is no plant cover.
+///
+/// The defaults are:
+///
+/// ```text
+/// seed = 0
+/// sea level = 0.0
+/// max slope = 0.25
+/// ```
+pub struct Vegetation<S> {
+    heights: Box<dyn NoiseMapGeneratorBase>,
+    moisture: Box<dyn NoiseMapGeneratorBase>,
+    temperature: Box<dyn NoiseMapGeneratorBase>,
+
+    species: Vec<Species<S>>,
+    seed: Seed,
+
+    sea_level: f64,
+    max_slope: f64,
+}
+
+impl<S: Clone> Vegetation<S> {
+    /// Construct plant cover over the given heightmap, moisture map and
+    /// temperature map, without any species.
+    pub fn new(
+        heights: Box<dyn NoiseMapGeneratorBase>,
+        moisture: Box<dyn NoiseMapGeneratorBase>,
+        temperature: Box<dyn NoiseMapGeneratorBase>,
+    ) -> Vegetation<S> {
+        Vegetation {
+            heights,
+            moisture,
+            temperature,
+
+            species: Vec::new(),
+            seed: Default::default(),
+
+            sea_level: 0.0,
+            max_slope: 0.25,
+        }
+    }
+
+    /// Add a species. Species are placed independently of each other, so
+    /// plants of different species can share a position.
+    #[allow(clippy::should_implement_trait)]
+    pub fn add(self, species: Species<S>) -> Vegetation<S> {
+        let mut new = self;
+        new.species.push(species);
+        new
+    }
+
+    /// Set the seed used to place plants.
+    pub fn seed(self, seed: Seed) -> Vegetation<S> {
+        Vegetation { seed, ..self }
+    }
+
+    /// Set the height below which there is no plant cover.
+    pub fn sea_level(self, sea_level: f64) -> Vegetation<S> {
+        Vegetation { sea_level, ..self }
+    }
+
+    /// Set the slope at which plant cover runs out.
+    pub fn max_slope(self, max_slope: f64) -> Vegetation<S> {
+        Vegetation {
+            max_slope: max_slope.max(f64::EPSILON),
+            ..self
+        }
+    }
+
+    /// Returns the conditions at a world position.
+    pub fn conditions(&self, x: i64, y: i64) -> Conditions {
+        let height = |x, y| self.heights.get_value(x, y);
+
+        self.derive(
+            height(x, y),
+            (
+                height(x + 1, y) - height(x - 1, y),
+                height(x, y + 1) - height(x, y - 1),
+            ),
+            self.moisture.get_value(x, y),
+            self.temperature.get_value(x, y),
+        )
+    }
+
+    /// Generates the plant cover over a chunk.
+    pub fn density(&self, size: Size, x: i64, y: i64) -> Chunk<f64> {
+        let heights = sample_with_margin(&*self.heights, size, x, y, 1);
+        let moisture = self.moisture.generate_sized_chunk(size, x, y);
+        let temperature = self.temperature.generate_sized_chunk(size, x, y);
+
+        (0..size.h as usize)
+            .map(|cy| {
+                (0..size.w as usize)
+                    .map(|cx| {
+                        let (hx, hy) = (cx + 1, cy + 1);
+
+                        self.derive(
+                            heights[hy][hx],
+                            (
+                                heights[hy][hx + 1] - heights[hy][hx - 1],
+                                heights[hy + 1][hx] - heights[hy - 1][hx],
+                            ),
+                            moisture[cy][cx],
+                            temperature[cy][cx],
+                        )
+                        .density
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the plants placed in a chunk, with positions relative to
+    /// the chunk.
+    pub fn generate(&self, size: Size, x: i64, y: i64) -> Vec<Plant<S>> {
+        let (x0, y0) = (x * size.w, y * size.h);
+
+        self.plants(x0, y0, size.w, size.h)
+            .into_iter()
+            .map(|plant| Plant {
+                x: plant.x - x0,
+                y: plant.y - y0,
+                ..plant
+            })
+            .collect()
+    }
+
+    /// Returns the plants placed in the area with the given top left world
+    /// position and size, with world positions.
+    pub fn plants(&self, x: i64, y: i64, width: i64, height: i64) -> Vec<Plant<S>> {
+        let mut plants = Vec::new();
+
+        for (index, species) in self.species.iter().enumerate() {
+            let spacing = species.spacing;
+            let cells = |start: i64, length: i64| {
+                start.div_euclid(spacing)..=(start + length - 1).div_euclid(spacing)
+            };
+
+            for gy in cells(y, height) {
+                for gx in cells(x, width) {
+                    let (px, py) = self.candidate(index, gx, gy);
+
+                    if (x..x + width).contains(&px)
+                        && (y..y + height).contains(&py)
+                        && self.placed(index, gx, gy)
+                    {
+                        plants.push(Plant {
+                            value: species.value.clone(),
+                            species: index,
+                            x: px,
+                            y: py,
+                        });
+                    }
+                }
+            }
+        }
+
+        plants
+    }
+
+    /// Derives the conditions from the height, the differences in height
+    /// across and down over two cells, the moisture, and the temperature.
+    fn derive(
+        &self,
+        height: f64,
+        (dx, dy): (f64, f64),
+        moisture: f64,
+        temperature: f64,
+    ) -> Conditions {
+        let slope = (dx * dx + dy * dy).sqrt() / 2.0;
+
+        let density = if height < self.sea_level {
+            0.0
+        } else {
+            let warmth = ((temperature + 1.0) / 2.0).clamp(0.0, 1.0);
+            let flatness = (1.0 - slope / self.max_slope).clamp(0.0, 1.0);
+            moisture.clamp(0.0, 1.0) * warmth * flatness
+        };
+
+        Conditions {
+            moisture,
+            temperature,
+            slope,
+            density,
+        }
+    }
+
+    /// Returns the candidate position of a species in the given square.
+    fn candidate(&self, index: usize, gx: i64, gy: i64) -> (i64, i64) {
+        let spacing = self.species[index].spacing;
+        let candidate = hash_all(self.seed.value, &[index as u64, gx as u64, gy as u64]);
+
+        (
+            gx * spacing + (hash(candidate, 0) % spacing as u64) as i64,
+            gy * spacing + (hash(candidate, 1) % spacing as u64) as i64,
+        )
+    }
+
+    /// Returns the priority of the candidate in the given square, higher
+    /// priorities winning over lower ones.
+    fn priority(&self, index: usize, gx: i64, gy: i64) -> u64 {
+        hash(
+            hash_all(self.seed.value, &[index as u64, gx as u64, gy as u64]),
+            2,
+        )
+    }
+
+    /// Returns true if the candidate in the given square is in the
+    /// species' habitat, and wins its roll against the plant cover there.
+    fn allowed(&self, index: usize, gx: i64, gy: i64) -> bool {
+        let species = &self.species[index];
+        let (px, py) = self.candidate(index, gx, gy);
+        let conditions = self.conditions(px, py);
+
+        let roll = hash(
+            hash_all(self.seed.value, &[index as u64, gx as u64, gy as u64]),
+            3,
+        );
+
+        species.grows_in(&conditions) && unit(roll) < conditions.density * species.abundance
+    }
+
+    /// Returns true if the candidate in the given square is allowed, and no
+    /// allowed candidate within the spacing outranks it.
+    fn placed(&self, index: usize, gx: i64, gy: i64) -> bool {
+        if !self.allowed(index, gx, gy) {
+            return false;
+        }
+
+        let spacing = self.species[index].spacing;
+        let (px, py) = self.candidate(index, gx, gy);
+        let priority = self.priority(index, gx, gy);
+
+        for ny in gy - 1..=gy + 1 {
+            for nx in gx - 1..=gx + 1 {
+                if (nx, ny) == (gx, gy) {
+                    continue;
+                }
+
+                let (ox, oy) = self.candidate(index, nx, ny);
+                let (dx, dy) = (ox - px, oy - py);
+
+                if dx * dx + dy * dy < spacing * spacing
+                    && self.priority(index, nx, ny) > priority
+                    && self.allowed(index, nx, ny)
+                {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
@@ -0,0 +1,84 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/version.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Versioning of the generation algorithms.
+//!
+//! The same world and seed always generate the same tiles with the same
+//! version of this crate, but an upgrade can change them, for example when
+//! a noise source or the placement of structures is fixed. Terrain that was
+//! already explored and saved would then no longer match the terrain
+//! generated next to it.
+//!
+//! `GENERATION_VERSION` changes whenever the tiles generated from the same
+//! world and seed change. It is written into chunks saved by a `FileStore`
+//! and into world definitions, and checked when they are read back:
+//!
+//! ```
+//! # use worldgen::version::{self, GENERATION_VERSION};
+//! // Saved with a game, along with its seed
+//! let saved = GENERATION_VERSION;
+//!
+//! // On loading the game
+//! if let Err(mismatch) = version::check(saved) {
+//!     println!("this world was made with another version: {}", mismatch);
+//! }
+//! # assert!(version::check(saved).is_ok());
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+/// The version of the generation algorithms, which changes whenever the
+/// tiles generated from the same world and seed change.
+pub const GENERATION_VERSION: u32 = 1;
+
+/// The error returned when something was generated with a different
+/// version of the generation algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionMismatch {
+    /// The version that was found.
+    pub found: u32,
+
+    /// The version of this crate, `GENERATION_VERSION`.
+    pub expected: u32,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "generated by version {} of the generation algorithms, but this is version {}",
+            self.found, self.expected
+        )
+    }
+}
+
+impl Error for VersionMismatch {}
+
+/// Checks that a version found with saved data is the version of this
+/// crate.
+pub fn check(found: u32) -> Result<(), VersionMismatch> {
+    if found == GENERATION_VERSION {
+        Ok(())
+    } else {
+        Err(VersionMismatch {
+            found,
+            expected: GENERATION_VERSION,
+        })
+    }
+}
@@ -0,0 +1,218 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/voxel.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Conversion of heightmaps into columns of blocks, for voxel worlds.
+//!
+//! A `Columns` generator turns each height into a stack of blocks: the
+//! surface layers on top (such as grass over dirt), the base material
+//! (such as stone) below them, and water above the ground up to sea level.
+//! Each column is a list of runs of blocks, from the bottom up:
+//!
+//! ```
+//! # use worldgen::voxel::Columns;
+//! let columns = Columns::new("stone")
+//!     .layer("grass", 1)
+//!     .layer("dirt", 3)
+//!     .water("water", 64)
+//!     .scale(32.0)
+//!     .base(64.0);
+//!
+//! // A hill, 80 blocks tall
+//! assert_eq!(
+//!     columns.column(0.5),
+//!     vec![("stone", 76), ("dirt", 3), ("grass", 1)]
+//! );
+//!
+//! // A lake bed, 48 blocks down, under 16 blocks of water
+//! assert_eq!(
+//!     columns.column(-0.5),
+//!     vec![("stone", 44), ("dirt", 3), ("grass", 1), ("water", 16)]
+//! );
+//! ```
+//!
+//! Each biome can have columns of its own, such as sand in deserts and
+//! snow on mountains, with `biome_columns`. The columns can then be written
+//! as Minecraft region files with the `anvil` exporter.
+
+use crate::noisemap::{NoiseMapGeneratorBase, Size};
+use crate::world::Chunk;
+
+/// A column of blocks, as runs of a material and the number of blocks it
+/// fills, from the bottom up.
+pub type Column<M> = Vec<(M, u32)>;
+
+/// Converts heights into columns of blocks.
+///
+/// A height value `h` becomes a column of ground `base + h * scale` blocks
+/// tall (rounded, and never below zero).
+///
+/// The defaults are:
+///
+/// ```text
+/// scale = 1.0
+/// base = 0.0
+/// ```
+///
+/// with no surface layers and no water.
+#[derive(Debug, Clone)]
+pub struct Columns<M> {
+    material: M,
+    layers: Vec<(M, u32)>,
+    water: Option<(M, u32)>,
+
+    scale: f64,
+    base: f64,
+}
+
+impl<M: Clone> Columns<M> {
+    /// Construct a generator filling the ground with the given material.
+    pub fn new(material: M) -> Columns<M> {
+        Columns {
+            material,
+            layers: Vec::new(),
+            water: None,
+
+            scale: 1.0,
+            base: 0.0,
+        }
+    }
+
+    /// Add a surface layer of the given depth in blocks, below the layers
+    /// added before it. Where the ground isn't deep enough for every layer,
+    /// the lowest layers are cut short.
+    pub fn layer(self, material: M, depth: u32) -> Columns<M> {
+        let mut new = self;
+        new.layers.push((material, depth));
+        new
+    }
+
+    /// Set the material filling columns up to the given sea level, in
+    /// blocks from the bottom of the column.
+    pub fn water(self, material: M, sea_level: u32) -> Columns<M> {
+        Columns {
+            water: Some((material, sea_level)),
+            ..self
+        }
+    }
+
+    /// Set the number of blocks a height value of 1 adds to a column.
+    pub fn scale(self, scale: f64) -> Columns<M> {
+        Columns { scale, ..self }
+    }
+
+    /// Set the height of the ground, in blocks, where the height value is
+    /// 0.
+    pub fn base(self, base: f64) -> Columns<M> {
+        Columns { base, ..self }
+    }
+
+    /// Returns the height of the ground, in blocks, for a height value.
+    pub fn ground_height(&self, height: f64) -> u32 {
+        (self.base + height * self.scale).round().max(0.0) as u32
+    }
+
+    /// Builds the column for a height value.
+    pub fn column(&self, height: f64) -> Column<M> {
+        let ground = self.ground_height(height);
+
+        let mut surface = Vec::with_capacity(self.layers.len());
+        let mut remaining = ground;
+
+        for (material, depth) in self.layers.iter() {
+            let depth = (*depth).min(remaining);
+            remaining -= depth;
+            surface.push((material.clone(), depth));
+        }
+
+        let mut column = Vec::with_capacity(self.layers.len() + 2);
+        column.push((self.material.clone(), remaining));
+        column.extend(surface.into_iter().rev());
+
+        if let Some((ref material, sea_level)) = self.water {
+            column.push((material.clone(), sea_level.saturating_sub(ground)));
+        }
+
+        column.retain(|&(_, count)| count > 0);
+        column
+    }
+
+    /// Builds the columns for a region of a heightmap.
+    pub fn columns(&self, heights: &[Vec<f64>]) -> Chunk<Column<M>> {
+        heights
+            .iter()
+            .map(|row| row.iter().map(|&height| self.column(height)).collect())
+            .collect()
+    }
+
+    /// Builds the columns for a chunk of a noise map.
+    pub fn columns_chunk<NM: NoiseMapGeneratorBase + ?Sized>(
+        &self,
+        nm: &NM,
+        size: Size,
+        x: i64,
+        y: i64,
+    ) -> Chunk<Column<M>> {
+        self.columns(&nm.generate_sized_chunk(size, x, y))
+    }
+}
+
+/// Builds the columns for a region of a heightmap, and the biome of each
+/// position, with the column generator of each biome.
+///
+/// Each column is paired with its biome, for exporters that record both.
+/// Positions outside of either region are left out.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::voxel::{biome_columns, Columns};
+/// let plains = Columns::new("stone").layer("grass", 1).base(64.0);
+/// let desert = Columns::new("stone").layer("sand", 4).base(64.0);
+///
+/// let heights = vec![vec![0.0, 0.0]];
+/// let biomes = vec![vec!["plains", "desert"]];
+///
+/// let columns = biome_columns(&heights, &biomes, |&biome| match biome {
+///     "desert" => &desert,
+///     _ => &plains,
+/// });
+///
+/// assert_eq!(columns[0][1], ("desert", vec![("stone", 60), ("sand", 4)]));
+/// ```
+pub fn biome_columns<'a, M, B, F>(
+    heights: &[Vec<f64>],
+    biomes: &[Vec<B>],
+    columns: F,
+) -> Chunk<(B, Column<M>)>
+where
+    M: Clone + 'a,
+    B: Clone,
+    F: Fn(&B) -> &'a Columns<M>,
+{
+    heights
+        .iter()
+        .zip(biomes.iter())
+        .map(|(heights, biomes)| {
+            heights
+                .iter()
+                .zip(biomes.iter())
+                .map(|(&height, biome)| (biome.clone(), columns(biome).column(height)))
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,162 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/wasm.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! A small facade for using the crate from JavaScript.
+//!
+//! With the `wasm` feature, these types are exported with wasm-bindgen, so
+//! a crate built for wasm32 can be used directly by browser map tools.
+//! Chunks are returned flat, row by row, which JavaScript receives as typed
+//! arrays ready to be drawn to a canvas:
+//!
+//! ```
+//! # use worldgen::wasm::{NoiseGenerator, TerrainGenerator};
+//! let noise = NoiseGenerator::new("hills", 64, 64, 0.02);
+//! let values = noise.chunk(0, -1);
+//! assert_eq!(values.len(), 64 * 64);
+//!
+//! let world = TerrainGenerator::preset("archipelago", "islands").unwrap();
+//! let tiles = world.chunk(2, 3).unwrap();
+//! assert_eq!(tiles.len(), (world.width() * world.height()) as usize);
+//! assert!(TerrainGenerator::tile_name(tiles[0]).is_some());
+//! ```
+//!
+//! Seeds are given as text, as JavaScript numbers can't hold every 64 bit
+//! seed, and read as by `Seed`'s `FromStr`: text made only of digits is
+//! used as the seed's exact value, and any other text is hashed. Chunk
+//! coordinates are 32 bit, as JavaScript numbers are.
+
+use wasm_bindgen::prelude::*;
+
+use crate::noise::coherent::CoherentNoise;
+use crate::noise::octaved::OctavedNoise;
+use crate::noise::perlin::PerlinNoise;
+use crate::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Seed, Size, Step};
+use crate::presets::{self, Terrain};
+use crate::world::World;
+
+/// The tiles of the preset worlds, indexed by the values in chunks.
+const TERRAIN: [Terrain; 10] = [
+    Terrain::DeepWater,
+    Terrain::Water,
+    Terrain::Beach,
+    Terrain::Grassland,
+    Terrain::Forest,
+    Terrain::Hills,
+    Terrain::Mountains,
+    Terrain::Snow,
+    Terrain::Wall,
+    Terrain::Floor,
+];
+
+/// Generates chunks of perlin noise.
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct NoiseGenerator {
+    nm: NoiseMap<OctavedNoise<CoherentNoise>>,
+}
+
+/// Generates chunks of one of the preset worlds.
+#[wasm_bindgen]
+pub struct TerrainGenerator {
+    world: World<Terrain>,
+}
+
+#[wasm_bindgen]
+impl NoiseGenerator {
+    /// Construct a generator of chunks of the given size, with the given
+    /// step between cells.
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: &str, width: u32, height: u32, step: f64) -> NoiseGenerator {
+        NoiseGenerator {
+            nm: NoiseMap::new(PerlinNoise::new())
+                .set(seed_of(seed))
+                .set(Size::of(width as i64, height as i64))
+                .set(Step::of(step, step)),
+        }
+    }
+
+    /// Generates the values of a chunk, row by row.
+    pub fn chunk(&self, x: i32, y: i32) -> Vec<f32> {
+        self.nm
+            .generate_chunk_f32(x as i64, y as i64)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+#[wasm_bindgen]
+impl TerrainGenerator {
+    /// Construct a generator of the preset with the given name, one of
+    /// `continents`, `archipelago`, `pangea`, `highlands` or `caves`.
+    pub fn preset(name: &str, seed: &str) -> Option<TerrainGenerator> {
+        let seed = seed_of(seed);
+
+        let definition = match name {
+            "continents" => presets::continents(seed),
+            "archipelago" => presets::archipelago(seed),
+            "pangea" => presets::pangea(seed),
+            "highlands" => presets::highlands(seed),
+            "caves" => presets::cave_world(seed),
+            _ => return None,
+        };
+
+        definition
+            .build()
+            .ok()
+            .map(|world| TerrainGenerator { world })
+    }
+
+    /// Returns the width of the chunks.
+    pub fn width(&self) -> u32 {
+        self.world.get_size().w as u32
+    }
+
+    /// Returns the height of the chunks.
+    pub fn height(&self) -> u32 {
+        self.world.get_size().h as u32
+    }
+
+    /// Generates the tiles of a chunk, row by row, as the index of each
+    /// tile. See `tile_name`.
+    pub fn chunk(&self, x: i32, y: i32) -> Result<Vec<u8>, String> {
+        let chunk = self
+            .world
+            .generate(x as i64, y as i64)
+            .map_err(|error| error.to_string())?;
+
+        Ok(chunk.into_iter().flatten().map(|tile| tile as u8).collect())
+    }
+
+    /// Returns the name of the tile with the given index.
+    pub fn tile_name(index: u8) -> Option<String> {
+        TERRAIN
+            .get(index as usize)
+            .map(|tile| format!("{:?}", tile))
+    }
+
+    /// Returns the character the tile with the given index is drawn with.
+    pub fn tile_glyph(index: u8) -> Option<char> {
+        TERRAIN.get(index as usize).map(|tile| tile.glyph())
+    }
+}
+
+/// Reads a seed given as text.
+fn seed_of(text: &str) -> Seed {
+    text.parse().unwrap_or_else(|never| match never {})
+}
@@ -0,0 +1,491 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/weather.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Weather that moves across the world over time.
+//!
+//! A `CloudMap` carries noise along the wind, so clouds drift across the
+//! world and swirl where the wind veers, forming and dissolving as they
+//! go. Its precipitation map rains wherever the clouds are thick enough.
+//! Both are noise maps, so they can be generated a chunk at a time or
+//! sampled at single positions, for example every frame around the player:
+//!
+//! ```
+//! # use worldgen::noise::perlin::PerlinNoise;
+//! # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase};
+//! # use worldgen::noisemap::{Seed, Size, Step};
+//! # use worldgen::weather::{CloudMap, Wind};
+//! let clouds = CloudMap::new(NoiseMap::new(PerlinNoise::new()))
+//!     .set(Seed::of("clouds"))
+//!     .set(Step::of(0.02, 0.02))
+//!     .set(Size::of(32, 32))
+//!     .wind(Wind::new(270.0, 2.0).veer(40.0))
+//!     .churn(0.05);
+//!
+//! for frame in 0..3 {
+//!     // One unit of time every second, at 60 frames a second
+//!     let clouds = clouds.time(frame as f64 / 60.0);
+//!     let rain = clouds.precipitation();
+//!
+//!     let sky = clouds.generate_chunk(0, 0);
+//!     assert!(sky.iter().flatten().all(|&cover| (0.0..=1.0).contains(&cover)));
+//!
+//!     let raining = rain.get_value(16, 16) > 0.0;
+//! #   let _ = raining;
+//! }
+//!
+//! // Blown east by the westerly wind
+//! let later = clouds.wind(Wind::new(270.0, 2.0)).time(10.0);
+//! assert!((later.get_value(20, 5) - clouds.get_value(0, 5)).abs() < 1e-6);
+//! ```
+
+use std::ops::Mul;
+
+use crate::noise::perlin::PerlinNoise;
+use crate::noise::NoiseProvider;
+use crate::noisemap::{
+    next_id, Amplitude, NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Property,
+    ScaledNoiseMap, Seed, Size, Step, Time,
+};
+
+/// A wind blowing across the world, which can veer from its prevailing
+/// direction from place to place.
+///
+/// The direction is given as the compass direction the wind blows from, in
+/// degrees clockwise from north, where north is the top of the map. The
+/// speed is in cells for each unit of time, so time can be counted in
+/// seconds, hours, or turns.
+///
+/// The defaults are:
+///
+/// ```text
+/// seed = 0
+/// veer = 0
+/// scale = 64.0
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Wind {
+    direction: f64,
+    speed: f64,
+    veer: f64,
+    scale: f64,
+    seed: Seed,
+}
+
+impl Wind {
+    /// Construct a wind blowing from the given direction at the given
+    /// speed everywhere.
+    pub fn new(direction: f64, speed: f64) -> Wind {
+        Wind {
+            direction,
+            speed,
+            veer: 0.0,
+            scale: 64.0,
+            seed: Seed::of_value(0),
+        }
+    }
+
+    /// Set the seed of the variation in the wind's direction.
+    pub fn set(self, seed: Seed) -> Wind {
+        Wind { seed, ..self }
+    }
+
+    /// Set the most, in degrees, the wind turns from its prevailing
+    /// direction.
+    pub fn veer(self, veer: f64) -> Wind {
+        Wind {
+            veer: veer.abs(),
+            ..self
+        }
+    }
+
+    /// Set the distance, in cells, over which the wind's direction
+    /// changes.
+    pub fn scale(self, scale: f64) -> Wind {
+        Wind {
+            scale: scale.max(f64::EPSILON),
+            ..self
+        }
+    }
+
+    /// Returns the wind's velocity at a world position, in cells across
+    /// and down for each unit of time.
+    pub fn velocity(&self, x: f64, y: f64) -> (f64, f64) {
+        let turn = if self.veer > 0.0 {
+            let noise =
+                PerlinNoise::new().generate(x / self.scale, y / self.scale, self.seed.value);
+            noise.clamp(-1.0, 1.0) * self.veer
+        } else {
+            0.0
+        };
+
+        // The direction the wind blows towards.
+        let angle = (self.direction + 180.0 + turn).to_radians();
+        (self.speed * angle.sin(), -self.speed * angle.cos())
+    }
+}
+
+/// A map of cloud cover carried by the wind, with values between 0 (clear
+/// sky) and 1 (overcast).
+///
+/// The clouds are the wrapped noise map's values, moved along the wind
+/// since time 0. Each position follows the wind back to where its air was
+/// at the start, in a number of steps, and takes the noise there. More
+/// steps follow winds that veer more closely, but take longer to generate.
+///
+/// As well as moving, clouds form and dissolve at the churn rate: the
+/// noise fades into a different pattern over each `1 / churn` units of
+/// time.
+///
+/// The coverage is roughly the fraction of the sky with clouds in it, and
+/// the softness is how gradually they thicken from their edges.
+///
+/// The defaults are:
+///
+/// ```text
+/// wind = 2.0 from the west
+/// time = 0.0
+/// steps = 8
+/// churn = 0.0
+/// coverage = 0.5
+/// softness = 0.5
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct CloudMap<T> {
+    nm: NoiseMap<T>,
+
+    wind: Wind,
+    time: f64,
+    steps: u32,
+    churn: f64,
+    coverage: f64,
+    softness: f64,
+
+    id: u64,
+}
+
+impl<T: NoiseProvider> CloudMap<T> {
+    /// Construct a map of clouds made from the given noise map.
+    pub fn new(nm: NoiseMap<T>) -> CloudMap<T> {
+        CloudMap {
+            nm,
+
+            wind: Wind::new(270.0, 2.0),
+            time: 0.0,
+            steps: 8,
+            churn: 0.0,
+            coverage: 0.5,
+            softness: 0.5,
+
+            id: next_id(),
+        }
+    }
+
+    /// Set the wind the clouds are carried by.
+    pub fn wind(self, wind: Wind) -> CloudMap<T> {
+        CloudMap { wind, ..self }
+    }
+
+    /// Set the time the clouds are generated for.
+    pub fn time(self, time: f64) -> CloudMap<T> {
+        CloudMap { time, ..self }
+    }
+
+    /// Set the number of steps each position follows the wind back in.
+    pub fn steps(self, steps: u32) -> CloudMap<T> {
+        CloudMap {
+            steps: steps.max(1),
+            ..self
+        }
+    }
+
+    /// Set how many times, in each unit of time, the clouds fade into a
+    /// new pattern.
+    pub fn churn(self, churn: f64) -> CloudMap<T> {
+        CloudMap {
+            churn: churn.abs(),
+            ..self
+        }
+    }
+
+    /// Set the rough fraction of the sky with clouds in it.
+    pub fn coverage(self, coverage: f64) -> CloudMap<T> {
+        CloudMap {
+            coverage: coverage.clamp(0.0, 1.0),
+            ..self
+        }
+    }
+
+    /// Set how gradually clouds thicken from their edges.
+    pub fn softness(self, softness: f64) -> CloudMap<T> {
+        CloudMap {
+            softness: softness.max(f64::EPSILON),
+            ..self
+        }
+    }
+
+    /// Returns a map of the precipitation falling from these clouds.
+    pub fn precipitation(self) -> PrecipitationMap<T> {
+        PrecipitationMap::new(self)
+    }
+
+    /// Returns the wind the clouds are carried by.
+    pub fn get_wind(&self) -> Wind {
+        self.wind
+    }
+
+    /// Returns the cloud cover at a world position between cells.
+    pub fn sample(&self, x: f64, y: f64) -> f64 {
+        // Follows the wind back to where the air was at time 0.
+        let dt = self.time / self.steps as f64;
+        let (mut px, mut py) = (x, y);
+
+        for _ in 0..self.steps {
+            let (vx, vy) = self.wind.velocity(px, py);
+            px -= vx * dt;
+            py -= vy * dt;
+        }
+
+        let noise = if self.churn > 0.0 {
+            let pattern = self.time * self.churn;
+            let (first, fade) = (pattern.floor(), pattern - pattern.floor());
+            let fade = fade * fade * (3.0 - 2.0 * fade);
+
+            let layer = |index: f64| self.nm.sample(px + index * 7919.0, py + index * 6271.0);
+            layer(first) * (1.0 - fade) + layer(first + 1.0) * fade
+        } else {
+            self.nm.sample(px, py)
+        };
+
+        let threshold = 1.0 - 2.0 * self.coverage;
+        ((noise - threshold) / self.softness).clamp(0.0, 1.0)
+    }
+}
+
+impl<T: NoiseProvider> NoiseMapGeneratorBase for CloudMap<T> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.nm.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        (y * size.h..(y + 1) * size.h)
+            .map(|y| {
+                (x * size.w..(x + 1) * size.w)
+                    .map(|x| self.get_value(x, y))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        self.sample(world_x as f64, world_y as f64)
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (0.0, 1.0)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<T: NoiseProvider> NoiseMapGenerator for CloudMap<T> {
+    fn set<P: Property>(self, property: P) -> CloudMap<T> {
+        CloudMap {
+            nm: self.nm.set(property),
+            ..self
+        }
+    }
+
+    fn get_size(&self) -> Size {
+        self.nm.get_size()
+    }
+
+    fn set_seed(self, seed: Seed) -> CloudMap<T> {
+        self.set(seed)
+    }
+
+    fn set_step(self, step: Step) -> CloudMap<T> {
+        self.set(step)
+    }
+
+    fn set_size(self, size: Size) -> CloudMap<T> {
+        self.set(size)
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> CloudMap<T> {
+        self.set(amplitude)
+    }
+
+    fn set_time(self, time: Time) -> CloudMap<T> {
+        self.set(time)
+    }
+}
+
+impl<T: NoiseProvider> Mul<i64> for CloudMap<T> {
+    type Output = ScaledNoiseMap<CloudMap<T>>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<CloudMap<T>> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
+
+/// A map of precipitation falling from a `CloudMap`, with values between 0
+/// (dry) and 1 (a downpour).
+///
+/// Precipitation falls wherever the cloud cover is above the threshold,
+/// getting heavier as the clouds thicken. Whether it falls as rain or snow
+/// is left to the game, for example by comparing a `TemperatureMap`.
+///
+/// The defaults are:
+///
+/// ```text
+/// threshold = 0.6
+/// ```
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, NoiseMapGeneratorBase, Size, Step};
+/// # use worldgen::weather::{CloudMap, PrecipitationMap};
+/// # let noise = PerlinNoise::new();
+/// let clouds = CloudMap::new(NoiseMap::new(noise).set(Step::of(0.05, 0.05)));
+/// let nm = PrecipitationMap::new(clouds).threshold(0.4);
+///
+/// let whole = nm.generate_sized_chunk(Size::of(24, 24), 1, -1);
+/// let part = nm.generate_sized_chunk(Size::of(5, 7), 5, -3);
+/// assert!(part.iter().zip(&whole[3..10]).all(|(row, whole)| row[..] == whole[1..6]));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct PrecipitationMap<T> {
+    clouds: CloudMap<T>,
+    threshold: f64,
+
+    id: u64,
+}
+
+impl<T: NoiseProvider> PrecipitationMap<T> {
+    /// Construct a map of the precipitation falling from the given clouds.
+    pub fn new(clouds: CloudMap<T>) -> PrecipitationMap<T> {
+        PrecipitationMap {
+            clouds,
+            threshold: 0.6,
+
+            id: next_id(),
+        }
+    }
+
+    /// Set the cloud cover above which precipitation falls.
+    pub fn threshold(self, threshold: f64) -> PrecipitationMap<T> {
+        PrecipitationMap {
+            threshold: threshold.clamp(0.0, 1.0 - f64::EPSILON),
+            ..self
+        }
+    }
+
+    /// Set the time the precipitation is generated for.
+    pub fn time(self, time: f64) -> PrecipitationMap<T> {
+        PrecipitationMap {
+            clouds: self.clouds.time(time),
+            ..self
+        }
+    }
+
+    /// Returns the precipitation at a world position between cells.
+    pub fn sample(&self, x: f64, y: f64) -> f64 {
+        self.precipitation(self.clouds.sample(x, y))
+    }
+
+    fn precipitation(&self, cover: f64) -> f64 {
+        ((cover - self.threshold) / (1.0 - self.threshold)).max(0.0)
+    }
+}
+
+impl<T: NoiseProvider> NoiseMapGeneratorBase for PrecipitationMap<T> {
+    fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+        let size = self.clouds.get_size();
+        self.generate_sized_chunk(size, x, y)
+    }
+
+    fn generate_sized_chunk(&self, size: Size, x: i64, y: i64) -> Vec<Vec<f64>> {
+        self.clouds
+            .generate_sized_chunk(size, x, y)
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|cover| self.precipitation(cover))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn get_value(&self, world_x: i64, world_y: i64) -> f64 {
+        self.sample(world_x as f64, world_y as f64)
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        (0.0, 1.0)
+    }
+
+    fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<T: NoiseProvider> NoiseMapGenerator for PrecipitationMap<T> {
+    fn set<P: Property>(self, property: P) -> PrecipitationMap<T> {
+        PrecipitationMap {
+            clouds: self.clouds.set(property),
+            ..self
+        }
+    }
+
+    fn get_size(&self) -> Size {
+        self.clouds.get_size()
+    }
+
+    fn set_seed(self, seed: Seed) -> PrecipitationMap<T> {
+        self.set(seed)
+    }
+
+    fn set_step(self, step: Step) -> PrecipitationMap<T> {
+        self.set(step)
+    }
+
+    fn set_size(self, size: Size) -> PrecipitationMap<T> {
+        self.set(size)
+    }
+
+    fn set_amplitude(self, amplitude: Amplitude) -> PrecipitationMap<T> {
+        self.set(amplitude)
+    }
+
+    fn set_time(self, time: Time) -> PrecipitationMap<T> {
+        self.set(time)
+    }
+}
+
+impl<T: NoiseProvider> Mul<i64> for PrecipitationMap<T> {
+    type Output = ScaledNoiseMap<PrecipitationMap<T>>;
+
+    fn mul(self, scale: i64) -> ScaledNoiseMap<PrecipitationMap<T>> {
+        ScaledNoiseMap::new(self, scale)
+    }
+}
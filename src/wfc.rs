@@ -0,0 +1,524 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/wfc.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Wave function collapse.
+//!
+//! Wave function collapse learns which tiles may sit next to each other
+//! from an example grid of tiles, and then fills a new grid with tiles that
+//! only sit next to each other in the ways the example allows. Starting
+//! with every tile possible in every cell, it repeatedly collapses the cell
+//! with the fewest possibilities left to a single tile (chosen at random,
+//! weighted by how often the tile appears in the example), and removes the
+//! possibilities this rules out from the surrounding cells.
+//!
+//! There are two models:
+//!
+//! * The tiled model learns which tiles may sit beside each other directly.
+//! * The overlapping model learns every square pattern of tiles of a given
+//!   size, and places patterns so that neighbouring patterns overlap where
+//!   they agree, which reproduces larger features of the example.
+//!
+//! ```
+//! # use worldgen::wfc::Model;
+//! # use worldgen::world::{Seed, Size};
+//! let example: Vec<Vec<char>> = vec![
+//!     "~~~..,,,".chars().collect(),
+//!     "~~..,,,,".chars().collect(),
+//!     "~~~..,,,".chars().collect(),
+//!     "~~~~..,,".chars().collect(),
+//! ];
+//!
+//! let model = Model::tiled(&example);
+//! let tiles = model.generate(Size::of(16, 16), Seed::of("coast")).unwrap();
+//!
+//! // Water and grass never touch
+//! for row in tiles.iter() {
+//!     assert!(!row.windows(2).any(|pair| pair == ['~', ','] || pair == [',', '~']));
+//! }
+//! ```
+//!
+//! Cells can also be fixed to tiles before generating, for example with the
+//! output of a `World`, so the result fits in with it.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+
+use crate::cancel::{CancelToken, Cancelled};
+use crate::noisemap::{Seed, Size};
+use crate::progress::Progress;
+use crate::rng::{hash_all, unit};
+use crate::world::Chunk;
+
+/// The offsets to the cells above, to the right of, below, and to the left
+/// of a cell.
+const DIRECTIONS: [(i64, i64); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+fn opposite(direction: usize) -> usize {
+    (direction + 2) % 4
+}
+
+/// The error returned when generation runs into a cell that no tile can
+/// fill, on every attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Contradiction {
+    /// The position of the cell, in the last attempt.
+    pub x: i64,
+    pub y: i64,
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no tile can fill position ({}, {})", self.x, self.y)
+    }
+}
+
+impl Error for Contradiction {}
+
+/// The error returned when a grid can't be generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WfcError {
+    /// Every attempt ran into a contradiction.
+    Contradiction(Contradiction),
+
+    /// Generation was stopped by the model's `CancelToken`.
+    Cancelled,
+}
+
+impl fmt::Display for WfcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            WfcError::Contradiction(ref contradiction) => contradiction.fmt(f),
+            WfcError::Cancelled => Cancelled.fmt(f),
+        }
+    }
+}
+
+impl Error for WfcError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            WfcError::Contradiction(ref contradiction) => Some(contradiction),
+            WfcError::Cancelled => None,
+        }
+    }
+}
+
+impl From<Contradiction> for WfcError {
+    fn from(contradiction: Contradiction) -> WfcError {
+        WfcError::Contradiction(contradiction)
+    }
+}
+
+impl From<Cancelled> for WfcError {
+    fn from(_: Cancelled) -> WfcError {
+        WfcError::Cancelled
+    }
+}
+
+/// The patterns learned from an example, and how they may be placed.
+///
+/// Generation is attempted again with different random choices when it
+/// runs into a contradiction, up to 10 times by default.
+#[derive(Debug, Clone)]
+pub struct Model<T> {
+    /// The tile each pattern places in its cell.
+    values: Vec<T>,
+
+    /// How often each pattern appears in the example.
+    weights: Vec<f64>,
+
+    /// For each pattern and direction, the patterns that may sit beside
+    /// it in that direction.
+    compatible: Vec<[Vec<usize>; 4]>,
+
+    attempts: u32,
+    progress: Progress,
+    cancel: CancelToken,
+}
+
+impl<T: Clone + Eq + Hash> Model<T> {
+    /// Construct a tiled model from an example grid, as a vector of rows.
+    pub fn tiled(example: &[Vec<T>]) -> Model<T> {
+        Model::overlapping(example, 1)
+    }
+
+    /// Construct an overlapping model from an example grid, as a vector of
+    /// rows, learning square patterns of the given size.
+    ///
+    /// The example is treated as wrapping around at its edges, so patterns
+    /// are also taken from across the edges.
+    ///
+    /// With a size of 1, this is the same as the tiled model.
+    pub fn overlapping(example: &[Vec<T>], n: usize) -> Model<T> {
+        let n = n.max(1);
+        let mut patterns: Vec<Vec<Vec<T>>> = Vec::new();
+        let mut indices: HashMap<Vec<Vec<T>>, usize> = HashMap::new();
+        let mut weights: Vec<f64> = Vec::new();
+
+        // Patterns beside each other in the example, for the tiled model.
+        let mut grid: Vec<Vec<usize>> = Vec::new();
+
+        // Larger patterns wrap around the edges of the example, so that
+        // every pattern has others that may overlap it.
+        let height = example.len();
+        let width = example.iter().map(Vec::len).min().unwrap_or(0);
+
+        for y in 0..height {
+            let mut row = Vec::new();
+
+            for x in 0..width {
+                let pattern: Vec<Vec<T>> = (0..n)
+                    .map(|dy| {
+                        (0..n)
+                            .map(|dx| example[(y + dy) % height][(x + dx) % width].clone())
+                            .collect()
+                    })
+                    .collect();
+
+                let index = *indices.entry(pattern.clone()).or_insert_with(|| {
+                    patterns.push(pattern);
+                    weights.push(0.0);
+                    patterns.len() - 1
+                });
+
+                weights[index] += 1.0;
+                row.push(index);
+            }
+
+            grid.push(row);
+        }
+
+        let mut compatible = vec![[Vec::new(), Vec::new(), Vec::new(), Vec::new()]; patterns.len()];
+
+        if n == 1 {
+            for (y, row) in grid.iter().enumerate() {
+                for (x, &a) in row.iter().enumerate() {
+                    for (direction, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+                        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                        if ny < 0 || nx < 0 {
+                            continue;
+                        }
+
+                        if let Some(&b) = grid.get(ny as usize).and_then(|r| r.get(nx as usize)) {
+                            if !compatible[a][direction].contains(&b) {
+                                compatible[a][direction].push(b);
+                            }
+                        }
+                    }
+                }
+            }
+        } else {
+            for (a, first) in patterns.iter().enumerate() {
+                for (b, second) in patterns.iter().enumerate() {
+                    for (direction, &offset) in DIRECTIONS.iter().enumerate() {
+                        if agrees(first, second, offset) {
+                            compatible[a][direction].push(b);
+                        }
+                    }
+                }
+            }
+        }
+
+        Model {
+            values: patterns
+                .into_iter()
+                .map(|pattern| pattern[0][0].clone())
+                .collect(),
+            weights,
+            compatible,
+
+            attempts: 10,
+            progress: Default::default(),
+            cancel: Default::default(),
+        }
+    }
+
+    /// Set the number of times generation is attempted before giving up.
+    pub fn attempts(self, attempts: u32) -> Model<T> {
+        Model {
+            attempts: attempts.max(1),
+            ..self
+        }
+    }
+
+    /// Set a callback to be told how far generation has got, for example
+    /// to drive a progress bar.
+    ///
+    /// The callback is given the stage `"wfc"` and the fraction of cells
+    /// that have been decided. The fraction starts again from the
+    /// beginning if an attempt runs into a contradiction.
+    pub fn on_progress<F>(self, callback: F) -> Model<T>
+    where
+        F: Fn(&str, f64) + Send + Sync + 'static,
+    {
+        Model {
+            progress: Progress::new(callback),
+            ..self
+        }
+    }
+
+    /// Set a token that stops generation when it is cancelled. See the
+    /// `cancel` module.
+    ///
+    /// The token is checked before each cell is collapsed.
+    pub fn cancel_on(self, token: CancelToken) -> Model<T> {
+        Model {
+            cancel: token,
+            ..self
+        }
+    }
+
+    /// Generates a grid of tiles of the given size.
+    pub fn generate(&self, size: Size, seed: Seed) -> Result<Chunk<T>, WfcError> {
+        self.generate_with(size, seed, &[])
+    }
+
+    /// Generates a grid of tiles of the given size, with cells fixed to the
+    /// tiles given in a prior grid, as a vector of rows.
+    ///
+    /// Cells holding `None`, or beyond the prior grid, are left free.
+    pub fn generate_with(
+        &self,
+        size: Size,
+        seed: Seed,
+        prior: &[Vec<Option<T>>],
+    ) -> Result<Chunk<T>, WfcError> {
+        let mut result = Err(WfcError::Contradiction(Contradiction { x: 0, y: 0 }));
+
+        for attempt in 0..self.attempts {
+            result = Wave::new(self, size, hash_all(seed.value, &[attempt as u64])).run(prior);
+
+            if !matches!(result, Err(WfcError::Contradiction(_))) {
+                break;
+            }
+        }
+
+        result
+    }
+}
+
+/// Returns true if the second pattern may sit at the given offset from the
+/// first, with the patterns agreeing where they overlap.
+fn agrees<T: PartialEq>(first: &[Vec<T>], second: &[Vec<T>], (dx, dy): (i64, i64)) -> bool {
+    let n = first.len() as i64;
+
+    (0.max(dy)..n.min(n + dy)).all(|y| {
+        (0.max(dx)..n.min(n + dx))
+            .all(|x| first[y as usize][x as usize] == second[(y - dy) as usize][(x - dx) as usize])
+    })
+}
+
+/// The state of a single attempt at generation.
+struct Wave<'a, T> {
+    model: &'a Model<T>,
+    size: Size,
+    seed: u64,
+
+    /// Whether each pattern is still possible in each cell.
+    possible: Vec<Vec<bool>>,
+
+    /// For each cell, pattern and direction, how many patterns are still
+    /// possible in the neighbouring cell in the opposite direction that
+    /// allow the pattern to sit beside them.
+    support: Vec<Vec<[usize; 4]>>,
+
+    /// The number, total weight, and total of weight times log weight of
+    /// the patterns still possible in each cell.
+    counts: Vec<usize>,
+    weights: Vec<f64>,
+    log_weights: Vec<f64>,
+
+    /// Patterns that have been ruled out, whose neighbours are yet to be
+    /// updated.
+    banned: Vec<(usize, usize)>,
+}
+
+impl<'a, T: Clone> Wave<'a, T> {
+    fn new(model: &'a Model<T>, size: Size, seed: u64) -> Wave<'a, T> {
+        let cells = (size.w * size.h).max(0) as usize;
+        let patterns = model.values.len();
+
+        let support: Vec<[usize; 4]> = (0..patterns)
+            .map(|pattern| {
+                let mut support = [0; 4];
+                for (direction, count) in support.iter_mut().enumerate() {
+                    *count = model.compatible[pattern][opposite(direction)].len();
+                }
+                support
+            })
+            .collect();
+
+        let weight: f64 = model.weights.iter().sum();
+        let log_weight: f64 = model.weights.iter().map(|w| w * w.ln()).sum();
+
+        Wave {
+            model,
+            size,
+            seed,
+
+            possible: vec![vec![true; patterns]; cells],
+            support: vec![support; cells],
+
+            counts: vec![patterns; cells],
+            weights: vec![weight; cells],
+            log_weights: vec![log_weight; cells],
+
+            banned: Vec::new(),
+        }
+    }
+
+    fn run(mut self, prior: &[Vec<Option<T>>]) -> Result<Chunk<T>, WfcError>
+    where
+        T: PartialEq,
+    {
+        for cell in 0..self.possible.len() {
+            if self.model.values.is_empty() {
+                return Err(self.contradiction(cell).into());
+            }
+
+            let (x, y) = (cell % self.size.w as usize, cell / self.size.w as usize);
+            if let Some(Some(value)) = prior.get(y).and_then(|row| row.get(x)) {
+                for pattern in 0..self.model.values.len() {
+                    if self.model.values[pattern] != *value && self.possible[cell][pattern] {
+                        self.ban(cell, pattern)?;
+                    }
+                }
+            }
+        }
+
+        self.propagate()?;
+
+        for step in 0.. {
+            self.model.cancel.check()?;
+
+            let cell = match self.observe(step) {
+                Some(cell) => cell,
+                None => break,
+            };
+
+            let total = self.weights[cell];
+            let mut roll = unit(hash_all(self.seed, &[step, 0])) * total;
+
+            let chosen = (0..self.model.values.len())
+                .filter(|&pattern| self.possible[cell][pattern])
+                .find(|&pattern| {
+                    roll -= self.model.weights[pattern];
+                    roll < 0.0
+                })
+                .or_else(|| (0..self.model.values.len()).rfind(|&p| self.possible[cell][p]));
+
+            for pattern in 0..self.model.values.len() {
+                if Some(pattern) != chosen && self.possible[cell][pattern] {
+                    self.ban(cell, pattern)?;
+                }
+            }
+
+            self.propagate()?;
+
+            if self.model.progress.is_set() {
+                let decided = self.counts.iter().filter(|&&count| count == 1).count();
+                self.model
+                    .progress
+                    .report("wfc", decided, self.counts.len());
+            }
+        }
+
+        let width = self.size.w as usize;
+        Ok(self
+            .possible
+            .chunks(width.max(1))
+            .map(|row| {
+                row.iter()
+                    .map(|possible| {
+                        let pattern = possible.iter().position(|&p| p).unwrap();
+                        self.model.values[pattern].clone()
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Returns the undecided cell with the lowest entropy, if any.
+    fn observe(&self, step: u64) -> Option<usize> {
+        (0..self.possible.len())
+            .filter(|&cell| self.counts[cell] > 1)
+            .map(|cell| {
+                let weight = self.weights[cell];
+                let entropy = weight.ln() - self.log_weights[cell] / weight;
+
+                // A little noise breaks ties between cells at random.
+                let noise = unit(hash_all(self.seed, &[step, 1, cell as u64])) * 1e-6;
+                (cell, entropy + noise)
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(cell, _)| cell)
+    }
+
+    /// Rules out a pattern in a cell.
+    fn ban(&mut self, cell: usize, pattern: usize) -> Result<(), Contradiction> {
+        let weight = self.model.weights[pattern];
+
+        self.possible[cell][pattern] = false;
+        self.counts[cell] -= 1;
+        self.weights[cell] -= weight;
+        self.log_weights[cell] -= weight * weight.ln();
+        self.banned.push((cell, pattern));
+
+        if self.counts[cell] == 0 {
+            Err(self.contradiction(cell))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Rules out the patterns that have lost all of their support.
+    fn propagate(&mut self) -> Result<(), Contradiction> {
+        let width = self.size.w;
+
+        while let Some((cell, pattern)) = self.banned.pop() {
+            let (x, y) = (cell as i64 % width, cell as i64 / width);
+
+            for (direction, &(dx, dy)) in DIRECTIONS.iter().enumerate() {
+                let (nx, ny) = (x + dx, y + dy);
+                if !(0..self.size.w).contains(&nx) || !(0..self.size.h).contains(&ny) {
+                    continue;
+                }
+
+                let neighbour = (ny * width + nx) as usize;
+                for &other in self.model.compatible[pattern][direction].iter() {
+                    let support = &mut self.support[neighbour][other][direction];
+                    *support -= 1;
+
+                    if *support == 0 && self.possible[neighbour][other] {
+                        self.ban(neighbour, other)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn contradiction(&self, cell: usize) -> Contradiction {
+        Contradiction {
+            x: cell as i64 % self.size.w,
+            y: cell as i64 / self.size.w,
+        }
+    }
+}
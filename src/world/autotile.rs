@@ -0,0 +1,80 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/autotile.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+/// The adjacency bitmask conventions used for auto-tiling. See
+/// `World::generate_bitmasks`.
+///
+/// Each bit is set when the neighbour in that direction connects to the
+/// tile, with the directions weighted as follows:
+///
+/// ```text
+/// Cardinal        Blob
+///
+///    1         128   1   2
+///  8 . 2        64   .   4
+///    4          32  16   8
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Bitmask {
+    /// The 4-bit mask of the four edge neighbours, giving the 16 tile
+    /// indices of a Wang tileset.
+    Cardinal,
+
+    /// The 8-bit mask of all eight neighbours, where a corner only counts
+    /// when both edges beside it connect too. This gives the 47 distinct
+    /// indices of a blob tileset.
+    Blob,
+}
+
+impl Bitmask {
+    /// Returns the bitmask of the cell at the given position in a grid,
+    /// which must have a neighbour in every direction.
+    pub(crate) fn of<C, F>(self, cells: &[Vec<C>], x: usize, y: usize, connects: &F) -> u8
+    where
+        F: Fn(&C, &C) -> bool,
+    {
+        let connected = |dx: isize, dy: isize| {
+            let neighbour = &cells[y.wrapping_add_signed(dy)][x.wrapping_add_signed(dx)];
+            connects(&cells[y][x], neighbour)
+        };
+
+        let (n, e, s, w) = (
+            connected(0, -1),
+            connected(1, 0),
+            connected(0, 1),
+            connected(-1, 0),
+        );
+
+        match self {
+            Bitmask::Cardinal => n as u8 | (e as u8) << 1 | (s as u8) << 2 | (w as u8) << 3,
+
+            Bitmask::Blob => {
+                let ne = n && e && connected(1, -1);
+                let se = s && e && connected(1, 1);
+                let sw = s && w && connected(-1, 1);
+                let nw = n && w && connected(-1, -1);
+
+                [n, ne, e, se, s, sw, w, nw]
+                    .iter()
+                    .enumerate()
+                    .fold(0, |mask, (bit, &set)| mask | (set as u8) << bit)
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/context.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use super::{Seed, Size};
+use crate::noisemap::NoiseMapGeneratorBase;
+use crate::rng::CellRng;
+
+/// A noise map chunk, generated by the first thread to ask for it.
+type CachedChunk = Arc<OnceLock<Vec<Vec<f64>>>>;
+
+/// The noise map chunks of a context, by noise map id and chunk position,
+/// shared with the contexts for parts of it.
+type Cache = Arc<Mutex<HashMap<(u64, i64, i64), CachedChunk>>>;
+
+/// The state used while generating a single chunk of a world.
+///
+/// This holds the position of the chunk and the properties of the world,
+/// along with a cache of the noise map chunks that have been generated so
+/// far, so that each noise map is only generated once per chunk no matter
+/// how many constraints use it.
+///
+/// Values can also be taken from positions outside the chunk, for passes
+/// that look at neighbouring cells, in which case the neighbouring chunks
+/// are generated and cached as well.
+///
+/// The cache can be shared between threads, with each noise map chunk
+/// still only generated once.
+pub struct ChunkContext {
+    pub chunk_x: i64,
+    pub chunk_y: i64,
+
+    pub size: Size,
+    pub seed: Seed,
+
+    /// The size of the chunks noise maps are generated in, which is the
+    /// size of the world's chunks even when only part of one is resolved.
+    sample_size: Size,
+    nms: Cache,
+}
+
+impl ChunkContext {
+    /// Construct the context for generating the given chunk.
+    pub fn new(size: Size, seed: Seed, chunk_x: i64, chunk_y: i64) -> ChunkContext {
+        ChunkContext {
+            chunk_x,
+            chunk_y,
+
+            size,
+            seed,
+
+            sample_size: size,
+            nms: Default::default(),
+        }
+    }
+
+    /// Construct the context for resolving the given chunk, with the noise
+    /// maps sampled only at the positions used rather than as whole chunks.
+    pub(crate) fn sampled_at_points(
+        size: Size,
+        seed: Seed,
+        chunk_x: i64,
+        chunk_y: i64,
+    ) -> ChunkContext {
+        ChunkContext {
+            sample_size: Size::of(1, 1),
+            ..ChunkContext::new(size, seed, chunk_x, chunk_y)
+        }
+    }
+
+    /// Construct a context for resolving part of this context's chunk, or
+    /// of the chunks around it, as a chunk of the given size and position.
+    ///
+    /// The noise maps are still generated in chunks of this context's
+    /// size, and shared with it, so every tile is chosen from the same
+    /// values as when the whole chunk is resolved.
+    pub(crate) fn part(&self, size: Size, chunk_x: i64, chunk_y: i64) -> ChunkContext {
+        ChunkContext {
+            chunk_x,
+            chunk_y,
+
+            size,
+            seed: self.seed,
+
+            sample_size: self.sample_size,
+            nms: self.nms.clone(),
+        }
+    }
+
+    /// Returns the value of a noise map at the given world position.
+    pub fn value(&self, nm: &dyn NoiseMapGeneratorBase, x: i64, y: i64) -> f64 {
+        let size = self.sample_size;
+        let (chunk_x, chunk_y) = (x.div_euclid(size.w), y.div_euclid(size.h));
+
+        // The lock is only held to find the chunk's slot, so other noise
+        // maps can be generated while this one is.
+        let cached = self
+            .nms
+            .lock()
+            .unwrap()
+            .entry((nm.id(), chunk_x, chunk_y))
+            .or_default()
+            .clone();
+
+        let chunk = cached.get_or_init(|| nm.generate_sized_chunk(size, chunk_x, chunk_y));
+
+        chunk[y.rem_euclid(size.h) as usize][x.rem_euclid(size.w) as usize]
+    }
+
+    /// Returns the random stream for the given world position and purpose,
+    /// derived from the world's seed.
+    ///
+    /// Passes should take their random values from here rather than from
+    /// any state of their own, so that the same tiles are generated no
+    /// matter which chunks are generated first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Tile, World};
+    /// let world = World::new()
+    ///     .add(Tile::new(','))
+    ///     .post(|chunk, context| {
+    ///         for (y, row) in chunk.iter_mut().enumerate() {
+    ///             for (x, tile) in row.iter_mut().enumerate() {
+    ///                 let (wx, wy) = context.world_position(x, y);
+    ///
+    ///                 if context.rng(wx, wy, "flowers").chance(0.1) {
+    ///                     *tile = '*';
+    ///                 }
+    ///             }
+    ///         }
+    ///     });
+    ///
+    /// assert_eq!(world.generate(3, 1).unwrap(), world.generate(3, 1).unwrap());
+    /// ```
+    pub fn rng(&self, x: i64, y: i64, purpose: &str) -> CellRng {
+        CellRng::new(self.seed, x, y, purpose)
+    }
+
+    /// Returns the world position of a position within the chunk.
+    pub fn world_position(&self, x: usize, y: usize) -> (i64, i64) {
+        (
+            self.chunk_x * self.size.w + x as i64,
+            self.chunk_y * self.size.h + y as i64,
+        )
+    }
+
+    /// Returns the values at the given world position of every noise map
+    /// that has been sampled so far, as pairs of noise map id and value,
+    /// ordered by id.
+    pub fn values_at(&self, x: i64, y: i64) -> Vec<(u64, f64)> {
+        let size = self.sample_size;
+        let position = (x.div_euclid(size.w), y.div_euclid(size.h));
+        let (x, y) = (x.rem_euclid(size.w) as usize, y.rem_euclid(size.h) as usize);
+
+        let mut values: Vec<_> = self
+            .nms
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(&(_, chunk_x, chunk_y), _)| (chunk_x, chunk_y) == position)
+            .filter_map(|(&(id, _, _), cached)| cached.get().map(|chunk| (id, chunk[y][x])))
+            .collect();
+
+        values.sort_by_key(|&(id, _)| id);
+        values
+    }
+}
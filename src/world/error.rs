@@ -0,0 +1,133 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/error.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::error::Error;
+use std::fmt;
+
+use crate::cancel::Cancelled;
+
+/// A problem with the definition of a world, found by `World::validate`.
+///
+/// Tiles are identified by their index, in the order they were added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValidationError {
+    /// There is no tile without constraints to fall back on, so some
+    /// positions may not match any tile.
+    NoFallback,
+
+    /// The constraints of the tile can never all be satisfied by the
+    /// values its noise maps produce.
+    Unsatisfiable(usize),
+
+    /// The tile comes after the fallback tile, so it will never be chosen.
+    Unreachable(usize),
+
+    /// A constraint of the tile refers to a noise map by a name that has
+    /// not been registered with `World::with_map`.
+    UnknownMap(usize, String),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::NoFallback => write!(f, "the world has no tile without constraints"),
+            ValidationError::Unsatisfiable(tile) => {
+                write!(f, "the constraints of tile {} can never be satisfied", tile)
+            }
+            ValidationError::Unreachable(tile) => {
+                write!(f, "tile {} comes after the fallback tile", tile)
+            }
+            ValidationError::UnknownMap(tile, ref name) => {
+                write!(f, "tile {} refers to an unknown noise map {:?}", tile, name)
+            }
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// The error returned when a position in a world matches none of its
+/// tiles.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenerateError {
+    /// The world coordinate of the position.
+    pub x: i64,
+    pub y: i64,
+
+    /// The values sampled from each noise map while trying to resolve the
+    /// position, as pairs of noise map id and value.
+    pub values: Vec<(u64, f64)>,
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no tile matches position ({}, {})", self.x, self.y)?;
+
+        for (index, (id, value)) in self.values.iter().enumerate() {
+            let separator = if index == 0 { " with values " } else { ", " };
+            write!(f, "{}{} = {}", separator, id, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for GenerateError {}
+
+/// The error returned when an area of a world can't be generated. See
+/// `World::generate_area`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AreaError {
+    /// A position in the area matches none of the world's tiles.
+    Generate(GenerateError),
+
+    /// Generation was stopped by the world's `CancelToken`.
+    Cancelled,
+}
+
+impl fmt::Display for AreaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AreaError::Generate(ref error) => error.fmt(f),
+            AreaError::Cancelled => Cancelled.fmt(f),
+        }
+    }
+}
+
+impl Error for AreaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            AreaError::Generate(ref error) => Some(error),
+            AreaError::Cancelled => None,
+        }
+    }
+}
+
+impl From<GenerateError> for AreaError {
+    fn from(error: GenerateError) -> AreaError {
+        AreaError::Generate(error)
+    }
+}
+
+impl From<Cancelled> for AreaError {
+    fn from(_: Cancelled) -> AreaError {
+        AreaError::Cancelled
+    }
+}
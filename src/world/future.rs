@@ -0,0 +1,204 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/future.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use super::{Chunk, GenerateError, World};
+use crate::pool::GenPool;
+
+/// A piece of work to be run away from the async runtime's threads.
+pub type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs jobs, on whatever threads it chooses.
+type Spawner = Arc<dyn Fn(Job) + Send + Sync>;
+
+/// The result of a job, or the panic it ended in.
+type Outcome<T> = thread::Result<Result<Chunk<T>, GenerateError>>;
+
+/// Generates the chunks of a world for async code, without blocking the
+/// threads of its runtime.
+///
+/// Each chunk is generated by a job run on a `GenPool` of background
+/// threads, or handed to a spawn function instead, such as the blocking
+/// pool of the runtime, and awaited with the `ChunkFuture` that's returned. The
+/// futures don't depend on any particular runtime.
+///
+/// As it needs threads, `AsyncWorld` isn't available on wasm32.
+///
+/// # Example
+///
+/// ```
+/// # use std::future::Future;
+/// # use std::pin::pin;
+/// # use std::sync::Arc;
+/// # use std::task::{Context, Poll, Wake};
+/// # use std::thread::{self, Thread};
+/// # struct Unpark(Thread);
+/// # impl Wake for Unpark {
+/// #     fn wake(self: Arc<Self>) {
+/// #         self.0.unpark()
+/// #     }
+/// # }
+/// # fn block_on<F: Future>(future: F) -> F::Output {
+/// #     let waker = Arc::new(Unpark(thread::current())).into();
+/// #     let mut context = Context::from_waker(&waker);
+/// #     let mut future = pin!(future);
+/// #     loop {
+/// #         if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+/// #             return output;
+/// #         }
+/// #         thread::park();
+/// #     }
+/// # }
+/// # use worldgen::world::{AsyncWorld, Size, Tile, World};
+/// let world = World::new()
+///     .set(Size::of(16, 16))
+///     .add(Tile::new('.'));
+///
+/// let generator = AsyncWorld::new(world, 2);
+///
+/// // In an async task
+/// # block_on(async {
+/// let chunk = generator.generate_chunk_async(0, 0).await.unwrap();
+/// assert_eq!(chunk[0][0], '.');
+/// # });
+/// ```
+///
+/// With tokio, the runtime's own blocking pool can be used instead of
+/// another pool of threads:
+///
+/// ```text
+/// let generator = AsyncWorld::with_spawner(world, |job| {
+///     tokio::task::spawn_blocking(job);
+/// });
+/// ```
+pub struct AsyncWorld<T> {
+    world: Arc<World<T>>,
+    spawn: Spawner,
+}
+
+/// A chunk being generated by an `AsyncWorld`.
+///
+/// Dropping the future doesn't stop the chunk being generated, but the
+/// chunk is thrown away.
+pub struct ChunkFuture<T> {
+    slot: Arc<Mutex<Slot<T>>>,
+}
+
+struct Slot<T> {
+    outcome: Option<Outcome<T>>,
+    waker: Option<Waker>,
+}
+
+impl<T: Clone + Send + Sync + 'static> AsyncWorld<T> {
+    /// Construct a generator running jobs on its own pool of the given
+    /// number of threads (at least one thread is always used).
+    ///
+    /// The threads finish any chunks still queued, then stop, when the
+    /// generator and its clones are dropped.
+    pub fn new(world: World<T>, threads: usize) -> AsyncWorld<T> {
+        AsyncWorld::with_pool(world, GenPool::new().threads(threads))
+    }
+
+    /// Construct a generator running jobs on the given pool, which may be
+    /// shared with other generators.
+    ///
+    /// Starting a chunk blocks while the queue of the pool is full.
+    pub fn with_pool(world: World<T>, pool: GenPool) -> AsyncWorld<T> {
+        AsyncWorld::with_spawner(world, move |job| pool.execute(job))
+    }
+
+    /// Construct a generator handing each job to the given function to run,
+    /// such as the `spawn_blocking` of an async runtime.
+    pub fn with_spawner<F>(world: World<T>, spawn: F) -> AsyncWorld<T>
+    where
+        F: Fn(Job) + Send + Sync + 'static,
+    {
+        AsyncWorld {
+            world: Arc::new(world),
+            spawn: Arc::new(spawn),
+        }
+    }
+
+    /// Returns the world the generator generates.
+    pub fn world(&self) -> &World<T> {
+        &self.world
+    }
+
+    /// Starts generating a chunk, returning a future that resolves to the
+    /// chunk, exactly as `World::generate` would return it.
+    ///
+    /// If generating the chunk panics, the panic is resumed where the
+    /// future is polled.
+    pub fn generate_chunk_async(&self, chunk_x: i64, chunk_y: i64) -> ChunkFuture<T> {
+        let slot = Arc::new(Mutex::new(Slot {
+            outcome: None,
+            waker: None,
+        }));
+
+        let (world, finished) = (self.world.clone(), slot.clone());
+
+        (self.spawn)(Box::new(move || {
+            let outcome =
+                panic::catch_unwind(AssertUnwindSafe(|| world.generate(chunk_x, chunk_y)));
+
+            let mut slot = finished.lock().unwrap();
+            slot.outcome = Some(outcome);
+
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        }));
+
+        ChunkFuture { slot }
+    }
+}
+
+impl<T> Clone for AsyncWorld<T> {
+    fn clone(&self) -> AsyncWorld<T> {
+        AsyncWorld {
+            world: self.world.clone(),
+            spawn: self.spawn.clone(),
+        }
+    }
+}
+
+impl<T> Future for ChunkFuture<T> {
+    type Output = Result<Chunk<T>, GenerateError>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap();
+
+        match slot.outcome.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(payload)) => {
+                drop(slot);
+                panic::resume_unwind(payload)
+            }
+            None => {
+                slot.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/guarantee.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::{Chunk, ChunkContext, GenerateError, Size, World};
+use crate::noisemap::Seed;
+use crate::rng::CellRng;
+
+/// A predicate on a tile.
+type Predicate<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+enum Rule<T> {
+    Only {
+        radius: i64,
+        predicate: Predicate<T>,
+        tile: T,
+    },
+
+    Nearby {
+        radius: i64,
+        predicate: Predicate<T>,
+        tile: T,
+    },
+}
+
+impl<T> Rule<T> {
+    fn radius(&self) -> i64 {
+        match *self {
+            Rule::Only { radius, .. } | Rule::Nearby { radius, .. } => radius,
+        }
+    }
+}
+
+/// Tiles forced around a position, such as the point where players start,
+/// so that the area around it is always playable. See `World::guarantee`.
+///
+/// Distances are measured in a straight line, so each rule covers a disc
+/// of cells. Rules are applied in the order they were added.
+pub struct Guarantee<T> {
+    x: i64,
+    y: i64,
+    rules: Vec<Rule<T>>,
+}
+
+impl<T: Clone> Guarantee<T> {
+    /// Construct a guarantee around the given world position, without any
+    /// rules.
+    pub fn at(x: i64, y: i64) -> Guarantee<T> {
+        Guarantee {
+            x,
+            y,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Fills every cell within the radius with the given tile.
+    pub fn fill(self, radius: i64, tile: T) -> Guarantee<T> {
+        self.only(radius, |_| false, tile)
+    }
+
+    /// Replaces every cell within the radius whose tile doesn't satisfy the
+    /// predicate with the given tile.
+    pub fn only<F>(self, radius: i64, predicate: F, tile: T) -> Guarantee<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.push(Rule::Only {
+            radius: radius.max(0),
+            predicate: Box::new(predicate),
+            tile,
+        })
+    }
+
+    /// Makes sure some cell within the radius has a tile satisfying the
+    /// predicate. If none does, the given tile is placed in a cell chosen
+    /// at random, using the world's seed.
+    pub fn nearby<F>(self, radius: i64, predicate: F, tile: T) -> Guarantee<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.push(Rule::Nearby {
+            radius: radius.max(0),
+            predicate: Box::new(predicate),
+            tile,
+        })
+    }
+
+    fn push(self, rule: Rule<T>) -> Guarantee<T> {
+        let mut new = self;
+        new.rules.push(rule);
+        new
+    }
+
+    /// Returns the distance from the position to the furthest cell any rule
+    /// covers.
+    fn radius(&self) -> i64 {
+        self.rules.iter().map(Rule::radius).max().unwrap_or(0)
+    }
+
+    /// Applies the rules to the tiles of the square area around the
+    /// position, `radius` cells from it on each side.
+    fn apply(&self, area: &mut Chunk<T>, seed: Seed) {
+        let centre = self.radius();
+        let mut rng = CellRng::new(seed, self.x, self.y, "guarantee");
+
+        for rule in self.rules.iter() {
+            let radius = rule.radius();
+            let cells: Vec<(usize, usize)> = (-radius..=radius)
+                .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+                .filter(|&(dx, dy)| dx * dx + dy * dy <= radius * radius)
+                .map(|(dx, dy)| ((centre + dx) as usize, (centre + dy) as usize))
+                .collect();
+
+            match *rule {
+                Rule::Only {
+                    ref predicate,
+                    ref tile,
+                    ..
+                } => {
+                    for &(i, j) in cells.iter() {
+                        if !predicate(&area[j][i]) {
+                            area[j][i] = tile.clone();
+                        }
+                    }
+                }
+
+                Rule::Nearby {
+                    ref predicate,
+                    ref tile,
+                    ..
+                } => {
+                    if !cells.iter().any(|&(i, j)| predicate(&area[j][i])) {
+                        let &(i, j) = rng.choose(&cells).unwrap();
+                        area[j][i] = tile.clone();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync> World<T> {
+    /// Add a guarantee, forcing tiles around a position after the tiles
+    /// have been chosen, and before any post-processing passes run.
+    ///
+    /// Each chunk the guarantee reaches works out the area around the
+    /// position in full, so the rules give the same result in every chunk.
+    /// As with post-processing passes, guarantees are left out where only
+    /// the chosen tiles are generated, such as by `rows`, `tile_at` and
+    /// `find`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Guarantee, Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm.clone(), < -0.2)))
+    ///     .add(Tile::new('^').when(constraint!(nm, > 0.4)))
+    ///     .add(Tile::new(','))
+    ///     // Start on dry, open ground, with water in reach
+    ///     .guarantee(
+    ///         Guarantee::at(0, 0)
+    ///             .only(3, |&tile| tile == ',', ',')
+    ///             .nearby(8, |&tile| tile == '~', '~'),
+    ///     );
+    ///
+    /// let area = world.generate_area((-1, -1)..=(0, 0)).unwrap();
+    /// assert_eq!(area[16][16], ',');
+    /// assert!(area[8..25].iter().any(|row| row[8..25].contains(&'~')));
+    /// # }
+    /// ```
+    pub fn guarantee(self, guarantee: Guarantee<T>) -> World<T> {
+        let mut new = self;
+        new.guarantees.push(guarantee);
+        new
+    }
+
+    /// Returns the tiles the guarantees force within the context's chunk,
+    /// as positions relative to the chunk and their tiles.
+    pub(super) fn guaranteed(
+        &self,
+        context: &ChunkContext,
+    ) -> Result<Vec<(usize, usize, T)>, GenerateError> {
+        let size = context.size;
+        let (x0, y0) = (context.chunk_x * size.w, context.chunk_y * size.h);
+        let mut forced = Vec::new();
+
+        if size.w <= 0 || size.h <= 0 {
+            return Ok(forced);
+        }
+
+        for guarantee in self.guarantees.iter() {
+            let radius = guarantee.radius();
+            let (ax, ay) = (guarantee.x - radius, guarantee.y - radius);
+            let width = 2 * radius + 1;
+
+            if ax >= x0 + size.w || ay >= y0 + size.h || ax + width <= x0 || ay + width <= y0 {
+                continue;
+            }
+
+            let mut area = self.resolve_area(ax, ay, width, width)?;
+            guarantee.apply(&mut area, self.seed);
+
+            for (row, y) in area.into_iter().zip(ay..) {
+                for (tile, x) in row.into_iter().zip(ax..) {
+                    if (x0..x0 + size.w).contains(&x) && (y0..y0 + size.h).contains(&y) {
+                        forced.push(((x - x0) as usize, (y - y0) as usize, tile));
+                    }
+                }
+            }
+        }
+
+        Ok(forced)
+    }
+
+    /// Chooses the tiles of an area, given its top left position and its
+    /// size, without running post-processing passes.
+    fn resolve_area(&self, x0: i64, y0: i64, w: i64, h: i64) -> Result<Chunk<T>, GenerateError> {
+        let mut area = vec![Vec::with_capacity(w as usize); h as usize];
+
+        // The area is resolved a row at a time, from chunks of its own size
+        // with the noise maps sampled at single positions, as `tile_at`
+        // samples them. A world sampling whole chunks resolves it from its
+        // own chunks instead, as `generate` samples them.
+        let size = if self.whole_chunks {
+            self.size
+        } else {
+            Size::of(w, h)
+        };
+
+        for chunk_y in y0.div_euclid(size.h)..=(y0 + h - 1).div_euclid(size.h) {
+            for chunk_x in x0.div_euclid(size.w)..=(x0 + w - 1).div_euclid(size.w) {
+                let context = if self.whole_chunks {
+                    ChunkContext::new(size, self.seed, chunk_x, chunk_y)
+                } else {
+                    ChunkContext::sampled_at_points(size, self.seed, chunk_x, chunk_y)
+                };
+
+                for y in (chunk_y * size.h).max(y0)..((chunk_y + 1) * size.h).min(y0 + h) {
+                    let tiles = self.resolve_row(&context, y)?;
+
+                    area[(y - y0) as usize].extend(
+                        tiles
+                            .into_iter()
+                            .zip(chunk_x * size.w..)
+                            .filter(|&(_, x)| (x0..x0 + w).contains(&x))
+                            .map(|(tile, _)| tile.value()),
+                    );
+                }
+            }
+        }
+
+        Ok(area)
+    }
+}
@@ -0,0 +1,57 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/layered.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::Chunk;
+
+/// A generated chunk of a world with layers, holding a chunk of tiles for
+/// the world itself and for each of its layers. See `World::layer`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayeredChunk<T> {
+    base: Chunk<T>,
+    layers: Vec<(String, Chunk<T>)>,
+}
+
+impl<T> LayeredChunk<T> {
+    pub(crate) fn new(base: Chunk<T>, layers: Vec<(String, Chunk<T>)>) -> LayeredChunk<T> {
+        LayeredChunk { base, layers }
+    }
+
+    /// Returns the tiles of the world itself.
+    pub fn base(&self) -> &Chunk<T> {
+        &self.base
+    }
+
+    /// Returns the tiles of the layer with the given name.
+    pub fn layer(&self, name: &str) -> Option<&Chunk<T>> {
+        self.layers
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, chunk)| chunk)
+    }
+
+    /// Returns the names of the layers, in the order they were added.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the tiles of the world itself, and the named tiles of each
+    /// layer.
+    pub fn into_parts(self) -> (Chunk<T>, Vec<(String, Chunk<T>)>) {
+        (self.base, self.layers)
+    }
+}
@@ -20,17 +20,136 @@
 //!
 //! Once we have a `NoiseMap` we can use a `World` and generate a map
 //! of specific tiles using threshold constraints.
+//!
+//! Each position takes the first tile whose constraints it satisfies.
+//! Tiles can also be given weights, in which case a run of consecutive
+//! weighted tiles acts as a group: when the first of them matches, one of
+//! the matching tiles in the group is picked at random, in proportion to
+//! their weights. The random choice depends only on the world's seed and
+//! the position, so the same world is always generated.
+//!
+//! ```
+//! # use worldgen::world::{Seed, Size, Tile, World};
+//! let world = World::new()
+//!     .set(Seed::of("meadow"))
+//!     .set(Size::of(20, 10))
+//!     .add(Tile::new(',').weight(9.0))
+//!     .add(Tile::new('"').weight(1.0));
+//!
+//! let tiles = world.generate(0, 0).unwrap();
+//! assert_eq!(tiles, world.generate(0, 0).unwrap());
+//! assert!(tiles.iter().flatten().any(|&tile| tile == '"'));
+//! ```
+//!
+//! With the `parallel` feature enabled, the rows of each chunk are
+//! resolved in parallel, and `par_generate_chunks` generates many chunks
+//! at once. Either way the same tiles are generated. An `AsyncWorld`
+//! generates chunks in the background for async code, returning futures.
+//!
+//! # Consistency between chunks
+//!
+//! Every tile depends only on the world and its position, never on which
+//! chunks were generated before it, so chunks always fit together without
+//! seams:
+//!
+//! * Chance constraints, blended constraints and weighted tiles only use
+//!   the seed and the position (see `rng::CellRng`).
+//! * Noise maps only use the seed and the position too, as long as they
+//!   give the same values whatever the size of the chunks they are
+//!   generated in. Every noise map in this crate does, but a noise map of
+//!   your own has to keep to it as well (see `NoiseMapGeneratorBase`).
+//! * Smoothing and neighbour constraints resolve a margin of positions
+//!   around each chunk, so cells at the border see the same neighbours as
+//!   the chunk next door sees.
+//! * Rivers are traced from their springs, and structures are placed from
+//!   anchors found the same way by every chunk they cross.
+//!
+//! `rows` and `find` resolve only part of a chunk, but sample the noise
+//! maps as whole chunks of the world's size, exactly as `generate` does,
+//! so they choose the same tiles as it even for a noise map that doesn't
+//! keep to the rule. `tile_at`, `trace` and guarantees only sample the
+//! noise maps at the positions they need, unless the world is set to
+//! `sample_whole_chunks`.
+//!
+//! Post-processing passes should keep to the same rule, taking random
+//! values from `ChunkContext::rng` and noise values from the context.
+//! `generate_with_apron` generates a chunk along with the cells around it,
+//! exactly as the neighbouring chunks generate them.
+
+use std::ops::{Range, RangeInclusive};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
-use std::collections::HashMap;
+use self::smooth::smooth_step;
+use crate::biome::BiomeTable;
+use crate::cancel::CancelToken;
+use crate::noisemap::NoiseMapGeneratorBase;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::pool::GenPool;
+use crate::progress::Progress;
 
+pub use self::autotile::Bitmask;
+pub use self::context::ChunkContext;
+pub use self::error::{AreaError, GenerateError, ValidationError};
+#[cfg(not(target_arch = "wasm32"))]
+pub use self::future::{AsyncWorld, ChunkFuture, Job};
+pub use self::guarantee::Guarantee;
+pub use self::layered::LayeredChunk;
+pub use self::overrides::Overrides;
 use self::property::Property;
-pub use self::property::Size;
+pub use self::property::{Seed, Size};
+pub use self::rows::Rows;
+pub use self::smooth::SmoothingRule;
+pub use self::start::StartCriteria;
+pub use self::stats::{MapStats, Stats};
+pub use self::streamer::WorldStreamer;
+pub use self::summary::Histogram;
+pub use self::tagged::TaggedChunk;
 pub use self::tile::Tile;
+pub use self::trace::{ConstraintTrace, TileTrace, Trace};
 
 #[macro_use]
 pub mod tile;
 
+mod autotile;
+mod context;
+mod error;
+#[cfg(not(target_arch = "wasm32"))]
+mod future;
+mod guarantee;
+mod layered;
+mod overrides;
 mod property;
+mod rows;
+mod smooth;
+mod start;
+mod stats;
+mod streamer;
+mod summary;
+mod tagged;
+mod trace;
+
+/// A generated chunk of tiles, as a vector of rows.
+pub type Chunk<T> = Vec<Vec<T>>;
+
+/// A generated tile, along with the noise values that were used to choose
+/// it. See `World::generate_with_values`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SampledTile<T> {
+    /// The value of the tile.
+    pub value: T,
+
+    /// Pairs of noise map id and the noise map's value at the tile's
+    /// position, ordered by id.
+    pub values: Vec<(u64, f64)>,
+}
+
+/// A noise map registered by name. See `World::with_map`.
+type NamedMap = (String, Arc<dyn NoiseMapGeneratorBase>);
+
+/// A post-processing pass over a generated chunk. See `World::post`.
+type PostPass<T> = Box<dyn Fn(&mut Chunk<T>, &mut ChunkContext) + Send + Sync>;
 
 /// The World class.
 ///
@@ -38,16 +157,41 @@ mod property;
 /// module documentation for for information.
 pub struct World<T> {
     tiles: Vec<Tile<T>>,
+    smoothing: Option<(usize, SmoothingRule)>,
+    posts: Vec<PostPass<T>>,
+    guarantees: Vec<Guarantee<T>>,
+    overrides: Overrides<T>,
+    layers: Vec<(String, World<T>)>,
+    maps: Vec<NamedMap>,
+    progress: Progress,
+    cancel: CancelToken,
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    pool: Option<GenPool>,
+    whole_chunks: bool,
 
     size: Size,
+    seed: Seed,
 }
 
 impl<T> Default for World<T> {
     fn default() -> World<T> {
         World {
             tiles: Vec::new(),
+            smoothing: None,
+            posts: Vec::new(),
+            guarantees: Vec::new(),
+            overrides: Default::default(),
+            layers: Vec::new(),
+            maps: Vec::new(),
+            progress: Default::default(),
+            cancel: Default::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            pool: None,
+            whole_chunks: false,
 
             size: Default::default(),
+            seed: Default::default(),
         }
     }
 }
@@ -63,12 +207,358 @@ impl<T: Clone> World<T> {
     }
 
     /// Add a tile definition to the world
+    #[allow(clippy::should_implement_trait)]
     pub fn add(self, tile: Tile<T>) -> World<T> {
         let mut new = self;
+        let mut tile = tile;
+
+        for (name, nm) in new.maps.iter() {
+            tile.bind_map(name, nm);
+        }
+
         new.tiles.push(tile);
         new
     }
 
+    /// Registers a noise map by name, so constraints can refer to it by
+    /// the name instead of holding their own boxed copy.
+    ///
+    /// Every constraint using the name shares the one noise map, so it is
+    /// only generated once per chunk, however many tiles use it. Tiles can
+    /// be added before or after the map is registered, and the map is
+    /// shared with the world's layers too. Names that are never registered
+    /// are reported by `validate`, and satisfy no constraint.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = NoiseMap::new(PerlinNoise::new()).set(Step::of(0.1, 0.1));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .with_map("elevation", Box::new(nm))
+    ///     .add(Tile::new('~').when(constraint!("elevation", < 0.0)))
+    ///     .add(Tile::new('.').when(constraint!("elevation", < 0.1)))
+    ///     .add(Tile::new(','));
+    ///
+    /// assert_eq!(world.validate(), Ok(()));
+    /// let tiles = world.generate(0, 0).unwrap();
+    /// # }
+    /// ```
+    pub fn with_map(self, name: &str, nm: Box<dyn NoiseMapGeneratorBase>) -> World<T> {
+        let mut new = self;
+        let nm: Arc<dyn NoiseMapGeneratorBase> = Arc::from(nm);
+
+        new.bind_map(name, &nm);
+        new.maps.push((name.to_string(), nm));
+        new
+    }
+
+    /// Binds a named noise map in the constraints of every tile, and of
+    /// every layer.
+    fn bind_map(&mut self, name: &str, nm: &Arc<dyn NoiseMapGeneratorBase>) {
+        for tile in self.tiles.iter_mut() {
+            tile.bind_map(name, nm);
+        }
+
+        for (_, layer) in self.layers.iter_mut() {
+            layer.bind_map(name, nm);
+        }
+    }
+
+    /// Add a tile definition for each biome in a biome table
+    pub fn add_biomes(self, biomes: &BiomeTable<T>) -> World<T> {
+        let mut new = self;
+        new.tiles.extend(biomes.tiles());
+        new
+    }
+
+    /// Smooth the generated tiles with a cellular automaton, replacing
+    /// positions with the tile most common among their neighbours, to
+    /// clean up the specks of single tiles left by thresholds.
+    ///
+    /// Each iteration looks one position further out, so the tiles around
+    /// each chunk are generated as well, and smoothing is seamless across
+    /// chunk borders. Tiles chosen by `tile_in` are not smoothed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, SmoothingRule, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.2, 0.2)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new(','))
+    ///     .smooth(2, SmoothingRule::Majority);
+    ///
+    /// let tiles = world.generate(0, 0).unwrap();
+    /// assert_eq!(tiles.len(), 16);
+    /// # }
+    /// ```
+    pub fn smooth(self, iterations: usize, rule: SmoothingRule) -> World<T> {
+        World {
+            smoothing: Some((iterations, rule)),
+            ..self
+        }
+    }
+
+    /// Add a post-processing pass, which is run over each chunk made by
+    /// `generate` after its tiles have been chosen.
+    ///
+    /// Passes run in the order they were added. Each is given the chunk's
+    /// context, holding the chunk's position and the noise generated for
+    /// it, so passes can sample the same noise maps the tiles used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.1, 0.1)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new(','))
+    ///     .post(|chunk, context| {
+    ///         // Mark the corner of every chunk
+    ///         if (context.chunk_x, context.chunk_y) != (0, 0) {
+    ///             chunk[0][0] = '+';
+    ///         }
+    ///     });
+    ///
+    /// assert_eq!(world.generate(1, 0).unwrap()[0][0], '+');
+    /// # }
+    /// ```
+    pub fn post<F>(self, pass: F) -> World<T>
+    where
+        F: Fn(&mut Chunk<T>, &mut ChunkContext) + Send + Sync + 'static,
+    {
+        let mut new = self;
+        new.posts.push(Box::new(pass));
+        new
+    }
+
+    /// Set the tiles placed by hand, which are merged over the generated
+    /// tiles after the post-processing passes, every time a chunk is
+    /// generated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Overrides, Size, Tile, World};
+    /// let mut overrides = Overrides::new();
+    /// overrides.rect(4, 4, 3, 3, '#');
+    ///
+    /// let mut world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new(','))
+    ///     .overrides(overrides);
+    ///
+    /// assert_eq!(world.generate(0, 0).unwrap()[5][5], '#');
+    ///
+    /// // Edits show in every chunk generated after them
+    /// world.overrides_mut().erase(5, 5);
+    /// assert_eq!(world.generate(0, 0).unwrap()[5][5], ',');
+    /// ```
+    pub fn overrides(self, overrides: Overrides<T>) -> World<T> {
+        World { overrides, ..self }
+    }
+
+    /// Returns the tiles placed by hand.
+    pub fn get_overrides(&self) -> &Overrides<T> {
+        &self.overrides
+    }
+
+    /// Returns the tiles placed by hand, for editing.
+    pub fn overrides_mut(&mut self) -> &mut Overrides<T> {
+        &mut self.overrides
+    }
+
+    /// Set a callback to be told how far generation has got, for example
+    /// to drive a progress bar.
+    ///
+    /// The callback is given the name of a stage and the fraction of it
+    /// that is done. Generating a chunk reports the `"tiles"` stage as rows
+    /// of tiles are chosen, then `"smoothing"` and `"post"` as each
+    /// smoothing iteration and post-processing pass finishes.
+    /// `par_generate_chunks` also reports `"chunks"` as each chunk is done.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::{Arc, Mutex};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// let reports = Arc::new(Mutex::new(Vec::new()));
+    /// let log = reports.clone();
+    ///
+    /// let world = World::new()
+    ///     .set(Size::of(8, 8))
+    ///     .add(Tile::new(','))
+    ///     .on_progress(move |stage, fraction| {
+    ///         log.lock().unwrap().push((stage.to_string(), fraction));
+    ///     });
+    ///
+    /// world.generate(0, 0).unwrap();
+    /// assert!(reports.lock().unwrap().contains(&("tiles".to_string(), 1.0)));
+    /// ```
+    pub fn on_progress<F>(self, callback: F) -> World<T>
+    where
+        F: Fn(&str, f64) + Send + Sync + 'static,
+    {
+        World {
+            progress: Progress::new(callback),
+            ..self
+        }
+    }
+
+    /// Set a token that stops `generate_area` when it is cancelled. See
+    /// the `cancel` module.
+    ///
+    /// The token is checked before each row of chunks, so a chunk that has
+    /// been started is always finished.
+    pub fn cancel_on(self, token: CancelToken) -> World<T> {
+        World {
+            cancel: token,
+            ..self
+        }
+    }
+
+    /// Set a pool for the parallel work of the world, with the `parallel`
+    /// feature enabled, instead of rayon's global pool. See the `pool`
+    /// module.
+    ///
+    /// Without the `parallel` feature, the world is generated on the
+    /// calling thread either way.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pool(self, pool: GenPool) -> World<T> {
+        World {
+            pool: Some(pool),
+            ..self
+        }
+    }
+
+    /// Set whether the tiles of single positions, as found by `tile_at` and
+    /// `trace`, and the areas of guarantees are chosen from noise sampled
+    /// as the whole chunks holding them, exactly as `generate` samples it.
+    /// By default each noise map is only sampled at the positions needed.
+    ///
+    /// Only a noise map whose values depend on the size of the chunks it is
+    /// generated in needs this, to be sure of the same tiles as `generate`,
+    /// at the cost of generating a whole chunk of every noise map for each
+    /// position.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noisemap::{NoiseMapGeneratorBase, Size};
+    /// # use worldgen::world::{Size as WorldSize, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// // The distance from the left of the chunk, which breaks the rule
+    /// // that values depend only on their position
+    /// struct Column;
+    ///
+    /// impl NoiseMapGeneratorBase for Column {
+    ///     fn generate_chunk(&self, x: i64, y: i64) -> Vec<Vec<f64>> {
+    ///         self.generate_sized_chunk(Size::of(16, 16), x, y)
+    ///     }
+    ///
+    ///     fn generate_sized_chunk(&self, size: Size, _: i64, _: i64) -> Vec<Vec<f64>> {
+    ///         vec![(0..size.w).map(|x| x as f64).collect(); size.h as usize]
+    ///     }
+    ///
+    ///     fn id(&self) -> u64 {
+    ///         0
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let nm = Box::new(Column);
+    /// let world = World::new()
+    ///     .set(WorldSize::of(16, 16))
+    ///     .add(Tile::new('#').when(constraint!(nm, > 7.5)))
+    ///     .add(Tile::new('.'))
+    ///     .sample_whole_chunks(true);
+    ///
+    /// let chunk = world.generate(0, 0).unwrap();
+    /// assert_eq!(world.tile_at(12, 4).unwrap(), chunk[4][12]);
+    /// assert_eq!(world.tile_at(12, 4).unwrap(), '#');
+    /// # }
+    /// ```
+    pub fn sample_whole_chunks(self, whole_chunks: bool) -> World<T> {
+        World {
+            whole_chunks,
+            ..self
+        }
+    }
+
+    /// Add a named layer of tiles, generated along with the world's own
+    /// tiles by `generate_layers`.
+    ///
+    /// The layer is itself a world, with its own tiles, smoothing and
+    /// post-processing passes, but it takes its size and seed from this
+    /// world, and shares the noise generated for each chunk, so noise maps
+    /// used by several layers are only generated once. Layers of the layer
+    /// are not generated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use std::sync::Arc;
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Arc::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.1, 0.1)));
+    /// let features = World::new()
+    ///     .add(Tile::new('T').when(constraint!(Box::new(nm.clone()), > 0.3)))
+    ///     .add(Tile::new(' '));
+    ///
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(Box::new(nm), < 0.0)))
+    ///     .add(Tile::new(','))
+    ///     .layer("features", features);
+    ///
+    /// let chunk = world.generate_layers(0, 0).unwrap();
+    /// let trees = chunk.layer("features").unwrap();
+    ///
+    /// for (ground, features) in chunk.base().iter().zip(trees.iter()) {
+    ///     for (&ground, &feature) in ground.iter().zip(features.iter()) {
+    ///         assert!(!(ground == '~' && feature == 'T'));
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn layer(self, name: &str, layer: World<T>) -> World<T> {
+        let mut new = self;
+        let mut layer = layer;
+
+        for (name, nm) in new.maps.iter() {
+            layer.bind_map(name, nm);
+        }
+
+        new.layers.push((name.to_string(), layer));
+        new
+    }
+
     /// Set a property on the world
     pub fn set<P: Property>(self, property: P) -> World<T> {
         property.set_to(self)
@@ -80,22 +570,1061 @@ impl<T: Clone> World<T> {
         new
     }
 
-    pub fn generate(&self, chunk_x: i64, chunk_y: i64) -> Option<Vec<Vec<T>>> {
-        let mut nms = HashMap::new();
+    pub fn set_seed(self, seed: Seed) -> World<T> {
+        let mut new = self;
+        new.seed = seed;
+        new
+    }
+
+    /// Returns the size of the world's chunks.
+    pub fn get_size(&self) -> Size {
+        self.size
+    }
+
+    /// Returns the world's seed.
+    pub fn get_seed(&self) -> Seed {
+        self.seed
+    }
+
+    /// Checks the definition of the world for problems that would stop
+    /// tiles from being generated as expected: a missing fallback tile
+    /// (without which `generate` may return an error), tiles whose
+    /// constraints can never be satisfied, and tiles that come after the
+    /// fallback tile.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::NoiseMap;
+    /// # use worldgen::world::{Tile, ValidationError, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()));
+    /// let world = World::new()
+    ///     .add(Tile::new('~').when(constraint!(nm.clone(), < -2.0)))
+    ///     .add(Tile::new(',').when(constraint!(nm, < 0.5)));
+    ///
+    /// assert_eq!(
+    ///     world.validate(),
+    ///     Err(vec![ValidationError::Unsatisfiable(0), ValidationError::NoFallback])
+    /// );
+    /// # }
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors: Vec<ValidationError> = self
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| !tile.satisfiable())
+            .map(|(index, _)| ValidationError::Unsatisfiable(index))
+            .collect();
+
+        errors.extend(self.tiles.iter().enumerate().flat_map(|(index, tile)| {
+            tile.unknown_maps()
+                .into_iter()
+                .map(move |name| ValidationError::UnknownMap(index, name))
+        }));
+
+        match self.tiles.iter().position(Tile::is_fallback) {
+            Some(fallback) => {
+                // A weighted fallback still lets the rest of its group through.
+                let reachable = if self.tiles[fallback].get_weight().is_some() {
+                    fallback
+                        + self.tiles[fallback..]
+                            .iter()
+                            .take_while(|tile| tile.get_weight().is_some())
+                            .count()
+                } else {
+                    fallback + 1
+                };
+
+                errors.extend((reachable..self.tiles.len()).map(ValidationError::Unreachable));
+            }
+
+            None => errors.push(ValidationError::NoFallback),
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync> World<T> {
+    /// Generates a chunk of the world.
+    ///
+    /// If any position in the chunk matches none of the tiles, an error is
+    /// returned describing the first such position. Worlds with a fallback
+    /// tile (see `validate`) never fail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::NoiseMap;
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()));
+    /// let world = World::new()
+    ///     .set(Size::of(10, 10))
+    ///     .add(Tile::new('~').when(constraint!(nm, > 100.0)));
+    ///
+    /// let error = world.generate(0, 0).unwrap_err();
+    /// assert_eq!((error.x, error.y), (0, 0));
+    /// assert_eq!(error.values.len(), 1);
+    /// # }
+    /// ```
+    pub fn generate(&self, chunk_x: i64, chunk_y: i64) -> Result<Chunk<T>, GenerateError> {
+        let mut context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+        self.finish_chunk(&mut context)
+    }
+
+    /// Generates a chunk of the world into an existing chunk, reusing its
+    /// rows rather than allocating new ones, for example when chunks are
+    /// regenerated every frame.
+    ///
+    /// The chunk is resized to fit. If generation fails, it is left
+    /// unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Size, Tile, World};
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new(','));
+    ///
+    /// let mut chunk = Vec::new();
+    ///
+    /// for x in 0..4 {
+    ///     world.generate_into(x, 0, &mut chunk).unwrap();
+    ///     assert_eq!(chunk, world.generate(x, 0).unwrap());
+    /// }
+    /// ```
+    pub fn generate_into(
+        &self,
+        chunk_x: i64,
+        chunk_y: i64,
+        chunk: &mut Chunk<T>,
+    ) -> Result<(), GenerateError> {
+        let mut context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+        self.finish_chunk_into(&mut context, chunk)
+    }
+
+    /// Generates a chunk of the world into a flat buffer, one row after
+    /// another, reusing the buffer's allocation.
+    ///
+    /// The tile at `(x, y)` in the chunk is at `y * width + x` in the
+    /// buffer. If generation fails, the buffer is left unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Size, Tile, World};
+    /// let world = World::new()
+    ///     .set(Size::of(16, 8))
+    ///     .add(Tile::new(','));
+    ///
+    /// let mut buffer = Vec::with_capacity(16 * 8);
+    /// world.generate_into_flat(0, 0, &mut buffer).unwrap();
+    ///
+    /// assert_eq!(buffer.len(), 128);
+    /// assert_eq!(buffer[3 * 16 + 5], world.generate(0, 0).unwrap()[3][5]);
+    /// ```
+    pub fn generate_into_flat(
+        &self,
+        chunk_x: i64,
+        chunk_y: i64,
+        buffer: &mut Vec<T>,
+    ) -> Result<(), GenerateError> {
+        // Post-processing passes work on rows, so need a chunk of their own.
+        if self.has_passes() {
+            let chunk = self.generate(chunk_x, chunk_y)?;
+
+            buffer.clear();
+            buffer.extend(chunk.into_iter().flatten());
+            return Ok(());
+        }
+
+        let context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+        let tiles = self.resolve_chunk(&context)?;
+
+        buffer.clear();
+        buffer.extend(tiles.into_iter().flatten().map(Tile::value));
+        Ok(())
+    }
+
+    /// Generates a chunk of the world, keeping track of the tile chosen at
+    /// each position so that their tags can be queried.
+    ///
+    /// The tags are those of the tiles that were chosen, so they are not
+    /// changed by post-processing passes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.1, 0.1)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)).tag("water"))
+    ///     .add(Tile::new(',').tag("walkable"));
+    ///
+    /// let chunk = world.generate_tagged(0, 0).unwrap();
+    ///
+    /// for (x, y) in chunk.positions_with("walkable") {
+    ///     assert_eq!(chunk.values()[y][x], ',');
+    ///     assert!(!chunk.has_tag(x, y, "water"));
+    /// }
+    /// # }
+    /// ```
+    pub fn generate_tagged(
+        &self,
+        chunk_x: i64,
+        chunk_y: i64,
+    ) -> Result<TaggedChunk<'_, T>, GenerateError> {
+        let mut context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+        let tiles = self.resolve_chunk(&context)?;
+
+        let mut values: Chunk<T> = tiles
+            .iter()
+            .map(|row| row.iter().map(|tile| tile.value()).collect())
+            .collect();
+
+        for (x, y, tile) in self.guaranteed(&context)? {
+            values[y][x] = tile;
+        }
+
+        for pass in self.posts.iter() {
+            pass(&mut values, &mut context);
+        }
+
+        self.overrides.apply(&mut values, &context);
+
+        Ok(TaggedChunk::new(values, tiles))
+    }
+
+    /// Generates a chunk of the world and of each of its layers, sharing
+    /// the noise generated for the chunk between them. See `layer`.
+    pub fn generate_layers(
+        &self,
+        chunk_x: i64,
+        chunk_y: i64,
+    ) -> Result<LayeredChunk<T>, GenerateError> {
+        let mut context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+        let base = self.finish_chunk(&mut context)?;
+
+        let layers = self
+            .layers
+            .iter()
+            .map(|(name, layer)| Ok((name.clone(), layer.finish_chunk(&mut context)?)))
+            .collect::<Result<_, GenerateError>>()?;
+
+        Ok(LayeredChunk::new(base, layers))
+    }
+
+    /// Generates each of the given chunks of the world, in parallel when
+    /// the `parallel` feature is enabled.
+    ///
+    /// The results are in the same order as the chunks were given.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Seed, Size, Tile, World};
+    /// let world = World::new()
+    ///     .set(Seed::of("meadow"))
+    ///     .set(Size::of(8, 8))
+    ///     .add(Tile::new(',').weight(9.0))
+    ///     .add(Tile::new('"').weight(1.0));
+    ///
+    /// let chunks = world.par_generate_chunks(&[(0, 0), (1, 0), (0, 1)]);
+    /// assert_eq!(chunks[1], world.generate(1, 0));
+    /// ```
+    pub fn par_generate_chunks(
+        &self,
+        chunks: &[(i64, i64)],
+    ) -> Vec<Result<Chunk<T>, GenerateError>> {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+
+            let done = AtomicUsize::new(0);
+
+            self.install(|| {
+                chunks
+                    .par_iter()
+                    .map(|&(chunk_x, chunk_y)| {
+                        let chunk = self.generate(chunk_x, chunk_y);
+
+                        let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                        self.progress.report("chunks", done, chunks.len());
+                        chunk
+                    })
+                    .collect()
+            })
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            chunks
+                .iter()
+                .enumerate()
+                .map(|(done, &(chunk_x, chunk_y))| {
+                    let chunk = self.generate(chunk_x, chunk_y);
+                    self.progress.report("chunks", done + 1, chunks.len());
+                    chunk
+                })
+                .collect()
+        }
+    }
+
+    /// Generates the chunks between two chunk coordinates (inclusive), as a
+    /// single grid of tiles.
+    ///
+    /// Each row of chunks is generated with `par_generate_chunks`, and
+    /// generation stops between rows if the world's `CancelToken` is
+    /// cancelled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Size, Tile, World};
+    /// let world = World::new()
+    ///     .set(Size::of(16, 8))
+    ///     .add(Tile::new(','));
+    ///
+    /// let area = world.generate_area((-1, -1)..=(1, 0)).unwrap();
+    /// assert_eq!((area[0].len(), area.len()), (48, 16));
+    /// ```
+    pub fn generate_area(&self, region: RangeInclusive<(i64, i64)>) -> Result<Chunk<T>, AreaError> {
+        let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+        let mut area = Vec::new();
+
+        for y in y0..=y1 {
+            self.cancel.check()?;
+
+            let positions: Vec<_> = (x0..=x1).map(|x| (x, y)).collect();
+            let chunks = self
+                .par_generate_chunks(&positions)
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()?;
+
+            area.extend((0..self.size.h as usize).map(|row| {
+                chunks
+                    .iter()
+                    .flat_map(|chunk| chunk[row].iter().cloned())
+                    .collect()
+            }));
+        }
+
+        Ok(area)
+    }
+
+    /// Returns an iterator over the rows of a chunk of the world, which
+    /// resolves each row only when it is reached.
+    ///
+    /// Only one row of tiles is held at a time, so very large chunks can be
+    /// written out as they are generated. The noise maps are sampled as
+    /// the whole chunk, exactly as `generate` samples them, and kept until
+    /// the iterator is dropped. The rows hold the same tiles as `generate`,
+    /// except that post-processing passes are not run, as they work on
+    /// whole chunks. Smoothing and neighbour constraints still apply, but
+    /// the rows around each row have to be resolved again for them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Seed, Size, Tile, World};
+    /// let world = World::new()
+    ///     .set(Seed::of("meadow"))
+    ///     .set(Size::of(16, 8))
+    ///     .add(Tile::new(',').weight(9.0))
+    ///     .add(Tile::new('"').weight(1.0));
+    ///
+    /// let chunk = world.generate(2, 1).unwrap();
+    ///
+    /// for (row, expected) in world.rows(2, 1).zip(chunk.iter()) {
+    ///     assert_eq!(&row.unwrap(), expected);
+    /// }
+    /// ```
+    ///
+    /// Rows of maps grown from the cells around them, such as caves, are
+    /// the same too:
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noisemap::{CaveMap, NoiseMapGenerator, Seed};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// let caves = Box::new(CaveMap::new().set(Seed::of("caves")));
+    /// let world = World::new()
+    ///     .set(Size::of(32, 32))
+    ///     .add(Tile::new('.').when(constraint!(caves, > 0.5)))
+    ///     .add(Tile::new('#'));
+    ///
+    /// let rows: Vec<_> = world.rows(0, 0).map(Result::unwrap).collect();
+    /// assert_eq!(rows, world.generate(0, 0).unwrap());
+    /// # }
+    /// ```
+    pub fn rows(&self, chunk_x: i64, chunk_y: i64) -> Rows<'_, T> {
+        Rows::new(self, chunk_x, chunk_y)
+    }
+
+    /// Returns the tile at a single world position, for example the one
+    /// under the cursor, without generating the rest of its chunk.
+    ///
+    /// Only the position (and, when the world is smoothed or has neighbour
+    /// constraints, the positions around it) is resolved, and each noise
+    /// map is only sampled at those positions. The tile is the one
+    /// `generate` chooses as long as the noise maps keep to the rule in the
+    /// module documentation; see `sample_whole_chunks` for those that
+    /// don't. Post-processing passes are not run, as they work on whole
+    /// chunks, but a tile placed by the world's overrides is returned as
+    /// it is.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, SmoothingRule, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.2, 0.2)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new(','))
+    ///     .smooth(1, SmoothingRule::Majority);
+    ///
+    /// let chunk = world.generate(1, 0).unwrap();
+    /// assert_eq!(world.tile_at(21, 3).unwrap(), chunk[3][5]);
+    /// # }
+    /// ```
+    pub fn tile_at(&self, world_x: i64, world_y: i64) -> Result<T, GenerateError> {
+        if let Some(tile) = self.overrides.get(world_x, world_y) {
+            return Ok(tile.clone());
+        }
+
+        let context = self.point_context(world_x, world_y);
+        Ok(self.resolve_chunk(&context)?[0][0].value())
+    }
+
+    /// Returns the positions between two world positions (inclusive) whose
+    /// tiles match the given predicate, in row order, for example to find
+    /// spawn points or ore.
+    ///
+    /// The predicate is given each tile along with the noise values used
+    /// to choose it, as in `generate_with_values`. The region is resolved a
+    /// row at a time, so whole chunks of tiles are never held in memory,
+    /// although the noise maps are sampled as whole chunks, exactly as
+    /// `generate` samples them, for one row of chunks at a time.
+    /// Post-processing passes are not run, and a world whose chunks are
+    /// empty finds nothing.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.2, 0.2)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new(','));
+    ///
+    /// let shores = world
+    ///     .find((-20, -20)..=(20, 20), |&tile, values| {
+    ///         tile == ',' && values[0].1 < 0.05
+    ///     })
+    ///     .unwrap();
+    ///
+    /// for &(x, y) in shores.iter() {
+    ///     assert_eq!(world.tile_at(x, y).unwrap(), ',');
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// A world whose chunks are empty has nothing to find:
+    ///
+    /// ```
+    /// # use worldgen::world::{Size, Tile, World};
+    /// let world = World::new().set(Size::of(0, 0)).add(Tile::new(','));
+    /// assert!(world.find((0, 0)..=(9, 9), |_, _| true).unwrap().is_empty());
+    /// ```
+    pub fn find<F>(
+        &self,
+        region: RangeInclusive<(i64, i64)>,
+        predicate: F,
+    ) -> Result<Vec<(i64, i64)>, GenerateError>
+    where
+        F: Fn(&T, &[(u64, f64)]) -> bool + Send + Sync,
+    {
+        let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+        let size = self.size;
 
-        (chunk_y * self.size.h..(chunk_y + 1) * self.size.h)
+        if size.w <= 0 || size.h <= 0 {
+            return Ok(Vec::new());
+        }
+
+        let (first, last) = (y0.div_euclid(size.h), y1.div_euclid(size.h));
+
+        let rows = self.map_rows(first..(last + 1).max(first), |chunk_y| {
+            let contexts: Vec<_> = (x0.div_euclid(size.w)..=x1.div_euclid(size.w))
+                .map(|chunk_x| ChunkContext::new(size, self.seed, chunk_x, chunk_y))
+                .collect();
+
+            let mut found = Vec::new();
+
+            for y in (chunk_y * size.h).max(y0)..((chunk_y + 1) * size.h).min(y1 + 1) {
+                for context in contexts.iter() {
+                    let tiles = self.resolve_row(context, y)?;
+
+                    for (tile, x) in tiles.iter().zip(context.chunk_x * size.w..) {
+                        if (x0..=x1).contains(&x)
+                            && predicate(&tile.value(), &context.values_at(x, y))
+                        {
+                            found.push((x, y));
+                        }
+                    }
+                }
+            }
+
+            Ok(found)
+        });
+
+        let mut found = Vec::new();
+        for row in rows {
+            found.extend(row?);
+        }
+
+        Ok(found)
+    }
+
+    /// Generates a chunk of the world, along with the noise values used to
+    /// choose each tile.
+    ///
+    /// Each position holds the values of every noise map that was consulted
+    /// while generating the chunk, as pairs of noise map id and value, so
+    /// they can be kept (for lighting, pathfinding costs, and so on) without
+    /// generating the noise maps again.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGeneratorBase};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()));
+    /// let elevation = nm.id();
+    ///
+    /// let world = World::new()
+    ///     .set(Size::of(10, 10))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new(','));
+    ///
+    /// let chunk = world.generate_with_values(0, 0).unwrap();
+    /// assert_eq!(chunk[0][0].values[0].0, elevation);
+    /// # }
+    /// ```
+    pub fn generate_with_values(
+        &self,
+        chunk_x: i64,
+        chunk_y: i64,
+    ) -> Result<Chunk<SampledTile<T>>, GenerateError> {
+        let context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+        let tiles = self.resolve_chunk(&context)?;
+
+        Ok(tiles
+            .into_iter()
+            .zip(chunk_y * self.size.h..)
+            .map(|(row, y)| {
+                row.into_iter()
+                    .zip(chunk_x * self.size.w..)
+                    .map(|(tile, x)| SampledTile {
+                        value: tile.value(),
+                        values: context.values_at(x, y),
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Generates a chunk of structured cells, built from the tile chosen at
+    /// each position by the given function.
+    ///
+    /// The function is also given the position and the chunk's context, so
+    /// it can sample other noise maps, or resolve the tiles of other worlds
+    /// with `tile_in`, sharing the noise generated for this world. This
+    /// builds cells from several layers in a single pass.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let noise = PerlinNoise::new();
+    /// # let elevation = Box::new(NoiseMap::new(noise).set(Seed::of("elevation")));
+    /// # let moisture = Box::new(NoiseMap::new(noise).set(Seed::of("moisture")));
+    /// struct Cell {
+    ///     terrain: char,
+    ///     tree: bool,
+    ///     moisture: f64,
+    /// }
+    ///
+    /// let terrain = World::new()
+    ///     .set(Size::of(10, 10))
+    ///     .add(Tile::new('~').when(constraint!(elevation.clone(), < 0.0)))
+    ///     .add(Tile::new(','));
+    ///
+    /// let vegetation = World::new()
+    ///     .set(Size::of(10, 10))
+    ///     .add(Tile::new(true).when(constraint!(moisture.clone(), > 0.2)))
+    ///     .add(Tile::new(false));
+    ///
+    /// let cells = terrain
+    ///     .generate_cells(0, 0, |terrain, x, y, context| Cell {
+    ///         terrain,
+    ///         tree: terrain == ',' && vegetation.tile_in(x, y, context) == Some(true),
+    ///         moisture: context.value(&*moisture, x, y),
+    ///     })
+    ///     .unwrap();
+    /// # }
+    /// ```
+    pub fn generate_cells<U, F>(
+        &self,
+        chunk_x: i64,
+        chunk_y: i64,
+        mut f: F,
+    ) -> Result<Chunk<U>, GenerateError>
+    where
+        F: FnMut(T, i64, i64, &mut ChunkContext) -> U,
+    {
+        let mut context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+        let tiles = self.resolve_chunk(&context)?;
+
+        Ok(tiles
+            .into_iter()
+            .zip(chunk_y * self.size.h..)
+            .map(|(row, y)| {
+                row.into_iter()
+                    .zip(chunk_x * self.size.w..)
+                    .map(|(tile, x)| f(tile.value(), x, y, &mut context))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Generates a chunk of the world, along with the auto-tiling bitmask
+    /// of each tile, for rendering with transition tilesets.
+    ///
+    /// The given function decides whether a tile connects to a neighbouring
+    /// tile, for example when they are equal. The tiles around the chunk
+    /// are generated as well, so the bitmasks at the edges of the chunk
+    /// take the neighbouring chunks into account.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Bitmask, Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.2, 0.2)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new(','));
+    ///
+    /// let tiles = world
+    ///     .generate_bitmasks(0, 0, Bitmask::Cardinal, |tile, other| tile == other)
+    ///     .unwrap();
+    ///
+    /// assert!(tiles.iter().flatten().all(|&(_, mask)| mask < 16));
+    /// # }
+    /// ```
+    pub fn generate_bitmasks<F>(
+        &self,
+        chunk_x: i64,
+        chunk_y: i64,
+        bitmask: Bitmask,
+        connects: F,
+    ) -> Result<Chunk<(T, u8)>, GenerateError>
+    where
+        F: Fn(&T, &T) -> bool,
+    {
+        let context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+        let tiles: Vec<Vec<T>> = self
+            .resolve_region(&context, 1)?
+            .into_iter()
+            .map(|row| row.into_iter().map(Tile::value).collect())
+            .collect();
+
+        Ok((1..=self.size.h as usize)
+            .map(|y| {
+                (1..=self.size.w as usize)
+                    .map(|x| (tiles[y][x].clone(), bitmask.of(&tiles, x, y, &connects)))
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Generates a chunk of the world along with a margin (an apron) of
+    /// the given number of cells on each side, for passes that need to see
+    /// past the edges of the chunk.
+    ///
+    /// The apron holds exactly the tiles generated for those cells by the
+    /// neighbouring chunks, post-processing passes included. The chunk
+    /// itself starts at `(margin, margin)` in the result.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, SmoothingRule, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.2, 0.2)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new(','))
+    ///     .smooth(2, SmoothingRule::Majority);
+    ///
+    /// let tiles = world.generate_with_apron(0, 0, 2).unwrap();
+    /// assert_eq!(tiles.len(), 20);
+    ///
+    /// // The apron matches the neighbouring chunks
+    /// assert_eq!(tiles[2][18], world.generate(1, 0).unwrap()[0][0]);
+    /// assert_eq!(tiles[0][0], world.generate(-1, -1).unwrap()[14][14]);
+    /// # }
+    /// ```
+    pub fn generate_with_apron(
+        &self,
+        chunk_x: i64,
+        chunk_y: i64,
+        margin: usize,
+    ) -> Result<Chunk<T>, GenerateError> {
+        let margin = margin as i64;
+
+        // Without post-processing passes the tiles depend only on their
+        // positions, so the whole region can be resolved at once.
+        if !self.has_passes() {
+            let context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+
+            return Ok(self
+                .resolve_region(&context, margin)?
+                .into_iter()
+                .map(|row| row.into_iter().map(Tile::value).collect())
+                .collect());
+        }
+
+        let size = self.size;
+        let (x0, y0) = (chunk_x * size.w - margin, chunk_y * size.h - margin);
+        let (x1, y1) = (x0 + size.w + 2 * margin, y0 + size.h + 2 * margin);
+
+        let chunks: Vec<_> = (y0.div_euclid(size.h)..=(y1 - 1).div_euclid(size.h))
+            .flat_map(|cy| {
+                (x0.div_euclid(size.w)..=(x1 - 1).div_euclid(size.w)).map(move |cx| (cx, cy))
+            })
+            .collect();
+
+        let generated = self
+            .par_generate_chunks(&chunks)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let columns = chunks.iter().filter(|&&(_, cy)| cy == chunks[0].1).count();
+        let first = chunks[0];
+
+        Ok((y0..y1)
             .map(|y| {
-                (chunk_x * self.size.w..(chunk_x + 1) * self.size.w)
+                (x0..x1)
                     .map(|x| {
-                        match self.tiles.iter().find(|tile| {
-                            tile.satisfied_by(x, y, self.size, chunk_x, chunk_y, &mut nms)
-                        }) {
-                            Some(tile) => Some(tile.value()),
-                            None => None,
-                        }
+                        let (cx, cy) = (x.div_euclid(size.w), y.div_euclid(size.h));
+                        let index = (cy - first.1) as usize * columns + (cx - first.0) as usize;
+
+                        generated[index][y.rem_euclid(size.h) as usize]
+                            [x.rem_euclid(size.w) as usize]
+                            .clone()
                     })
                     .collect()
             })
-            .collect()
+            .collect())
+    }
+
+    /// Returns the tile this world chooses for a position, using the noise
+    /// already generated in the given context.
+    ///
+    /// The position should be within the context's chunk, and the world
+    /// should have the same size as the one the context was created for.
+    pub fn tile_in(&self, x: i64, y: i64, context: &ChunkContext) -> Option<T> {
+        self.resolve(x, y, context, None).map(Tile::value)
+    }
+
+    /// Chooses the tile for every position in the context's chunk, runs the
+    /// post-processing passes over them, and merges the overrides.
+    fn finish_chunk(&self, context: &mut ChunkContext) -> Result<Chunk<T>, GenerateError> {
+        let mut chunk = Vec::new();
+        self.finish_chunk_into(context, &mut chunk)?;
+
+        Ok(chunk)
+    }
+
+    /// Chooses the tile for every position in the context's chunk, runs the
+    /// post-processing passes over them, and merges the overrides, reusing the rows of the given
+    /// chunk. The chunk is left unchanged on failure.
+    fn finish_chunk_into(
+        &self,
+        context: &mut ChunkContext,
+        chunk: &mut Chunk<T>,
+    ) -> Result<(), GenerateError> {
+        let tiles = self.resolve_chunk(context)?;
+        let forced = self.guaranteed(context)?;
+
+        chunk.resize_with(tiles.len(), Vec::new);
+        for (row, tiles) in chunk.iter_mut().zip(tiles) {
+            row.clear();
+            row.extend(tiles.into_iter().map(Tile::value));
+        }
+
+        for (x, y, tile) in forced {
+            chunk[y][x] = tile;
+        }
+
+        for (done, pass) in self.posts.iter().enumerate() {
+            pass(chunk, context);
+            self.progress.report("post", done + 1, self.posts.len());
+        }
+
+        self.overrides.apply(chunk, context);
+
+        Ok(())
+    }
+
+    /// Returns true if anything changes the tiles after they are chosen.
+    fn has_passes(&self) -> bool {
+        !self.posts.is_empty() || !self.guarantees.is_empty() || !self.overrides.is_empty()
+    }
+
+    /// Chooses the tile for every position in the context's chunk.
+    fn resolve_chunk(&self, context: &ChunkContext) -> Result<Chunk<&Tile<T>>, GenerateError> {
+        self.resolve_region(context, 0)
+    }
+
+    /// Chooses the tiles of a single row of the context's chunk, given its
+    /// world position, sharing the noise maps of the whole chunk.
+    fn resolve_row(&self, context: &ChunkContext, y: i64) -> Result<Vec<&Tile<T>>, GenerateError> {
+        let row = context.part(Size::of(context.size.w, 1), context.chunk_x, y);
+        Ok(self.resolve_chunk(&row)?.pop().unwrap_or_default())
+    }
+
+    /// Returns the context for resolving a world position on its own, as a
+    /// chunk of its own.
+    ///
+    /// The noise maps are sampled at single positions too, unless the world
+    /// samples whole chunks, in which case they are sampled as the chunk
+    /// holding the position. A world whose chunks are empty has no such
+    /// chunk, so is always sampled at single positions.
+    fn point_context(&self, world_x: i64, world_y: i64) -> ChunkContext {
+        let (size, point) = (self.size, Size::of(1, 1));
+
+        if !self.whole_chunks || size.w <= 0 || size.h <= 0 {
+            return ChunkContext::new(point, self.seed, world_x, world_y);
+        }
+
+        let (chunk_x, chunk_y) = (world_x.div_euclid(size.w), world_y.div_euclid(size.h));
+        ChunkContext::new(size, self.seed, chunk_x, chunk_y).part(point, world_x, world_y)
+    }
+
+    /// Chooses the tile for every position in the context's chunk, along
+    /// with a margin of positions around it.
+    fn resolve_region(
+        &self,
+        context: &ChunkContext,
+        margin: i64,
+    ) -> Result<Chunk<&Tile<T>>, GenerateError> {
+        let (chunk_x, chunk_y, size) = (context.chunk_x, context.chunk_y, context.size);
+        let (iterations, rule) = self.smoothing.unwrap_or((0, SmoothingRule::Majority));
+        let phased = self.tiles.iter().any(Tile::has_neighbour_constraints);
+        let margin = margin + iterations as i64 + phased as i64;
+
+        let (x0, y0) = (chunk_x * size.w - margin, chunk_y * size.h - margin);
+        let (w, h) = (size.w + 2 * margin, size.h + 2 * margin);
+
+        // The rows of both phases count towards progress, although the
+        // second phase only does much work for neighbour constraints.
+        let offset = phased as i64;
+        let total = (h + (h - 2 * offset) * phased as i64).max(0) as usize;
+        let done = AtomicUsize::new(0);
+        let row_done = || {
+            let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+            self.progress.report("tiles", done, total);
+        };
+
+        let first: Vec<Vec<Option<&Tile<T>>>> = self.map_rows(y0..y0 + h, |y| {
+            let row = (x0..x0 + w)
+                .map(|x| self.resolve(x, y, context, None))
+                .collect();
+
+            row_done();
+            row
+        });
+
+        // The second phase leaves out the outermost ring of positions, as
+        // they don't have every neighbour.
+        let rows = self.map_rows(offset..h - offset, |j| {
+            let j = j as usize;
+
+            if phased {
+                row_done();
+            }
+
+            (offset as usize..(w - offset) as usize)
+                .map(|i| {
+                    let (x, y) = (x0 + i as i64, y0 + j as i64);
+                    let tile = if phased {
+                        let neighbours: Vec<_> = (j - 1..=j + 1)
+                            .flat_map(|nj| (i - 1..=i + 1).map(move |ni| (ni, nj)))
+                            .filter(|&position| position != (i, j))
+                            .map(|(ni, nj)| first[nj][ni])
+                            .collect();
+
+                        self.resolve(x, y, context, Some(&neighbours))
+                    } else {
+                        first[j][i]
+                    };
+
+                    tile.ok_or_else(|| GenerateError {
+                        x,
+                        y,
+                        values: context.values_at(x, y),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        });
+
+        // Collecting the rows in order reports the first failing position.
+        let mut tiles = rows.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+        for iteration in 0..iterations {
+            tiles = smooth_step(&tiles, rule);
+            self.progress.report("smoothing", iteration + 1, iterations);
+        }
+
+        Ok(tiles)
+    }
+
+    /// Returns the tile chosen for the given position.
+    ///
+    /// Without neighbours, tiles with neighbour constraints never match.
+    fn resolve(
+        &self,
+        x: i64,
+        y: i64,
+        context: &ChunkContext,
+        neighbours: Option<&[Option<&Tile<T>>]>,
+    ) -> Option<&Tile<T>> {
+        let matches = |tile: &Tile<T>| {
+            let neighbours_match = match neighbours {
+                Some(neighbours) => tile.satisfied_by_neighbours(neighbours),
+                None => !tile.has_neighbour_constraints(),
+            };
+
+            neighbours_match && tile.satisfied_by(x, y, context)
+        };
+
+        let first = self.tiles.iter().position(matches)?;
+
+        let tile = &self.tiles[first];
+        let weight = match tile.get_weight() {
+            Some(weight) => weight,
+            None => return Some(tile),
+        };
+
+        let mut candidates = vec![(tile, weight)];
+        for other in self.tiles[first + 1..].iter() {
+            let weight = match other.get_weight() {
+                Some(weight) => weight,
+                None => break,
+            };
+
+            if matches(other) {
+                candidates.push((other, weight));
+            }
+        }
+
+        let total: f64 = candidates.iter().map(|&(_, weight)| weight).sum();
+        let mut roll = context.rng(x, y, "tile").next_f64() * total;
+
+        for &(candidate, weight) in candidates.iter() {
+            if roll < weight {
+                return Some(candidate);
+            }
+
+            roll -= weight;
+        }
+
+        candidates.last().map(|&(candidate, _)| candidate)
+    }
+
+    /// Builds a row for each of the given positions, in parallel when the
+    /// `parallel` feature is enabled.
+    fn map_rows<R, F>(&self, rows: Range<i64>, f: F) -> Vec<R>
+    where
+        R: Send,
+        F: Fn(i64) -> R + Send + Sync,
+    {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+
+            self.install(|| rows.into_par_iter().map(f).collect())
+        }
+
+        #[cfg(not(feature = "parallel"))]
+        {
+            rows.map(f).collect()
+        }
+    }
+
+    /// Runs a function on the world's pool, if it has one.
+    #[cfg(feature = "parallel")]
+    fn install<R: Send, F: FnOnce() -> R + Send>(&self, f: F) -> R {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(ref pool) = self.pool {
+            return pool.install(f);
+        }
+
+        f()
     }
 }
@@ -0,0 +1,206 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/overrides.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::collections::BTreeMap;
+
+use super::{Chunk, ChunkContext};
+
+/// The inclusive top left and bottom right corners of an area.
+type Area = ((i64, i64), (i64, i64));
+
+/// Tiles placed by hand, which replace the generated tiles at their
+/// positions every time a chunk is generated. See `World::overrides`.
+///
+/// Tiles are placed with brushes, each replacing whatever was placed at
+/// its positions before.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::world::Overrides;
+/// let mut overrides = Overrides::new();
+///
+/// overrides.rect(0, 0, 8, 4, '.');
+/// overrides.ellipse(20, 10, 3, 2, '~');
+/// overrides.stamp(2, 1, &[vec![Some('#'), None, Some('#')]]);
+///
+/// assert_eq!(overrides.get(2, 1), Some(&'#'));
+/// assert_eq!(overrides.get(3, 1), Some(&'.'));
+/// assert_eq!(overrides.get(23, 10), Some(&'~'));
+/// assert_eq!(overrides.get(23, 11), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Overrides<T> {
+    // Keyed by row first, so that each row of a chunk is a range.
+    tiles: BTreeMap<(i64, i64), T>,
+
+    // The inclusive corners of the area changed since it was last taken.
+    changed: Option<Area>,
+}
+
+impl<T> Default for Overrides<T> {
+    fn default() -> Overrides<T> {
+        Overrides {
+            tiles: BTreeMap::new(),
+            changed: None,
+        }
+    }
+}
+
+impl<T: Clone> Overrides<T> {
+    /// Construct an empty set of overrides.
+    pub fn new() -> Overrides<T> {
+        Default::default()
+    }
+
+    /// Places a tile at a single world position.
+    pub fn set(&mut self, x: i64, y: i64, tile: T) {
+        self.tiles.insert((y, x), tile);
+        self.mark((x, y), (x, y));
+    }
+
+    /// Removes the tile placed at a world position, if there is one, so
+    /// that the generated tile shows again.
+    pub fn erase(&mut self, x: i64, y: i64) -> Option<T> {
+        self.mark((x, y), (x, y));
+        self.tiles.remove(&(y, x))
+    }
+
+    /// Removes every placed tile.
+    pub fn clear(&mut self) {
+        let positions: Vec<(i64, i64)> = self.tiles.keys().map(|&(y, x)| (x, y)).collect();
+
+        for (x, y) in positions {
+            self.mark((x, y), (x, y));
+        }
+
+        self.tiles.clear();
+    }
+
+    /// Fills a rectangle, given its top left position and its size.
+    pub fn rect(&mut self, x: i64, y: i64, w: i64, h: i64, tile: T) {
+        for y in y..y + h {
+            for x in x..x + w {
+                self.tiles.insert((y, x), tile.clone());
+            }
+        }
+
+        if w > 0 && h > 0 {
+            self.mark((x, y), (x + w - 1, y + h - 1));
+        }
+    }
+
+    /// Fills an ellipse, given its centre and its radius along each axis.
+    pub fn ellipse(&mut self, x: i64, y: i64, rx: i64, ry: i64, tile: T) {
+        let (rx, ry) = (rx.max(0), ry.max(0));
+
+        for dy in -ry..=ry {
+            for dx in -rx..=rx {
+                if dx * dx * ry * ry + dy * dy * rx * rx <= rx * rx * ry * ry {
+                    self.tiles.insert((y + dy, x + dx), tile.clone());
+                }
+            }
+        }
+
+        self.mark((x - rx, y - ry), (x + rx, y + ry));
+    }
+
+    /// Places a prefab, given as rows of tiles, with its top left corner at
+    /// the given position. Cells holding `None` are left as they are.
+    pub fn stamp(&mut self, x: i64, y: i64, prefab: &[Vec<Option<T>>]) {
+        let mut width = 0;
+
+        for (row, y) in prefab.iter().zip(y..) {
+            width = width.max(row.len() as i64);
+
+            for (tile, x) in row.iter().zip(x..) {
+                if let Some(tile) = tile {
+                    self.tiles.insert((y, x), tile.clone());
+                }
+            }
+        }
+
+        if width > 0 {
+            self.mark((x, y), (x + width - 1, y + prefab.len() as i64 - 1));
+        }
+    }
+
+    /// Returns the tile placed at a world position, if there is one.
+    pub fn get(&self, x: i64, y: i64) -> Option<&T> {
+        self.tiles.get(&(y, x))
+    }
+
+    /// Returns an iterator over the placed tiles and their world positions,
+    /// in row order, for example to save them.
+    pub fn iter(&self) -> impl Iterator<Item = (i64, i64, &T)> {
+        self.tiles.iter().map(|(&(y, x), tile)| (x, y, tile))
+    }
+
+    /// Returns the number of placed tiles.
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Returns true if no tiles are placed.
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Replaces the tiles of the context's chunk with the tiles placed
+    /// within it.
+    pub(super) fn apply(&self, chunk: &mut Chunk<T>, context: &ChunkContext) {
+        let size = context.size;
+        let (x0, y0) = (context.chunk_x * size.w, context.chunk_y * size.h);
+
+        for (row, y) in chunk.iter_mut().zip(y0..) {
+            for (&(_, x), tile) in self.tiles.range((y, x0)..(y, x0 + size.w)) {
+                if let Some(cell) = row.get_mut((x - x0) as usize) {
+                    *cell = tile.clone();
+                }
+            }
+        }
+    }
+
+    /// Returns the inclusive corners of the area changed since this was
+    /// last called, if anything changed.
+    pub(super) fn take_changed(&mut self) -> Option<Area> {
+        self.changed.take()
+    }
+
+    /// Grows the changed area to cover the given inclusive corners.
+    fn mark(&mut self, (x0, y0): (i64, i64), (x1, y1): (i64, i64)) {
+        self.changed = Some(match self.changed {
+            None => ((x0, y0), (x1, y1)),
+            Some(((cx0, cy0), (cx1, cy1))) => {
+                ((cx0.min(x0), cy0.min(y0)), (cx1.max(x1), cy1.max(y1)))
+            }
+        });
+    }
+}
+
+impl<T: Clone> FromIterator<(i64, i64, T)> for Overrides<T> {
+    fn from_iter<I: IntoIterator<Item = (i64, i64, T)>>(iter: I) -> Overrides<T> {
+        Overrides {
+            tiles: iter
+                .into_iter()
+                .map(|(x, y, tile)| ((y, x), tile))
+                .collect(),
+            changed: None,
+        }
+    }
+}
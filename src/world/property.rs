@@ -18,7 +18,7 @@
 
 use super::World;
 
-pub use crate::noisemap::Size;
+pub use crate::noisemap::{Seed, Size};
 
 pub trait Property: Default + Copy {
     fn set_to<T: Clone>(self, w: World<T>) -> World<T>;
@@ -29,3 +29,9 @@ impl Property for Size {
         w.set_size(self)
     }
 }
+
+impl Property for Seed {
+    fn set_to<T: Clone>(self, w: World<T>) -> World<T> {
+        w.set_seed(self)
+    }
+}
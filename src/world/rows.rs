@@ -0,0 +1,61 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/rows.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::ops::Range;
+
+use super::{ChunkContext, GenerateError, Tile, World};
+
+/// An iterator over the rows of a chunk of a world, resolving each row as
+/// it is needed. See `World::rows`.
+pub struct Rows<'a, T> {
+    world: &'a World<T>,
+    context: ChunkContext,
+    rows: Range<i64>,
+}
+
+impl<'a, T> Rows<'a, T> {
+    pub(crate) fn new(world: &'a World<T>, chunk_x: i64, chunk_y: i64) -> Rows<'a, T> {
+        let height = world.size.h;
+
+        Rows {
+            world,
+            context: ChunkContext::new(world.size, world.seed, chunk_x, chunk_y),
+            rows: chunk_y * height..(chunk_y + 1) * height,
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync> Iterator for Rows<'_, T> {
+    type Item = Result<Vec<T>, GenerateError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let y = self.rows.next()?;
+
+        Some(
+            self.world
+                .resolve_row(&self.context, y)
+                .map(|row| row.into_iter().map(Tile::value).collect()),
+        )
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+impl<T: Clone + Send + Sync> ExactSizeIterator for Rows<'_, T> {}
@@ -0,0 +1,83 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/smooth.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::ptr;
+
+/// The rule used to smooth generated tiles. See `World::smooth`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SmoothingRule {
+    /// A position takes the most common of its eight neighbours' tiles
+    /// when more than half of them agree.
+    Majority,
+
+    /// A position takes the most common of its eight neighbours' tiles
+    /// when at least the given number of them agree.
+    AtLeast(usize),
+}
+
+impl SmoothingRule {
+    fn threshold(self) -> usize {
+        match self {
+            SmoothingRule::Majority => 5,
+            SmoothingRule::AtLeast(count) => count.max(1),
+        }
+    }
+}
+
+/// Runs one step of the smoothing over a grid of cells, returning the grid
+/// without its outermost ring of cells, which don't have every neighbour.
+///
+/// Cells are compared by identity, so tiles that are defined separately
+/// count as different tiles even if their values are equal.
+pub(crate) fn smooth_step<'a, C>(cells: &[Vec<&'a C>], rule: SmoothingRule) -> Vec<Vec<&'a C>> {
+    let threshold = rule.threshold();
+
+    (1..cells.len() - 1)
+        .map(|y| {
+            (1..cells[y].len() - 1)
+                .map(|x| {
+                    let neighbours: Vec<&C> = (y - 1..=y + 1)
+                        .flat_map(|ny| (x - 1..=x + 1).map(move |nx| (nx, ny)))
+                        .filter(|&position| position != (x, y))
+                        .map(|(nx, ny)| cells[ny][nx])
+                        .collect();
+
+                    neighbours
+                        .iter()
+                        .map(|&candidate| {
+                            let count = neighbours
+                                .iter()
+                                .filter(|&&other| ptr::eq(candidate, other))
+                                .count();
+                            (candidate, count)
+                        })
+                        .fold(
+                            None,
+                            |best: Option<(&C, usize)>, (candidate, count)| match best {
+                                Some((_, best_count)) if best_count >= count => best,
+                                _ => Some((candidate, count)),
+                            },
+                        )
+                        .filter(|&(_, count)| count >= threshold)
+                        .map_or(cells[y][x], |(candidate, _)| candidate)
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,269 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/start.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+
+use super::{Chunk, GenerateError, World};
+
+/// A predicate on a tile.
+type Predicate<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+enum Rule<T> {
+    /// Every cell within the radius satisfies the predicate.
+    Around(i64, Predicate<T>),
+
+    /// Some cell within the radius satisfies the predicate.
+    Near(i64, Predicate<T>),
+}
+
+/// What makes a good place to start. See `World::find_start`.
+///
+/// Distances are measured in a straight line, so each rule covers a disc
+/// of cells. The defaults are:
+///
+/// ```text
+/// origin = (0, 0)
+/// max distance = 1024
+/// step = 1
+/// ```
+pub struct StartCriteria<T> {
+    rules: Vec<Rule<T>>,
+
+    origin: (i64, i64),
+    max_distance: i64,
+    step: i64,
+}
+
+impl<T> Default for StartCriteria<T> {
+    fn default() -> StartCriteria<T> {
+        StartCriteria {
+            rules: Vec::new(),
+
+            origin: (0, 0),
+            max_distance: 1024,
+            step: 1,
+        }
+    }
+}
+
+impl<T> StartCriteria<T> {
+    /// Construct criteria that any cell satisfies.
+    pub fn new() -> StartCriteria<T> {
+        Default::default()
+    }
+
+    /// Requires the tile of the cell itself to satisfy the predicate.
+    pub fn on<F: Fn(&T) -> bool + Send + Sync + 'static>(self, predicate: F) -> StartCriteria<T> {
+        self.around(0, predicate)
+    }
+
+    /// Requires every cell within the radius to satisfy the predicate, for
+    /// example to start on open land away from cliffs.
+    pub fn around<F>(self, radius: i64, predicate: F) -> StartCriteria<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let mut new = self;
+        new.rules
+            .push(Rule::Around(radius.max(0), Box::new(predicate)));
+        new
+    }
+
+    /// Requires some cell within the radius to satisfy the predicate, for
+    /// example to start near water.
+    pub fn near<F>(self, radius: i64, predicate: F) -> StartCriteria<T>
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let mut new = self;
+        new.rules
+            .push(Rule::Near(radius.max(0), Box::new(predicate)));
+        new
+    }
+
+    /// Set the world position the search starts from.
+    pub fn origin(self, x: i64, y: i64) -> StartCriteria<T> {
+        StartCriteria {
+            origin: (x, y),
+            ..self
+        }
+    }
+
+    /// Set the furthest the search goes from the origin, along either
+    /// axis, before giving up.
+    pub fn max_distance(self, max_distance: i64) -> StartCriteria<T> {
+        StartCriteria {
+            max_distance: max_distance.max(0),
+            ..self
+        }
+    }
+
+    /// Set the spacing between the positions tried. Larger steps search
+    /// further for the same work, at the cost of missing small sites.
+    pub fn step(self, step: i64) -> StartCriteria<T> {
+        StartCriteria {
+            step: step.max(1),
+            ..self
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync> World<T> {
+    /// Searches outwards from the origin for the nearest position meeting
+    /// the given criteria, for placing players at the start of a game.
+    ///
+    /// Positions are tried in square rings of growing size around the
+    /// origin, and the first ring holding a suitable position gives the
+    /// one closest to the origin. Chunks are generated as the search
+    /// reaches them, post-processing passes included, and kept until it
+    /// ends.
+    ///
+    /// Returns `None` if no position within the maximum distance meets the
+    /// criteria.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, StartCriteria, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm.clone(), < -0.1)))
+    ///     .add(Tile::new('^').when(constraint!(nm, > 0.3)))
+    ///     .add(Tile::new(','));
+    ///
+    /// // On grassland, clear of mountains, and near water
+    /// let criteria = StartCriteria::new()
+    ///     .around(2, |&tile| tile == ',')
+    ///     .near(6, |&tile| tile == '~')
+    ///     .max_distance(200);
+    ///
+    /// if let Some((x, y)) = world.find_start(&criteria).unwrap() {
+    ///     assert_eq!(world.tile_at(x, y).unwrap(), ',');
+    /// }
+    /// # }
+    /// ```
+    pub fn find_start(
+        &self,
+        criteria: &StartCriteria<T>,
+    ) -> Result<Option<(i64, i64)>, GenerateError> {
+        let mut chunks = HashMap::new();
+        let (ox, oy) = criteria.origin;
+        let step = criteria.step;
+
+        for ring in 0..=criteria.max_distance / step {
+            let mut best: Option<((i64, i64), i64)> = None;
+
+            for (i, j) in ring_positions(ring) {
+                let (x, y) = (ox + i * step, oy + j * step);
+                let distance = (x - ox).pow(2) + (y - oy).pow(2);
+
+                if best.is_some_and(|(_, best)| best <= distance) {
+                    continue;
+                }
+
+                if self.meets(criteria, x, y, &mut chunks)? {
+                    best = Some(((x, y), distance));
+                }
+            }
+
+            if let Some((position, _)) = best {
+                return Ok(Some(position));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns true if the position meets every rule of the criteria.
+    fn meets(
+        &self,
+        criteria: &StartCriteria<T>,
+        x: i64,
+        y: i64,
+        chunks: &mut HashMap<(i64, i64), Chunk<T>>,
+    ) -> Result<bool, GenerateError> {
+        for rule in criteria.rules.iter() {
+            let (radius, predicate, all) = match *rule {
+                Rule::Around(radius, ref predicate) => (radius, predicate, true),
+                Rule::Near(radius, ref predicate) => (radius, predicate, false),
+            };
+
+            let mut found = false;
+
+            'cells: for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if dx * dx + dy * dy > radius * radius {
+                        continue;
+                    }
+
+                    let tile = self.cached_tile(x + dx, y + dy, chunks)?;
+                    if predicate(tile) != all {
+                        found = true;
+                        break 'cells;
+                    }
+                }
+            }
+
+            // For `Around`, finding a cell means one failed the predicate.
+            if found == all {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the tile at a world position, generating its chunk if it
+    /// isn't cached.
+    fn cached_tile<'a>(
+        &self,
+        x: i64,
+        y: i64,
+        chunks: &'a mut HashMap<(i64, i64), Chunk<T>>,
+    ) -> Result<&'a T, GenerateError> {
+        let (cx, cy) = (x.div_euclid(self.size.w), y.div_euclid(self.size.h));
+
+        let chunk = match chunks.entry((cx, cy)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(self.generate(cx, cy)?),
+        };
+
+        Ok(&chunk[y.rem_euclid(self.size.h) as usize][x.rem_euclid(self.size.w) as usize])
+    }
+}
+
+/// Returns the offsets on the square ring at the given distance from the
+/// centre, in row order.
+fn ring_positions(ring: i64) -> impl Iterator<Item = (i64, i64)> {
+    (-ring..=ring).flat_map(move |j| {
+        let row: Vec<i64> = if j.abs() == ring {
+            (-ring..=ring).collect()
+        } else {
+            vec![-ring, ring]
+        };
+
+        row.into_iter().map(move |i| (i, j))
+    })
+}
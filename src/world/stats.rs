@@ -0,0 +1,202 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/stats.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::cmp::Reverse;
+use std::ops::RangeInclusive;
+
+use super::{AreaError, ChunkContext, Histogram, World};
+
+/// The values a noise map took over a region. See `World::stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapStats {
+    /// The id of the noise map.
+    pub id: u64,
+
+    /// The name the noise map was registered with, if it was registered
+    /// with `World::with_map`.
+    pub name: Option<String>,
+
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+/// A report of the tiles and noise values generated over a region. See
+/// `World::stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats<T> {
+    /// The number of positions in the region.
+    pub cells: usize,
+
+    /// The number of times each tile appears, most common first.
+    pub tiles: Histogram<T>,
+
+    /// The values of every noise map used to choose the tiles, ordered by
+    /// id.
+    pub maps: Vec<MapStats>,
+}
+
+impl<T: PartialEq> Stats<T> {
+    /// Returns the percentage (between 0 and 100) of the region covered by
+    /// the given tile.
+    pub fn coverage(&self, tile: &T) -> f64 {
+        let count = self
+            .tiles
+            .iter()
+            .find(|(other, _)| other == tile)
+            .map_or(0, |&(_, count)| count);
+
+        percentage(count, self.cells)
+    }
+
+    /// Returns the percentage of the region covered by each tile, most
+    /// common first.
+    pub fn coverages(&self) -> Vec<(&T, f64)> {
+        self.tiles
+            .iter()
+            .map(|(tile, count)| (tile, percentage(*count, self.cells)))
+            .collect()
+    }
+
+    /// Returns the values of the noise map registered with the given name.
+    pub fn map(&self, name: &str) -> Option<&MapStats> {
+        self.maps
+            .iter()
+            .find(|map| map.name.as_deref() == Some(name))
+    }
+}
+
+impl<T: Clone + Send + Sync + PartialEq> World<T> {
+    /// Generates the chunks between two chunk coordinates (inclusive), and
+    /// reports how often each tile appears, along with the range of values
+    /// each noise map actually took, to check a world against what it was
+    /// designed to look like.
+    ///
+    /// The tiles are those of `generate`, including post-processing passes
+    /// and overrides. Only the noise maps used to choose the tiles are
+    /// reported. The area is generated a chunk at a time, with only the
+    /// counts kept, and generation stops between rows of chunks if the
+    /// world's `CancelToken` is cancelled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .with_map("elevation", Box::new(nm))
+    ///     .add(Tile::new('~').when(constraint!("elevation", < 0.0)))
+    ///     .add(Tile::new(','));
+    ///
+    /// let stats = world.stats((0, 0)..=(3, 3)).unwrap();
+    /// assert_eq!(stats.cells, 64 * 64);
+    ///
+    /// let land = stats.coverage(&',');
+    /// assert!((stats.coverage(&'~') + land - 100.0).abs() < 1e-9);
+    ///
+    /// let elevation = stats.map("elevation").unwrap();
+    /// assert!(elevation.min <= elevation.mean && elevation.mean <= elevation.max);
+    /// # }
+    /// ```
+    pub fn stats(&self, region: RangeInclusive<(i64, i64)>) -> Result<Stats<T>, AreaError> {
+        let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+
+        let mut tiles: Histogram<T> = Vec::new();
+        let mut maps: Vec<MapStats> = Vec::new();
+
+        // The sum and number of the values taken by each map.
+        let mut totals: Vec<(f64, usize)> = Vec::new();
+        let mut cells = 0;
+
+        for chunk_y in y0..=y1 {
+            self.cancel.check()?;
+
+            for chunk_x in x0..=x1 {
+                let mut context = ChunkContext::new(self.size, self.seed, chunk_x, chunk_y);
+                let chunk = self.finish_chunk(&mut context)?;
+
+                for (j, row) in chunk.into_iter().enumerate() {
+                    for (i, tile) in row.into_iter().enumerate() {
+                        cells += 1;
+
+                        match tiles.iter_mut().find(|(other, _)| *other == tile) {
+                            Some(entry) => entry.1 += 1,
+                            None => tiles.push((tile, 1)),
+                        }
+
+                        let (x, y) = context.world_position(i, j);
+                        for (id, value) in context.values_at(x, y) {
+                            match maps.iter().position(|map| map.id == id) {
+                                Some(index) => {
+                                    let map = &mut maps[index];
+                                    map.min = map.min.min(value);
+                                    map.max = map.max.max(value);
+                                    totals[index].0 += value;
+                                    totals[index].1 += 1;
+                                }
+                                None => {
+                                    maps.push(MapStats {
+                                        id,
+                                        name: self.map_name(id),
+                                        min: value,
+                                        max: value,
+                                        mean: 0.0,
+                                    });
+                                    totals.push((value, 1));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for (map, (total, count)) in maps.iter_mut().zip(totals) {
+            map.mean = total / count as f64;
+        }
+
+        // A stable sort keeps ties in the order the tiles first appeared.
+        tiles.sort_by_key(|&(_, count)| Reverse(count));
+        maps.sort_by_key(|map| map.id);
+
+        Ok(Stats { cells, tiles, maps })
+    }
+
+    /// Returns the name of the registered noise map with the given id.
+    fn map_name(&self, id: u64) -> Option<String> {
+        self.maps
+            .iter()
+            .find(|(_, nm)| nm.id() == id)
+            .map(|(name, _)| name.clone())
+    }
+}
+
+/// Returns a count as a percentage of a total.
+fn percentage(count: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / total as f64
+    }
+}
@@ -0,0 +1,350 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/streamer.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::mem;
+
+use super::{Chunk, ChunkContext, GenerateError, Overrides, World};
+use crate::store::ChunkStore;
+
+/// A callback given each chunk as it is evicted from a `WorldStreamer`.
+type EvictCallback<T> = Box<dyn FnMut(i64, i64, Chunk<T>)>;
+
+/// A callback given the errors from a `WorldStreamer`'s chunk store.
+type StoreErrorCallback = Box<dyn FnMut(i64, i64, io::Error)>;
+
+/// Keeps the most recently used chunks of a world in memory, generating
+/// chunks as they are needed.
+///
+/// The cache is limited by a memory budget in bytes, counting the tiles of
+/// each chunk. When it is exceeded, the least recently used chunks are
+/// evicted, and passed to the eviction callback, if there is one, so they
+/// can be saved. The chunks still cached are evicted the same way when the
+/// streamer is dropped.
+///
+/// With a `ChunkStore`, chunks are loaded from the store before being
+/// generated, and evicted chunks are saved to it unless they were loaded
+/// from it unchanged. Chunks changed with `insert` are saved again, so
+/// changes to the world, and chunks that are slow to generate, survive
+/// between runs.
+///
+/// Tiles placed by hand are changed with `edit`, which generates the cached
+/// chunks it reaches again. Chunks loaded from the store have the world's
+/// overrides merged over them as well, though a tile erased from the
+/// overrides stays in a stored chunk until the chunk is generated again.
+///
+/// # Example
+///
+/// ```
+/// # use worldgen::world::{Size, Tile, World, WorldStreamer};
+/// # use std::sync::mpsc::channel;
+/// let world = World::new()
+///     .set(Size::of(16, 16))
+///     .add(Tile::new('.'));
+///
+/// // Room for four chunks of chars
+/// let budget = 4 * 16 * 16 * std::mem::size_of::<char>();
+/// let (saved, evicted) = channel();
+///
+/// let mut streamer = WorldStreamer::new(world, budget)
+///     .on_evict(move |x, y, _chunk| saved.send((x, y)).unwrap());
+///
+/// for x in 0..5 {
+///     let chunk = streamer.get_or_generate(x, 0).unwrap();
+///     assert_eq!(chunk[0][0], '.');
+/// }
+///
+/// assert_eq!(streamer.len(), 4);
+/// assert_eq!(evicted.try_recv(), Ok((0, 0)));
+/// ```
+pub struct WorldStreamer<T> {
+    world: World<T>,
+    budget: usize,
+
+    chunks: HashMap<(i64, i64), Cached<T>>,
+    recent: BTreeMap<u64, (i64, i64)>,
+    clock: u64,
+
+    on_evict: Option<EvictCallback<T>>,
+    store: Option<Box<dyn ChunkStore<T>>>,
+    on_store_error: Option<StoreErrorCallback>,
+}
+
+/// A cached chunk, with when it was last used and whether the store
+/// already holds it.
+struct Cached<T> {
+    chunk: Chunk<T>,
+    used: u64,
+    stored: bool,
+}
+
+impl<T: Clone + Send + Sync> WorldStreamer<T> {
+    /// Construct a streamer generating chunks of the given world, keeping
+    /// as many as fit within the given budget in bytes (and at least one).
+    pub fn new(world: World<T>, budget: usize) -> WorldStreamer<T> {
+        WorldStreamer {
+            world,
+            budget,
+
+            chunks: HashMap::new(),
+            recent: BTreeMap::new(),
+            clock: 0,
+
+            on_evict: None,
+            store: None,
+            on_store_error: None,
+        }
+    }
+
+    /// Set the callback given each chunk as it is evicted.
+    pub fn on_evict<F: FnMut(i64, i64, Chunk<T>) + 'static>(self, callback: F) -> WorldStreamer<T> {
+        let mut new = self;
+        new.on_evict = Some(Box::new(callback));
+        new
+    }
+
+    /// Set the store chunks are loaded from and saved to.
+    pub fn store<S: ChunkStore<T> + 'static>(self, store: S) -> WorldStreamer<T> {
+        let mut new = self;
+        new.store = Some(Box::new(store));
+        new
+    }
+
+    /// Set the callback given the coordinates of a chunk and the error when
+    /// it can't be loaded from or saved to the store. Chunks that can't be
+    /// loaded are generated instead. Without a callback, these errors are
+    /// ignored.
+    pub fn on_store_error<F: FnMut(i64, i64, io::Error) + 'static>(
+        self,
+        callback: F,
+    ) -> WorldStreamer<T> {
+        let mut new = self;
+        new.on_store_error = Some(Box::new(callback));
+        new
+    }
+
+    /// Returns the world the chunks are generated from.
+    pub fn world(&self) -> &World<T> {
+        &self.world
+    }
+
+    /// Returns the number of chunks currently cached.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns true if no chunks are cached.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Returns the chunk if it is cached, marking it as recently used.
+    pub fn get(&mut self, x: i64, y: i64) -> Option<&Chunk<T>> {
+        self.touch(x, y);
+        self.chunks.get(&(x, y)).map(|cached| &cached.chunk)
+    }
+
+    /// Returns the chunk, loading it from the store or generating it, and
+    /// caching it, if it is not already cached.
+    pub fn get_or_generate(&mut self, x: i64, y: i64) -> Result<&Chunk<T>, GenerateError> {
+        if !self.chunks.contains_key(&(x, y)) {
+            match self.load(x, y) {
+                Some(chunk) => self.cache(x, y, chunk, true),
+                None => {
+                    let chunk = self.world.generate(x, y)?;
+                    self.cache(x, y, chunk, false);
+                }
+            }
+        }
+
+        Ok(self.get(x, y).unwrap())
+    }
+
+    /// Adds a chunk to the cache, such as one that was saved earlier or
+    /// has been changed, evicting other chunks to stay within the budget.
+    pub fn insert(&mut self, x: i64, y: i64, chunk: Chunk<T>) {
+        self.cache(x, y, chunk, false);
+    }
+
+    /// Edits the world's overrides, then generates each cached chunk the
+    /// edits reached again, so that it shows them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Size, Tile, World, WorldStreamer};
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('.'));
+    ///
+    /// let mut streamer = WorldStreamer::new(world, 1 << 20);
+    /// assert_eq!(streamer.get_or_generate(1, 0).unwrap()[2][3], '.');
+    ///
+    /// // A hand-placed wall, crossing into the cached chunk
+    /// streamer
+    ///     .edit(|overrides| overrides.rect(10, 2, 10, 1, '#'))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(streamer.get(1, 0).unwrap()[2][3], '#');
+    /// ```
+    pub fn edit<F: FnOnce(&mut Overrides<T>)>(&mut self, edit: F) -> Result<(), GenerateError> {
+        self.world.overrides.take_changed();
+        edit(&mut self.world.overrides);
+
+        let ((x0, y0), (x1, y1)) = match self.world.overrides.take_changed() {
+            Some(area) => area,
+            None => return Ok(()),
+        };
+
+        let size = self.world.get_size();
+        let (cx0, cy0) = (x0.div_euclid(size.w), y0.div_euclid(size.h));
+        let (cx1, cy1) = (x1.div_euclid(size.w), y1.div_euclid(size.h));
+
+        let reached: Vec<(i64, i64)> = self
+            .chunks
+            .keys()
+            .copied()
+            .filter(|&(x, y)| (cx0..=cx1).contains(&x) && (cy0..=cy1).contains(&y))
+            .collect();
+
+        for (x, y) in reached {
+            let chunk = self.world.generate(x, y)?;
+
+            let cached = self.chunks.get_mut(&(x, y)).unwrap();
+            cached.chunk = chunk;
+            cached.stored = false;
+        }
+
+        Ok(())
+    }
+
+    /// Removes a chunk from the cache, saving it to the store if needed and
+    /// passing it to the eviction callback.
+    pub fn evict(&mut self, x: i64, y: i64) {
+        if let Some(cached) = self.remove(x, y) {
+            self.release(x, y, cached);
+        }
+    }
+
+    /// Evicts every chunk from the cache.
+    pub fn flush(&mut self) {
+        while let Some((_, (x, y))) = self.recent.pop_first() {
+            self.evict(x, y);
+        }
+    }
+
+    /// Loads a chunk from the store, if there is one and it holds the
+    /// chunk, merging the overrides over it.
+    fn load(&mut self, x: i64, y: i64) -> Option<Chunk<T>> {
+        match self.store.as_mut()?.load(x, y) {
+            Ok(chunk) => chunk.map(|mut chunk| {
+                let context = ChunkContext::new(self.world.size, self.world.seed, x, y);
+                self.world.overrides.apply(&mut chunk, &context);
+                chunk
+            }),
+            Err(error) => {
+                if let Some(ref mut on_store_error) = self.on_store_error {
+                    on_store_error(x, y, error);
+                }
+
+                None
+            }
+        }
+    }
+
+    /// Adds a chunk to the cache, evicting other chunks to stay within the
+    /// budget.
+    fn cache(&mut self, x: i64, y: i64, chunk: Chunk<T>, stored: bool) {
+        self.remove(x, y);
+
+        self.clock += 1;
+        self.recent.insert(self.clock, (x, y));
+        self.chunks.insert(
+            (x, y),
+            Cached {
+                chunk,
+                used: self.clock,
+                stored,
+            },
+        );
+
+        let cost = self.chunk_cost().max(1);
+        while self.chunks.len() > 1 && self.chunks.len() * cost > self.budget {
+            let (_, (oldest_x, oldest_y)) = self.recent.pop_first().unwrap();
+            self.evict(oldest_x, oldest_y);
+        }
+    }
+
+    /// Removes a chunk from the cache without saving it or passing it to
+    /// the eviction callback.
+    fn remove(&mut self, x: i64, y: i64) -> Option<Cached<T>> {
+        self.chunks.remove(&(x, y)).inspect(|cached| {
+            self.recent.remove(&cached.used);
+        })
+    }
+
+    /// Marks a chunk as recently used.
+    fn touch(&mut self, x: i64, y: i64) {
+        if let Some(Cached { used, .. }) = self.chunks.get_mut(&(x, y)) {
+            self.recent.remove(used);
+
+            self.clock += 1;
+            *used = self.clock;
+            self.recent.insert(self.clock, (x, y));
+        }
+    }
+
+    /// Returns the approximate number of bytes used by a chunk.
+    fn chunk_cost(&self) -> usize {
+        let size = self.world.get_size();
+        let (w, h) = (size.w.max(0) as usize, size.h.max(0) as usize);
+
+        w * h * mem::size_of::<T>()
+    }
+}
+
+impl<T> WorldStreamer<T> {
+    /// Saves an evicted chunk to the store if it doesn't already hold it,
+    /// then passes it to the eviction callback.
+    fn release(&mut self, x: i64, y: i64, cached: Cached<T>) {
+        if !cached.stored {
+            if let Some(ref mut store) = self.store {
+                if let Err(error) = store.save(x, y, &cached.chunk) {
+                    if let Some(ref mut on_store_error) = self.on_store_error {
+                        on_store_error(x, y, error);
+                    }
+                }
+            }
+        }
+
+        if let Some(ref mut on_evict) = self.on_evict {
+            on_evict(x, y, cached.chunk);
+        }
+    }
+}
+
+impl<T> Drop for WorldStreamer<T> {
+    fn drop(&mut self) {
+        for (_, (x, y)) in mem::take(&mut self.recent) {
+            if let Some(cached) = self.chunks.remove(&(x, y)) {
+                self.release(x, y, cached);
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/summary.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::cmp::Reverse;
+use std::ops::RangeInclusive;
+
+use super::{AreaError, Chunk, World};
+
+/// The number of times each tile appears in a block, most common first.
+pub type Histogram<T> = Vec<(T, usize)>;
+
+impl<T: Clone + Send + Sync + PartialEq> World<T> {
+    /// Generates a reduced grid of the chunks between two chunk coordinates
+    /// (inclusive), for minimaps and overviews. Each cell of the grid holds
+    /// the most common tile of a square block of tiles, `factor` tiles
+    /// across.
+    ///
+    /// Ties go to the tile found first while generating the block. Blocks at
+    /// the right and bottom edges are smaller if the area doesn't divide
+    /// evenly.
+    ///
+    /// Like `generate_area`, the area is generated a row of chunks at a
+    /// time, but only the summary of each row is kept, and generation stops
+    /// between rows if the world's `CancelToken` is cancelled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.02, 0.02)));
+    /// let world = World::new()
+    ///     .set(Size::of(32, 32))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new(','));
+    ///
+    /// // A 256x256 area, as a 32x32 minimap
+    /// let minimap = world.summarize((0, 0)..=(7, 7), 8).unwrap();
+    /// assert_eq!((minimap[0].len(), minimap.len()), (32, 32));
+    /// # }
+    /// ```
+    pub fn summarize(
+        &self,
+        region: RangeInclusive<(i64, i64)>,
+        factor: usize,
+    ) -> Result<Chunk<T>, AreaError> {
+        Ok(self
+            .summarize_histograms(region, factor)?
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|histogram| histogram[0].0.clone())
+                    .collect()
+            })
+            .collect())
+    }
+
+    /// Generates a reduced grid of the chunks between two chunk coordinates
+    /// (inclusive), as `summarize` does, but with each cell holding the
+    /// number of times each tile appears in its block, most common first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use worldgen::world::{Size, Tile, World};
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new(','));
+    ///
+    /// let histograms = world.summarize_histograms((0, 0)..=(0, 0), 4).unwrap();
+    /// assert_eq!(histograms[0][0], vec![(',', 16)]);
+    /// ```
+    pub fn summarize_histograms(
+        &self,
+        region: RangeInclusive<(i64, i64)>,
+        factor: usize,
+    ) -> Result<Chunk<Histogram<T>>, AreaError> {
+        let (&(x0, y0), &(x1, y1)) = (region.start(), region.end());
+        let factor = factor.max(1);
+
+        let width = ((x1 - x0 + 1).max(0) * self.size.w) as usize;
+        let height = ((y1 - y0 + 1).max(0) * self.size.h) as usize;
+
+        let mut grid: Chunk<Histogram<T>> =
+            vec![vec![Vec::new(); width.div_ceil(factor)]; height.div_ceil(factor)];
+
+        for (chunk_row, y) in (y0..=y1).enumerate() {
+            self.cancel.check()?;
+
+            let positions: Vec<_> = (x0..=x1).map(|x| (x, y)).collect();
+            let chunks = self
+                .par_generate_chunks(&positions)
+                .into_iter()
+                .collect::<Result<Vec<_>, _>>()?;
+
+            for (chunk_column, chunk) in chunks.into_iter().enumerate() {
+                for (j, row) in chunk.into_iter().enumerate() {
+                    let block_y = (chunk_row * self.size.h as usize + j) / factor;
+
+                    for (i, tile) in row.into_iter().enumerate() {
+                        let block_x = (chunk_column * self.size.w as usize + i) / factor;
+                        let histogram = &mut grid[block_y][block_x];
+
+                        match histogram.iter_mut().find(|(other, _)| *other == tile) {
+                            Some(entry) => entry.1 += 1,
+                            None => histogram.push((tile, 1)),
+                        }
+                    }
+                }
+            }
+        }
+
+        // A stable sort keeps ties in the order the tiles first appeared.
+        for histogram in grid.iter_mut().flatten() {
+            histogram.sort_by_key(|&(_, count)| Reverse(count));
+        }
+
+        Ok(grid)
+    }
+}
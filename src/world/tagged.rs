@@ -0,0 +1,65 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/tagged.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2016 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use super::{Chunk, Tile};
+
+/// A generated chunk of the world, along with the tile chosen at each
+/// position, so the tags of the tiles can be queried. See
+/// `World::generate_tagged`.
+pub struct TaggedChunk<'a, T> {
+    values: Chunk<T>,
+    tiles: Chunk<&'a Tile<T>>,
+}
+
+impl<'a, T: Clone> TaggedChunk<'a, T> {
+    pub(crate) fn new(values: Chunk<T>, tiles: Chunk<&'a Tile<T>>) -> TaggedChunk<'a, T> {
+        TaggedChunk { values, tiles }
+    }
+
+    /// Returns the generated tiles.
+    pub fn values(&self) -> &Chunk<T> {
+        &self.values
+    }
+
+    /// Returns the generated tiles, dropping the tags.
+    pub fn into_values(self) -> Chunk<T> {
+        self.values
+    }
+
+    /// Returns the tags of the tile at a position in the chunk.
+    pub fn tags_at(&self, x: usize, y: usize) -> &'a [String] {
+        self.tiles[y][x].tags()
+    }
+
+    /// Returns true if the tile at a position in the chunk has the given
+    /// tag.
+    pub fn has_tag(&self, x: usize, y: usize, tag: &str) -> bool {
+        self.tiles[y][x].has_tag(tag)
+    }
+
+    /// Returns the positions in the chunk whose tiles have the given tag,
+    /// row by row.
+    pub fn positions_with<'b>(&'b self, tag: &'b str) -> impl Iterator<Item = (usize, usize)> + 'b {
+        self.tiles.iter().enumerate().flat_map(move |(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(move |(_, tile)| tile.has_tag(tag))
+                .map(move |(x, _)| (x, y))
+        })
+    }
+}
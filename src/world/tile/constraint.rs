@@ -16,12 +16,16 @@
 //  limitations under the License.
 //////////////////////////////////////////////////////////////////////////////
 
-use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::noisemap::NoiseMapGeneratorBase;
-use crate::world::Size;
+#[cfg(feature = "rhai")]
+use super::script::{Script, ScriptError};
+use crate::noisemap::{NoiseMapGeneratorBase, Seed};
+use crate::rng::{hash, CellRng};
+use crate::world::{ChunkContext, ConstraintTrace};
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ConstraintType {
     /// This constraint is satisfied when the noise value is
     /// lower than the given threshold.
@@ -30,15 +34,201 @@ pub enum ConstraintType {
     /// This constraint is satisfied when the noise value is
     /// greater than the given threshold.
     GT(f64),
+
+    /// This constraint is satisfied when the noise value is within
+    /// the given range, including the lower bound but not the upper
+    /// (like a Rust `Range`).
+    Between(f64, f64),
+
+    /// This constraint is satisfied when the noise value is
+    /// lower than or equal to the given threshold.
+    LE(f64),
+
+    /// This constraint is satisfied when the noise value is
+    /// greater than or equal to the given threshold.
+    GE(f64),
+
+    /// This constraint is satisfied when the noise value is within
+    /// the given epsilon (inclusive) of the given value.
+    EqApprox(f64, f64),
+}
+
+impl ConstraintType {
+    /// Returns true if the given noise value satisfies this constraint type.
+    pub fn matches(self, value: f64) -> bool {
+        match self {
+            ConstraintType::LT(threshold) => value < threshold,
+            ConstraintType::GT(threshold) => value > threshold,
+            ConstraintType::Between(low, high) => (low..high).contains(&value),
+            ConstraintType::LE(threshold) => value <= threshold,
+            ConstraintType::GE(threshold) => value >= threshold,
+            ConstraintType::EqApprox(target, epsilon) => (value - target).abs() <= epsilon,
+        }
+    }
+}
+
+impl ConstraintType {
+    /// Returns false if no value within the given bounds could satisfy
+    /// this constraint type.
+    fn satisfiable(self, (low, high): (f64, f64)) -> bool {
+        // Bounds that are not a valid range are treated as unknown.
+        if low.is_nan() || high.is_nan() || low > high {
+            return true;
+        }
+
+        match self {
+            ConstraintType::LT(threshold) => low < threshold,
+            ConstraintType::GT(threshold) => high > threshold,
+            ConstraintType::Between(lower, upper) => lower < upper && lower <= high && upper > low,
+            ConstraintType::LE(threshold) => low <= threshold,
+            ConstraintType::GE(threshold) => high >= threshold,
+            ConstraintType::EqApprox(target, epsilon) => {
+                target + epsilon >= low && target - epsilon <= high
+            }
+        }
+    }
 }
 
 /// A constraint that limits when a tile should be chosen for
 /// the generated world.
+///
+/// Constraints can be combined with `or`, `and` and `not`, or with the
+/// `any_of!` and `all_of!` macros:
+///
+/// ```
+/// # #[macro_use] extern crate worldgen;
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::NoiseMap;
+/// # use worldgen::world::tile::{Constraint, ConstraintType};
+/// # fn main() {
+/// # let sea = Box::new(NoiseMap::new(PerlinNoise::new()));
+/// # let lake = Box::new(NoiseMap::new(PerlinNoise::new()));
+/// let beach = constraint!(sea.clone(), 0.0 .. 0.1).or(constraint!(lake.clone(), 0.0 .. 0.05));
+/// let inland = constraint!(sea.clone(), < 0.0).not();
+///
+/// let either = any_of!(constraint!(sea.clone(), > 0.5), constraint!(lake.clone(), > 0.5));
+/// let both = all_of!(constraint!(sea, > 0.5), constraint!(lake, > 0.5));
+/// # }
+/// ```
 pub struct Constraint {
-    nm: Box<dyn NoiseMapGeneratorBase>,
-    constraint: ConstraintType,
+    kind: Kind,
+}
+
+/// A noise map used by a constraint: either a boxed noise map, or the name
+/// of a noise map registered on the world with `World::with_map`.
+///
+/// Constraints accept anything that converts into a `MapRef`, so names can
+/// be used wherever noise maps can:
+///
+/// ```
+/// # #[macro_use] extern crate worldgen;
+/// # use worldgen::world::tile::{Constraint, ConstraintType};
+/// # fn main() {
+/// let lowland = constraint!("elevation", < 0.2);
+/// # }
+/// ```
+pub enum MapRef {
+    Map(Box<dyn NoiseMapGeneratorBase>),
+    Named(String),
+}
+
+impl MapRef {
+    /// Returns the noise map, unless it is a name that has not been
+    /// registered.
+    pub(super) fn get(&self) -> Option<&dyn NoiseMapGeneratorBase> {
+        match *self {
+            MapRef::Map(ref nm) => Some(&**nm),
+            MapRef::Named(_) => None,
+        }
+    }
+
+    fn bounds(&self) -> (f64, f64) {
+        self.get()
+            .map_or((f64::NEG_INFINITY, f64::INFINITY), |nm| nm.bounds())
+    }
+
+    /// Replaces the name with the shared noise map, if it has this name.
+    pub(super) fn bind(&mut self, name: &str, nm: &Arc<dyn NoiseMapGeneratorBase>) {
+        if matches!(*self, MapRef::Named(ref other) if other == name) {
+            *self = MapRef::Map(Box::new(nm.clone()));
+        }
+    }
+
+    pub(super) fn unknown(&self, names: &mut Vec<String>) {
+        if let MapRef::Named(ref name) = *self {
+            names.push(name.clone());
+        }
+    }
+}
+
+impl From<Box<dyn NoiseMapGeneratorBase>> for MapRef {
+    fn from(nm: Box<dyn NoiseMapGeneratorBase>) -> MapRef {
+        MapRef::Map(nm)
+    }
+}
+
+impl<NM: NoiseMapGeneratorBase + 'static> From<Box<NM>> for MapRef {
+    fn from(nm: Box<NM>) -> MapRef {
+        MapRef::Map(nm)
+    }
+}
+
+impl From<&str> for MapRef {
+    fn from(name: &str) -> MapRef {
+        MapRef::Named(name.to_string())
+    }
+}
+
+impl From<String> for MapRef {
+    fn from(name: String) -> MapRef {
+        MapRef::Named(name)
+    }
+}
+
+enum Kind {
+    Threshold {
+        nm: MapRef,
+        constraint: ConstraintType,
+        blend: f64,
+    },
+
+    Difference {
+        a: MapRef,
+        b: MapRef,
+        constraint: ConstraintType,
+        blend: f64,
+    },
+
+    Chance {
+        probability: f64,
+        seed: Option<Seed>,
+    },
+
+    #[cfg(feature = "rhai")]
+    Script(Script),
+
+    Any(Vec<Constraint>),
+    All(Vec<Constraint>),
+    Not(Box<Constraint>),
 }
 
+/// Constructs a constraint from a boxed noise map and a comparison:
+///
+/// ```
+/// # #[macro_use] extern crate worldgen;
+/// # use worldgen::noise::perlin::PerlinNoise;
+/// # use worldgen::noisemap::NoiseMap;
+/// # use worldgen::world::tile::{Constraint, ConstraintType};
+/// # fn main() {
+/// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()));
+/// let below = constraint!(nm.clone(), < -0.1);
+/// let above = constraint!(nm.clone(), > 0.8);
+/// let at_most = constraint!(nm.clone(), <= 0.0);
+/// let at_least = constraint!(nm.clone(), >= 0.5);
+/// let near = constraint!(nm.clone(), == 0.3, 0.05);
+/// let between = constraint!(nm, 0.2 .. 0.45);
+/// # }
+/// ```
 #[macro_export]
 macro_rules! constraint {
     ($nm:expr, < $v:expr) => {
@@ -47,35 +237,542 @@ macro_rules! constraint {
     ($nm:expr, > $v:expr) => {
         Constraint::new($nm, ConstraintType::GT($v))
     };
+    ($nm:expr, <= $v:expr) => {
+        Constraint::new($nm, ConstraintType::LE($v))
+    };
+    ($nm:expr, >= $v:expr) => {
+        Constraint::new($nm, ConstraintType::GE($v))
+    };
+    ($nm:expr, == $v:expr, $epsilon:expr) => {
+        Constraint::new($nm, ConstraintType::EqApprox($v, $epsilon))
+    };
+    ($nm:expr, $range:expr) => {{
+        let range: ::std::ops::Range<f64> = $range;
+        Constraint::new($nm, ConstraintType::Between(range.start, range.end))
+    }};
+}
+
+/// Constructs a constraint that is satisfied when any of the given
+/// constraints are.
+#[macro_export]
+macro_rules! any_of {
+    ($($constraint:expr),* $(,)?) => {
+        $crate::world::tile::Constraint::any(vec![$($constraint),*])
+    };
+}
+
+/// Constructs a constraint that is satisfied when all of the given
+/// constraints are.
+#[macro_export]
+macro_rules! all_of {
+    ($($constraint:expr),* $(,)?) => {
+        $crate::world::tile::Constraint::all(vec![$($constraint),*])
+    };
 }
 
 impl Constraint {
-    pub fn new(nm: Box<dyn NoiseMapGeneratorBase>, constraint: ConstraintType) -> Constraint {
-        Constraint { nm, constraint }
+    pub fn new<NM: Into<MapRef>>(nm: NM, constraint: ConstraintType) -> Constraint {
+        Constraint {
+            kind: Kind::Threshold {
+                nm: nm.into(),
+                constraint,
+                blend: 0.0,
+            },
+        }
+    }
+
+    /// Constructs a constraint on the difference between the values of two
+    /// noise maps (the value of `a` minus the value of `b`).
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # let a = Box::new(NoiseMap::new(PerlinNoise::new()).set(Seed::of("a")));
+    /// # let b = Box::new(NoiseMap::new(PerlinNoise::new()).set(Seed::of("b")));
+    /// // a - b < 0.1
+    /// let close = Constraint::difference(a, b, ConstraintType::LT(0.1));
+    /// ```
+    pub fn difference<A: Into<MapRef>, B: Into<MapRef>>(
+        a: A,
+        b: B,
+        constraint: ConstraintType,
+    ) -> Constraint {
+        Constraint {
+            kind: Kind::Difference {
+                a: a.into(),
+                b: b.into(),
+                constraint,
+                blend: 0.0,
+            },
+        }
     }
 
-    /// Returns true if the given value would satisfy this constraint.
-    pub fn satisfied_by(
-        &self,
-        x: i64,
-        y: i64,
-        size: Size,
-        chunk_x: i64,
-        chunk_y: i64,
-        nms: &mut HashMap<u64, Vec<Vec<f64>>>,
-    ) -> bool {
-        let id = self.nm.id();
+    /// Constructs a constraint comparing the values of two noise maps.
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # let moisture = Box::new(NoiseMap::new(PerlinNoise::new()).set(Seed::of("moisture")));
+    /// # let temperature = Box::new(NoiseMap::new(PerlinNoise::new()).set(Seed::of("temperature")));
+    /// // moisture > temperature
+    /// let humid = Constraint::compare(moisture, ConstraintType::GT, temperature);
+    /// ```
+    pub fn compare<A: Into<MapRef>, B: Into<MapRef>>(
+        a: A,
+        comparison: fn(f64) -> ConstraintType,
+        b: B,
+    ) -> Constraint {
+        Constraint::difference(a, b, comparison(0.0))
+    }
 
-        let nm = nms
-            .entry(id)
-            .or_insert_with(|| self.nm.generate_sized_chunk(size, chunk_x, chunk_y));
+    /// Constructs a constraint that is satisfied at a random fraction of
+    /// positions, given by the probability (between 0 and 1).
+    ///
+    /// Which positions are chosen depends only on the world's seed and the
+    /// position, so the same world is always generated. Chance constraints
+    /// with the same probability choose the same positions; use
+    /// `chance_with_seed` for independent choices.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::NoiseMap;
+    /// # use worldgen::world::Tile;
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()));
+    /// // Boulders on 2% of the mountains
+    /// let boulder = Tile::new('o')
+    ///     .when(constraint!(nm, > 0.8))
+    ///     .when(Constraint::chance(0.02));
+    /// # }
+    /// ```
+    pub fn chance(probability: f64) -> Constraint {
+        Constraint {
+            kind: Kind::Chance {
+                probability,
+                seed: None,
+            },
+        }
+    }
 
-        let x = (x as i64).rem_euclid(size.w);
-        let y = (y as i64).rem_euclid(size.h);
+    /// Constructs a chance constraint that chooses positions using the
+    /// given seed as well as the world's seed.
+    pub fn chance_with_seed(probability: f64, seed: Seed) -> Constraint {
+        Constraint {
+            kind: Kind::Chance {
+                probability,
+                seed: Some(seed),
+            },
+        }
+    }
+
+    /// Constructs a constraint from a rhai script, which is satisfied where
+    /// the script evaluates to `true`. Requires the `rhai` feature.
+    ///
+    /// The script can read the position as the integers `x` and `y`, and
+    /// the value of each of the given noise maps at the position through a
+    /// variable of the same name. The names refer to noise maps registered
+    /// on the world with `World::with_map`. A script that fails while
+    /// running, or doesn't evaluate to a boolean, is not satisfied.
+    ///
+    /// Returns an error if the script doesn't compile.
+    ///
+    /// ```
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Seed, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::Constraint;
+    /// # let noise = PerlinNoise::new();
+    /// # let elevation = NoiseMap::new(noise).set(Seed::of("elevation")).set(Step::of(0.05, 0.05));
+    /// # let moisture = NoiseMap::new(noise).set(Seed::of("moisture")).set(Step::of(0.05, 0.05));
+    /// let swamp = Constraint::script(
+    ///     "elevation < 0.1 && moisture > elevation + 0.2",
+    ///     &["elevation", "moisture"],
+    /// ).unwrap();
+    ///
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .with_map("elevation", Box::new(elevation))
+    ///     .with_map("moisture", Box::new(moisture))
+    ///     .add(Tile::new('%').when(swamp))
+    ///     .add(Tile::new(','));
+    ///
+    /// assert!(world.validate().is_ok());
+    /// ```
+    #[cfg(feature = "rhai")]
+    pub fn script(source: &str, maps: &[&str]) -> Result<Constraint, ScriptError> {
+        Constraint::script_with_maps(
+            source,
+            maps.iter()
+                .map(|&name| (name, MapRef::from(name)))
+                .collect(),
+        )
+    }
+
+    /// Constructs a constraint from a rhai script, as `script` does, with
+    /// each variable given its own noise map (or name of a registered noise
+    /// map). Requires the `rhai` feature.
+    #[cfg(feature = "rhai")]
+    pub fn script_with_maps(
+        source: &str,
+        maps: Vec<(&str, MapRef)>,
+    ) -> Result<Constraint, ScriptError> {
+        let maps = maps
+            .into_iter()
+            .map(|(name, nm)| (name.to_string(), nm))
+            .collect();
+
+        Ok(Constraint {
+            kind: Kind::Script(Script::new(source, maps)?),
+        })
+    }
+
+    /// Constructs a constraint that is satisfied when any of the given
+    /// constraints are. An empty list is never satisfied.
+    pub fn any(constraints: Vec<Constraint>) -> Constraint {
+        Constraint {
+            kind: Kind::Any(constraints),
+        }
+    }
 
-        match self.constraint {
-            ConstraintType::LT(threshold) => nm[y as usize][x as usize] < threshold,
-            ConstraintType::GT(threshold) => nm[y as usize][x as usize] > threshold,
+    /// Constructs a constraint that is satisfied when all of the given
+    /// constraints are. An empty list is always satisfied.
+    pub fn all(constraints: Vec<Constraint>) -> Constraint {
+        Constraint {
+            kind: Kind::All(constraints),
         }
     }
+
+    /// Combines this constraint with another, satisfied when either is.
+    pub fn or(self, other: Constraint) -> Constraint {
+        Constraint::any(vec![self, other])
+    }
+
+    /// Combines this constraint with another, satisfied when both are.
+    pub fn and(self, other: Constraint) -> Constraint {
+        Constraint::all(vec![self, other])
+    }
+
+    /// Softens the thresholds of this constraint (and the constraints it
+    /// combines) into a border of the given width, so the edges between
+    /// tiles are no longer sharp lines.
+    ///
+    /// Before comparing, each position's noise value is moved by a random
+    /// offset of up to the width either way, which depends only on the
+    /// world's seed and the position. Within the width of a threshold, the
+    /// tile is chosen with a probability falling from one side of the
+    /// border to the other, and the next matching tile is chosen otherwise.
+    /// Every blended constraint uses the same offset at a position, so
+    /// neighbouring bands of the same noise map stay in order.
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.05, 0.05)));
+    /// // Forest fades into grassland rather than stopping at a line
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('T').when(constraint!(nm, > 0.2).blend(0.1)))
+    ///     .add(Tile::new(','));
+    /// # }
+    /// ```
+    pub fn blend(self, width: f64) -> Constraint {
+        let width = width.max(0.0);
+
+        let kind = match self.kind {
+            Kind::Threshold { nm, constraint, .. } => Kind::Threshold {
+                nm,
+                constraint,
+                blend: width,
+            },
+
+            Kind::Difference {
+                a, b, constraint, ..
+            } => Kind::Difference {
+                a,
+                b,
+                constraint,
+                blend: width,
+            },
+
+            Kind::Any(constraints) => Kind::Any(
+                constraints
+                    .into_iter()
+                    .map(|constraint| constraint.blend(width))
+                    .collect(),
+            ),
+
+            Kind::All(constraints) => Kind::All(
+                constraints
+                    .into_iter()
+                    .map(|constraint| constraint.blend(width))
+                    .collect(),
+            ),
+
+            Kind::Not(constraint) => Kind::Not(Box::new(constraint.blend(width))),
+
+            kind => kind,
+        };
+
+        Constraint { kind }
+    }
+
+    /// Inverts this constraint.
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Constraint {
+        Constraint {
+            kind: Kind::Not(Box::new(self)),
+        }
+    }
+
+    /// Returns false if the constraint can never be satisfied, judging by
+    /// the bounds of the values its noise maps can produce.
+    pub fn satisfiable(&self) -> bool {
+        match self.kind {
+            Kind::Threshold {
+                ref nm,
+                constraint,
+                blend,
+            } => {
+                let (low, high) = nm.bounds();
+                constraint.satisfiable((low - blend, high + blend))
+            }
+
+            Kind::Difference {
+                ref a,
+                ref b,
+                constraint,
+                blend,
+            } => {
+                let (low_a, high_a) = a.bounds();
+                let (low_b, high_b) = b.bounds();
+
+                constraint.satisfiable((low_a - high_b - blend, high_a - low_b + blend))
+            }
+
+            Kind::Chance { probability, .. } => probability > 0.0,
+
+            #[cfg(feature = "rhai")]
+            Kind::Script(_) => true,
+
+            Kind::Any(ref constraints) => constraints.iter().any(Constraint::satisfiable),
+            Kind::All(ref constraints) => constraints.iter().all(Constraint::satisfiable),
+
+            // Only the bounds of the inner constraint are known, not whether
+            // it is always satisfied.
+            Kind::Not(_) => true,
+        }
+    }
+
+    /// Returns true if the given position would satisfy this constraint.
+    pub fn satisfied_by(&self, x: i64, y: i64, context: &ChunkContext) -> bool {
+        match self.kind {
+            Kind::Threshold {
+                ref nm,
+                constraint,
+                blend,
+            } => constraint.matches(value(nm, x, y, context) + offset(blend, x, y, context)),
+
+            Kind::Difference {
+                ref a,
+                ref b,
+                constraint,
+                blend,
+            } => constraint.matches(
+                value(a, x, y, context) - value(b, x, y, context) + offset(blend, x, y, context),
+            ),
+
+            Kind::Chance { probability, seed } => {
+                let salt = seed.map_or(probability.to_bits(), |seed| seed.value);
+                let seed = Seed::of_value(hash(context.seed.value, salt));
+
+                CellRng::new(seed, x, y, "chance").chance(probability)
+            }
+
+            #[cfg(feature = "rhai")]
+            Kind::Script(ref script) => script.satisfied_by(x, y, context),
+
+            Kind::Any(ref constraints) => constraints
+                .iter()
+                .any(|constraint| constraint.satisfied_by(x, y, context)),
+
+            Kind::All(ref constraints) => constraints
+                .iter()
+                .all(|constraint| constraint.satisfied_by(x, y, context)),
+
+            Kind::Not(ref constraint) => !constraint.satisfied_by(x, y, context),
+        }
+    }
+
+    /// Returns what the constraint made of the given position, evaluating
+    /// it the same way as `satisfied_by`. See `World::trace`.
+    pub(crate) fn trace(&self, x: i64, y: i64, context: &ChunkContext) -> ConstraintTrace {
+        let (kind, values, inner) = match self.kind {
+            Kind::Threshold {
+                ref nm, constraint, ..
+            } => (
+                format!("{:?}", constraint),
+                sampled(&[nm], x, y, context),
+                Vec::new(),
+            ),
+
+            Kind::Difference {
+                ref a,
+                ref b,
+                constraint,
+                ..
+            } => (
+                format!("difference {:?}", constraint),
+                sampled(&[a, b], x, y, context),
+                Vec::new(),
+            ),
+
+            Kind::Chance { probability, .. } => {
+                (format!("chance {}", probability), Vec::new(), Vec::new())
+            }
+
+            #[cfg(feature = "rhai")]
+            Kind::Script(_) => ("script".to_string(), Vec::new(), Vec::new()),
+
+            // Like `satisfied_by`, stops at the first constraint that
+            // decides the result.
+            Kind::Any(ref constraints) => (
+                "any".to_string(),
+                Vec::new(),
+                trace_until(constraints, true, x, y, context),
+            ),
+
+            Kind::All(ref constraints) => (
+                "all".to_string(),
+                Vec::new(),
+                trace_until(constraints, false, x, y, context),
+            ),
+
+            Kind::Not(ref constraint) => (
+                "not".to_string(),
+                Vec::new(),
+                vec![constraint.trace(x, y, context)],
+            ),
+        };
+
+        ConstraintTrace {
+            kind,
+            values,
+            satisfied: self.satisfied_by(x, y, context),
+            inner,
+        }
+    }
+
+    /// Replaces references to a named noise map with the shared map.
+    pub(crate) fn bind(&mut self, name: &str, nm: &Arc<dyn NoiseMapGeneratorBase>) {
+        match self.kind {
+            Kind::Threshold {
+                nm: ref mut map, ..
+            } => map.bind(name, nm),
+
+            Kind::Difference {
+                ref mut a,
+                ref mut b,
+                ..
+            } => {
+                a.bind(name, nm);
+                b.bind(name, nm);
+            }
+
+            Kind::Chance { .. } => {}
+
+            #[cfg(feature = "rhai")]
+            Kind::Script(ref mut script) => script.bind(name, nm),
+
+            Kind::Any(ref mut constraints) | Kind::All(ref mut constraints) => {
+                for constraint in constraints.iter_mut() {
+                    constraint.bind(name, nm);
+                }
+            }
+
+            Kind::Not(ref mut constraint) => constraint.bind(name, nm),
+        }
+    }
+
+    /// Adds the names of the noise maps the constraint refers to that have
+    /// not been registered.
+    pub(crate) fn unknown_maps(&self, names: &mut Vec<String>) {
+        match self.kind {
+            Kind::Threshold { ref nm, .. } => nm.unknown(names),
+
+            Kind::Difference { ref a, ref b, .. } => {
+                a.unknown(names);
+                b.unknown(names);
+            }
+
+            Kind::Chance { .. } => {}
+
+            #[cfg(feature = "rhai")]
+            Kind::Script(ref script) => script.unknown_maps(names),
+
+            Kind::Any(ref constraints) | Kind::All(ref constraints) => {
+                for constraint in constraints.iter() {
+                    constraint.unknown_maps(names);
+                }
+            }
+
+            Kind::Not(ref constraint) => constraint.unknown_maps(names),
+        }
+    }
+}
+
+/// Returns the random offset of up to the blend width either way that
+/// blended constraints move a position's values by.
+fn offset(blend: f64, x: i64, y: i64, context: &ChunkContext) -> f64 {
+    if blend > 0.0 {
+        (context.rng(x, y, "blend").next_f64() * 2.0 - 1.0) * blend
+    } else {
+        0.0
+    }
+}
+
+/// Traces constraints in order, up to and including the first one whose
+/// result is the given one.
+fn trace_until(
+    constraints: &[Constraint],
+    result: bool,
+    x: i64,
+    y: i64,
+    context: &ChunkContext,
+) -> Vec<ConstraintTrace> {
+    let mut traces = Vec::new();
+
+    for constraint in constraints {
+        let trace = constraint.trace(x, y, context);
+        let done = trace.satisfied == result;
+
+        traces.push(trace);
+        if done {
+            break;
+        }
+    }
+
+    traces
+}
+
+/// Returns the ids and values at a position of the given noise maps,
+/// leaving out names that have not been registered.
+fn sampled(nms: &[&MapRef], x: i64, y: i64, context: &ChunkContext) -> Vec<(u64, f64)> {
+    nms.iter()
+        .filter_map(|nm| nm.get())
+        .map(|nm| (nm.id(), context.value(nm, x, y)))
+        .collect()
+}
+
+/// Returns the value of a noise map at a position, which is `NaN` (and so
+/// satisfies no constraint type) for names that have not been registered.
+fn value(nm: &MapRef, x: i64, y: i64, context: &ChunkContext) -> f64 {
+    nm.get().map_or(f64::NAN, |nm| context.value(nm, x, y))
 }
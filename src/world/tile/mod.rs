@@ -16,18 +16,29 @@
 //  limitations under the License.
 //////////////////////////////////////////////////////////////////////////////
 
-use std::collections::HashMap;
+pub use self::constraint::{Constraint, ConstraintType, MapRef};
+#[cfg(feature = "rhai")]
+pub use self::script::ScriptError;
+use std::sync::Arc;
 
-pub use self::constraint::{Constraint, ConstraintType};
-use crate::world::Size;
+use crate::noisemap::NoiseMapGeneratorBase;
+use crate::world::{ChunkContext, ConstraintTrace};
 
 #[macro_use]
 mod constraint;
+#[cfg(feature = "rhai")]
+mod script;
+
+/// A predicate on the tile of a neighbouring position.
+type NeighbourPredicate<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
 
 /// Objects to generate in the world based on given constraints
 pub struct Tile<T> {
     value: T,
     constraints: Vec<Constraint>,
+    neighbours: Vec<NeighbourPredicate<T>>,
+    weight: Option<f64>,
+    tags: Vec<String>,
 }
 
 impl<T: Clone> Tile<T> {
@@ -36,6 +47,9 @@ impl<T: Clone> Tile<T> {
         Tile {
             value,
             constraints: Vec::new(),
+            neighbours: Vec::new(),
+            weight: None,
+            tags: Vec::new(),
         }
     }
 
@@ -51,24 +65,158 @@ impl<T: Clone> Tile<T> {
         }
     }
 
+    /// Requires one of the eight tiles around the tile to satisfy the given
+    /// predicate.
+    ///
+    /// Neighbour constraints are checked in a second phase: the world first
+    /// chooses tiles ignoring every tile with neighbour constraints, and
+    /// then chooses again with the neighbours taken from the first phase.
+    /// The tiles around each chunk are included, so neighbour constraints
+    /// work across chunk borders.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = Box::new(NoiseMap::new(PerlinNoise::new()).set(Step::of(0.1, 0.1)));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .add(Tile::new('~').when(constraint!(nm, < 0.0)))
+    ///     .add(Tile::new('.').next_to(|&tile| tile == '~'))
+    ///     .add(Tile::new(','));
+    ///
+    /// let tiles = world.generate(0, 0).unwrap();
+    ///
+    /// for y in 1..15 {
+    ///     for x in 1..15 {
+    ///         if tiles[y][x] == '.' {
+    ///             assert!((y - 1..=y + 1).any(|ny| tiles[ny][x - 1..=x + 1].contains(&'~')));
+    ///         }
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn next_to<F: Fn(&T) -> bool + Send + Sync + 'static>(self, predicate: F) -> Tile<T> {
+        let mut new = self;
+        new.neighbours.push(Box::new(predicate));
+        new
+    }
+
+    /// Gives the tile a weight, for picking randomly between tiles that
+    /// match the same position. See the `world` module documentation.
+    pub fn weight(self, weight: f64) -> Tile<T> {
+        Tile {
+            weight: Some(weight),
+            ..self
+        }
+    }
+
+    /// Tags the tile, for asking questions about generated chunks (is this
+    /// position walkable?) without a table keyed by tile value. See
+    /// `World::generate_tagged`.
+    pub fn tag(self, tag: &str) -> Tile<T> {
+        let mut new = self;
+        new.tags.push(tag.to_string());
+        new
+    }
+
+    /// Returns the tags of the tile, in the order they were added.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns true if the tile has the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|other| other == tag)
+    }
+
+    /// Returns the weight of the tile, if it has one.
+    pub fn get_weight(&self) -> Option<f64> {
+        self.weight
+    }
+
     /// Returns the value this tile is represented by.
     pub fn value(&self) -> T {
         self.value.clone()
     }
 
-    /// Returns true if the given value would satisfy all of this tile's
+    /// Returns true if the tile has no constraints, so it matches every
+    /// position.
+    pub fn is_fallback(&self) -> bool {
+        self.constraints.is_empty() && self.neighbours.is_empty()
+    }
+
+    /// Returns true if the tile has any neighbour constraints.
+    pub fn has_neighbour_constraints(&self) -> bool {
+        !self.neighbours.is_empty()
+    }
+
+    /// Returns false if the tile's constraints can never all be satisfied.
+    pub fn satisfiable(&self) -> bool {
+        self.constraints.iter().all(Constraint::satisfiable)
+    }
+
+    /// Returns true if the given position would satisfy all of this tile's
     /// constraints.
-    pub fn satisfied_by(
-        &self,
-        x: i64,
-        y: i64,
-        size: Size,
-        chunk_x: i64,
-        chunk_y: i64,
-        nms: &mut HashMap<u64, Vec<Vec<f64>>>,
-    ) -> bool {
+    pub fn satisfied_by(&self, x: i64, y: i64, context: &ChunkContext) -> bool {
         self.constraints
             .iter()
-            .all(|constraint| constraint.satisfied_by(x, y, size, chunk_x, chunk_y, nms))
+            .all(|constraint| constraint.satisfied_by(x, y, context))
+    }
+
+    /// Returns what each of the tile's constraints made of the given
+    /// position, up to the first one that isn't satisfied, as
+    /// `satisfied_by` evaluates them.
+    pub(crate) fn trace(&self, x: i64, y: i64, context: &ChunkContext) -> Vec<ConstraintTrace> {
+        let mut traces = Vec::new();
+
+        for constraint in self.constraints.iter() {
+            let trace = constraint.trace(x, y, context);
+            let satisfied = trace.satisfied;
+
+            traces.push(trace);
+            if !satisfied {
+                break;
+            }
+        }
+
+        traces
+    }
+
+    /// Returns true if the given neighbouring tiles would satisfy all of
+    /// this tile's neighbour constraints. Neighbours without a tile satisfy
+    /// nothing.
+    pub fn satisfied_by_neighbours(&self, neighbours: &[Option<&Tile<T>>]) -> bool {
+        self.neighbours.iter().all(|predicate| {
+            neighbours
+                .iter()
+                .flatten()
+                .any(|neighbour| predicate(&neighbour.value))
+        })
+    }
+
+    /// Replaces references to a named noise map in the tile's constraints
+    /// with the shared map.
+    pub(crate) fn bind_map(&mut self, name: &str, nm: &Arc<dyn NoiseMapGeneratorBase>) {
+        for constraint in self.constraints.iter_mut() {
+            constraint.bind(name, nm);
+        }
+    }
+
+    /// Returns the names of the noise maps the tile's constraints refer to
+    /// that have not been registered.
+    pub(crate) fn unknown_maps(&self) -> Vec<String> {
+        let mut names = Vec::new();
+
+        for constraint in self.constraints.iter() {
+            constraint.unknown_maps(&mut names);
+        }
+
+        names
     }
 }
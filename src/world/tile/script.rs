@@ -0,0 +1,100 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/tile/script.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use rhai::{Engine, ParseError, Scope, AST};
+
+use super::MapRef;
+use crate::noisemap::NoiseMapGeneratorBase;
+use crate::world::ChunkContext;
+
+/// The most operations a script may run for a single position, so a
+/// script that never finishes can't stall generation.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// The error returned when a constraint script doesn't compile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError(Box<ParseError>);
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid constraint script: {}", self.0)
+    }
+}
+
+impl Error for ScriptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+/// A compiled constraint script, and the noise maps whose values it reads.
+pub(super) struct Script {
+    engine: Arc<Engine>,
+    ast: AST,
+    maps: Vec<(String, MapRef)>,
+}
+
+impl Script {
+    pub(super) fn new(source: &str, maps: Vec<(String, MapRef)>) -> Result<Script, ScriptError> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let ast = engine
+            .compile(source)
+            .map_err(|err| ScriptError(Box::new(err)))?;
+
+        Ok(Script {
+            engine: Arc::new(engine),
+            ast,
+            maps,
+        })
+    }
+
+    /// Runs the script for a position. Scripts that fail, or don't give a
+    /// boolean, are not satisfied.
+    pub(super) fn satisfied_by(&self, x: i64, y: i64, context: &ChunkContext) -> bool {
+        let mut scope = Scope::new();
+        scope.push_constant("x", x);
+        scope.push_constant("y", y);
+
+        for (name, nm) in self.maps.iter() {
+            let value = nm.get().map_or(f64::NAN, |nm| context.value(nm, x, y));
+            scope.push_constant(name.as_str(), value);
+        }
+
+        self.engine
+            .eval_ast_with_scope::<bool>(&mut scope, &self.ast)
+            .unwrap_or(false)
+    }
+
+    pub(super) fn bind(&mut self, name: &str, nm: &Arc<dyn NoiseMapGeneratorBase>) {
+        for (_, map) in self.maps.iter_mut() {
+            map.bind(name, nm);
+        }
+    }
+
+    pub(super) fn unknown_maps(&self, names: &mut Vec<String>) {
+        for (_, map) in self.maps.iter() {
+            map.unknown(names);
+        }
+    }
+}
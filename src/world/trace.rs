@@ -0,0 +1,189 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/world/trace.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+use std::ptr;
+
+use super::{GenerateError, Tile, World};
+
+/// What a single constraint made of a position. See `World::trace`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintTrace {
+    /// What kind of constraint it is, such as `LT(0.3)`, `difference
+    /// GT(0.1)`, `chance 0.5`, `any`, `all` or `not`.
+    pub kind: String,
+
+    /// Pairs of noise map id and the noise map's value at the position,
+    /// for the noise maps the constraint compares directly. Blended
+    /// constraints are compared against these values moved by their random
+    /// offset.
+    pub values: Vec<(u64, f64)>,
+
+    /// Whether the position satisfied the constraint.
+    pub satisfied: bool,
+
+    /// The constraints inside an `any`, `all` or `not` constraint, up to
+    /// the one that decided the result.
+    pub inner: Vec<ConstraintTrace>,
+}
+
+/// What one of the world's tiles made of a position. See `World::trace`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileTrace<T> {
+    /// The index of the tile, in the order tiles were added.
+    pub index: usize,
+
+    /// The value of the tile.
+    pub value: T,
+
+    /// Whether the tiles around the position satisfied the tile's neighbour
+    /// constraints, for tiles that have them.
+    pub neighbours: Option<bool>,
+
+    /// The tile's constraints, in order, up to the first one that isn't
+    /// satisfied. Constraints aren't evaluated if the neighbour constraints
+    /// already failed.
+    pub constraints: Vec<ConstraintTrace>,
+
+    /// Whether the tile matched the position.
+    pub matched: bool,
+}
+
+/// A record of how the tile at a position was chosen. See `World::trace`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace<T> {
+    pub x: i64,
+    pub y: i64,
+
+    /// The tiles that were tried, in order, up to the first that matched,
+    /// followed by the rest of its group if it is weighted.
+    pub tiles: Vec<TileTrace<T>>,
+
+    /// The index of the tile chosen by the constraints, if any matched.
+    pub chosen: Option<usize>,
+
+    /// The tile at the position, as `tile_at` returns it, after smoothing
+    /// and overrides.
+    pub tile: T,
+}
+
+impl<T: Clone + Send + Sync> World<T> {
+    /// Records how the tile at a single world position is chosen: which
+    /// constraints of which tiles were evaluated, the noise values they
+    /// sampled, and which tile won, to find out why thresholds interact
+    /// the way they do.
+    ///
+    /// Tracing is opt-in, costing nothing unless it is asked for: the
+    /// choice is made again for the position, exactly as `generate` makes
+    /// it. The final tile can still differ from the chosen one when the
+    /// world is smoothed, or has overrides, and post-processing passes are
+    /// not run, as with `tile_at`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[macro_use] extern crate worldgen;
+    /// # use worldgen::noise::perlin::PerlinNoise;
+    /// # use worldgen::noisemap::{NoiseMap, NoiseMapGenerator, Step};
+    /// # use worldgen::world::{Size, Tile, World};
+    /// # use worldgen::world::tile::{Constraint, ConstraintType};
+    /// # fn main() {
+    /// # let nm = NoiseMap::new(PerlinNoise::new()).set(Step::of(0.1, 0.1));
+    /// let world = World::new()
+    ///     .set(Size::of(16, 16))
+    ///     .with_map("elevation", Box::new(nm))
+    ///     .add(Tile::new('~').when(constraint!("elevation", < 0.0)))
+    ///     .add(Tile::new(','));
+    ///
+    /// let trace = world.trace(5, 3).unwrap();
+    /// assert_eq!(trace.tile, world.tile_at(5, 3).unwrap());
+    ///
+    /// let water = &trace.tiles[0];
+    /// assert_eq!(water.constraints[0].kind, "LT(0.0)");
+    ///
+    /// if water.matched {
+    ///     assert_eq!(trace.chosen, Some(0));
+    ///     assert!(water.constraints[0].values[0].1 < 0.0);
+    /// } else {
+    ///     assert_eq!(trace.chosen, Some(1));
+    /// }
+    /// # }
+    /// ```
+    pub fn trace(&self, world_x: i64, world_y: i64) -> Result<Trace<T>, GenerateError> {
+        let context = self.point_context(world_x, world_y);
+
+        // The tiles around the position, as chosen in the first phase, for
+        // neighbour constraints.
+        let phased = self.tiles.iter().any(Tile::has_neighbour_constraints);
+        let neighbours: Option<Vec<_>> = phased.then(|| {
+            (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                .filter(|&offset| offset != (0, 0))
+                .map(|(dx, dy)| self.resolve(world_x + dx, world_y + dy, &context, None))
+                .collect()
+        });
+        let neighbours = neighbours.as_deref();
+
+        let mut tiles = Vec::new();
+        let mut group = false;
+
+        for (index, tile) in self.tiles.iter().enumerate() {
+            if group && tile.get_weight().is_none() {
+                break;
+            }
+
+            let fits = tile.has_neighbour_constraints().then(|| {
+                neighbours.is_some_and(|neighbours| tile.satisfied_by_neighbours(neighbours))
+            });
+
+            let constraints = if fits == Some(false) {
+                Vec::new()
+            } else {
+                tile.trace(world_x, world_y, &context)
+            };
+
+            let matched = fits != Some(false) && constraints.iter().all(|trace| trace.satisfied);
+
+            tiles.push(TileTrace {
+                index,
+                value: tile.value(),
+                neighbours: fits,
+                constraints,
+                matched,
+            });
+
+            if matched && !group {
+                match tile.get_weight() {
+                    Some(_) => group = true,
+                    None => break,
+                }
+            }
+        }
+
+        let chosen = self
+            .resolve(world_x, world_y, &context, neighbours)
+            .and_then(|chosen| self.tiles.iter().position(|tile| ptr::eq(tile, chosen)));
+
+        Ok(Trace {
+            x: world_x,
+            y: world_y,
+            tiles,
+            chosen,
+            tile: self.tile_at(world_x, world_y)?,
+        })
+    }
+}
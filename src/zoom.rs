@@ -0,0 +1,291 @@
+//////////////////////////////////////////////////////////////////////////////
+//  File: rust-worldgen/zoom.rs
+//////////////////////////////////////////////////////////////////////////////
+//  Copyright 2015 Samuel Sleight
+//
+//  Licensed under the Apache License, Version 2.0 (the "License");
+//  you may not use this file except in compliance with the License.
+//  You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//  Unless required by applicable law or agreed to in writing, software
+//  distributed under the License is distributed on an "AS IS" BASIS,
+//  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//  See the License for the specific language governing permissions and
+//  limitations under the License.
+//////////////////////////////////////////////////////////////////////////////
+
+//! Tile maps refined from a coarse map by repeated zooming.
+//!
+//! Rather than choosing tiles from noise values, a `ZoomLayers` starts from
+//! a very coarse map of tiles (say, one cell per continent), and builds up
+//! the final map through a stack of layers. Each zoom layer doubles the
+//! resolution, choosing the tiles between the old cells randomly from their
+//! neighbours, which roughens the edges of every area. Layers between the
+//! zooms change tiles, either cell by cell or by looking at the cells
+//! around them, to add islands, shores, or rarer biomes at the scale they
+//! belong to.
+//!
+//! Every random choice depends only on the seed, the layer and the
+//! position, so any area can be generated on its own and chunks fit
+//! together without seams:
+//!
+//! ```
+//! # use worldgen::noisemap::Seed;
+//! # use worldgen::world::Size;
+//! # use worldgen::zoom::ZoomLayers;
+//! let layers = ZoomLayers::new(|_, _, rng| if rng.chance(0.3) { '.' } else { '~' })
+//!     .set(Seed::of("continents"))
+//!     .zoom()
+//!     .zoom()
+//!     // Shores where land meets water
+//!     .map_neighbours(|&tile, neighbours, _| {
+//!         if tile == '.' && neighbours.contains(&'~') { ',' } else { tile }
+//!     })
+//!     .zoom()
+//!     .smooth_zoom();
+//!
+//! // Each cell of the base map covers 16x16 cells of the final map
+//! assert_eq!(layers.scale(), 16);
+//!
+//! let chunk = layers.generate_chunk(Size::of(32, 32), 1, 0);
+//! assert_eq!(chunk[5][7], layers.generate_chunk(Size::of(64, 32), 0, 0)[5][39]);
+//! ```
+
+use crate::noisemap::{Seed, Size};
+use crate::rng::{hash, CellRng};
+use crate::world::Chunk;
+
+type Base<T> = Box<dyn Fn(i64, i64, &mut CellRng) -> T + Send + Sync>;
+type Map<T> = Box<dyn Fn(&T, &mut CellRng) -> T + Send + Sync>;
+type MapNeighbours<T> = Box<dyn Fn(&T, &[T; 4], &mut CellRng) -> T + Send + Sync>;
+
+enum Layer<T> {
+    Zoom { smooth: bool },
+    Map(Map<T>),
+    MapNeighbours(MapNeighbours<T>),
+}
+
+/// A stack of layers refining a coarse tile map.
+///
+/// The defaults are:
+///
+/// ```text
+/// seed = 0
+/// ```
+pub struct ZoomLayers<T> {
+    seed: Seed,
+    base: Base<T>,
+    layers: Vec<Layer<T>>,
+}
+
+impl<T: Clone + PartialEq> ZoomLayers<T> {
+    /// Construct a stack of layers over a base map, given by a function of
+    /// each base cell's position and a stream of random values for it.
+    pub fn new<F>(base: F) -> ZoomLayers<T>
+    where
+        F: Fn(i64, i64, &mut CellRng) -> T + Send + Sync + 'static,
+    {
+        ZoomLayers {
+            seed: Seed::of_value(0),
+            base: Box::new(base),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Set the seed of every random choice.
+    pub fn set(self, seed: Seed) -> ZoomLayers<T> {
+        ZoomLayers { seed, ..self }
+    }
+
+    /// Add a layer doubling the resolution. Each cell between two of the
+    /// old cells takes the tile of one of them at random, and each cell
+    /// between four takes the tile of one of the four, so the edges of
+    /// areas become ragged.
+    pub fn zoom(self) -> ZoomLayers<T> {
+        self.push(Layer::Zoom { smooth: false })
+    }
+
+    /// Add a layer doubling the resolution, as `zoom` does, except that a
+    /// cell between four old cells takes the most common of their tiles
+    /// (choosing randomly between ties), for smoother edges.
+    pub fn smooth_zoom(self) -> ZoomLayers<T> {
+        self.push(Layer::Zoom { smooth: true })
+    }
+
+    /// Add a layer changing each cell's tile with the given function, which
+    /// is also given a stream of random values for the cell.
+    pub fn map<F>(self, map: F) -> ZoomLayers<T>
+    where
+        F: Fn(&T, &mut CellRng) -> T + Send + Sync + 'static,
+    {
+        self.push(Layer::Map(Box::new(map)))
+    }
+
+    /// Add a layer changing each cell's tile with the given function, which
+    /// is also given the tiles of the four cells beside it (in the order
+    /// north, east, south, west) and a stream of random values for the
+    /// cell.
+    pub fn map_neighbours<F>(self, map: F) -> ZoomLayers<T>
+    where
+        F: Fn(&T, &[T; 4], &mut CellRng) -> T + Send + Sync + 'static,
+    {
+        self.push(Layer::MapNeighbours(Box::new(map)))
+    }
+
+    fn push(self, layer: Layer<T>) -> ZoomLayers<T> {
+        let mut new = self;
+        new.layers.push(layer);
+        new
+    }
+
+    /// Returns the number of cells of the final map across each cell of
+    /// the base map.
+    pub fn scale(&self) -> i64 {
+        let zooms = self
+            .layers
+            .iter()
+            .filter(|layer| matches!(layer, Layer::Zoom { .. }))
+            .count();
+
+        1 << zooms
+    }
+
+    /// Generates the chunk of the final map at the given chunk coordinates.
+    pub fn generate_chunk(&self, size: Size, x: i64, y: i64) -> Chunk<T> {
+        self.generate_area(x * size.w, y * size.h, size.w, size.h)
+    }
+
+    /// Generates an area of the final map, given its top left position and
+    /// its size.
+    pub fn generate_area(&self, x: i64, y: i64, w: i64, h: i64) -> Chunk<T> {
+        self.generate_layer(self.layers.len(), x, y, w.max(0), h.max(0))
+    }
+
+    /// Returns the tile of the final map at the given position.
+    pub fn tile_at(&self, x: i64, y: i64) -> T {
+        self.generate_area(x, y, 1, 1)[0][0].clone()
+    }
+
+    /// Generates an area of the map as it is after the given number of
+    /// layers.
+    fn generate_layer(&self, count: usize, x0: i64, y0: i64, w: i64, h: i64) -> Chunk<T> {
+        if count == 0 {
+            let seed = self.layer_seed(0);
+
+            return (y0..y0 + h)
+                .map(|y| {
+                    (x0..x0 + w)
+                        .map(|x| (self.base)(x, y, &mut CellRng::new(seed, x, y, "zoom")))
+                        .collect()
+                })
+                .collect();
+        }
+
+        let seed = self.layer_seed(count);
+        let rng = |x, y| CellRng::new(seed, x, y, "zoom");
+
+        match self.layers[count - 1] {
+            Layer::Zoom { smooth } => {
+                // The parent cells covering the area, and one more to the
+                // right and below for the cells between them.
+                let (px0, py0) = (x0.div_euclid(2), y0.div_euclid(2));
+                let (px1, py1) = ((x0 + w - 1).div_euclid(2), (y0 + h - 1).div_euclid(2));
+                let parent = self.generate_layer(count - 1, px0, py0, px1 - px0 + 2, py1 - py0 + 2);
+
+                (y0..y0 + h)
+                    .map(|y| {
+                        (x0..x0 + w)
+                            .map(|x| {
+                                let (i, j) = (
+                                    (x.div_euclid(2) - px0) as usize,
+                                    (y.div_euclid(2) - py0) as usize,
+                                );
+                                let corners = [
+                                    &parent[j][i],
+                                    &parent[j][i + 1],
+                                    &parent[j + 1][i],
+                                    &parent[j + 1][i + 1],
+                                ];
+
+                                let mut rng = rng(x, y);
+                                match (x.rem_euclid(2), y.rem_euclid(2)) {
+                                    (0, 0) => corners[0].clone(),
+                                    (1, 0) => choose(&[corners[0], corners[1]], &mut rng),
+                                    (0, _) => choose(&[corners[0], corners[2]], &mut rng),
+                                    _ if smooth => most_common(&corners, &mut rng),
+                                    _ => choose(&corners, &mut rng),
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+
+            Layer::Map(ref map) => {
+                let parent = self.generate_layer(count - 1, x0, y0, w, h);
+
+                parent
+                    .iter()
+                    .zip(y0..)
+                    .map(|(row, y)| {
+                        row.iter()
+                            .zip(x0..)
+                            .map(|(tile, x)| map(tile, &mut rng(x, y)))
+                            .collect()
+                    })
+                    .collect()
+            }
+
+            Layer::MapNeighbours(ref map) => {
+                let parent = self.generate_layer(count - 1, x0 - 1, y0 - 1, w + 2, h + 2);
+
+                (0..h as usize)
+                    .map(|j| {
+                        (0..w as usize)
+                            .map(|i| {
+                                let (pi, pj) = (i + 1, j + 1);
+                                let neighbours = [
+                                    parent[pj - 1][pi].clone(),
+                                    parent[pj][pi + 1].clone(),
+                                    parent[pj + 1][pi].clone(),
+                                    parent[pj][pi - 1].clone(),
+                                ];
+
+                                let (x, y) = (x0 + i as i64, y0 + j as i64);
+                                map(&parent[pj][pi], &neighbours, &mut rng(x, y))
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Returns the seed of the layer at the given depth, with the base map
+    /// at depth 0.
+    fn layer_seed(&self, depth: usize) -> Seed {
+        Seed::of_value(hash(self.seed.value, depth as u64))
+    }
+}
+
+/// Picks one of the given tiles at random.
+fn choose<T: Clone>(tiles: &[&T], rng: &mut CellRng) -> T {
+    (*rng.choose(tiles).unwrap()).clone()
+}
+
+/// Picks the most common of the given tiles, choosing randomly between
+/// ties.
+fn most_common<T: Clone + PartialEq>(tiles: &[&T], rng: &mut CellRng) -> T {
+    let count = |tile: &T| tiles.iter().filter(|&&other| other == tile).count();
+    let best = tiles.iter().map(|&tile| count(tile)).max().unwrap_or(0);
+
+    let candidates: Vec<&T> = tiles
+        .iter()
+        .copied()
+        .filter(|&tile| count(tile) == best)
+        .collect();
+
+    choose(&candidates, rng)
+}